@@ -0,0 +1,111 @@
+//! A tiny UCI engine standing in for real engines in tests. It always plays the first
+//! legal move, predicting the first legal reply, and doesn't search at all.
+//!
+//! Setting the `Mute` option makes it ignore `go`, to exercise timeouts.
+
+use poob::game::Game;
+use poob::movegen::MoveGenerator;
+use poob::moves::Move;
+use std::io::{self, BufRead, Write};
+
+fn main() -> io::Result<()> {
+    let move_gen = MoveGenerator::new();
+    let mut game = Game::new(&move_gen);
+    let mut mute = false;
+    let mut searching = false;
+    let mut out = io::stdout().lock();
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let mut tokens = line.split_ascii_whitespace();
+
+        match tokens.next() {
+            Some("uci") => {
+                writeln!(out, "id name poob fake engine")?;
+                writeln!(out, "id author the poob developers")?;
+                writeln!(out, "option name Hash type spin default 16 min 1 max 1024")?;
+                writeln!(out, "option name Mute type check default false")?;
+                writeln!(out, "uciok")?;
+            }
+            Some("isready") => writeln!(out, "readyok")?,
+            Some("setoption") if line.starts_with("setoption name Mute value ") => {
+                mute = line.ends_with("true");
+            }
+            Some("position") => {
+                if let Some(position) = set_position(&move_gen, tokens) {
+                    game = position;
+                }
+            }
+            Some("go") if mute => (),
+            Some("go") if line.contains("infinite") => searching = true,
+            Some("go") => best_move(&game, &mut out)?,
+            Some("stop") if searching => {
+                searching = false;
+                best_move(&game, &mut out)?;
+            }
+            Some("quit") => break,
+            _ => (),
+        }
+
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Returns the game set up by the arguments of a `position` command.
+fn set_position<'a, 't>(
+    move_gen: &'a MoveGenerator,
+    mut tokens: impl Iterator<Item = &'t str>,
+) -> Option<Game<'a>> {
+    let mut game = match tokens.next()? {
+        "startpos" => {
+            tokens.next();
+            Game::new(move_gen)
+        }
+        "fen" => {
+            let fen: Vec<&str> = tokens
+                .by_ref()
+                .take_while(|&token| token != "moves")
+                .collect();
+            Game::from_fen(&fen.join(" "), move_gen).ok()?
+        }
+        _ => return None,
+    };
+
+    for mv in tokens {
+        game.try_make_move(mv.parse::<Move>().ok()?).ok()?;
+    }
+
+    Some(game)
+}
+
+/// Sends an `info` line and the `bestmove` for the current position.
+fn best_move(game: &Game, out: &mut impl Write) -> io::Result<()> {
+    let Some(mv) = game.enumerate_moves().get_move(0) else {
+        return writeln!(out, "bestmove 0000");
+    };
+
+    let mut after = game.clone();
+    after.unchecked_make_move(mv);
+
+    match after.enumerate_moves().get_move(0) {
+        Some(reply) => {
+            writeln!(
+                out,
+                "info depth 1 seldepth 2 score cp 0 nodes 2 nps 2000 time 1 pv {mv} {reply}"
+            )?;
+            writeln!(out, "bestmove {mv} ponder {reply}")
+        }
+        None => {
+            let score = if after.is_in_check() {
+                "mate 1"
+            } else {
+                "cp 0"
+            };
+
+            writeln!(out, "info depth 1 score {score} nodes 1 time 1 pv {mv}")?;
+            writeln!(out, "bestmove {mv}")
+        }
+    }
+}
@@ -2,50 +2,54 @@
 pub mod bitboard_constants {
     /// Constants of the starting positions for each piece and color.
     pub mod starting_positions {
-        pub const DEFAULT_PAWNS_WHITE: u64 = 0b11111111 << 8;
-        pub const DEFAULT_PAWNS_BLACK: u64 = 0b11111111 << 48;
-        pub const DEFAULT_ROOKS_WHITE: u64 = 0b10000001;
-        pub const DEFAULT_ROOKS_BLACK: u64 = 0b10000001 << 56;
-        pub const DEFAULT_KNIGHTS_WHITE: u64 = 0b01000010;
-        pub const DEFAULT_KNIGHTS_BLACK: u64 = 0b01000010 << 56;
-        pub const DEFAULT_BISHOPS_WHITE: u64 = 0b00100100;
-        pub const DEFAULT_BISHOPS_BLACK: u64 = 0b00100100 << 56;
-        pub const DEFAULT_QUEENS_WHITE: u64 = 0b00001000;
-        pub const DEFAULT_QUEENS_BLACK: u64 = 0b00001000 << 56;
-        pub const DEFAULT_KING_WHITE: u64 = 0b00010000;
-        pub const DEFAULT_KING_BLACK: u64 = 0b00010000 << 56;
+        use crate::bitboards::Bitboard;
+
+        pub const DEFAULT_PAWNS_WHITE: Bitboard = Bitboard(0b11111111 << 8);
+        pub const DEFAULT_PAWNS_BLACK: Bitboard = Bitboard(0b11111111 << 48);
+        pub const DEFAULT_ROOKS_WHITE: Bitboard = Bitboard(0b10000001);
+        pub const DEFAULT_ROOKS_BLACK: Bitboard = Bitboard(0b10000001 << 56);
+        pub const DEFAULT_KNIGHTS_WHITE: Bitboard = Bitboard(0b01000010);
+        pub const DEFAULT_KNIGHTS_BLACK: Bitboard = Bitboard(0b01000010 << 56);
+        pub const DEFAULT_BISHOPS_WHITE: Bitboard = Bitboard(0b00100100);
+        pub const DEFAULT_BISHOPS_BLACK: Bitboard = Bitboard(0b00100100 << 56);
+        pub const DEFAULT_QUEENS_WHITE: Bitboard = Bitboard(0b00001000);
+        pub const DEFAULT_QUEENS_BLACK: Bitboard = Bitboard(0b00001000 << 56);
+        pub const DEFAULT_KING_WHITE: Bitboard = Bitboard(0b00010000);
+        pub const DEFAULT_KING_BLACK: Bitboard = Bitboard(0b00010000 << 56);
     }
 
     /// Masks of each rank and file.
     pub mod rank_file {
-        pub const RANK_1: u64 = 0b11111111;
-        pub const RANK_2: u64 = 0b11111111 << 8;
-        pub const RANK_3: u64 = 0b11111111 << 16;
-        pub const RANK_4: u64 = 0b11111111 << 24;
-        pub const RANK_5: u64 = 0b11111111 << 32;
-        pub const RANK_6: u64 = 0b11111111 << 40;
-        pub const RANK_7: u64 = 0b11111111 << 48;
-        pub const RANK_8: u64 = 0b11111111 << 56;
-
-        pub const FILE_A: u64 =
-            1 | 1 << 8 | 1 << 16 | 1 << 24 | 1 << 32 | 1 << 40 | 1 << 48 | 1 << 56;
-        pub const FILE_B: u64 =
-            1 << 1 | 1 << 9 | 1 << 17 | 1 << 25 | 1 << 33 | 1 << 41 | 1 << 49 | 1 << 57;
-        pub const FILE_C: u64 =
-            1 << 2 | 1 << 10 | 1 << 18 | 1 << 26 | 1 << 34 | 1 << 42 | 1 << 50 | 1 << 58;
-        pub const FILE_D: u64 =
-            1 << 3 | 1 << 11 | 1 << 19 | 1 << 27 | 1 << 35 | 1 << 43 | 1 << 51 | 1 << 59;
-        pub const FILE_E: u64 =
-            1 << 4 | 1 << 12 | 1 << 20 | 1 << 28 | 1 << 36 | 1 << 44 | 1 << 52 | 1 << 60;
-        pub const FILE_F: u64 =
-            1 << 5 | 1 << 13 | 1 << 21 | 1 << 29 | 1 << 37 | 1 << 45 | 1 << 53 | 1 << 61;
-        pub const FILE_G: u64 =
-            1 << 6 | 1 << 14 | 1 << 22 | 1 << 30 | 1 << 38 | 1 << 46 | 1 << 54 | 1 << 62;
-        pub const FILE_H: u64 =
-            1 << 7 | 1 << 15 | 1 << 23 | 1 << 31 | 1 << 39 | 1 << 47 | 1 << 55 | 1 << 63;
-    }
-
-    /// Constants mapping each color and piece to a corresponding index in a `[[u64; 6]; 2]`.
+        use crate::bitboards::Bitboard;
+
+        pub const RANK_1: Bitboard = Bitboard(0b11111111);
+        pub const RANK_2: Bitboard = Bitboard(0b11111111 << 8);
+        pub const RANK_3: Bitboard = Bitboard(0b11111111 << 16);
+        pub const RANK_4: Bitboard = Bitboard(0b11111111 << 24);
+        pub const RANK_5: Bitboard = Bitboard(0b11111111 << 32);
+        pub const RANK_6: Bitboard = Bitboard(0b11111111 << 40);
+        pub const RANK_7: Bitboard = Bitboard(0b11111111 << 48);
+        pub const RANK_8: Bitboard = Bitboard(0b11111111 << 56);
+
+        pub const FILE_A: Bitboard =
+            Bitboard(1 | 1 << 8 | 1 << 16 | 1 << 24 | 1 << 32 | 1 << 40 | 1 << 48 | 1 << 56);
+        pub const FILE_B: Bitboard =
+            Bitboard(1 << 1 | 1 << 9 | 1 << 17 | 1 << 25 | 1 << 33 | 1 << 41 | 1 << 49 | 1 << 57);
+        pub const FILE_C: Bitboard =
+            Bitboard(1 << 2 | 1 << 10 | 1 << 18 | 1 << 26 | 1 << 34 | 1 << 42 | 1 << 50 | 1 << 58);
+        pub const FILE_D: Bitboard =
+            Bitboard(1 << 3 | 1 << 11 | 1 << 19 | 1 << 27 | 1 << 35 | 1 << 43 | 1 << 51 | 1 << 59);
+        pub const FILE_E: Bitboard =
+            Bitboard(1 << 4 | 1 << 12 | 1 << 20 | 1 << 28 | 1 << 36 | 1 << 44 | 1 << 52 | 1 << 60);
+        pub const FILE_F: Bitboard =
+            Bitboard(1 << 5 | 1 << 13 | 1 << 21 | 1 << 29 | 1 << 37 | 1 << 45 | 1 << 53 | 1 << 61);
+        pub const FILE_G: Bitboard =
+            Bitboard(1 << 6 | 1 << 14 | 1 << 22 | 1 << 30 | 1 << 38 | 1 << 46 | 1 << 54 | 1 << 62);
+        pub const FILE_H: Bitboard =
+            Bitboard(1 << 7 | 1 << 15 | 1 << 23 | 1 << 31 | 1 << 39 | 1 << 47 | 1 << 55 | 1 << 63);
+    }
+
+    /// Constants mapping each color and piece to a corresponding index in a `[[Bitboard; 6]; 2]`.
     pub mod bitboard_indices {
         pub const WHITE: usize = 0;
         pub const BLACK: usize = 1;
@@ -60,37 +64,292 @@ pub mod bitboard_constants {
 
     /// Masks of squares relevant for castling.
     pub mod castle_squares {
-        pub const KINGSIDE_WHITE_KING_TARGET_SQUARE: u64 = 0b01000000;
-        pub const KINGSIDE_BLACK_KING_TARGET_SQUARE: u64 = 0b01000000 << 56;
-        pub const QUEENSIDE_WHITE_KING_TARGET_SQUARE: u64 = 0b00000100;
-        pub const QUEENSIDE_BLACK_KING_TARGET_SQUARE: u64 = 0b00000100 << 56;
+        use crate::bitboards::Bitboard;
 
-        pub const KINGSIDE_WHITE_ROOK_TARGET_SQUARE: u64 = 0b00100000;
-        pub const KINGSIDE_BLACK_ROOK_TARGET_SQUARE: u64 = 0b00100000 << 56;
-        pub const QUEENSIDE_WHITE_ROOK_TARGET_SQUARE: u64 = 0b00001000;
-        pub const QUEENSIDE_BLACK_ROOK_TARGET_SQUARE: u64 = 0b00001000 << 56;
+        pub const KINGSIDE_WHITE_KING_TARGET_SQUARE: Bitboard = Bitboard(0b01000000);
+        pub const KINGSIDE_BLACK_KING_TARGET_SQUARE: Bitboard = Bitboard(0b01000000 << 56);
+        pub const QUEENSIDE_WHITE_KING_TARGET_SQUARE: Bitboard = Bitboard(0b00000100);
+        pub const QUEENSIDE_BLACK_KING_TARGET_SQUARE: Bitboard = Bitboard(0b00000100 << 56);
 
-        pub const KINGSIDE_WHITE_SQUARES: u64 = 0b01100000;
-        pub const KINGSIDE_BLACK_SQUARES: u64 = 0b01100000 << 56;
-        pub const QUEENSIDE_WHITE_SQUARES: u64 = 0b00001100;
-        pub const QUEENSIDE_BLACK_SQUARES: u64 = 0b00001100 << 56;
+        pub const KINGSIDE_WHITE_ROOK_TARGET_SQUARE: Bitboard = Bitboard(0b00100000);
+        pub const KINGSIDE_BLACK_ROOK_TARGET_SQUARE: Bitboard = Bitboard(0b00100000 << 56);
+        pub const QUEENSIDE_WHITE_ROOK_TARGET_SQUARE: Bitboard = Bitboard(0b00001000);
+        pub const QUEENSIDE_BLACK_ROOK_TARGET_SQUARE: Bitboard = Bitboard(0b00001000 << 56);
 
-        pub const QUEENSIDE_ROOK_SQUARE_WHITE: u64 = 0b00000010;
-        pub const QUEENSIDE_ROOK_SQUARE_BLACK: u64 = 0b00000010 << 56;
+        pub const KINGSIDE_WHITE_SQUARES: Bitboard = Bitboard(0b01100000);
+        pub const KINGSIDE_BLACK_SQUARES: Bitboard = Bitboard(0b01100000 << 56);
+        pub const QUEENSIDE_WHITE_SQUARES: Bitboard = Bitboard(0b00001100);
+        pub const QUEENSIDE_BLACK_SQUARES: Bitboard = Bitboard(0b00001100 << 56);
+
+        pub const QUEENSIDE_ROOK_SQUARE_WHITE: Bitboard = Bitboard(0b00000010);
+        pub const QUEENSIDE_ROOK_SQUARE_BLACK: Bitboard = Bitboard(0b00000010 << 56);
     }
 
     /// Various useful masks.
     pub mod masks {
-        pub const NOT_KINGSIDE_WHITE_ROOK_START_SQUARE: u64 = !0b10000000;
-        pub const NOT_KINGSIDE_BLACK_ROOK_START_SQUARE: u64 = !(0b10000000 << 56);
-        pub const NOT_QUEENSIDE_WHITE_ROOK_START_SQUARE: u64 = !1;
-        pub const NOT_QUEENSIDE_BLACK_ROOK_START_SQUARE: u64 = !(1 << 56);
+        use crate::bitboards::Bitboard;
+
+        pub const NOT_KINGSIDE_WHITE_ROOK_START_SQUARE: Bitboard = Bitboard(!0b10000000);
+        pub const NOT_KINGSIDE_BLACK_ROOK_START_SQUARE: Bitboard = Bitboard(!(0b10000000 << 56));
+        pub const NOT_QUEENSIDE_WHITE_ROOK_START_SQUARE: Bitboard = Bitboard(!1);
+        pub const NOT_QUEENSIDE_BLACK_ROOK_START_SQUARE: Bitboard = Bitboard(!(1 << 56));
     }
 }
 
 use crate::moves::Move;
+use crate::square::{Square, SquareParseError};
 use crate::{Color, Piece};
 use bitboard_constants::{bitboard_indices::*, castle_squares::*, masks::*, starting_positions::*};
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, ShlAssign, Shr,
+    ShrAssign,
+};
+use std::str::FromStr;
+
+/// A set of squares, stored as a [`u64`] with bit `n` representing the square with index `n`
+/// (see [`Square`]).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    /// The bitboard with no squares set.
+    pub const EMPTY: Bitboard = Bitboard(0);
+
+    /// The bitboard with every square set.
+    pub const FULL: Bitboard = Bitboard(!0);
+
+    /// Returns the number of set squares.
+    pub const fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Returns `true` if no squares are set, and `false` otherwise.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if more than one square is set, and `false` otherwise.
+    pub const fn has_many(self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0
+    }
+
+    /// Returns `true` if the given square is set, and `false` otherwise.
+    pub const fn contains(self, square: Square) -> bool {
+        self.0 & 1 << square as u8 != 0
+    }
+
+    /// Returns this bitboard with the given square set.
+    pub const fn with(self, square: Square) -> Self {
+        Bitboard(self.0 | 1 << square as u8)
+    }
+
+    /// Returns this bitboard with the given square cleared.
+    pub const fn without(self, square: Square) -> Self {
+        Bitboard(self.0 & !(1 << square as u8))
+    }
+
+    /// Returns the lowest set square, or [`None`] if the bitboard is empty.
+    pub fn lsb(self) -> Option<Square> {
+        Square::from_index(self.0.trailing_zeros() as u8)
+    }
+
+    /// Returns the highest set square, or [`None`] if the bitboard is empty.
+    pub fn msb(self) -> Option<Square> {
+        63u32
+            .checked_sub(self.0.leading_zeros())
+            .and_then(|index| Square::from_index(index as u8))
+    }
+
+    /// Clears the lowest set square and returns it, or returns [`None`] if the bitboard is
+    /// empty.
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let square = self.lsb()?;
+        self.0 &= self.0 - 1;
+
+        Some(square)
+    }
+
+    /// Returns the only set square, or [`None`] if the bitboard has zero or several squares set.
+    pub fn to_square(self) -> Option<Square> {
+        if self.has_many() {
+            return None;
+        }
+
+        self.lsb()
+    }
+
+    /// Returns an iterator over the set squares, from lowest to highest.
+    pub fn iter(self) -> BitboardIter {
+        BitboardIter(self)
+    }
+}
+
+/// An iterator over the set squares of a [`Bitboard`], popping the lowest square each step.
+#[derive(Debug, Clone, Copy)]
+pub struct BitboardIter(Bitboard);
+
+impl Iterator for BitboardIter {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_lsb()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count() as usize;
+
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for BitboardIter {}
+
+impl FusedIterator for BitboardIter {}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(iter: I) -> Self {
+        iter.into_iter().fold(Bitboard::EMPTY, Bitboard::with)
+    }
+}
+
+impl From<Square> for Bitboard {
+    fn from(square: Square) -> Self {
+        square.bitboard()
+    }
+}
+
+macro_rules! impl_bitboard_op {
+    ($op:ident, $op_fn:ident, $assign:ident, $assign_fn:ident) => {
+        impl $op for Bitboard {
+            type Output = Bitboard;
+
+            fn $op_fn(self, rhs: Bitboard) -> Self::Output {
+                Bitboard($op::$op_fn(self.0, rhs.0))
+            }
+        }
+
+        impl $assign for Bitboard {
+            fn $assign_fn(&mut self, rhs: Bitboard) {
+                $assign::$assign_fn(&mut self.0, rhs.0);
+            }
+        }
+    };
+}
+
+impl_bitboard_op!(BitAnd, bitand, BitAndAssign, bitand_assign);
+impl_bitboard_op!(BitOr, bitor, BitOrAssign, bitor_assign);
+impl_bitboard_op!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Self::Output {
+        Bitboard(!self.0)
+    }
+}
+
+impl Shl<u32> for Bitboard {
+    type Output = Bitboard;
+
+    fn shl(self, rhs: u32) -> Self::Output {
+        Bitboard(self.0 << rhs)
+    }
+}
+
+impl ShlAssign<u32> for Bitboard {
+    fn shl_assign(&mut self, rhs: u32) {
+        self.0 <<= rhs;
+    }
+}
+
+impl Shr<u32> for Bitboard {
+    type Output = Bitboard;
+
+    fn shr(self, rhs: u32) -> Self::Output {
+        Bitboard(self.0 >> rhs)
+    }
+}
+
+impl ShrAssign<u32> for Bitboard {
+    fn shr_assign(&mut self, rhs: u32) {
+        self.0 >>= rhs;
+    }
+}
+
+impl fmt::Debug for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bitboard({:#018x})", self.0)
+    }
+}
+
+/// Displays the bitboard as an 8x8 grid from [white's](Color::White) perspective, with `x`
+/// for set squares and `.` for empty ones.
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rank in (0..8).rev() {
+            for file in 0..8 {
+                let c = if self.0 & 1 << (rank * 8 + file) != 0 {
+                    'x'
+                } else {
+                    '.'
+                };
+
+                write!(f, "{c}")?;
+            }
+
+            if rank > 0 {
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the grid written by the [`Display`](fmt::Display) implementation. Whitespace is
+/// ignored, and `x`, `X` or `1` mark set squares while `.` or `0` mark empty ones.
+impl FromStr for Bitboard {
+    type Err = SquareParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bitboard = Bitboard::EMPTY;
+        let mut cells = 0;
+
+        for c in s.chars().filter(|c| !c.is_whitespace()) {
+            if cells == 64 {
+                return Err(SquareParseError::BadBitboard);
+            }
+
+            // The grid starts at a8 and ends at h1.
+            let square = (7 - cells / 8) * 8 + cells % 8;
+
+            match c {
+                'x' | 'X' | '1' => bitboard.0 |= 1 << square,
+                '.' | '0' => (),
+                _ => return Err(SquareParseError::BadBitboard),
+            }
+
+            cells += 1;
+        }
+
+        if cells != 64 {
+            return Err(SquareParseError::BadBitboard);
+        }
+
+        Ok(bitboard)
+    }
+}
 
 /// Error variants when constructing a new bitboard
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -99,12 +358,6 @@ pub enum BitBoardCreationError {
     BadKingCount,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum BitBoardConversionError {
-    BadSquare,
-    BadBitboard,
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BitBoardMoveError {
     NoInitialSquarePiece,
@@ -117,13 +370,13 @@ pub trait From<BitBoardCreationError> {
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BitBoards {
-    boards: [[u64; 6]; 2],
+    boards: [[Bitboard; 6]; 2],
 }
 
 impl BitBoards {
     /// Returns a collection of bitboards in the default starting position.
     pub fn default() -> Self {
-        let mut default_boards = [[0; 6], [0; 6]];
+        let mut default_boards = [[Bitboard::EMPTY; 6]; 2];
 
         default_boards[WHITE][PAWN] = DEFAULT_PAWNS_WHITE;
         default_boards[WHITE][KNIGHT] = DEFAULT_KNIGHTS_WHITE;
@@ -144,225 +397,212 @@ impl BitBoards {
         }
     }
 
-    pub fn new(boards: [[u64; 6]; 2]) -> Result<Self, BitBoardCreationError> {
-        if boards[WHITE][KING].count_ones() != 1 || boards[BLACK][KING].count_ones() != 1 {
+    pub fn new(boards: [[Bitboard; 6]; 2]) -> Result<Self, BitBoardCreationError> {
+        if boards[WHITE][KING].count() != 1 || boards[BLACK][KING].count() != 1 {
             return Err(BitBoardCreationError::BadKingCount);
         }
 
-        let mut all_boards = 0;
+        let mut all_boards = Bitboard::EMPTY;
         let mut all_pieces = 0;
 
         for &board in boards.iter().flatten() {
             all_boards |= board;
-            all_pieces += board.count_ones();
+            all_pieces += board.count();
         }
 
-        if all_boards.count_ones() != all_pieces {
+        if all_boards.count() != all_pieces {
             return Err(BitBoardCreationError::PieceOverlap);
         }
 
         Ok(Self { boards })
     }
 
+    /// Returns the bitboard representing the position of the given [color](Color) and [piece](Piece).
+    pub fn board(&self, color: Color, piece: Piece) -> Bitboard {
+        self.boards[color.to_index()][piece.to_index()]
+    }
+
     /// Returns the bitboard representing all pieces in the current position.
-    pub fn all_boards(&self) -> u64 {
+    pub fn all_boards(&self) -> Bitboard {
         self.boards
             .iter()
             .flatten()
-            .fold(0, |acc, &board| acc | board)
+            .fold(Bitboard::EMPTY, |acc, &board| acc | board)
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) and [white](Color::White) [pawns](Piece::Pawn).
-    pub fn pawns(&self) -> u64 {
+    pub fn pawns(&self) -> Bitboard {
         self.boards[WHITE][PAWN] | self.boards[BLACK][PAWN]
     }
 
     /// Returns the bitboard representing the position of the [white](Color::White) [pawns](Piece::Pawn).
-    pub fn pawns_white(&self) -> u64 {
+    pub fn pawns_white(&self) -> Bitboard {
         self.boards[WHITE][PAWN]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) [pawns](Piece::Pawn).
-    pub fn pawns_black(&self) -> u64 {
+    pub fn pawns_black(&self) -> Bitboard {
         self.boards[BLACK][PAWN]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) and [white](Color::White) [rooks](Piece::Rook).
-    pub fn rooks(&self) -> u64 {
+    pub fn rooks(&self) -> Bitboard {
         self.boards[WHITE][ROOK] | self.boards[BLACK][ROOK]
     }
 
     /// Returns the bitboard representing the position of the [white](Color::White) [rooks](Piece::Rook).
-    pub fn rooks_white(&self) -> u64 {
+    pub fn rooks_white(&self) -> Bitboard {
         self.boards[WHITE][ROOK]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) [rooks](Piece::Rook).
-    pub fn rooks_black(&self) -> u64 {
+    pub fn rooks_black(&self) -> Bitboard {
         self.boards[BLACK][ROOK]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) and [white](Color::White) [knights](Piece::Knight).
-    pub fn knights(&self) -> u64 {
+    pub fn knights(&self) -> Bitboard {
         self.boards[WHITE][KNIGHT] | self.boards[BLACK][KNIGHT]
     }
 
     /// Returns the bitboard representing the position of the [white](Color::White) [knights](Piece::Knight).
-    pub fn knights_white(&self) -> u64 {
+    pub fn knights_white(&self) -> Bitboard {
         self.boards[WHITE][KNIGHT]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) [knights](Piece::Knight).
-    pub fn knights_black(&self) -> u64 {
+    pub fn knights_black(&self) -> Bitboard {
         self.boards[BLACK][KNIGHT]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) and [white](Color::White) [bishops](Piece::Bishop).
-    pub fn bishops(&self) -> u64 {
+    pub fn bishops(&self) -> Bitboard {
         self.boards[WHITE][BISHOP] | self.boards[BLACK][BISHOP]
     }
 
     /// Returns the bitboard representing the position of the [white](Color::White) [bishops](Piece::Bishop).
-    pub fn bishops_white(&self) -> u64 {
+    pub fn bishops_white(&self) -> Bitboard {
         self.boards[WHITE][BISHOP]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) [bishops](Piece::Bishop).
-    pub fn bishops_black(&self) -> u64 {
+    pub fn bishops_black(&self) -> Bitboard {
         self.boards[BLACK][BISHOP]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) and [white](Color::White) [queens](Piece::Queen).
-    pub fn queens(&self) -> u64 {
+    pub fn queens(&self) -> Bitboard {
         self.boards[WHITE][QUEEN] | self.boards[BLACK][QUEEN]
     }
 
     /// Returns the bitboard representing the position of the [white](Color::White) [queens](Piece::Queen).
-    pub fn queens_white(&self) -> u64 {
+    pub fn queens_white(&self) -> Bitboard {
         self.boards[WHITE][QUEEN]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) [queens](Piece::Queen).
-    pub fn queens_black(&self) -> u64 {
+    pub fn queens_black(&self) -> Bitboard {
         self.boards[BLACK][QUEEN]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) and [white](Color::White) kings.
-    pub fn kings(&self) -> u64 {
+    pub fn kings(&self) -> Bitboard {
         self.boards[WHITE][KING] | self.boards[BLACK][KING]
     }
 
     /// Returns the bitboard representing the position of the [white](Color::White) king.
-    pub fn king_white(&self) -> u64 {
+    pub fn king_white(&self) -> Bitboard {
         self.boards[WHITE][KING]
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) king.
-    pub fn king_black(&self) -> u64 {
+    pub fn king_black(&self) -> Bitboard {
         self.boards[BLACK][KING]
     }
 
     /// Returns the bitboard representing the position of the [white](Color::White) pieces.
-    pub fn white(&self) -> u64 {
-        self.boards[WHITE].iter().fold(0, |acc, e| acc | *e)
+    pub fn white(&self) -> Bitboard {
+        self.boards[WHITE]
+            .iter()
+            .fold(Bitboard::EMPTY, |acc, e| acc | *e)
     }
 
     /// Returns the bitboard representing the position of the [black](Color::Black) pieces.
-    pub fn black(&self) -> u64 {
-        self.boards[BLACK].iter().fold(0, |acc, e| acc | *e)
+    pub fn black(&self) -> Bitboard {
+        self.boards[BLACK]
+            .iter()
+            .fold(Bitboard::EMPTY, |acc, e| acc | *e)
+    }
+
+    /// Returns the bitboard representing the position of the pieces of the given color.
+    pub fn pieces(&self, color: Color) -> Bitboard {
+        match color {
+            Color::White => self.white(),
+            Color::Black => self.black(),
+        }
     }
 
     /// Returns the total piece count.
     pub fn total_pieces(&self) -> u32 {
-        self.all_boards().count_ones()
+        self.all_boards().count()
     }
 
     /// "Clears" the square from all bitboards, setting the bit at that
     /// position to `0`.
-    pub fn clear_square(&mut self, square: u8) {
-        let clear_mask = !(1 << square);
-
+    pub fn clear_square(&mut self, square: Square) {
         for board in self.boards.iter_mut().flatten() {
-            *board &= clear_mask;
-        }
-    }
-
-    pub fn square_to_bitboard(square: u8) -> Result<u64, BitBoardConversionError> {
-        if square > 63 {
-            return Err(BitBoardConversionError::BadSquare);
-        }
-
-        Ok(1 << square)
-    }
-
-    pub fn unchecked_square_to_bitboard(square: u8) -> u64 {
-        1 << square
-    }
-
-    pub fn bitboard_to_square(bitboard: u64) -> Result<u8, BitBoardConversionError> {
-        if bitboard.count_ones() != 1 {
-            return Err(BitBoardConversionError::BadBitboard);
+            *board = board.without(square);
         }
-
-        Ok(bitboard.trailing_zeros() as u8)
-    }
-
-    pub fn unchecked_bitboard_to_square(bitboard: u64) -> u8 {
-        bitboard.trailing_zeros() as u8
     }
 
     /// Returns `Some((Color, Piece))` of the piece on a given square, or [`None`]
     /// if that square is unoccupied.
-    pub fn piece_at(&self, square: u8) -> Option<(Color, Piece)> {
-        let Ok(bitboard) = BitBoards::square_to_bitboard(square) else {
-            return None;
-        };
-
-        if self.boards[WHITE][PAWN] & bitboard != 0 {
+    pub fn piece_at(&self, square: Square) -> Option<(Color, Piece)> {
+        if self.boards[WHITE][PAWN].contains(square) {
             return Some((Color::White, Piece::Pawn));
         }
 
-        if self.boards[WHITE][KNIGHT] & bitboard != 0 {
+        if self.boards[WHITE][KNIGHT].contains(square) {
             return Some((Color::White, Piece::Knight));
         }
 
-        if self.boards[WHITE][BISHOP] & bitboard != 0 {
+        if self.boards[WHITE][BISHOP].contains(square) {
             return Some((Color::White, Piece::Bishop));
         }
 
-        if self.boards[WHITE][ROOK] & bitboard != 0 {
+        if self.boards[WHITE][ROOK].contains(square) {
             return Some((Color::White, Piece::Rook));
         }
 
-        if self.boards[WHITE][QUEEN] & bitboard != 0 {
+        if self.boards[WHITE][QUEEN].contains(square) {
             return Some((Color::White, Piece::Queen));
         }
 
-        if self.boards[WHITE][KING] & bitboard != 0 {
+        if self.boards[WHITE][KING].contains(square) {
             return Some((Color::White, Piece::King));
         }
 
-        if self.boards[BLACK][PAWN] & bitboard != 0 {
+        if self.boards[BLACK][PAWN].contains(square) {
             return Some((Color::Black, Piece::Pawn));
         }
 
-        if self.boards[BLACK][KNIGHT] & bitboard != 0 {
+        if self.boards[BLACK][KNIGHT].contains(square) {
             return Some((Color::Black, Piece::Knight));
         }
 
-        if self.boards[BLACK][BISHOP] & bitboard != 0 {
+        if self.boards[BLACK][BISHOP].contains(square) {
             return Some((Color::Black, Piece::Bishop));
         }
 
-        if self.boards[BLACK][ROOK] & bitboard != 0 {
+        if self.boards[BLACK][ROOK].contains(square) {
             return Some((Color::Black, Piece::Rook));
         }
 
-        if self.boards[BLACK][QUEEN] & bitboard != 0 {
+        if self.boards[BLACK][QUEEN].contains(square) {
             return Some((Color::Black, Piece::Queen));
         }
 
-        if self.boards[BLACK][KING] & bitboard != 0 {
+        if self.boards[BLACK][KING].contains(square) {
             return Some((Color::Black, Piece::King));
         }
 
@@ -381,8 +621,8 @@ impl BitBoards {
             return;
         };
 
-        let initial_bitboard = BitBoards::unchecked_square_to_bitboard(initial_square);
-        let target_bitboard = BitBoards::unchecked_square_to_bitboard(target_square);
+        let initial_bitboard = initial_square.bitboard();
+        let target_bitboard = target_square.bitboard();
 
         if let Some((target_color, target_piece)) = self.piece_at(target_square) {
             self.boards[target_color.to_index()][target_piece.to_index()] ^= target_bitboard;
@@ -437,10 +677,10 @@ impl BitBoards {
     pub fn en_passant_white(&mut self, mv: Move) {
         let (initial_square, target_square) = (mv.get_initial_square(), mv.get_target_square());
 
-        let initial_bitboard = BitBoards::unchecked_square_to_bitboard(initial_square);
-        let target_bitboard = BitBoards::unchecked_square_to_bitboard(target_square);
+        let initial_bitboard = initial_square.bitboard();
+        let target_bitboard = target_square.bitboard();
 
-        self.clear_square(target_square - 8);
+        self.clear_square(Square::new(target_square.file(), initial_square.rank()));
 
         self.boards[WHITE][PAWN] ^= initial_bitboard | target_bitboard;
     }
@@ -452,10 +692,10 @@ impl BitBoards {
     pub fn en_passant_black(&mut self, mv: Move) {
         let (initial_square, target_square) = (mv.get_initial_square(), mv.get_target_square());
 
-        let initial_bitboard = BitBoards::unchecked_square_to_bitboard(initial_square);
-        let target_bitboard = BitBoards::unchecked_square_to_bitboard(target_square);
+        let initial_bitboard = initial_square.bitboard();
+        let target_bitboard = target_square.bitboard();
 
-        self.clear_square(target_square + 8);
+        self.clear_square(Square::new(target_square.file(), initial_square.rank()));
 
         self.boards[BLACK][PAWN] ^= initial_bitboard | target_bitboard;
     }
@@ -463,21 +703,13 @@ impl BitBoards {
     /// Performs a promotion move for [white](Color::White). Removes the [pawn](Piece::Pawn) from the initial square
     /// and places the piece specified in the move in the target square.
     pub fn promote_white(&mut self, mv: Move) {
-        let promote_to = if mv.is_queen_promotion() {
-            QUEEN
-        } else if mv.is_knight_promotion() {
-            KNIGHT
-        } else if mv.is_rook_promotion() {
-            ROOK
-        } else {
-            BISHOP
-        };
+        let promote_to = mv.promotion_piece().map_or(QUEEN, |piece| piece.to_index());
 
         let (initial_square, target_square) = (mv.get_initial_square(), mv.get_target_square());
         self.clear_square(target_square);
 
-        let initial_bitboard = BitBoards::unchecked_square_to_bitboard(initial_square);
-        let target_bitboard = BitBoards::unchecked_square_to_bitboard(target_square);
+        let initial_bitboard = initial_square.bitboard();
+        let target_bitboard = target_square.bitboard();
 
         self.boards[WHITE][PAWN] ^= initial_bitboard;
         self.boards[WHITE][promote_to] ^= target_bitboard;
@@ -488,21 +720,13 @@ impl BitBoards {
     /// Performs a promotion move for [black](Color::Black). Removes the [pawn](Piece::Pawn) from the initial square
     /// and places the piece specified in the move in the target square.
     pub fn promote_black(&mut self, mv: Move) {
-        let promote_to = if mv.is_queen_promotion() {
-            QUEEN
-        } else if mv.is_knight_promotion() {
-            KNIGHT
-        } else if mv.is_rook_promotion() {
-            ROOK
-        } else {
-            BISHOP
-        };
+        let promote_to = mv.promotion_piece().map_or(QUEEN, |piece| piece.to_index());
 
         let (initial_square, target_square) = (mv.get_initial_square(), mv.get_target_square());
         self.clear_square(target_square);
 
-        let initial_bitboard = BitBoards::unchecked_square_to_bitboard(initial_square);
-        let target_bitboard = BitBoards::unchecked_square_to_bitboard(target_square);
+        let initial_bitboard = initial_square.bitboard();
+        let target_bitboard = target_square.bitboard();
 
         self.boards[BLACK][PAWN] ^= initial_bitboard;
         self.boards[BLACK][promote_to] ^= target_bitboard;
@@ -513,7 +737,7 @@ impl BitBoards {
 mod tests {
     use super::*;
 
-    fn test_board_state(board: BitBoards, expected_position: u64) {
+    fn test_board_state(board: BitBoards, expected_position: Bitboard) {
         assert_eq!(board.all_boards(), expected_position);
     }
 
@@ -521,55 +745,109 @@ mod tests {
     fn default_board_state() {
         test_board_state(
             BitBoards::default(),
-            0b11111111_11111111_00000000_00000000_00000000_00000000_11111111_11111111,
+            Bitboard(0b11111111_11111111_00000000_00000000_00000000_00000000_11111111_11111111),
         );
     }
 
     #[test]
     fn test_bitboard_conversion() {
+        assert_eq!(Square::F7.bitboard(), Bitboard(1 << 53));
+        assert_eq!(Bitboard(0b11).to_square(), None);
+        assert_eq!(Bitboard::EMPTY.to_square(), None);
+        assert_eq!(Bitboard(1 << 53).to_square(), Some(Square::F7));
+    }
+
+    #[test]
+    fn test_bitboard_iter() {
+        let bitboard: Bitboard = [Square::H8, Square::A1, Square::E4].into_iter().collect();
+
+        assert_eq!(bitboard.count(), 3);
+        assert_eq!(bitboard.lsb(), Some(Square::A1));
+        assert_eq!(bitboard.msb(), Some(Square::H8));
         assert_eq!(
-            BitBoards::square_to_bitboard(69),
-            Err(BitBoardConversionError::BadSquare)
+            bitboard.iter().collect::<Vec<_>>(),
+            vec![Square::A1, Square::E4, Square::H8]
         );
-        assert_eq!(BitBoards::square_to_bitboard(53), Ok(1 << 53));
+        assert_eq!(bitboard.iter().len(), 3);
+        assert_eq!(Bitboard::EMPTY.iter().next(), None);
+        assert_eq!(Bitboard::EMPTY.msb(), None);
+
+        let mut popped = bitboard;
+        assert_eq!(popped.pop_lsb(), Some(Square::A1));
+        assert_eq!(popped, bitboard.without(Square::A1));
+    }
 
+    #[test]
+    fn test_bitboard_ops() {
+        let a = Square::A1.bitboard();
+        let b = Square::B1.bitboard();
+
+        assert_eq!(a | b, Bitboard(0b11));
+        assert_eq!((a | b) & b, b);
+        assert_eq!((a | b) ^ a, b);
+        assert_eq!(!Bitboard::EMPTY, Bitboard::FULL);
+        assert_eq!(a << 1, b);
+        assert_eq!(b >> 1, a);
+        assert!((a | b).has_many());
+        assert!(!a.has_many());
+        assert!(Bitboard::EMPTY.is_empty());
+    }
+
+    #[test]
+    fn test_bitboard_display() {
+        let bitboard = Square::A8.bitboard() | Square::H1.bitboard() | Square::E4.bitboard();
+        let grid = "x.......\n\
+                    ........\n\
+                    ........\n\
+                    ........\n\
+                    ....x...\n\
+                    ........\n\
+                    ........\n\
+                    .......x";
+
+        assert_eq!(bitboard.to_string(), grid);
+        assert_eq!(grid.parse(), Ok(bitboard));
         assert_eq!(
-            BitBoards::bitboard_to_square(0b11),
-            Err(BitBoardConversionError::BadBitboard)
+            "x.......".parse::<Bitboard>(),
+            Err(SquareParseError::BadBitboard)
         );
-        assert_eq!(BitBoards::bitboard_to_square(1 << 53), Ok(53));
     }
 
     #[test]
     fn test_piece_at() {
         let board = BitBoards::default();
 
-        assert_eq!(board.piece_at(69), None);
-        assert_eq!(board.piece_at(27), None);
-        assert_eq!(board.piece_at(0), Some((Color::White, Piece::Rook)));
-        assert_eq!(board.piece_at(60), Some((Color::Black, Piece::King)));
+        assert_eq!(board.piece_at(Square::D4), None);
+        assert_eq!(
+            board.piece_at(Square::A1),
+            Some((Color::White, Piece::Rook))
+        );
+        assert_eq!(
+            board.piece_at(Square::E8),
+            Some((Color::Black, Piece::King))
+        );
     }
 
     #[test]
     fn test_move_piece() {
         let mut board = BitBoards::default();
 
-        let move_1 = Move::from_squares(15, 31).unwrap();
+        let move_1 = Move::from_squares(Square::H2, Square::H4);
 
         board.move_piece(move_1);
 
         assert_eq!(
             board.all_boards(),
-            0b11111111_11111111_00000000_00000000_10000000_00000000_01111111_11111111
+            Bitboard(0b11111111_11111111_00000000_00000000_10000000_00000000_01111111_11111111)
         );
 
-        let move_2 = Move::from_squares(7, 31).unwrap();
+        let move_2 = Move::from_squares(Square::H1, Square::H4);
 
         board.move_piece(move_2);
 
         assert_eq!(
             board.all_boards(),
-            0b11111111_11111111_00000000_00000000_10000000_00000000_01111111_01111111
+            Bitboard(0b11111111_11111111_00000000_00000000_10000000_00000000_01111111_01111111)
         );
     }
 }
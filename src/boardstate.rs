@@ -1,10 +1,14 @@
 use crate::bitboards::bitboard_constants::bitboard_indices::*;
-use crate::bitboards::bitboard_constants::rank_file::{FILE_A, FILE_H};
+use crate::bitboards::bitboard_constants::rank_file::{FILE_A, FILE_H, RANK_1, RANK_8};
 use crate::bitboards::bitboard_constants::starting_positions::*;
-use crate::bitboards::{BitBoardCreationError, BitBoards};
+use crate::bitboards::{BitBoardCreationError, BitBoards, Bitboard};
+use crate::eval;
+use crate::movegen::MoveGenerator;
 use crate::moves::*;
-use crate::util::*;
-use crate::{Color, Piece};
+use crate::tablebase::Material;
+use crate::{Color, File, Piece, Rank, Square};
+use std::error::Error;
+use std::fmt;
 
 pub mod boardstate_constants {
     pub const CAN_CASTLE_KINGSIDE_WHITE: u8 = 0b0010;
@@ -16,10 +20,21 @@ pub mod boardstate_constants {
 
 use boardstate_constants::*;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Human-readable names of the six FEN fields, in order.
+const FEN_FIELD_NAMES: [&str; 6] = [
+    "piece placement",
+    "side to move",
+    "castling rights",
+    "en passant square",
+    "halfmove clock",
+    "fullmove number",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BoardStateCreationError {
     BitBoardCreationError,
-    BadFenString(FenStringError),
+    BadFenString(FenError),
+    IllegalPosition(Vec<PositionError>),
 }
 
 impl From<BitBoardCreationError> for BoardStateCreationError {
@@ -28,6 +43,8 @@ impl From<BitBoardCreationError> for BoardStateCreationError {
     }
 }
 
+/// The kind of problem found in a FEN string. Each variant (other than
+/// [`MalformedString`](FenStringError::MalformedString)) corresponds to one FEN field.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FenStringError {
     MalformedString,
@@ -39,12 +56,193 @@ pub enum FenStringError {
     BadTurnCount,
 }
 
+/// A FEN parsing error, pointing at the offending part of the string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FenError {
+    pub kind: FenStringError,
+    /// The zero-based index of the offending field.
+    pub field: usize,
+    /// The byte offset of the problem within the FEN string.
+    pub offset: usize,
+    /// The offending text (usually the whole field).
+    pub text: String,
+    /// A human-readable explanation of the problem.
+    pub message: String,
+}
+
+impl FenError {
+    pub fn new(
+        kind: FenStringError,
+        field: usize,
+        offset: usize,
+        text: &str,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            field,
+            offset,
+            text: text.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field_name = FEN_FIELD_NAMES.get(self.field).unwrap_or(&"field");
+
+        write!(
+            f,
+            "bad FEN {field_name} (field {}) at byte {}",
+            self.field + 1,
+            self.offset
+        )?;
+
+        if !self.text.is_empty() {
+            write!(f, " `{}`", self.text)?;
+        }
+
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl Error for FenError {}
+
+impl From<FenError> for BoardStateCreationError {
+    fn from(err: FenError) -> Self {
+        Self::BadFenString(err)
+    }
+}
+
+impl fmt::Display for BoardStateCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BitBoardCreationError => write!(
+                f,
+                "position must have one king per side and no overlapping pieces"
+            ),
+            Self::BadFenString(err) => write!(f, "{err}"),
+            Self::IllegalPosition(errors) => {
+                write!(f, "illegal position: ")?;
+
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+
+                    write!(f, "{err}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for BoardStateCreationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::BadFenString(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Reasons a [`BoardState`] could not have arisen in a legal game, as reported by
+/// [`BoardState::validate()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionError {
+    /// The given color does not have exactly one king.
+    BadKingCount(Color),
+    /// There is a pawn on the first or eighth rank, on the given square.
+    PawnOnBackRank(Square),
+    AdjacentKings,
+    /// The side not to move is in check.
+    OpponentInCheck,
+    /// The side to move is in check from more than two pieces.
+    TooManyCheckers(u32),
+    /// The checking pieces could not have all given check with a single move.
+    ImpossibleCheck,
+    /// The given castling bitflag is set, but the king or rook is not on its starting square.
+    BadCastlingRights(u8),
+    /// The en passant square could not have been created by a double pawn push.
+    BadEnPassant(Square),
+    /// The given color has more pieces than it could have, even with promotions.
+    TooManyPieces(Color),
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadKingCount(color) => write!(f, "{color:?} does not have exactly one king"),
+            Self::PawnOnBackRank(square) => write!(f, "pawn on the back rank at {square}"),
+            Self::AdjacentKings => write!(f, "the kings are on adjacent squares"),
+            Self::OpponentInCheck => write!(f, "the side not to move is in check"),
+            Self::TooManyCheckers(count) => write!(f, "the king is in check from {count} pieces"),
+            Self::ImpossibleCheck => write!(f, "the checking pieces could not have given check"),
+            Self::BadCastlingRights(_) => {
+                write!(f, "castling rights don't match the king and rook placement")
+            }
+            Self::BadEnPassant(square) => {
+                write!(f, "no pawn could have just double pushed past {square}")
+            }
+            Self::TooManyPieces(color) => {
+                write!(f, "{color:?} has more pieces than promotions allow")
+            }
+        }
+    }
+}
+
+impl Error for PositionError {}
+
+/// A piece pinned to its king by an enemy rook, bishop or queen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pin {
+    pub pinned: Square,
+    pub pinner: Square,
+    /// The squares the pinned piece can move to without exposing its king: the squares
+    /// between the king and the pinner, including its own, and the pinner's square.
+    pub ray: Bitboard,
+}
+
+/// The highest game phase at which [`BoardState::endgame`] considers the position an
+/// endgame: a queen and a rook each.
+const ENDGAME_PHASE: i32 = 12;
+
+/// The light squares of the board, `b1`, `a2`, and so on.
+const LIGHT_SQUARES: Bitboard = Bitboard(0x55AA_55AA_55AA_55AA);
+
+/// The kind of endgame on the board, according to the pieces other than kings and pawns
+/// left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endgame {
+    /// Only kings and pawns.
+    Pawn,
+    /// Only knights.
+    Knight,
+    /// Only bishops, other than a single bishop each on squares of opposite colors.
+    Bishop,
+    /// A single bishop each, on squares of opposite colors.
+    OppositeColoredBishops,
+    /// Both knights and bishops.
+    Minor,
+    /// Only rooks.
+    Rook,
+    /// Rooks and minor pieces.
+    RookAndMinor,
+    /// Only queens.
+    Queen,
+    /// Queens along with other pieces.
+    Mixed,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct BoardState {
     pub position: BitBoards,
     pub side_to_move: Color,
     castling_rights: u8,
-    pub en_passant_square: Option<u8>,
+    pub en_passant_square: Option<Square>,
     pub fifty_move_rule: u8,
     pub turn_count: u16,
 }
@@ -67,7 +265,7 @@ impl BoardState {
         turn_count: u16,
         fifty_move_rule: u8,
         castling_rights: u8,
-        en_passant_square: Option<u8>,
+        en_passant_square: Option<Square>,
     ) -> Self {
         Self {
             side_to_move,
@@ -79,163 +277,146 @@ impl BoardState {
         }
     }
 
+    /// Parses a FEN string with all six fields.
     pub fn from_fen(fen: &str) -> Result<Self, BoardStateCreationError> {
-        if fen.split_ascii_whitespace().count() != 6 {
-            return Err(BoardStateCreationError::BadFenString(
-                FenStringError::MalformedString,
-            ));
-        }
-
-        let mut chunks = fen.split_ascii_whitespace();
+        Self::parse_fen(fen, false)
+    }
 
-        let Some(position) = chunks.next() else {
-            return Err(BoardStateCreationError::BadFenString(
-                FenStringError::BadPosition,
-            ));
-        };
+    /// Like [`from_fen()`](Self::from_fen()), but also accepts FEN strings missing the
+    /// halfmove clock and fullmove number (as found in EPD files), defaulting them to `0`
+    /// and `1`.
+    pub fn from_fen_lenient(fen: &str) -> Result<Self, BoardStateCreationError> {
+        Self::parse_fen(fen, true)
+    }
 
-        if position.split('/').count() != 8 {
-            return Err(BoardStateCreationError::BadFenString(
-                FenStringError::BadPosition,
-            ));
-        }
+    fn parse_fen(fen: &str, lenient: bool) -> Result<Self, BoardStateCreationError> {
+        let fields = fen_fields(fen);
+        let min_fields = if lenient { 4 } else { 6 };
 
-        let mut ranks = position.split('/').rev();
-        let mut unchecked_bitboards = [[0; 6]; 2];
-        let mut bit = 1;
+        if fields.len() < min_fields || fields.len() > 6 {
+            let (field, offset, text) = match fields.get(6) {
+                Some(&(offset, text)) => (6, offset, text),
+                None => (fields.len(), fen.len(), ""),
+            };
 
-        while let Some(rank) = ranks.next() {
-            if rank.chars().count() > 8 {
-                return Err(BoardStateCreationError::BadFenString(
-                    FenStringError::BadPosition,
-                ));
-            }
+            let expected = if lenient { "4 to 6" } else { "6" };
 
-            let mut chars = rank.chars();
-
-            while let Some(c) = chars.next() {
-                match c {
-                    '1'..='8' => {
-                        let shift = match c.to_digit(10) {
-                            Some(digit) => digit,
-                            None => {
-                                return Err(BoardStateCreationError::BadFenString(
-                                    FenStringError::BadPosition,
-                                ));
-                            }
-                        };
-
-                        bit <<= shift;
-                        continue;
-                    }
-                    'p' => unchecked_bitboards[BLACK][PAWN] |= bit,
-                    'r' => unchecked_bitboards[BLACK][ROOK] |= bit,
-                    'n' => unchecked_bitboards[BLACK][KNIGHT] |= bit,
-                    'b' => unchecked_bitboards[BLACK][BISHOP] |= bit,
-                    'q' => unchecked_bitboards[BLACK][QUEEN] |= bit,
-                    'k' => unchecked_bitboards[BLACK][KING] |= bit,
-                    'P' => unchecked_bitboards[WHITE][PAWN] |= bit,
-                    'R' => unchecked_bitboards[WHITE][ROOK] |= bit,
-                    'N' => unchecked_bitboards[WHITE][KNIGHT] |= bit,
-                    'B' => unchecked_bitboards[WHITE][BISHOP] |= bit,
-                    'Q' => unchecked_bitboards[WHITE][QUEEN] |= bit,
-                    'K' => unchecked_bitboards[WHITE][KING] |= bit,
-                    _ => {
-                        return Err(BoardStateCreationError::BadFenString(
-                            FenStringError::BadPosition,
-                        ));
-                    }
-                };
-
-                bit <<= 1;
-            }
+            return Err(FenError::new(
+                FenStringError::MalformedString,
+                field,
+                offset,
+                text,
+                format!("expected {expected} fields, found {}", fields.len()),
+            )
+            .into());
         }
 
-        let side_to_move = match chunks.next() {
-            Some("w") => Color::White,
-            Some("b") => Color::Black,
+        let (offset, placement) = fields[0];
+        let unchecked_bitboards = parse_fen_placement(placement, offset)?;
+
+        let (offset, side) = fields[1];
+        let side_to_move = match side {
+            "w" => Color::White,
+            "b" => Color::Black,
             _ => {
-                return Err(BoardStateCreationError::BadFenString(
+                return Err(FenError::new(
                     FenStringError::BadColor,
-                ));
+                    1,
+                    offset,
+                    side,
+                    "side to move must be `w` or `b`",
+                )
+                .into());
             }
         };
 
-        let Some(castling) = chunks.next() else {
-            return Err(BoardStateCreationError::BadFenString(
-                FenStringError::BadCastling,
-            ));
-        };
-
+        let (offset, castling) = fields[2];
         let mut castling_rights = 0;
 
         if castling != "-" {
-            if castling.chars().count() > 4 {
-                return Err(BoardStateCreationError::BadFenString(
-                    FenStringError::BadCastling,
-                ));
-            }
-
-            let mut chars = castling.chars();
-
-            while let Some(c) = chars.next() {
-                match c {
-                    'K' => castling_rights |= CAN_CASTLE_KINGSIDE_WHITE,
-                    'Q' => castling_rights |= CAN_CASTLE_QUEENSIDE_WHITE,
-                    'k' => castling_rights |= CAN_CASTLE_KINGSIDE_BLACK,
-                    'q' => castling_rights |= CAN_CASTLE_QUEENSIDE_BLACK,
+            for (i, c) in castling.char_indices() {
+                let flag = match c {
+                    'K' => CAN_CASTLE_KINGSIDE_WHITE,
+                    'Q' => CAN_CASTLE_QUEENSIDE_WHITE,
+                    'k' => CAN_CASTLE_KINGSIDE_BLACK,
+                    'q' => CAN_CASTLE_QUEENSIDE_BLACK,
                     _ => {
-                        return Err(BoardStateCreationError::BadFenString(
+                        return Err(FenError::new(
                             FenStringError::BadCastling,
-                        ));
+                            2,
+                            offset + i,
+                            castling,
+                            format!("unexpected character `{c}`, expected `-` or any of `KQkq`"),
+                        )
+                        .into());
                     }
                 };
-            }
-        }
 
-        let Some(en_passant) = chunks.next() else {
-            return Err(BoardStateCreationError::BadFenString(
-                FenStringError::BadEnPassant,
-            ));
-        };
+                if castling_rights & flag != 0 {
+                    return Err(FenError::new(
+                        FenStringError::BadCastling,
+                        2,
+                        offset + i,
+                        castling,
+                        format!("castling right `{c}` appears more than once"),
+                    )
+                    .into());
+                }
 
-        let en_passant_square = square_str_to_index(en_passant);
+                castling_rights |= flag;
+            }
+        }
 
-        let Some(half_move_counter) = chunks.next() else {
-            return Err(BoardStateCreationError::BadFenString(
-                FenStringError::BadHalfTurnCount,
-            ));
+        let (offset, en_passant) = fields[3];
+        let en_passant_square = match en_passant {
+            "-" => None,
+            _ => match en_passant.parse() {
+                Ok(square) => Some(square),
+                Err(_) => {
+                    return Err(FenError::new(
+                        FenStringError::BadEnPassant,
+                        3,
+                        offset,
+                        en_passant,
+                        "en passant square must be `-` or a square such as `e3`",
+                    )
+                    .into());
+                }
+            },
         };
 
-        let fifty_move_rule = match half_move_counter.parse() {
-            Ok(num) => match num {
-                0..100 => num,
+        let fifty_move_rule = match fields.get(4) {
+            None => 0,
+            Some(&(offset, half_move_counter)) => match half_move_counter.parse() {
+                Ok(num) => num,
                 _ => {
-                    return Err(BoardStateCreationError::BadFenString(
+                    return Err(FenError::new(
                         FenStringError::BadHalfTurnCount,
-                    ));
+                        4,
+                        offset,
+                        half_move_counter,
+                        "halfmove clock must be a number from 0 to 255",
+                    )
+                    .into());
                 }
             },
-            _ => {
-                return Err(BoardStateCreationError::BadFenString(
-                    FenStringError::BadHalfTurnCount,
-                ));
-            }
         };
 
-        let Some(turn_counter) = chunks.next() else {
-            return Err(BoardStateCreationError::BadFenString(
-                FenStringError::BadTurnCount,
-            ));
-        };
-
-        let turn_count = match turn_counter.parse() {
-            Ok(num) if num != 0 => num,
-            _ => {
-                return Err(BoardStateCreationError::BadFenString(
-                    FenStringError::BadTurnCount,
-                ));
-            }
+        let turn_count = match fields.get(5) {
+            None => 1,
+            Some(&(offset, turn_counter)) => match turn_counter.parse() {
+                Ok(num) if num != 0 => num,
+                _ => {
+                    return Err(FenError::new(
+                        FenStringError::BadTurnCount,
+                        5,
+                        offset,
+                        turn_counter,
+                        "fullmove number must be a positive number",
+                    )
+                    .into());
+                }
+            },
         };
 
         let position = BitBoards::new(unchecked_bitboards)?;
@@ -250,10 +431,480 @@ impl BoardState {
         ))
     }
 
+    /// Like [`from_fen()`](Self::from_fen()), but also rejects any position that fails
+    /// [`validate()`](Self::validate()).
+    pub fn from_fen_strict(fen: &str) -> Result<Self, BoardStateCreationError> {
+        let board_state = Self::from_fen(fen)?;
+
+        board_state
+            .validate()
+            .map_err(BoardStateCreationError::IllegalPosition)?;
+
+        Ok(board_state)
+    }
+
+    /// Checks that this [`BoardState`] could have arisen in a legal game, returning every
+    /// problem found.
+    ///
+    /// This builds a [`MoveGenerator`]; use [`validate_with()`](Self::validate_with()) to
+    /// check many positions with the same one.
+    pub fn validate(&self) -> Result<(), Vec<PositionError>> {
+        self.validate_with(&MoveGenerator::new())
+    }
+
+    /// Like [`validate()`](Self::validate()), but with the given move generator.
+    pub fn validate_with(&self, move_gen: &MoveGenerator) -> Result<(), Vec<PositionError>> {
+        let mut errors = Vec::new();
+
+        for color in [Color::White, Color::Black] {
+            if self.position.board(color, Piece::King).count() != 1 {
+                errors.push(PositionError::BadKingCount(color));
+            }
+        }
+
+        // The remaining checks need exactly one king of each color.
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        for square in self.position.pawns() & (RANK_1 | RANK_8) {
+            errors.push(PositionError::PawnOnBackRank(square));
+        }
+
+        let (Some(white_king), Some(black_king)) = (
+            self.position.king_white().to_square(),
+            self.position.king_black().to_square(),
+        ) else {
+            unreachable!("king counts are checked above");
+        };
+
+        if !(move_gen.get_king_attacks(white_king) & self.position.king_black()).is_empty() {
+            errors.push(PositionError::AdjacentKings);
+        }
+
+        let (king, enemy_king) = match self.side_to_move {
+            Color::White => (white_king, black_king),
+            Color::Black => (black_king, white_king),
+        };
+
+        let occupancy = self.position.all_boards();
+
+        // Adjacent kings are reported on their own above.
+        let checkers = self.attackers_to(move_gen, enemy_king, self.side_to_move, occupancy);
+
+        if !(checkers & !self.position.kings()).is_empty() {
+            errors.push(PositionError::OpponentInCheck);
+        }
+
+        if let Some(error) = self.validate_checkers(move_gen, king) {
+            errors.push(error);
+        }
+
+        for (has_right, flag, king_start, rook_start) in [
+            (
+                self.has_castling_rights_kingside_white(),
+                CAN_CASTLE_KINGSIDE_WHITE,
+                DEFAULT_KING_WHITE,
+                DEFAULT_ROOKS_WHITE & FILE_H,
+            ),
+            (
+                self.has_castling_rights_queenside_white(),
+                CAN_CASTLE_QUEENSIDE_WHITE,
+                DEFAULT_KING_WHITE,
+                DEFAULT_ROOKS_WHITE & FILE_A,
+            ),
+            (
+                self.has_castling_rights_kingside_black(),
+                CAN_CASTLE_KINGSIDE_BLACK,
+                DEFAULT_KING_BLACK,
+                DEFAULT_ROOKS_BLACK & FILE_H,
+            ),
+            (
+                self.has_castling_rights_queenside_black(),
+                CAN_CASTLE_QUEENSIDE_BLACK,
+                DEFAULT_KING_BLACK,
+                DEFAULT_ROOKS_BLACK & FILE_A,
+            ),
+        ] {
+            let color = if king_start == DEFAULT_KING_WHITE {
+                Color::White
+            } else {
+                Color::Black
+            };
+
+            if has_right
+                && (self.position.board(color, Piece::King) != king_start
+                    || (self.position.board(color, Piece::Rook) & rook_start).is_empty())
+            {
+                errors.push(PositionError::BadCastlingRights(flag));
+            }
+        }
+
+        if let Some(square) = self.en_passant_square
+            && !self.is_valid_en_passant_square(square)
+        {
+            errors.push(PositionError::BadEnPassant(square));
+        }
+
+        for color in [Color::White, Color::Black] {
+            let count = |piece| self.position.board(color, piece).count();
+
+            let promoted = count(Piece::Queen).saturating_sub(1)
+                + count(Piece::Rook).saturating_sub(2)
+                + count(Piece::Bishop).saturating_sub(2)
+                + count(Piece::Knight).saturating_sub(2);
+
+            let pieces = match color {
+                Color::White => self.position.white(),
+                Color::Black => self.position.black(),
+            };
+
+            if count(Piece::Pawn) > 8 || count(Piece::Pawn) + promoted > 8 || pieces.count() > 16 {
+                errors.push(PositionError::TooManyPieces(color));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns a bitboard of every piece of the given color attacking the given square.
+    ///
+    /// Sliding pieces are blocked by the given occupancy rather than the position's, so
+    /// that attacks can be looked up as if pieces had moved or been captured.
+    pub fn attackers_to(
+        &self,
+        move_gen: &MoveGenerator,
+        square: Square,
+        by: Color,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        let open_squares = !occupancy;
+        let board = |piece| self.position.board(by, piece);
+
+        let pawn_attacks = match by {
+            Color::White => move_gen.get_black_pawn_attacks(square),
+            Color::Black => move_gen.get_white_pawn_attacks(square),
+        };
+
+        (pawn_attacks & board(Piece::Pawn)
+            | move_gen.get_knight_attacks(square) & board(Piece::Knight)
+            | move_gen.get_king_attacks(square) & board(Piece::King)
+            | MoveGenerator::get_rook_attacks(square, open_squares)
+                & (board(Piece::Rook) | board(Piece::Queen))
+            | MoveGenerator::get_bishop_attacks(square, open_squares)
+                & (board(Piece::Bishop) | board(Piece::Queen)))
+            & occupancy
+    }
+
+    /// Returns a bitboard of every piece of either color attacking the given square. See
+    /// [`attackers_to()`](Self::attackers_to()).
+    pub fn all_attackers_to(
+        &self,
+        move_gen: &MoveGenerator,
+        square: Square,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        self.attackers_to(move_gen, square, Color::White, occupancy)
+            | self.attackers_to(move_gen, square, Color::Black, occupancy)
+    }
+
+    /// Returns `true` if a piece of the given color attacks the given square, and `false`
+    /// otherwise.
+    pub fn is_square_attacked(&self, move_gen: &MoveGenerator, square: Square, by: Color) -> bool {
+        !self
+            .attackers_to(move_gen, square, by, self.position.all_boards())
+            .is_empty()
+    }
+
+    /// Returns a bitboard of the enemy pieces giving check to the side to move.
+    pub fn checkers(&self, move_gen: &MoveGenerator) -> Bitboard {
+        let Some(king) = self
+            .position
+            .board(self.side_to_move, Piece::King)
+            .to_square()
+        else {
+            return Bitboard::EMPTY;
+        };
+
+        self.attackers_to(
+            move_gen,
+            king,
+            self.side_to_move.enemy(),
+            self.position.all_boards(),
+        )
+    }
+
+    /// Returns the pieces of the given color pinned to their king, along with the enemy
+    /// pieces pinning them.
+    pub fn pins(&self, move_gen: &MoveGenerator, color: Color) -> Vec<Pin> {
+        let Some(king) = self.position.board(color, Piece::King).to_square() else {
+            return Vec::new();
+        };
+
+        let enemy = |piece| self.position.board(color.enemy(), piece);
+        let occupancy = self.position.all_boards();
+        let friendly_pieces = self.position.pieces(color);
+
+        // The enemy sliders that would attack the king on an empty board.
+        let pinners = MoveGenerator::get_rook_attacks(king, Bitboard::FULL)
+            & (enemy(Piece::Rook) | enemy(Piece::Queen))
+            | MoveGenerator::get_bishop_attacks(king, Bitboard::FULL)
+                & (enemy(Piece::Bishop) | enemy(Piece::Queen));
+
+        pinners
+            .into_iter()
+            .filter_map(|pinner| {
+                let between = move_gen.between(king, pinner);
+                let blockers = between & occupancy;
+
+                if blockers.count() != 1 || (blockers & friendly_pieces).is_empty() {
+                    return None;
+                }
+
+                Some(Pin {
+                    pinned: blockers.to_square()?,
+                    pinner,
+                    ray: between | pinner.bitboard(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns a bitboard of the pieces of the given color pinned to their king. See
+    /// [`pins()`](Self::pins()).
+    pub fn pinned(&self, move_gen: &MoveGenerator, color: Color) -> Bitboard {
+        self.pins(move_gen, color)
+            .into_iter()
+            .map(|pin| pin.pinned)
+            .collect()
+    }
+
+    /// Returns a bitboard of the sliding pieces of the given color that attack the given
+    /// square through exactly one piece of either color, i.e. that would attack it if the
+    /// piece in the way moved.
+    pub fn xray_attackers(&self, square: Square, by: Color, occupancy: Bitboard) -> Bitboard {
+        let board = |piece| self.position.board(by, piece);
+
+        let rook_attacks = MoveGenerator::get_rook_attacks(square, !occupancy);
+        let rook_xrays =
+            MoveGenerator::get_rook_attacks(square, !(occupancy ^ rook_attacks & occupancy))
+                & !rook_attacks;
+
+        let bishop_attacks = MoveGenerator::get_bishop_attacks(square, !occupancy);
+        let bishop_xrays =
+            MoveGenerator::get_bishop_attacks(square, !(occupancy ^ bishop_attacks & occupancy))
+                & !bishop_attacks;
+
+        rook_xrays & (board(Piece::Rook) | board(Piece::Queen))
+            | bishop_xrays & (board(Piece::Bishop) | board(Piece::Queen))
+    }
+
+    /// Returns the [`Material`] on the board.
+    pub fn material(&self) -> Material {
+        Material::from_bitboards(&self.position)
+    }
+
+    /// Returns the material signature of the position, such as `KRPvKR`, with white's
+    /// pieces first.
+    pub fn material_signature(&self) -> String {
+        self.material().to_string()
+    }
+
+    /// Returns the game phase computed from the non-pawn material, from [`MAX_PHASE`] in the
+    /// opening down to `0` once only kings and pawns are left.
+    ///
+    /// [`MAX_PHASE`]: crate::eval::MAX_PHASE
+    pub fn phase(&self) -> i32 {
+        eval::phase(self)
+    }
+
+    /// Returns the difference between the number of white and black pieces of the given
+    /// type: positive when white has more.
+    pub fn material_imbalance(&self, piece: Piece) -> i32 {
+        self.material().imbalance(piece)
+    }
+
+    /// Returns the kind of endgame on the board, or [`None`] if there's still too much
+    /// material for the position to be an endgame.
+    pub fn endgame(&self) -> Option<Endgame> {
+        if self.phase() > ENDGAME_PHASE {
+            return None;
+        }
+
+        let position = &self.position;
+        let knights = position.knights();
+        let bishops = position.bishops();
+        let rooks = position.rooks();
+        let queens = position.queens();
+        let minors = knights | bishops;
+
+        let endgame = if (minors | rooks | queens).is_empty() {
+            Endgame::Pawn
+        } else if !queens.is_empty() {
+            if (minors | rooks).is_empty() {
+                Endgame::Queen
+            } else {
+                Endgame::Mixed
+            }
+        } else if !rooks.is_empty() {
+            if minors.is_empty() {
+                Endgame::Rook
+            } else {
+                Endgame::RookAndMinor
+            }
+        } else if bishops.is_empty() {
+            Endgame::Knight
+        } else if knights.is_empty() {
+            let white = position.bishops_white();
+            let black = position.bishops_black();
+
+            if white.count() == 1 && black.count() == 1 && (bishops & LIGHT_SQUARES).count() == 1 {
+                Endgame::OppositeColoredBishops
+            } else {
+                Endgame::Bishop
+            }
+        } else {
+            Endgame::Minor
+        };
+
+        Some(endgame)
+    }
+
+    /// Returns `true` if neither side has the material to force mate, such as KNNvK, KBvK
+    /// or a lone minor piece against another, and `false` otherwise.
+    ///
+    /// This is a heuristic for evaluation: some of these positions can still end in mate
+    /// if the defender blunders, so they aren't drawn by the rules. See
+    /// [`is_insufficient_material()`](Self::is_insufficient_material()) for that.
+    pub fn is_drawn_material(&self) -> bool {
+        let position = &self.position;
+
+        if !(position.pawns() | position.rooks() | position.queens()).is_empty() {
+            return false;
+        }
+
+        let minors =
+            |color| position.board(color, Piece::Knight) | position.board(color, Piece::Bishop);
+        let (white, black) = (minors(Color::White), minors(Color::Black));
+        let bishops = position.bishops();
+
+        // Bishops all on the same color can never mate, whatever their number.
+        let same_colored_bishops = position.knights().is_empty()
+            && ((bishops & LIGHT_SQUARES).is_empty() || (bishops & !LIGHT_SQUARES).is_empty());
+
+        (white | black).count() <= 1
+            || white.count() == 1 && black.count() == 1
+            || white.is_empty() && black == position.knights_black() && black.count() == 2
+            || black.is_empty() && white == position.knights_white() && white.count() == 2
+            || same_colored_bishops
+    }
+
+    /// Returns `true` if no sequence of legal moves can end in mate, so that the game is
+    /// drawn by the rules: KvK, a lone minor piece against a bare king, or bishops all on
+    /// squares of the same color. Returns `false` otherwise, even for material that can't
+    /// force mate, such as KNNvK, as [`is_drawn_material()`](Self::is_drawn_material())
+    /// reports.
+    pub fn is_insufficient_material(&self) -> bool {
+        let position = &self.position;
+
+        if !(position.pawns() | position.rooks() | position.queens()).is_empty() {
+            return false;
+        }
+
+        let bishops = position.bishops();
+
+        (position.knights() | bishops).count() <= 1
+            || position.knights().is_empty()
+                && ((bishops & LIGHT_SQUARES).is_empty() || (bishops & !LIGHT_SQUARES).is_empty())
+    }
+
+    /// Checks that the pieces giving check to the side to move could have done so with the
+    /// previous move.
+    fn validate_checkers(&self, move_gen: &MoveGenerator, king: Square) -> Option<PositionError> {
+        let enemy = self.side_to_move.enemy();
+        let occupancy = self.position.all_boards();
+        let checkers = self.attackers_to(move_gen, king, enemy, occupancy);
+
+        match checkers.count() {
+            0 => return None,
+            1 => (),
+            2 => {
+                let (Some(first), Some(second)) = (checkers.lsb(), checkers.msb()) else {
+                    unreachable!("there are two checkers");
+                };
+                let sliders =
+                    self.position.rooks() | self.position.bishops() | self.position.queens();
+
+                // One of the checks must have been discovered by a slider, and the two
+                // checkers can't share a line with the king.
+                if (checkers & sliders).is_empty() || are_aligned(first, second, king) {
+                    return Some(PositionError::ImpossibleCheck);
+                }
+            }
+            count => return Some(PositionError::TooManyCheckers(count)),
+        }
+
+        let en_passant_square = self.en_passant_square?;
+
+        // After a double pawn push, the only possible checks come from the pushed pawn or
+        // from a slider uncovered by it.
+        let (from_rank, to_rank) = match enemy {
+            Color::White => (Rank::Second, Rank::Fourth),
+            Color::Black => (Rank::Seventh, Rank::Fifth),
+        };
+        let pushed_from = Square::new(en_passant_square.file(), from_rank);
+        let pushed_to = Square::new(en_passant_square.file(), to_rank);
+
+        if checkers.has_many() {
+            return Some(PositionError::ImpossibleCheck);
+        }
+
+        let occupancy_before = occupancy.without(pushed_to).with(pushed_from);
+
+        if checkers != pushed_to.bitboard()
+            && !self
+                .attackers_to(move_gen, king, enemy, occupancy_before)
+                .without(pushed_to)
+                .is_empty()
+        {
+            return Some(PositionError::ImpossibleCheck);
+        }
+
+        None
+    }
+
+    /// Returns `true` if an enemy pawn could have just double pushed past the given square.
+    fn is_valid_en_passant_square(&self, square: Square) -> bool {
+        let (rank, pawns, to_rank, from_rank) = match self.side_to_move {
+            Color::White => (
+                Rank::Sixth,
+                self.position.pawns_black(),
+                Rank::Fifth,
+                Rank::Seventh,
+            ),
+            Color::Black => (
+                Rank::Third,
+                self.position.pawns_white(),
+                Rank::Fourth,
+                Rank::Second,
+            ),
+        };
+
+        let pushed_to = Square::new(square.file(), to_rank);
+        let pushed_from = Square::new(square.file(), from_rank);
+        let occupancy = self.position.all_boards();
+
+        square.rank() == rank
+            && pawns.contains(pushed_to)
+            && !occupancy.contains(square)
+            && !occupancy.contains(pushed_from)
+    }
+
     /// Returns a FEN string of this [`BoardState`].
-    /// # Panics
-    /// Currently calls [`expect()`](std::option::Option::expect) on [`index_to_square_str()`]. This should not fail,
-    /// as the [`BoardState`] representation should always be valid.
     pub fn to_fen(&self) -> String {
         let mut fen: Vec<String> = Vec::with_capacity(6);
 
@@ -293,7 +944,7 @@ impl BoardState {
         fen.push(castling_rights);
 
         let en_passant = match self.en_passant_square {
-            Some(square) => index_to_square_str(square).expect("Invalid square"),
+            Some(square) => square.to_string(),
             None => '-'.to_string(),
         };
 
@@ -305,19 +956,26 @@ impl BoardState {
         fen.join(" ")
     }
 
+    /// Returns `true` if both states have the same piece placement, side to move, castling
+    /// rights and en passant square, i.e. they count as the same position for repetitions.
+    pub fn is_same_position(&self, other: &BoardState) -> bool {
+        self.position == other.position
+            && self.side_to_move == other.side_to_move
+            && self.castling_rights == other.castling_rights
+            && self.en_passant_square == other.en_passant_square
+    }
+
     /// Returns the piece placement section of a FEN string of this [`BoardState`].
     pub fn bitboard_to_fen(&self) -> String {
         let mut ranks = vec![String::with_capacity(8); 8];
 
-        for (rank, rank_square) in ranks.iter_mut().rev().zip((0..64).step_by(8)) {
+        for (rank_str, rank) in ranks.iter_mut().rev().zip(Rank::ALL) {
             let mut empty_squares = 0;
 
-            for file in 0..8 {
-                let square = rank_square + file;
-
-                if let Some((color, piece)) = self.position.piece_at(square) {
+            for file in File::ALL {
+                if let Some((color, piece)) = self.position.piece_at(Square::new(file, rank)) {
                     if empty_squares > 0 {
-                        rank.push_str(&empty_squares.to_string());
+                        rank_str.push_str(&empty_squares.to_string());
                         empty_squares = 0;
                     }
 
@@ -336,14 +994,14 @@ impl BoardState {
                         (Color::Black, Piece::King) => 'k',
                     };
 
-                    rank.push(c);
+                    rank_str.push(c);
                 } else {
                     empty_squares += 1;
                 }
             }
 
             if empty_squares > 0 {
-                rank.push_str(&empty_squares.to_string());
+                rank_str.push_str(&empty_squares.to_string());
             }
         }
 
@@ -421,7 +1079,8 @@ impl BoardState {
                 } else if mv.is_promotion() {
                     self.position.promote_white(mv);
                 } else if mv.is_double_pawn_push() {
-                    self.en_passant_square = Some(mv.get_initial_square() + 8);
+                    self.position.move_piece(mv);
+                    self.en_passant_square = mv.get_initial_square().offset(0, 1);
                 } else {
                     self.position.move_piece(mv);
                 }
@@ -441,7 +1100,8 @@ impl BoardState {
                 } else if mv.is_promotion() {
                     self.position.promote_black(mv);
                 } else if mv.is_double_pawn_push() {
-                    self.en_passant_square = Some(mv.get_initial_square() - 8);
+                    self.position.move_piece(mv);
+                    self.en_passant_square = mv.get_initial_square().offset(0, -1);
                 } else {
                     self.position.move_piece(mv);
                 }
@@ -456,30 +1116,237 @@ impl BoardState {
         if moved_piece == Piece::Pawn || mv.is_capture() {
             self.fifty_move_rule = 0;
         } else {
-            self.fifty_move_rule += 1;
+            self.fifty_move_rule = self.fifty_move_rule.saturating_add(1);
         }
 
-        if self.position.rooks_white() & (DEFAULT_ROOKS_WHITE & FILE_A) == 0 {
+        if (self.position.rooks_white() & (DEFAULT_ROOKS_WHITE & FILE_A)).is_empty() {
             self.remove_castling_rights_queenside_white();
         }
 
-        if self.position.rooks_black() & (DEFAULT_BISHOPS_BLACK & FILE_A) == 0 {
+        if (self.position.rooks_black() & (DEFAULT_ROOKS_BLACK & FILE_A)).is_empty() {
             self.remove_castling_rights_queenside_black();
         }
 
-        if self.position.rooks_white() & (DEFAULT_ROOKS_WHITE & FILE_H) == 0 {
+        if (self.position.rooks_white() & (DEFAULT_ROOKS_WHITE & FILE_H)).is_empty() {
             self.remove_castling_rights_kingside_white();
         }
 
-        if self.position.rooks_black() & (DEFAULT_ROOKS_BLACK & FILE_H) == 0 {
+        if (self.position.rooks_black() & (DEFAULT_ROOKS_BLACK & FILE_H)).is_empty() {
             self.remove_castling_rights_kingside_black();
         }
 
-        self.turn_count += 1;
+        if self.side_to_move == Color::Black {
+            self.turn_count += 1;
+        }
+
         self.side_to_move = self.side_to_move.enemy();
     }
 }
 
+/// Serialises the position as a FEN string in human-readable formats, and packed in
+/// [`PACKED_BOARD_SIZE`](crate::encoding::PACKED_BOARD_SIZE) bytes by
+/// [`pack_board()`](crate::encoding::pack_board()) in the others. Deserialising rejects
+/// illegal positions, as [`from_fen_strict()`](BoardState::from_fen_strict()) does.
+#[cfg(feature = "serde")]
+impl serde::Serialize for BoardState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error as _;
+
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_fen())
+        } else {
+            let packed = crate::encoding::pack_board(self).map_err(S::Error::custom)?;
+            serializer.serialize_bytes(&packed)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BoardState {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error as _;
+
+        let board_state = if deserializer.is_human_readable() {
+            let fen = String::deserialize(deserializer)?;
+            Self::from_fen(&fen).map_err(D::Error::custom)?
+        } else {
+            let packed = deserializer.deserialize_bytes(PackedBoardVisitor)?;
+            crate::encoding::unpack_board(&packed).map_err(D::Error::custom)?
+        };
+
+        // Histories hold a position per move, so they share a generator to validate them.
+        board_state
+            .validate_with(MoveGenerator::shared())
+            .map_err(|errors| D::Error::custom(BoardStateCreationError::IllegalPosition(errors)))?;
+
+        Ok(board_state)
+    }
+}
+
+/// Reads a packed position, from bytes or from a sequence of them.
+#[cfg(feature = "serde")]
+struct PackedBoardVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for PackedBoardVisitor {
+    type Value = [u8; crate::encoding::PACKED_BOARD_SIZE];
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a position packed in {} bytes",
+            crate::encoding::PACKED_BOARD_SIZE
+        )
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        bytes
+            .try_into()
+            .map_err(|_| E::invalid_length(bytes.len(), &self))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        use serde::de::Error as _;
+
+        let mut packed = [0; crate::encoding::PACKED_BOARD_SIZE];
+
+        for (i, byte) in packed.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+        }
+
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(A::Error::invalid_length(packed.len() + 1, &self));
+        }
+
+        Ok(packed)
+    }
+}
+
+/// Splits a FEN string on ASCII whitespace, keeping the byte offset of each field.
+fn fen_fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::with_capacity(6);
+    let mut start = None;
+
+    for (i, c) in fen.char_indices() {
+        match (c.is_ascii_whitespace(), start) {
+            (true, Some(field_start)) => {
+                fields.push((field_start, &fen[field_start..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => (),
+        }
+    }
+
+    if let Some(field_start) = start {
+        fields.push((field_start, &fen[field_start..]));
+    }
+
+    fields
+}
+
+/// Parses the piece placement field of a FEN string into unchecked bitboards. `offset` is
+/// the byte offset of the field within the whole FEN string.
+fn parse_fen_placement(placement: &str, offset: usize) -> Result<[[Bitboard; 6]; 2], FenError> {
+    let bad_position = |offset, text: &str, message: String| {
+        FenError::new(FenStringError::BadPosition, 0, offset, text, message)
+    };
+
+    let rank_count = placement.split('/').count();
+
+    if rank_count != 8 {
+        return Err(bad_position(
+            offset,
+            placement,
+            format!("expected 8 ranks separated by `/`, found {rank_count}"),
+        ));
+    }
+
+    let mut unchecked_bitboards = [[Bitboard::EMPTY; 6]; 2];
+    let mut rank_offset = offset;
+
+    for (rank_name, rank) in Rank::ALL.into_iter().rev().zip(placement.split('/')) {
+        let mut file = 0;
+
+        for (i, c) in rank.char_indices() {
+            let (color, piece) = match c {
+                '1'..='8' => {
+                    file += c as usize - '0' as usize;
+
+                    if file > 8 {
+                        return Err(bad_position(
+                            rank_offset + i,
+                            rank,
+                            format!("rank {rank_name} has more than 8 squares"),
+                        ));
+                    }
+
+                    continue;
+                }
+                'p' => (BLACK, PAWN),
+                'r' => (BLACK, ROOK),
+                'n' => (BLACK, KNIGHT),
+                'b' => (BLACK, BISHOP),
+                'q' => (BLACK, QUEEN),
+                'k' => (BLACK, KING),
+                'P' => (WHITE, PAWN),
+                'R' => (WHITE, ROOK),
+                'N' => (WHITE, KNIGHT),
+                'B' => (WHITE, BISHOP),
+                'Q' => (WHITE, QUEEN),
+                'K' => (WHITE, KING),
+                _ => {
+                    return Err(bad_position(
+                        rank_offset + i,
+                        rank,
+                        format!("unexpected character `{c}`, expected a piece letter or 1-8"),
+                    ));
+                }
+            };
+
+            if file >= 8 {
+                return Err(bad_position(
+                    rank_offset + i,
+                    rank,
+                    format!("rank {rank_name} has more than 8 squares"),
+                ));
+            }
+
+            unchecked_bitboards[color][piece] |= Square::new(File::ALL[file], rank_name).bitboard();
+            file += 1;
+        }
+
+        if file != 8 {
+            return Err(bad_position(
+                rank_offset,
+                rank,
+                format!("rank {rank_name} has {file} squares, expected 8"),
+            ));
+        }
+
+        rank_offset += rank.len() + 1;
+    }
+
+    Ok(unchecked_bitboards)
+}
+
+/// Returns `true` if the three squares lie on the same rank, file or diagonal.
+fn are_aligned(a: Square, b: Square, c: Square) -> bool {
+    let (a_rank, a_file) = (a.rank() as i8, a.file() as i8);
+    let (b_rank, b_file) = (b.rank() as i8, b.file() as i8);
+    let (c_rank, c_file) = (c.rank() as i8, c.file() as i8);
+
+    let (rank_delta, file_delta) = (b_rank - a_rank, b_file - a_file);
+
+    if rank_delta != 0 && file_delta != 0 && rank_delta.abs() != file_delta.abs() {
+        return false;
+    }
+
+    (c_rank - a_rank) * file_delta == (c_file - a_file) * rank_delta
+}
+
 #[cfg(test)]
 mod tests {
     use crate::bitboards::bitboard_constants::starting_positions::*;
@@ -490,21 +1357,21 @@ mod tests {
     fn test_from_fen() {
         let fen = "rnbq1bnr/ppppkppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR w - - 2 5";
 
-        let mut position = [[0; 6]; 2];
+        let mut position = [[Bitboard::EMPTY; 6]; 2];
 
-        position[WHITE][PAWN] = 0b00010000_00000000_11101111 << 8;
+        position[WHITE][PAWN] = Bitboard(0b00010000_00000000_11101111 << 8);
         position[WHITE][ROOK] = DEFAULT_ROOKS_WHITE;
         position[WHITE][KNIGHT] = DEFAULT_KNIGHTS_WHITE;
         position[WHITE][BISHOP] = DEFAULT_BISHOPS_WHITE;
         position[WHITE][QUEEN] = DEFAULT_QUEENS_WHITE;
-        position[WHITE][KING] = 0b00010000 << 8;
+        position[WHITE][KING] = Bitboard(0b00010000 << 8);
 
-        position[BLACK][PAWN] = 0b11101111_00000000_00010000 << 32;
+        position[BLACK][PAWN] = Bitboard(0b11101111_00000000_00010000 << 32);
         position[BLACK][ROOK] = DEFAULT_ROOKS_BLACK;
         position[BLACK][KNIGHT] = DEFAULT_KNIGHTS_BLACK;
         position[BLACK][BISHOP] = DEFAULT_BISHOPS_BLACK;
         position[BLACK][QUEEN] = DEFAULT_QUEENS_BLACK;
-        position[BLACK][KING] = 0b00010000 << 48;
+        position[BLACK][KING] = Bitboard(0b00010000 << 48);
 
         let board_state = BoardState::new(
             Color::White,
@@ -538,4 +1405,274 @@ mod tests {
 
         assert_eq!(board_state.to_fen(), fen.to_string());
     }
+
+    fn validation_errors(fen: &str) -> Vec<PositionError> {
+        match BoardState::from_fen(fen).unwrap().validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(BoardState::default().validate(), Ok(()));
+
+        assert_eq!(
+            validation_errors(
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+            ),
+            vec![]
+        );
+        assert_eq!(
+            validation_errors("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"),
+            vec![PositionError::PawnOnBackRank(Square::A1)]
+        );
+        assert_eq!(
+            validation_errors("8/8/8/3kK3/8/8/8/8 w - - 0 1"),
+            vec![PositionError::AdjacentKings]
+        );
+        assert_eq!(validation_errors("4k3/8/8/8/8/8/8/4KR2 w - - 0 1"), vec![]);
+        assert_eq!(
+            validation_errors("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"),
+            vec![PositionError::OpponentInCheck]
+        );
+        assert_eq!(
+            validation_errors("4k3/8/8/8/8/3n1n2/8/4K3 w - - 0 1"),
+            vec![PositionError::ImpossibleCheck]
+        );
+        assert_eq!(
+            validation_errors("4r3/8/8/8/4K3/8/8/4r2k w - - 0 1"),
+            vec![PositionError::ImpossibleCheck]
+        );
+        assert_eq!(
+            validation_errors("4k3/8/8/8/1b6/5n2/8/r3K3 w - - 0 1"),
+            vec![PositionError::TooManyCheckers(3)]
+        );
+        assert_eq!(
+            validation_errors("4k3/8/8/8/8/5n2/8/r3K3 w - - 0 1"),
+            vec![]
+        );
+        assert_eq!(
+            validation_errors("r3k3/8/8/8/8/8/8/4K2R w KQkq - 0 1"),
+            vec![
+                PositionError::BadCastlingRights(CAN_CASTLE_QUEENSIDE_WHITE),
+                PositionError::BadCastlingRights(CAN_CASTLE_KINGSIDE_BLACK),
+            ]
+        );
+        assert_eq!(
+            validation_errors("4k3/8/8/4p3/8/8/8/4K3 w - e6 0 1"),
+            vec![]
+        );
+        assert_eq!(
+            validation_errors("4k3/8/8/4p3/8/8/8/4K3 w - d6 0 1"),
+            vec![PositionError::BadEnPassant(Square::D6)]
+        );
+        assert_eq!(
+            validation_errors("4k3/8/8/4p3/8/8/8/4K3 b - e6 0 1"),
+            vec![PositionError::BadEnPassant(Square::E6)]
+        );
+        assert_eq!(
+            validation_errors("8/7k/8/QQQQQ3/QQQQ4/8/P7/4K3 w - - 0 1"),
+            vec![PositionError::TooManyPieces(Color::White)]
+        );
+    }
+
+    #[test]
+    fn test_from_fen_strict() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(BoardState::from_fen_strict(fen), Ok(BoardState::default()));
+
+        let fen = "8/8/8/3kK3/8/8/8/8 w - - 0 1";
+        assert!(BoardState::from_fen(fen).is_ok());
+        assert_eq!(
+            BoardState::from_fen_strict(fen),
+            Err(BoardStateCreationError::IllegalPosition(vec![
+                PositionError::AdjacentKings
+            ]))
+        );
+    }
+
+    fn fen_error(fen: &str) -> FenError {
+        match BoardState::from_fen(fen) {
+            Err(BoardStateCreationError::BadFenString(err)) => err,
+            other => panic!("expected a FEN error for {fen}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_fen_errors() {
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -");
+        assert_eq!(err.kind, FenStringError::MalformedString);
+        assert_eq!(err.field, 4);
+
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 extra");
+        assert_eq!(err.kind, FenStringError::MalformedString);
+        assert_eq!((err.field, err.offset, err.text.as_str()), (6, 57, "extra"));
+
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN w KQkq - 0 1");
+        assert_eq!(err.kind, FenStringError::BadPosition);
+        assert_eq!(
+            (err.field, err.offset, err.text.as_str()),
+            (0, 35, "RNBQKBN")
+        );
+
+        let err = fen_error("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(err.kind, FenStringError::BadPosition);
+        assert_eq!(err.offset, 18);
+
+        let err = fen_error("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(err.kind, FenStringError::BadPosition);
+        assert_eq!(err.offset, 13);
+
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1");
+        assert_eq!(err.kind, FenStringError::BadPosition);
+
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR  W KQkq - 0 1");
+        assert_eq!(err.kind, FenStringError::BadColor);
+        assert_eq!((err.field, err.offset), (1, 45));
+
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1");
+        assert_eq!(err.kind, FenStringError::BadCastling);
+        assert_eq!(err.offset, 48);
+        assert_eq!(
+            err.to_string(),
+            "bad FEN castling rights (field 3) at byte 48 `KQxq`: \
+             unexpected character `x`, expected `-` or any of `KQkq`"
+        );
+
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQKq - 0 1");
+        assert_eq!(err.kind, FenStringError::BadCastling);
+        assert_eq!(err.offset, 48);
+
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1");
+        assert_eq!(err.kind, FenStringError::BadEnPassant);
+        assert_eq!((err.field, err.offset, err.text.as_str()), (3, 51, "e9"));
+
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 256 1");
+        assert_eq!(err.kind, FenStringError::BadHalfTurnCount);
+        assert_eq!(err.offset, 53);
+
+        // Clocks past the fifty-move point are kept, since `to_fen()` writes them.
+        let fen = "4k3/8/8/8/8/8/8/4K2R b - - 100 80";
+        assert_eq!(BoardState::from_fen(fen).unwrap().to_fen(), fen);
+
+        let err = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0");
+        assert_eq!(err.kind, FenStringError::BadTurnCount);
+        assert_eq!(err.offset, 55);
+    }
+
+    #[test]
+    fn test_from_fen_lenient() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        assert_eq!(BoardState::from_fen_lenient(fen), Ok(BoardState::default()));
+
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3";
+        assert_eq!(
+            BoardState::from_fen_lenient(fen).unwrap().fifty_move_rule,
+            3
+        );
+
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq";
+        assert!(BoardState::from_fen_lenient(fen).is_err());
+    }
+
+    #[test]
+    fn test_attack_queries() {
+        let move_gen = MoveGenerator::new();
+        let board_state = BoardState::from_fen("4r1k1/8/8/8/1b5q/8/3N4/r2RK3 w - - 0 1").unwrap();
+        let occupancy = board_state.position.all_boards();
+        let squares = |squares: &[Square]| squares.iter().copied().collect::<Bitboard>();
+
+        assert_eq!(
+            board_state.checkers(&move_gen),
+            squares(&[Square::H4, Square::E8])
+        );
+        assert_eq!(
+            board_state.all_attackers_to(&move_gen, Square::C1, occupancy),
+            squares(&[Square::A1, Square::D1])
+        );
+        assert!(board_state.is_square_attacked(&move_gen, Square::F2, Color::Black));
+        assert!(!board_state.is_square_attacked(&move_gen, Square::A8, Color::White));
+
+        assert_eq!(
+            board_state.pins(&move_gen, Color::White),
+            [
+                Pin {
+                    pinned: Square::D1,
+                    pinner: Square::A1,
+                    ray: squares(&[Square::A1, Square::B1, Square::C1, Square::D1]),
+                },
+                Pin {
+                    pinned: Square::D2,
+                    pinner: Square::B4,
+                    ray: squares(&[Square::B4, Square::C3, Square::D2]),
+                },
+            ]
+        );
+        assert_eq!(board_state.pinned(&move_gen, Color::Black), Bitboard::EMPTY);
+
+        assert_eq!(
+            board_state.xray_attackers(Square::E1, Color::Black, occupancy),
+            squares(&[Square::A1, Square::B4])
+        );
+    }
+
+    #[test]
+    fn test_material_queries() {
+        let endgame = |fen| BoardState::from_fen(fen).unwrap().endgame();
+        let drawn = |fen| BoardState::from_fen(fen).unwrap().is_drawn_material();
+        let dead = |fen| {
+            BoardState::from_fen(fen)
+                .unwrap()
+                .is_insufficient_material()
+        };
+
+        let board_state = BoardState::from_fen("3rk3/8/8/8/8/4P3/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board_state.material_signature(), "KRPvKR");
+        assert_eq!(board_state.phase(), 4);
+        assert_eq!(board_state.material_imbalance(Piece::Pawn), 1);
+        assert_eq!(board_state.material_imbalance(Piece::Rook), 0);
+        assert_eq!(board_state.endgame(), Some(Endgame::Rook));
+
+        assert_eq!(BoardState::default().phase(), 24);
+        assert_eq!(BoardState::default().endgame(), None);
+        assert_eq!(
+            endgame("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1"),
+            Some(Endgame::Pawn)
+        );
+        assert_eq!(
+            endgame("4k1b1/4p3/8/8/8/8/4P3/2B1K3 w - - 0 1"),
+            Some(Endgame::OppositeColoredBishops)
+        );
+        assert_eq!(
+            endgame("2b1k3/4p3/8/8/8/8/4P3/4KB2 w - - 0 1"),
+            Some(Endgame::Bishop)
+        );
+        assert_eq!(
+            endgame("4k3/4p3/8/8/8/8/4P3/1NB1K3 w - - 0 1"),
+            Some(Endgame::Minor)
+        );
+        assert_eq!(
+            endgame("3qk3/8/8/8/8/8/8/3QK2R w - - 0 1"),
+            Some(Endgame::Mixed)
+        );
+
+        assert!(drawn("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(drawn("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(drawn("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1"));
+        assert!(drawn("4kn2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(drawn("2b1k3/8/8/8/8/8/8/B3K3 w - - 0 1"));
+        assert!(!drawn("4k3/8/8/8/8/8/8/1BB1K3 w - - 0 1"));
+        assert!(!drawn("4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1"));
+        assert!(!drawn("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+
+        assert!(dead("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(dead("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(dead("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1"));
+        assert!(dead("1b2k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!dead("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1"));
+        assert!(!dead("4kn2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!dead("2b1k3/8/8/8/8/8/8/B3K3 w - - 0 1"));
+        assert!(!dead("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+    }
 }
@@ -0,0 +1,567 @@
+//! Self-play data generation, producing positions labelled with search scores and game
+//! results to tune evaluations and train networks on.
+//!
+//! Each game starts from the starting position or a book opening, plays a few random
+//! plies so that games differ, then lets the engine play itself with a fixed node budget.
+//! Searches limited by nodes alone are deterministic, so each thread's games only depend on
+//! its seed: the same seed and thread count always reproduce the same dataset.
+//!
+//! # Binary format
+//!
+//! The binary format is a sequence of [`BINARY_RECORD_SIZE`]-byte records:
+//!
+//! - the position packed in [`PACKED_BOARD_SIZE`] bytes by [`encoding::pack_board()`]
+//! - the score from white's point of view as a little-endian `i16`
+//! - the result from white's point of view: `0` for a loss, `1` for a draw and `2` for a
+//!   win
+//! - a zero byte
+
+use crate::Color;
+use crate::boardstate::BoardState;
+use crate::encoding::{self, PACKED_BOARD_SIZE};
+use crate::eval;
+use crate::game::{Game, Outcome};
+use crate::movegen::MoveGenerator;
+use crate::nnue::{Network, TrainingRecord};
+use crate::pawns::PawnHashTable;
+use crate::search::{self, SearchLimits};
+use crate::tournament::Opening;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+/// The number of nodes each move is searched for by default.
+pub const DEFAULT_NODES: u64 = 5_000;
+
+/// The number of random plies played at the start of each game by default.
+pub const DEFAULT_RANDOM_PLIES: u32 = 8;
+
+/// Self-play games still going after this many plies are scored as draws, by default.
+pub const MAX_GAME_PLIES: usize = 400;
+
+/// The size of a record in the binary format.
+pub const BINARY_RECORD_SIZE: usize = 36;
+
+/// The number of times a game start is drawn before giving up on it, when the openings or
+/// the random plies keep ending the game.
+const MAX_START_ATTEMPTS: u32 = 100;
+
+/// How the records are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DataFormat {
+    /// One [`TrainingRecord`] per line, as `<fen> | <score> | <result>`.
+    #[default]
+    Text,
+    /// Fixed-size records, see the [module documentation](self).
+    Binary,
+}
+
+impl FromStr for DataFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "binary" => Ok(Self::Binary),
+            _ => Err(format!("unknown format `{s}`, expected `text` or `binary`")),
+        }
+    }
+}
+
+/// The settings of a data generation run.
+#[derive(Debug, Clone)]
+pub struct DatagenConfig {
+    /// The number of games to play.
+    pub games: u32,
+    /// The number of games played at once.
+    pub threads: usize,
+    /// The node budget of each search.
+    pub nodes: u64,
+    /// The number of random plies played before the engine takes over.
+    pub random_plies: u32,
+    /// The number of plies after which games are scored as draws.
+    pub max_plies: usize,
+    /// The openings games start from, picked at random, or the starting position if empty.
+    pub openings: Vec<Opening>,
+    pub seed: u64,
+    pub format: DataFormat,
+}
+
+impl Default for DatagenConfig {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            threads: 1,
+            nodes: DEFAULT_NODES,
+            random_plies: DEFAULT_RANDOM_PLIES,
+            max_plies: MAX_GAME_PLIES,
+            openings: Vec::new(),
+            seed: 0,
+            format: DataFormat::default(),
+        }
+    }
+}
+
+/// The progress of a data generation run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DatagenStats {
+    /// The number of games written.
+    pub games: u32,
+    /// The number of positions written.
+    pub positions: u64,
+    /// The number of games left empty because no start was found that doesn't end the game.
+    pub skipped: u32,
+}
+
+/// A position of a self-play game, with its search score from white's point of view.
+#[derive(Debug, Clone)]
+pub(crate) struct Sample {
+    pub(crate) board_state: BoardState,
+    pub(crate) score: i32,
+}
+
+/// A SplitMix64 generator: small, fast, and good enough to pick random moves.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    /// Returns the generator of a thread, so that each thread gets its own sequence.
+    fn for_thread(seed: u64, thread: usize) -> Self {
+        let mut rng = Self(seed ^ (thread as u64).wrapping_mul(0xD6E8_FEB8_6659_FD93));
+        rng.next();
+
+        rng
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number below `n`, which must not be `0`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Plays a game of the engine against itself from the given position and returns its
+/// quiet positions, labelled with their search scores, along with the result from white's
+/// point of view.
+///
+/// Positions in check, with a mate score, where the engine captures, or where a capture
+/// wins material are left out, since their static evaluation says little. Games end by the
+/// rules or after `max_plies` plies, scored as a draw.
+pub(crate) fn play_game(
+    start: &Game,
+    limits: SearchLimits,
+    max_plies: usize,
+    network: Option<&Network>,
+    pawn_table: &mut PawnHashTable,
+) -> (Vec<Sample>, f32) {
+    let mut game = start.clone();
+    let mut samples = Vec::new();
+    let stop = AtomicBool::new(false);
+
+    let outcome = loop {
+        if let Some(outcome) = game.outcome() {
+            break outcome;
+        }
+
+        let board_state = game.get_board_state();
+
+        if game.is_threefold_repetition()
+            || board_state.fifty_move_rule >= 100
+            || board_state.is_drawn_material()
+            || game.ply() >= start.ply() + max_plies
+        {
+            break Outcome::Draw;
+        }
+
+        let result = search::search_with_network(&game, limits, &stop, network, |_| ());
+        let Some(mv) = result.best_move else {
+            break Outcome::Draw;
+        };
+
+        let quiet = search::mate_in(result.score).is_none()
+            && !game.is_in_check()
+            && !mv.is_capture()
+            && search::quiescence(&game, pawn_table) == eval::evaluate(board_state);
+
+        if quiet {
+            samples.push(Sample {
+                board_state: board_state.clone(),
+                score: match board_state.side_to_move {
+                    Color::White => result.score,
+                    Color::Black => -result.score,
+                },
+            });
+        }
+
+        if game.try_make_move(mv).is_err() {
+            break Outcome::Draw;
+        }
+    };
+
+    let result = match outcome {
+        Outcome::Win(Color::White) => 1.0,
+        Outcome::Win(Color::Black) => 0.0,
+        Outcome::Draw => 0.5,
+    };
+
+    (samples, result)
+}
+
+/// Returns a game from a random opening with the random plies played, or [`None`] if no
+/// start could be found that doesn't end the game.
+fn start_position<'a>(
+    config: &DatagenConfig,
+    rng: &mut Rng,
+    move_gen: &'a MoveGenerator,
+) -> Option<Game<'a>> {
+    'attempts: for _ in 0..MAX_START_ATTEMPTS {
+        let mut game = match config.openings.len() {
+            0 => Game::new(move_gen),
+            len => match config.openings[rng.below(len)].game(move_gen) {
+                Ok(game) => game,
+                Err(_) => continue,
+            },
+        };
+
+        for _ in 0..config.random_plies {
+            let moves = game.enumerate_moves();
+            let count = moves.len();
+
+            if count == 0 {
+                continue 'attempts;
+            }
+
+            let Some(mv) = moves.get_move(rng.below(count)) else {
+                continue 'attempts;
+            };
+
+            if game.try_make_move(mv).is_err() {
+                continue 'attempts;
+            }
+        }
+
+        if game.outcome().is_none() {
+            return Some(game);
+        }
+    }
+
+    None
+}
+
+/// Generates `config.games` self-play games across `config.threads` threads, writing the
+/// records of each game to `out` in game order, and calls `progress` whenever more games have
+/// been written.
+///
+/// # Errors
+/// Returns an [`io::ErrorKind::InvalidInput`] error if an opening isn't a legal position or
+/// has an illegal move, before any game is played. Otherwise returns the first error writing
+/// to `out`, once the running games have finished.
+pub fn generate(
+    config: &DatagenConfig,
+    move_gen: &MoveGenerator,
+    network: Option<&Network>,
+    out: impl Write + Send,
+    progress: impl Fn(&DatagenStats) + Sync,
+) -> io::Result<DatagenStats> {
+    for (i, opening) in config.openings.iter().enumerate() {
+        let invalid = |reason: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("opening {}: {reason}", i + 1),
+            )
+        };
+
+        let game = opening
+            .game(move_gen)
+            .map_err(|err| invalid(err.to_string()))?;
+
+        if let Err(errors) = game.get_board_state().validate_with(move_gen) {
+            return Err(invalid(errors[0].to_string()));
+        }
+    }
+
+    let threads = config.threads.max(1);
+    let limits = SearchLimits {
+        nodes: Some(config.nodes),
+        ..SearchLimits::default()
+    };
+    let output = Mutex::new(Output {
+        out,
+        next: 0,
+        pending: BTreeMap::new(),
+        stats: DatagenStats::default(),
+        error: None,
+    });
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        for thread in 0..threads {
+            let (output, stop, progress) = (&output, &stop, &progress);
+
+            scope.spawn(move || {
+                let mut rng = Rng::for_thread(config.seed, thread);
+                let mut pawn_table = PawnHashTable::default();
+
+                for number in (thread as u32..config.games).step_by(threads) {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    let record = start_position(config, &mut rng, move_gen).map(|game| {
+                        let (samples, result) =
+                            play_game(&game, limits, config.max_plies, network, &mut pawn_table);
+
+                        encode(&samples, result, config.format)
+                    });
+                    let mut output = output.lock().unwrap_or_else(PoisonError::into_inner);
+
+                    let written = output.stats.games;
+
+                    output.submit(number, record);
+
+                    if output.error.is_some() {
+                        stop.store(true, Ordering::Relaxed);
+                    } else if output.stats.games > written {
+                        progress(&output.stats);
+                    }
+                }
+            });
+        }
+    });
+
+    let mut output = output.into_inner().unwrap_or_else(PoisonError::into_inner);
+
+    match output.error.take() {
+        Some(err) => Err(err),
+        None => output.out.flush().map(|()| output.stats),
+    }
+}
+
+/// The writer of a run, holding back games finished out of order.
+struct Output<W> {
+    out: W,
+    /// The number of the next game to write.
+    next: u32,
+    /// The encoded records and position counts of the games waiting for earlier ones, or
+    /// [`None`] for games that found no start.
+    pending: BTreeMap<u32, Option<(Vec<u8>, u64)>>,
+    stats: DatagenStats,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Output<W> {
+    fn submit(&mut self, number: u32, record: Option<(Vec<u8>, u64)>) {
+        self.pending.insert(number, record);
+
+        while self.error.is_none()
+            && let Some(record) = self.pending.remove(&self.next)
+        {
+            match record {
+                Some((bytes, positions)) => {
+                    if let Err(err) = self.out.write_all(&bytes) {
+                        self.error = Some(err);
+                    }
+
+                    self.stats.positions += positions;
+                }
+                None => self.stats.skipped += 1,
+            }
+
+            self.next += 1;
+            self.stats.games += 1;
+        }
+    }
+}
+
+/// Encodes the samples of a game in the given format, returning the records along with their
+/// number. Samples that can't be packed are left out of the binary format.
+fn encode(samples: &[Sample], result: f32, format: DataFormat) -> (Vec<u8>, u64) {
+    let mut bytes = Vec::new();
+    let mut positions = 0;
+
+    for sample in samples {
+        match format {
+            DataFormat::Text => {
+                let record = TrainingRecord {
+                    fen: sample.board_state.to_fen(),
+                    score: sample.score,
+                    result,
+                };
+                let _ = writeln!(bytes, "{record}");
+            }
+            DataFormat::Binary => {
+                let score = sample.score.clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+
+                let Ok(board) = encoding::pack_board(&sample.board_state) else {
+                    continue;
+                };
+
+                bytes.extend_from_slice(&board);
+                bytes.extend_from_slice(&score.to_le_bytes());
+                bytes.push((result * 2.0) as u8);
+                bytes.push(0);
+            }
+        }
+
+        positions += 1;
+    }
+
+    (bytes, positions)
+}
+
+/// Parses records in the binary format.
+///
+/// # Errors
+/// Returns an [`io::ErrorKind::InvalidData`] error if the data isn't a whole number of
+/// valid records.
+pub fn parse_binary(bytes: &[u8]) -> io::Result<Vec<TrainingRecord>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "bad binary record");
+
+    if !bytes.len().is_multiple_of(BINARY_RECORD_SIZE) {
+        return Err(invalid());
+    }
+
+    bytes
+        .chunks_exact(BINARY_RECORD_SIZE)
+        .map(|record| {
+            let (board, rest) = record.split_at(PACKED_BOARD_SIZE);
+            let board = board
+                .try_into()
+                .expect("the record holds a packed position");
+            let board_state = encoding::unpack_board(board).map_err(|_| invalid())?;
+
+            if rest[2] > 2 || rest[3] != 0 {
+                return Err(invalid());
+            }
+
+            Ok(TrainingRecord {
+                fen: board_state.to_fen(),
+                score: i16::from_le_bytes([rest[0], rest[1]]).into(),
+                result: f32::from(rest[2]) / 2.0,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament;
+    use crate::tuner::Dataset;
+
+    #[test]
+    fn test_generate() {
+        let move_gen = MoveGenerator::new();
+        let config = DatagenConfig {
+            games: 2,
+            threads: 2,
+            nodes: 300,
+            random_plies: 4,
+            max_plies: 12,
+            seed: 7,
+            ..DatagenConfig::default()
+        };
+        let mut text = Vec::new();
+        let stats = generate(&config, &move_gen, None, &mut text, |_| ()).unwrap();
+        let text = String::from_utf8(text).unwrap();
+
+        assert_eq!(stats.games, 2);
+        assert_eq!(stats.positions, text.lines().count() as u64);
+        assert!(stats.positions > 0);
+
+        // The text format feeds the tuner directly.
+        assert_eq!(
+            Dataset::parse(&text, false, 1, &move_gen).unwrap().len() as u64,
+            stats.positions
+        );
+
+        // The same seed and thread count reproduce the same games, whatever the format.
+        let mut binary = Vec::new();
+        let binary_config = DatagenConfig {
+            format: DataFormat::Binary,
+            openings: Vec::new(),
+            ..config.clone()
+        };
+        let binary_stats = generate(&binary_config, &move_gen, None, &mut binary, |_| ()).unwrap();
+
+        assert_eq!(binary_stats, stats);
+        assert_eq!(binary.len(), stats.positions as usize * BINARY_RECORD_SIZE);
+        assert_eq!(
+            parse_binary(&binary)
+                .unwrap()
+                .iter()
+                .map(|record| format!("{record}\n"))
+                .collect::<String>(),
+            text
+        );
+        assert!(parse_binary(&binary[1..]).is_err());
+
+        // Book openings with no random plies.
+        let openings = tournament::parse_epd("4k3/8/8/8/8/8/8/R3K3 w - -").unwrap();
+        let config = DatagenConfig {
+            games: 1,
+            random_plies: 0,
+            openings,
+            ..config
+        };
+        let mut text = Vec::new();
+        generate(&config, &move_gen, None, &mut text, |_| ()).unwrap();
+
+        assert!(
+            String::from_utf8(text)
+                .unwrap()
+                .starts_with("4k3/8/8/8/8/8/8/R3K3 w - - 0 1 | ")
+        );
+    }
+
+    #[test]
+    fn test_bad_starts() {
+        let move_gen = MoveGenerator::new();
+        let config = DatagenConfig {
+            games: 2,
+            nodes: 100,
+            random_plies: 1,
+            format: DataFormat::Binary,
+            ..DatagenConfig::default()
+        };
+
+        // An opening with too many pieces to be legal is rejected before any game is played.
+        let openings =
+            tournament::parse_epd("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKBNR w KQkq -")
+                .unwrap();
+        let config = DatagenConfig { openings, ..config };
+        let mut binary = Vec::new();
+        let err = generate(&config, &move_gen, None, &mut binary, |_| ()).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().starts_with("opening 1: "));
+        assert!(binary.is_empty());
+
+        // Black is mated, so no random ply can be played: the games are counted as skipped.
+        let openings = tournament::parse_epd("R5k1/5ppp/8/8/8/8/8/6K1 b - -").unwrap();
+        let config = DatagenConfig { openings, ..config };
+        let stats = generate(&config, &move_gen, None, &mut binary, |_| ()).unwrap();
+
+        assert_eq!(
+            stats,
+            DatagenStats {
+                games: 2,
+                positions: 0,
+                skipped: 2,
+            }
+        );
+        assert!(binary.is_empty());
+    }
+}
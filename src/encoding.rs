@@ -0,0 +1,404 @@
+//! Compact binary encodings of positions and move sequences, to store large numbers of
+//! them far more cheaply than as FEN strings and SAN.
+//!
+//! # Positions
+//!
+//! A position is packed in [`PACKED_BOARD_SIZE`] bytes, numbers little-endian:
+//!
+//! - the occupancy bitboard as a `u64`
+//! - a 4-bit code for each occupied square from a1 to h8, low nibble first, in 16 bytes:
+//!   [`Piece::to_index()`](crate::Piece::to_index()) for white pieces and six more for black ones
+//! - a byte holding the side to move (bit 0, set for black) and the castling bitflags (bits
+//!   1 to 4, see [`boardstate_constants`](crate::boardstate::boardstate_constants))
+//! - the en passant square index, or `255` if there is none
+//! - the halfmove clock
+//! - the fullmove number as a `u16`
+//! - three zero bytes
+//!
+//! Every legal position fits, as neither side can have more than 16 pieces, and each
+//! position has exactly one encoding.
+//!
+//! # Move sequences
+//!
+//! Moves are packed either as their raw [`u16`] form ([`Move::to_u16()`]), two bytes each,
+//! or as their index among the legal moves of the position they are played in, one byte
+//! each. The latter halves the size but needs the starting position to decode, and
+//! replays the moves to do so.
+
+use crate::Color;
+use crate::bitboards::{BitBoards, Bitboard};
+use crate::boardstate::BoardState;
+use crate::boardstate::boardstate_constants::*;
+use crate::eval::ALL_PIECES;
+use crate::game::Game;
+use crate::movegen::MoveGenerator;
+use crate::moves::{Move, MoveError, MoveListIterator};
+use crate::square::Square;
+use std::error::Error;
+use std::fmt;
+
+/// The size of a packed position.
+pub const PACKED_BOARD_SIZE: usize = 32;
+
+/// The errors that can occur when packing or unpacking positions and moves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodingError {
+    /// The position has more than 32 pieces.
+    TooManyPieces(u32),
+    /// A bit or byte that should be zero isn't.
+    ReservedBits,
+    /// A square holds a code that isn't a piece.
+    BadPieceCode(u8),
+    /// The en passant byte is neither a square index nor `255`.
+    BadEnPassantSquare(u8),
+    /// A side doesn't have exactly one king.
+    BadKingCount,
+    /// The data ends in the middle of a move.
+    Truncated,
+    BadMove(MoveError),
+    /// The move at the given ply isn't legal.
+    IllegalMove {
+        ply: usize,
+        mv: Move,
+    },
+    /// The index at the given ply is past the last legal move.
+    BadMoveIndex {
+        ply: usize,
+        index: u8,
+    },
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyPieces(count) => {
+                write!(f, "{count} pieces don't fit in a packed position")
+            }
+            Self::ReservedBits => write!(f, "reserved bits are set"),
+            Self::BadPieceCode(code) => write!(f, "bad piece code {code}"),
+            Self::BadEnPassantSquare(index) => write!(f, "bad en passant square {index}"),
+            Self::BadKingCount => write!(f, "a side does not have exactly one king"),
+            Self::Truncated => write!(f, "the data ends in the middle of a move"),
+            Self::BadMove(err) => write!(f, "{err}"),
+            Self::IllegalMove { ply, mv } => write!(f, "illegal move {mv} at ply {ply}"),
+            Self::BadMoveIndex { ply, index } => {
+                write!(f, "no legal move with index {index} at ply {ply}")
+            }
+        }
+    }
+}
+
+impl Error for EncodingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::BadMove(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<MoveError> for EncodingError {
+    fn from(err: MoveError) -> Self {
+        Self::BadMove(err)
+    }
+}
+
+/// Packs a position, see the [module documentation](self).
+///
+/// # Errors
+/// Returns [`EncodingError::TooManyPieces`] if the position has more than 32 pieces.
+pub fn pack_board(board_state: &BoardState) -> Result<[u8; PACKED_BOARD_SIZE], EncodingError> {
+    let mut bytes = [0; PACKED_BOARD_SIZE];
+    let position = &board_state.position;
+    let occupancy = position.all_boards();
+
+    if occupancy.count() > 32 {
+        return Err(EncodingError::TooManyPieces(occupancy.count()));
+    }
+
+    bytes[..8].copy_from_slice(&occupancy.0.to_le_bytes());
+
+    for (i, square) in occupancy.into_iter().enumerate() {
+        if let Some((color, piece)) = position.piece_at(square) {
+            let code = (color.to_index() * 6 + piece.to_index()) as u8;
+            bytes[8 + i / 2] |= code << (4 * (i % 2));
+        }
+    }
+
+    let castling = [
+        (
+            board_state.has_castling_rights_queenside_white(),
+            CAN_CASTLE_QUEENSIDE_WHITE,
+        ),
+        (
+            board_state.has_castling_rights_kingside_white(),
+            CAN_CASTLE_KINGSIDE_WHITE,
+        ),
+        (
+            board_state.has_castling_rights_queenside_black(),
+            CAN_CASTLE_QUEENSIDE_BLACK,
+        ),
+        (
+            board_state.has_castling_rights_kingside_black(),
+            CAN_CASTLE_KINGSIDE_BLACK,
+        ),
+    ]
+    .into_iter()
+    .filter(|&(has, _)| has)
+    .fold(0, |rights, (_, flag)| rights | flag);
+
+    bytes[24] = board_state.side_to_move.to_index() as u8 | castling << 1;
+    bytes[25] = board_state
+        .en_passant_square
+        .map_or(255, |square| square.index() as u8);
+    bytes[26] = board_state.fifty_move_rule;
+    bytes[27..29].copy_from_slice(&board_state.turn_count.to_le_bytes());
+
+    Ok(bytes)
+}
+
+/// Unpacks a position packed by [`pack_board()`].
+///
+/// Only the encoding is checked, not whether the position could arise in a game: see
+/// [`BoardState::validate()`] for that.
+///
+/// # Errors
+/// Returns an [`EncodingError`] if the bytes don't hold a packed position.
+pub fn unpack_board(bytes: &[u8; PACKED_BOARD_SIZE]) -> Result<BoardState, EncodingError> {
+    let mut occupancy = [0; 8];
+    occupancy.copy_from_slice(&bytes[..8]);
+    let occupancy = Bitboard(u64::from_le_bytes(occupancy));
+
+    if occupancy.count() > 32 {
+        return Err(EncodingError::TooManyPieces(occupancy.count()));
+    }
+
+    // The nibbles past the last piece, the bits above the castling rights and the padding.
+    let unused_nibbles = bytes[8 + occupancy.count() as usize / 2..24]
+        .iter()
+        .enumerate()
+        .any(|(i, &byte)| match (i, occupancy.count() % 2) {
+            (0, 1) => byte >> 4 != 0,
+            _ => byte != 0,
+        });
+
+    if unused_nibbles || bytes[24] >> 5 != 0 || bytes[29..].iter().any(|&byte| byte != 0) {
+        return Err(EncodingError::ReservedBits);
+    }
+
+    let mut boards = [[Bitboard::EMPTY; 6]; 2];
+
+    for (i, square) in occupancy.into_iter().enumerate() {
+        let code = bytes[8 + i / 2] >> (4 * (i % 2)) & 0xF;
+
+        if code >= 12 {
+            return Err(EncodingError::BadPieceCode(code));
+        }
+
+        let code = usize::from(code);
+        boards[code / 6][ALL_PIECES[code % 6].to_index()] |= square.bitboard();
+    }
+
+    let side_to_move = match bytes[24] & 1 {
+        0 => Color::White,
+        _ => Color::Black,
+    };
+    let en_passant_square = match bytes[25] {
+        255 => None,
+        index => Some(Square::from_index(index).ok_or(EncodingError::BadEnPassantSquare(index))?),
+    };
+
+    Ok(BoardState::new(
+        side_to_move,
+        BitBoards::new(boards).map_err(|_| EncodingError::BadKingCount)?,
+        u16::from_le_bytes([bytes[27], bytes[28]]),
+        bytes[26],
+        bytes[24] >> 1,
+        en_passant_square,
+    ))
+}
+
+/// Packs moves in their raw [`u16`] form, two bytes each.
+pub fn pack_moves(moves: &[Move]) -> Vec<u8> {
+    moves
+        .iter()
+        .flat_map(|mv| mv.to_u16().to_le_bytes())
+        .collect()
+}
+
+/// Unpacks moves packed by [`pack_moves()`].
+///
+/// # Errors
+/// Returns an [`EncodingError`] if the data has an odd length or holds an invalid move.
+pub fn unpack_moves(bytes: &[u8]) -> Result<Vec<Move>, EncodingError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(EncodingError::Truncated);
+    }
+
+    bytes
+        .chunks_exact(2)
+        .map(|pair| Ok(Move::try_from(u16::from_le_bytes([pair[0], pair[1]]))?))
+        .collect()
+}
+
+/// Packs moves played from the given position as their indices among the legal moves,
+/// one byte each. The moves may have incomplete flags, as accepted by
+/// [`Game::resolve_move()`].
+///
+/// # Errors
+/// Returns [`EncodingError::IllegalMove`] if a move isn't legal where it is played.
+pub fn pack_move_indices(
+    board_state: &BoardState,
+    moves: &[Move],
+    move_gen: &MoveGenerator,
+) -> Result<Vec<u8>, EncodingError> {
+    let mut game = Game::from_board_state(board_state.clone(), move_gen);
+    let mut bytes = Vec::with_capacity(moves.len());
+
+    for (ply, &mv) in moves.iter().enumerate() {
+        let illegal = || EncodingError::IllegalMove { ply, mv };
+        let mv = game.resolve_move(mv).map_err(|_| illegal())?;
+        let legal_moves = game.enumerate_moves();
+        let index = MoveListIterator::new(&legal_moves)
+            .position(|legal| legal.mv == mv)
+            .ok_or_else(illegal)?;
+
+        // There are never more than 218 legal moves.
+        bytes.push(index as u8);
+        game.unchecked_make_move(mv);
+    }
+
+    Ok(bytes)
+}
+
+/// Unpacks moves packed by [`pack_move_indices()`] from the same position, returning them
+/// fully flagged.
+///
+/// # Errors
+/// Returns [`EncodingError::BadMoveIndex`] if an index is past the last legal move.
+pub fn unpack_move_indices(
+    board_state: &BoardState,
+    bytes: &[u8],
+    move_gen: &MoveGenerator,
+) -> Result<Vec<Move>, EncodingError> {
+    let mut game = Game::from_board_state(board_state.clone(), move_gen);
+    let mut moves = Vec::with_capacity(bytes.len());
+
+    for (ply, &index) in bytes.iter().enumerate() {
+        let mv = game
+            .enumerate_moves()
+            .get_move(usize::from(index))
+            .ok_or(EncodingError::BadMoveIndex { ply, index })?;
+
+        moves.push(mv);
+        game.unchecked_make_move(mv);
+    }
+
+    Ok(moves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_board() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/6P1/8/3pP3/8/8/2p5/R3K2R w Kq d6 0 41",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "8/5k2/8/8/8/8/8/4K3 b - - 99 300",
+        ] {
+            let board_state = BoardState::from_fen(fen).unwrap();
+            let packed = pack_board(&board_state).unwrap();
+
+            assert_eq!(unpack_board(&packed).unwrap().to_fen(), fen);
+            assert_eq!(pack_board(&unpack_board(&packed).unwrap()), Ok(packed));
+        }
+
+        let packed = pack_board(&BoardState::default()).unwrap();
+
+        let mut bad = packed;
+        bad[8] = 0xFF;
+        assert_eq!(unpack_board(&bad), Err(EncodingError::BadPieceCode(15)));
+
+        let mut bad = packed;
+        bad[31] = 1;
+        assert_eq!(unpack_board(&bad), Err(EncodingError::ReservedBits));
+
+        let mut bad = packed;
+        bad[25] = 64;
+        assert_eq!(
+            unpack_board(&bad),
+            Err(EncodingError::BadEnPassantSquare(64))
+        );
+
+        // The white king on e1, the fifth piece, turned into a queen.
+        let mut bad = packed;
+        bad[10] = bad[10] & 0xF0 | 4;
+        assert_eq!(unpack_board(&bad), Err(EncodingError::BadKingCount));
+    }
+
+    #[test]
+    fn test_pack_moves() {
+        let move_gen = MoveGenerator::new();
+        let fen = "r3k2r/6P1/8/3pP3/8/8/2p5/R3K2R w KQkq d6 0 1";
+        let board_state = BoardState::from_fen(fen).unwrap();
+
+        // En passant, both castles, promotions with and without a capture.
+        let mut game = Game::from_board_state(board_state.clone(), &move_gen);
+        let moves: Vec<Move> = ["e5d6", "e8c8", "e1g1", "c2c1q", "g7h8n", "c8b8", "g1h1"]
+            .into_iter()
+            .map(|text| {
+                let mv = game.resolve_move(text.parse().unwrap()).unwrap();
+                game.try_make_move(mv).unwrap();
+                mv
+            })
+            .collect();
+
+        let packed = pack_moves(&moves);
+        assert_eq!(packed.len(), 2 * moves.len());
+        assert_eq!(unpack_moves(&packed), Ok(moves.clone()));
+        assert_eq!(unpack_moves(&packed[1..]), Err(EncodingError::Truncated));
+        assert!(matches!(
+            unpack_moves(&[0x00, 0x60]),
+            Err(EncodingError::BadMove(_))
+        ));
+
+        // Indices accept moves with incomplete flags and give back the flagged moves.
+        let unflagged: Vec<Move> = moves
+            .iter()
+            .map(|mv| Move::from_squares(mv.get_initial_square(), mv.get_target_square()))
+            .collect();
+        let indices = pack_move_indices(&board_state, &moves, &move_gen).unwrap();
+
+        assert_eq!(indices.len(), moves.len());
+        assert_eq!(
+            unpack_move_indices(&board_state, &indices, &move_gen),
+            Ok(moves.clone())
+        );
+
+        // Replaying the unpacked moves reaches the same position.
+        let mut replayed = Game::from_board_state(board_state.clone(), &move_gen);
+        for mv in unpack_move_indices(&board_state, &indices, &move_gen).unwrap() {
+            replayed.try_make_move(mv).unwrap();
+        }
+        assert_eq!(replayed.to_fen(), game.to_fen());
+
+        assert_eq!(
+            pack_move_indices(&board_state, &unflagged[..3], &move_gen).unwrap(),
+            indices[..3]
+        );
+        assert_eq!(
+            pack_move_indices(&board_state, &moves[1..], &move_gen),
+            Err(EncodingError::IllegalMove {
+                ply: 0,
+                mv: moves[1]
+            })
+        );
+        assert_eq!(
+            unpack_move_indices(&board_state, &[indices[0], 250], &move_gen),
+            Err(EncodingError::BadMoveIndex { ply: 1, index: 250 })
+        );
+    }
+}
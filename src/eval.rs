@@ -0,0 +1,305 @@
+use crate::boardstate::{BoardState, Endgame};
+use crate::pawns::{PawnHashTable, PawnStructure};
+use crate::{Color, Piece};
+
+/// The value of each piece in centipawns, indexed by its bitboard index.
+pub const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+/// The pieces whose material counts towards the game phase, along with how much they count.
+const PHASE_WEIGHTS: [(Piece, i32); 4] = [
+    (Piece::Knight, 1),
+    (Piece::Bishop, 1),
+    (Piece::Rook, 2),
+    (Piece::Queen, 4),
+];
+
+/// The game phase of the starting position. The phase drops towards `0` as pieces are
+/// traded off.
+pub const MAX_PHASE: i32 = 24;
+
+pub(crate) const ALL_PIECES: [Piece; 6] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+// Piece-square tables, from white's point of view. They are laid out the way the board is
+// printed, so the first row is the eighth rank.
+#[rustfmt::skip]
+pub(crate) const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+pub(crate) const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+pub(crate) const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+pub(crate) const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+pub(crate) const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+pub(crate) const KING_MIDDLEGAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+pub(crate) const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+/// Returns the static evaluation of the position in centipawns, from the point of view of
+/// the side to move: positive scores favor the side to move.
+///
+/// The evaluation only counts material, piece placement and pawn structure. It doesn't
+/// know about checkmate or stalemate, which are left to the search.
+pub fn evaluate(board_state: &BoardState) -> i32 {
+    let pawns = PawnStructure::from_position(&board_state.position).score();
+
+    evaluate_with_pawns(board_state, pawns)
+}
+
+/// Returns the static evaluation of the position like [`evaluate`], looking the pawn
+/// structure score up in the given table instead of recomputing it.
+pub fn evaluate_cached(board_state: &BoardState, pawn_table: &mut PawnHashTable) -> i32 {
+    let position = &board_state.position;
+    let pawns = pawn_table.probe(position.pawns_white(), position.pawns_black());
+
+    evaluate_with_pawns(board_state, pawns)
+}
+
+/// Returns the evaluation from the side to move's point of view, given the pawn structure
+/// score from white's point of view.
+fn evaluate_with_pawns(board_state: &BoardState, pawns: i32) -> i32 {
+    if board_state.is_drawn_material() {
+        return 0;
+    }
+
+    let mut score =
+        evaluate_side(board_state, Color::White) - evaluate_side(board_state, Color::Black) + pawns;
+
+    // Opposite-colored bishop endings are notoriously drawish, even a pawn or two up.
+    if board_state.endgame() == Some(Endgame::OppositeColoredBishops) {
+        score /= 2;
+    }
+
+    match board_state.side_to_move {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Returns the game phase of the position, from [`MAX_PHASE`] in the opening down to `0`
+/// once only kings and pawns are left.
+pub fn phase(board_state: &BoardState) -> i32 {
+    let phase = PHASE_WEIGHTS
+        .iter()
+        .map(|&(piece, weight)| {
+            let pieces = board_state.position.board(Color::White, piece)
+                | board_state.position.board(Color::Black, piece);
+
+            pieces.count() as i32 * weight
+        })
+        .sum::<i32>();
+
+    phase.min(MAX_PHASE)
+}
+
+/// Returns the material and placement score of one side's pieces.
+fn evaluate_side(board_state: &BoardState, color: Color) -> i32 {
+    let (material, placement) = side_terms(board_state, color);
+
+    material + placement
+}
+
+/// Returns the value of one side's pieces and their piece-square table score.
+fn side_terms(board_state: &BoardState, color: Color) -> (i32, i32) {
+    let phase = phase(board_state);
+    let (mut material, mut placement) = (0, 0);
+
+    for piece in ALL_PIECES {
+        for square in board_state.position.board(color, piece) {
+            // The tables are written from white's point of view with the eighth rank first.
+            let index = match color {
+                Color::White => square.flip_rank().index(),
+                Color::Black => square.index(),
+            };
+
+            material += PIECE_VALUES[piece.to_index()];
+            placement += match piece {
+                Piece::Pawn => PAWN_TABLE[index],
+                Piece::Knight => KNIGHT_TABLE[index],
+                Piece::Bishop => BISHOP_TABLE[index],
+                Piece::Rook => ROOK_TABLE[index],
+                Piece::Queen => QUEEN_TABLE[index],
+                Piece::King => {
+                    (KING_MIDDLEGAME_TABLE[index] * phase
+                        + KING_ENDGAME_TABLE[index] * (MAX_PHASE - phase))
+                        / MAX_PHASE
+                }
+            };
+        }
+    }
+
+    (material, placement)
+}
+
+/// The terms making up the static evaluation of a position, see [`breakdown()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalBreakdown {
+    /// The value of each side's pieces, indexed by [`Color::to_index()`].
+    pub material: [i32; 2],
+    /// The piece-square table score of each side's pieces, indexed by [`Color::to_index()`].
+    pub placement: [i32; 2],
+    /// The pawn structure score from white's point of view.
+    pub pawns: i32,
+    /// The game phase, see [`phase()`].
+    pub phase: i32,
+    /// Whether neither side has the material to force mate, which makes the evaluation `0`.
+    pub drawn_material: bool,
+    /// Whether the score is halved for an opposite-colored bishop ending.
+    pub opposite_bishops: bool,
+    /// The evaluation from the side to move's point of view, as returned by [`evaluate()`].
+    pub total: i32,
+}
+
+/// Returns the terms of the static evaluation of the position, to explain the score
+/// returned by [`evaluate()`].
+pub fn breakdown(board_state: &BoardState) -> EvalBreakdown {
+    let (white_material, white_placement) = side_terms(board_state, Color::White);
+    let (black_material, black_placement) = side_terms(board_state, Color::Black);
+    let mut material = [0; 2];
+    let mut placement = [0; 2];
+
+    material[Color::White.to_index()] = white_material;
+    material[Color::Black.to_index()] = black_material;
+    placement[Color::White.to_index()] = white_placement;
+    placement[Color::Black.to_index()] = black_placement;
+
+    EvalBreakdown {
+        material,
+        placement,
+        pawns: PawnStructure::from_position(&board_state.position).score(),
+        phase: phase(board_state),
+        drawn_material: board_state.is_drawn_material(),
+        opposite_bishops: board_state.endgame() == Some(Endgame::OppositeColoredBishops),
+        total: evaluate(board_state),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let start = BoardState::default();
+
+        assert_eq!(evaluate(&start), 0);
+        assert_eq!(phase(&start), MAX_PHASE);
+
+        // The same position with colors swapped scores the same for the side to move.
+        let white = BoardState::from_fen("4k3/8/8/3q4/8/2N5/PP6/4K3 w - - 0 1").unwrap();
+        let black = BoardState::from_fen("4k3/pp6/2n5/8/3Q4/8/8/4K3 b - - 0 1").unwrap();
+
+        assert_eq!(evaluate(&white), evaluate(&black));
+        assert!(evaluate(&white) < 0);
+        assert_eq!(phase(&white), 5);
+
+        // Doubled, isolated pawns cost the side that has them.
+        let doubled = BoardState::from_fen("4k3/4p3/8/8/8/4P3/4P3/4K3 w - - 0 1").unwrap();
+        let mut pawn_table = PawnHashTable::default();
+
+        assert!(evaluate(&doubled) < PIECE_VALUES[Piece::Pawn.to_index()]);
+        assert_eq!(
+            evaluate_cached(&doubled, &mut pawn_table),
+            evaluate(&doubled)
+        );
+        assert_eq!(
+            evaluate_cached(&doubled, &mut pawn_table),
+            evaluate(&doubled)
+        );
+        assert_eq!(pawn_table.stats(), (1, 2));
+
+        let drawn = BoardState::from_fen("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1").unwrap();
+        assert_eq!(evaluate(&drawn), 0);
+        assert!(breakdown(&drawn).drawn_material);
+
+        // The terms add up to the evaluation.
+        let breakdown = breakdown(&black);
+        let (white, black) = (Color::White.to_index(), Color::Black.to_index());
+
+        assert_eq!(
+            breakdown.material[white] + breakdown.placement[white] + breakdown.pawns
+                - breakdown.material[black]
+                - breakdown.placement[black],
+            -breakdown.total
+        );
+        assert_eq!(breakdown.material[black], 2 * 100 + 320);
+        assert!(!breakdown.opposite_bishops);
+    }
+}
@@ -1,15 +1,14 @@
-use crate::bitboards::{
-    bitboard_constants::{castle_squares::*, rank_file::*},
-    *,
-};
+use crate::bitboards::{bitboard_constants::castle_squares::*, *};
 use crate::boardstate::*;
+use crate::history::*;
 use crate::movegen::*;
 use crate::moves::*;
+use crate::pgn;
+use crate::play::{PlaySession, Player};
 use crate::rende::*;
-use crate::util::*;
-use crate::{Color, Piece};
-
-const PROJECTED_GAME_LENGTH: usize = 40;
+use crate::{Color, File, Piece, Rank, Square};
+use std::error::Error;
+use std::fmt;
 
 /// Struct encapsulating the game logic.
 #[derive(Debug, Clone)]
@@ -17,20 +16,134 @@ pub struct Game<'a> {
     board_state: BoardState,
     outcome: Option<Outcome>,
     move_gen: &'a MoveGenerator,
+    history: BoardHistory,
 }
 
 /// Represents the possible outcomes of a [`Game`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(dead_code)]
 pub enum Outcome {
     Win(Color),
     Draw,
 }
 
+/// The rules that draw a game without either player asking for it, see
+/// [`Game::draw_by_rule()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    /// The current position has occurred three times.
+    Repetition,
+    /// A hundred plies have been played without a capture or a pawn move.
+    FiftyMoves,
+    /// Neither side has the material to mate, whatever the moves played: see
+    /// [`BoardState::is_insufficient_material()`].
+    InsufficientMaterial,
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Repetition => write!(f, "3-fold repetition"),
+            Self::FiftyMoves => write!(f, "fifty moves rule"),
+            Self::InsufficientMaterial => write!(f, "insufficient material"),
+        }
+    }
+}
+
+/// The reasons a move can be rejected by [`Game::try_make_move()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMoveError {
+    /// There is no piece on the initial square.
+    EmptySquare(Square),
+    /// The piece on the initial square belongs to the side that isn't moving.
+    WrongSide(Square),
+    /// The piece can't reach the target square from the initial square.
+    UnreachableSquare(Piece, Square),
+    /// The move would leave (or put) the moving side's king in check.
+    IntoCheck,
+    /// The piece on the given square is pinned to its king.
+    Pinned(Square),
+    /// The king or the rook has already moved.
+    NoCastlingRights,
+    /// There are pieces between the king and the rook.
+    CastlingBlocked,
+    /// The king can't castle out of check.
+    CastlingOutOfCheck,
+    /// The king can't castle through or into the given attacked square.
+    CastlingThroughCheck(Square),
+    /// A pawn reaching the last rank has to promote, but the move has no promotion piece.
+    MissingPromotion,
+    /// Only pawns reaching the last rank can promote.
+    UnexpectedPromotion,
+    /// The flags of the move don't match the legal move between the same squares.
+    WrongFlags(Move),
+}
+
+impl fmt::Display for IllegalMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptySquare(square) => write!(f, "there is no piece on {square}"),
+            Self::WrongSide(square) => write!(f, "the piece on {square} belongs to the other side"),
+            Self::UnreachableSquare(piece, square) => {
+                write!(f, "the {piece:?} can't move to {square}")
+            }
+            Self::IntoCheck => write!(f, "the move would leave the king in check"),
+            Self::Pinned(square) => write!(f, "the piece on {square} is pinned to its king"),
+            Self::NoCastlingRights => write!(f, "castling rights have been lost"),
+            Self::CastlingBlocked => write!(f, "there are pieces between the king and the rook"),
+            Self::CastlingOutOfCheck => write!(f, "the king can't castle out of check"),
+            Self::CastlingThroughCheck(square) => {
+                write!(
+                    f,
+                    "the king can't castle through {square}, which is attacked"
+                )
+            }
+            Self::MissingPromotion => write!(f, "the pawn has to promote, e.g. `e7e8q`"),
+            Self::UnexpectedPromotion => write!(f, "only pawns reaching the last rank can promote"),
+            Self::WrongFlags(legal) => {
+                write!(f, "the move's flags don't match the legal move {legal:?}")
+            }
+        }
+    }
+}
+
+impl Error for IllegalMoveError {}
+
+/// The reasons a move in standard algebraic notation can be rejected by
+/// [`Game::parse_san()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    /// The string isn't a move in SAN, e.g. `Nf3`, `exd6`, `O-O` or `e8=Q+`.
+    BadNotation(String),
+    /// No legal move matches the SAN.
+    NoMatch(String),
+    /// More than one legal move matches the SAN, e.g. `Ne2` when both knights can go there.
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadNotation(san) => write!(f, "`{san}` is not a move in SAN"),
+            Self::NoMatch(san) => write!(f, "no legal move matches `{san}`"),
+            Self::Ambiguous(san) => write!(f, "more than one legal move matches `{san}`"),
+        }
+    }
+}
+
+impl Error for SanError {}
+
 impl<'a> Game<'a> {
     pub fn new(move_gen: &'a MoveGenerator) -> Self {
+        Self::from_board_state(BoardState::default(), move_gen)
+    }
+
+    /// Starts a game from the given position, with an empty history.
+    pub fn from_board_state(board_state: BoardState, move_gen: &'a MoveGenerator) -> Self {
         Self {
-            board_state: BoardState::default(),
+            history: BoardHistory::new(board_state.clone()),
+            board_state,
             move_gen,
             outcome: None,
         }
@@ -40,43 +153,50 @@ impl<'a> Game<'a> {
         fen: &str,
         move_gen: &'a MoveGenerator,
     ) -> Result<Self, BoardStateCreationError> {
-        Ok(Self {
-            board_state: BoardState::from_fen(fen)?,
-            move_gen,
-            outcome: None,
-        })
+        Ok(Self::from_board_state(BoardState::from_fen(fen)?, move_gen))
     }
 
-    pub fn play_sandbox(&mut self) {
-        let mut history = BoardHistory::new();
-
-        while self.outcome == None {
-            self.print();
-
-            let Some(mv) = read_move() else {
-                continue;
-            };
+    /// Like [`from_fen()`](Self::from_fen()), but also accepts FEN strings without the move
+    /// counters. See [`BoardState::from_fen_lenient()`].
+    pub fn from_fen_lenient(
+        fen: &str,
+        move_gen: &'a MoveGenerator,
+    ) -> Result<Self, BoardStateCreationError> {
+        Ok(Self::from_board_state(
+            BoardState::from_fen_lenient(fen)?,
+            move_gen,
+        ))
+    }
 
-            history.push(self.board_state.clone());
+    /// Like [`from_fen()`](Self::from_fen()), but rejects positions that could not have arisen
+    /// in a legal game. See [`BoardState::validate()`].
+    pub fn from_fen_strict(
+        fen: &str,
+        move_gen: &'a MoveGenerator,
+    ) -> Result<Self, BoardStateCreationError> {
+        let board_state = BoardState::from_fen(fen)?;
 
-            self.get_mut_position().move_piece(mv);
+        board_state
+            .validate_with(move_gen)
+            .map_err(BoardStateCreationError::IllegalPosition)?;
 
-            self.board_state.turn_count += 1;
-        }
+        Ok(Self::from_board_state(board_state, move_gen))
+    }
 
-        let Some(outcome) = self.outcome else {
-            return;
-        };
+    /// Plays the game on the terminal with both sides entered by hand. See
+    /// [`PlaySession`](crate::play::PlaySession) for games against the engine.
+    pub fn play_sandbox(&mut self) {
+        let mut session = PlaySession::new(self.clone(), Player::Human, Player::Human);
 
-        match outcome {
-            Outcome::Win(Color::White) => println!("White wins!"),
-            Outcome::Win(Color::Black) => println!("Black wins!"),
-            Outcome::Draw => println!("Draw!"),
-        }
+        session.run();
+        *self = session.into_game();
     }
 
     pub fn print(&self) {
-        print_bitboard(self.get_position(), self.board_state.side_to_move);
+        print!(
+            "{}",
+            UnicodeRenderer.render(&self.board_state, &RenderOptions::for_game(self))
+        );
         println!("Turn count: {}", self.board_state.turn_count);
     }
 
@@ -84,6 +204,15 @@ impl<'a> Game<'a> {
         &self.board_state.position
     }
 
+    pub fn get_board_state(&self) -> &BoardState {
+        &self.board_state
+    }
+
+    /// Returns the move generator the game was created with.
+    pub fn move_gen(&self) -> &'a MoveGenerator {
+        self.move_gen
+    }
+
     pub fn get_mut_position(&mut self) -> &mut BitBoards {
         &mut self.board_state.position
     }
@@ -94,68 +223,121 @@ impl Game<'_> {
         self.board_state.to_fen()
     }
 
-    /// Returns a [`u64`] bitboard of all squares being attacked by pieces of a given [color](crate::Color).
+    /// Returns a [`Bitboard`] of all squares being attacked by pieces of a given [color](crate::Color).
     /// This includes squares currently occupied by other friendly pieces.
-    pub fn get_attacks(&self, checked_color: Color) -> u64 {
-        let mut attacks = 0;
+    pub fn get_attacks(&self, checked_color: Color) -> Bitboard {
         let open_squares = !self.board_state.position.all_boards();
+        let board = |piece| self.board_state.position.board(checked_color, piece);
+        let mut attacks = Bitboard::EMPTY;
 
-        for square in 0..64 {
-            let piece = match self.board_state.position.piece_at(square) {
-                Some((color, piece)) if color == checked_color => piece,
-                _ => continue,
+        for square in board(Piece::Pawn) {
+            attacks |= match checked_color {
+                Color::White => self.move_gen.get_white_pawn_attacks(square),
+                Color::Black => self.move_gen.get_black_pawn_attacks(square),
             };
+        }
 
-            attacks |= match (checked_color, piece) {
-                (Color::Black, Piece::Pawn) => self.move_gen.get_black_pawn_attacks(square),
-                (Color::White, Piece::Pawn) => self.move_gen.get_white_pawn_attacks(square),
-                (_, Piece::Knight) => self.move_gen.get_knight_attacks(square),
-                (_, Piece::King) => self.move_gen.get_king_attacks(square),
-                (_, Piece::Rook) => MoveGenerator::get_rook_attacks(square, open_squares),
-                (_, Piece::Bishop) => MoveGenerator::get_bishop_attacks(square, open_squares),
-                (_, Piece::Queen) => MoveGenerator::get_queen_attacks(square, open_squares),
-            };
+        for square in board(Piece::Knight) {
+            attacks |= self.move_gen.get_knight_attacks(square);
+        }
+
+        for square in board(Piece::King) {
+            attacks |= self.move_gen.get_king_attacks(square);
+        }
+
+        for square in board(Piece::Rook) | board(Piece::Queen) {
+            attacks |= MoveGenerator::get_rook_attacks(square, open_squares);
+        }
+
+        for square in board(Piece::Bishop) | board(Piece::Queen) {
+            attacks |= MoveGenerator::get_bishop_attacks(square, open_squares);
         }
 
         attacks
     }
 
+    /// Returns a bitboard of every piece of the given color attacking the given square.
+    pub fn attackers_to(&self, square: Square, by: Color) -> Bitboard {
+        self.board_state.attackers_to(
+            self.move_gen,
+            square,
+            by,
+            self.board_state.position.all_boards(),
+        )
+    }
+
+    /// Returns `true` if a piece of the given color attacks the given square, and `false`
+    /// otherwise.
+    pub fn is_square_attacked(&self, square: Square, by: Color) -> bool {
+        self.board_state
+            .is_square_attacked(self.move_gen, square, by)
+    }
+
+    /// Returns a bitboard of the enemy pieces giving check to the side to move.
+    pub fn checkers(&self) -> Bitboard {
+        self.board_state.checkers(self.move_gen)
+    }
+
+    /// Returns the pieces of the given color pinned to their king, along with the enemy
+    /// pieces pinning them.
+    pub fn pins(&self, color: Color) -> Vec<Pin> {
+        self.board_state.pins(self.move_gen, color)
+    }
+
     /// Returns `true` if the [white](crate::Color::White) [king](crate::Piece::King) is in check, and `false` otherwise.
-    pub fn is_in_check_white(&self, enemy_attacks: u64) -> bool {
-        self.board_state.position.king_white() & enemy_attacks != 0
+    pub fn is_in_check_white(&self, enemy_attacks: Bitboard) -> bool {
+        !(self.board_state.position.king_white() & enemy_attacks).is_empty()
     }
 
     /// Returns `true` if the [black](crate::Color::Black) [king](crate::Piece::King) is in check, and `false` otherwise.
-    pub fn is_in_check_black(&self, enemy_attacks: u64) -> bool {
-        self.board_state.position.king_black() & enemy_attacks != 0
+    pub fn is_in_check_black(&self, enemy_attacks: Bitboard) -> bool {
+        !(self.board_state.position.king_black() & enemy_attacks).is_empty()
     }
 
     /// Returns `true` if the provided move would put the [white](crate::Color::White) [king](crate::Piece::King) in check,
     /// and `false` otherwise.
     pub fn would_check_white(&self, mv: Move) -> bool {
-        let mut next_turn = self.clone();
+        let mut next_turn = self.board_state.clone();
 
-        next_turn.board_state.make_move(mv);
+        next_turn.make_move(mv);
 
-        let enemy_attacks = next_turn.get_attacks(Color::Black);
+        let Some(king_square) = next_turn.position.king_white().to_square() else {
+            return false;
+        };
 
-        next_turn.is_in_check_white(enemy_attacks)
+        !next_turn
+            .attackers_to(
+                self.move_gen,
+                king_square,
+                Color::Black,
+                next_turn.position.all_boards(),
+            )
+            .is_empty()
     }
 
     /// Returns `true` if the provided move would put the [black](crate::Color::Black) [king](crate::Piece::King) in check,
     /// and `false` otherwise.
     pub fn would_check_black(&self, mv: Move) -> bool {
-        let mut next_turn = self.clone();
+        let mut next_turn = self.board_state.clone();
 
-        next_turn.board_state.make_move(mv);
+        next_turn.make_move(mv);
 
-        let enemy_attacks = next_turn.get_attacks(Color::White);
+        let Some(king_square) = next_turn.position.king_black().to_square() else {
+            return false;
+        };
 
-        next_turn.is_in_check_black(enemy_attacks)
+        !next_turn
+            .attackers_to(
+                self.move_gen,
+                king_square,
+                Color::White,
+                next_turn.position.all_boards(),
+            )
+            .is_empty()
     }
 
     /// Returns `true` if [white](crate::Color::White) can castle kingside, and `false` otherwise.
-    pub fn can_castle_kingside_white(&self, bitboard: u64, enemy_attacks: u64) -> bool {
+    pub fn can_castle_kingside_white(&self, bitboard: Bitboard, enemy_attacks: Bitboard) -> bool {
         if !self.board_state.has_castling_rights_kingside_white() {
             return false;
         }
@@ -164,11 +346,11 @@ impl Game<'_> {
             return false;
         }
 
-        if bitboard & KINGSIDE_WHITE_SQUARES != 0 {
+        if !(bitboard & KINGSIDE_WHITE_SQUARES).is_empty() {
             return false;
         }
 
-        if enemy_attacks & KINGSIDE_WHITE_SQUARES != 0 {
+        if !(enemy_attacks & KINGSIDE_WHITE_SQUARES).is_empty() {
             return false;
         }
 
@@ -176,7 +358,7 @@ impl Game<'_> {
     }
 
     /// Returns `true` if [black](crate::Color::Black) can castle kingside, and `false` otherwise.
-    pub fn can_castle_kingside_black(&self, bitboard: u64, enemy_attacks: u64) -> bool {
+    pub fn can_castle_kingside_black(&self, bitboard: Bitboard, enemy_attacks: Bitboard) -> bool {
         if !self.board_state.has_castling_rights_kingside_black() {
             return false;
         }
@@ -185,11 +367,11 @@ impl Game<'_> {
             return false;
         }
 
-        if bitboard & KINGSIDE_BLACK_SQUARES != 0 {
+        if !(bitboard & KINGSIDE_BLACK_SQUARES).is_empty() {
             return false;
         }
 
-        if enemy_attacks & KINGSIDE_BLACK_SQUARES != 0 {
+        if !(enemy_attacks & KINGSIDE_BLACK_SQUARES).is_empty() {
             return false;
         }
 
@@ -197,7 +379,7 @@ impl Game<'_> {
     }
 
     /// Returns `true` if [white](crate::Color::White) can castle queenside, and `false` otherwise.
-    pub fn can_castle_queenside_white(&self, bitboard: u64, enemy_attacks: u64) -> bool {
+    pub fn can_castle_queenside_white(&self, bitboard: Bitboard, enemy_attacks: Bitboard) -> bool {
         if !self.board_state.has_castling_rights_queenside_white() {
             return false;
         }
@@ -206,11 +388,11 @@ impl Game<'_> {
             return false;
         }
 
-        if bitboard & (QUEENSIDE_WHITE_SQUARES | QUEENSIDE_ROOK_SQUARE_WHITE) != 0 {
+        if !(bitboard & (QUEENSIDE_WHITE_SQUARES | QUEENSIDE_ROOK_SQUARE_WHITE)).is_empty() {
             return false;
         }
 
-        if enemy_attacks & QUEENSIDE_WHITE_SQUARES != 0 {
+        if !(enemy_attacks & QUEENSIDE_WHITE_SQUARES).is_empty() {
             return false;
         }
 
@@ -218,7 +400,7 @@ impl Game<'_> {
     }
 
     /// Returns `true` if [black](crate::Color::Black) can castle queenside, and `false` otherwise.
-    pub fn can_castle_queenside_black(&self, bitboard: u64, enemy_attacks: u64) -> bool {
+    pub fn can_castle_queenside_black(&self, bitboard: Bitboard, enemy_attacks: Bitboard) -> bool {
         if !self.board_state.has_castling_rights_queenside_black() {
             return false;
         }
@@ -227,11 +409,11 @@ impl Game<'_> {
             return false;
         }
 
-        if bitboard & (QUEENSIDE_BLACK_SQUARES | QUEENSIDE_ROOK_SQUARE_BLACK) != 0 {
+        if !(bitboard & (QUEENSIDE_BLACK_SQUARES | QUEENSIDE_ROOK_SQUARE_BLACK)).is_empty() {
             return false;
         }
 
-        if enemy_attacks & QUEENSIDE_BLACK_SQUARES != 0 {
+        if !(enemy_attacks & QUEENSIDE_BLACK_SQUARES).is_empty() {
             return false;
         }
 
@@ -247,7 +429,7 @@ impl Game<'_> {
                 let enemy_pieces = self.board_state.position.black();
                 //let enemy_attacks = self.get_attacks(Color::Black);
 
-                for initial_square in 0..64 {
+                for initial_square in friendly_pieces {
                     match self.board_state.position.piece_at(initial_square) {
                         Some((Color::White, Piece::Pawn)) => {
                             self.enumerate_white_pawn_moves(
@@ -295,7 +477,7 @@ impl Game<'_> {
                 let friendly_pieces = self.board_state.position.black();
                 let enemy_pieces = self.board_state.position.white();
 
-                for initial_square in 0..64 {
+                for initial_square in friendly_pieces {
                     match self.board_state.position.piece_at(initial_square) {
                         Some((Color::Black, Piece::Pawn)) => {
                             self.enumerate_black_pawn_moves(
@@ -346,41 +528,40 @@ impl Game<'_> {
 
     pub fn enumerate_white_pawn_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let mut target_squares =
             self.move_gen.get_white_pawn_moves(initial_square) & !(friendly_pieces | enemy_pieces);
 
-        let en_passant_square = BitBoards::unchecked_square_to_bitboard(
-            self.board_state.en_passant_square.unwrap_or(0),
-        );
+        let en_passant_square = self
+            .board_state
+            .en_passant_square
+            .map_or(Bitboard::EMPTY, Square::bitboard);
 
         target_squares |= self.move_gen.get_white_pawn_attacks(initial_square)
             & (enemy_pieces | en_passant_square);
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
-
-            if target_squares & target_square_bit == 0 {
-                continue;
-            };
-
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            match target_square - initial_square {
-                16 if enemy_pieces & (target_square_bit >> 8) != 0 => continue,
+            match target_square.index() - initial_square.index() {
+                16 if !((friendly_pieces | enemy_pieces) & (target_square.bitboard() >> 8))
+                    .is_empty() =>
+                {
+                    continue;
+                }
                 16 => mv.set_double_pawn_push(),
                 _ => (),
             };
 
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
-            if target_square_bit & en_passant_square != 0 {
+            if en_passant_square.contains(target_square) {
                 mv.set_en_passant_capture();
 
                 if self.would_check_white(mv) {
@@ -391,7 +572,7 @@ impl Game<'_> {
                 continue;
             }
 
-            if target_square_bit & RANK_8 != 0 {
+            if target_square.rank() == Rank::Eighth {
                 if self.would_check_white(mv) {
                     continue;
                 }
@@ -424,41 +605,40 @@ impl Game<'_> {
 
     pub fn enumerate_black_pawn_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let mut target_squares =
             self.move_gen.get_black_pawn_moves(initial_square) & !(friendly_pieces | enemy_pieces);
 
-        let en_passant_square = BitBoards::unchecked_square_to_bitboard(
-            self.board_state.en_passant_square.unwrap_or(63),
-        );
+        let en_passant_square = self
+            .board_state
+            .en_passant_square
+            .map_or(Bitboard::EMPTY, Square::bitboard);
 
         target_squares |= self.move_gen.get_black_pawn_attacks(initial_square)
             & (enemy_pieces | en_passant_square);
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
-
-            if target_squares & target_square_bit == 0 {
-                continue;
-            };
-
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            match initial_square - target_square {
-                16 if enemy_pieces & (target_square_bit << 8) != 0 => continue,
+            match initial_square.index() - target_square.index() {
+                16 if !((friendly_pieces | enemy_pieces) & (target_square.bitboard() << 8))
+                    .is_empty() =>
+                {
+                    continue;
+                }
                 16 => mv.set_double_pawn_push(),
                 _ => (),
             };
 
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
-            if target_square_bit & en_passant_square != 0 {
+            if en_passant_square.contains(target_square) {
                 mv.set_en_passant_capture();
 
                 if self.would_check_black(mv) {
@@ -469,7 +649,7 @@ impl Game<'_> {
                 continue;
             }
 
-            if target_square_bit & RANK_1 != 0 {
+            if target_square.rank() == Rank::First {
                 if self.would_check_black(mv) {
                     continue;
                 }
@@ -502,23 +682,17 @@ impl Game<'_> {
 
     pub fn enumerate_white_knight_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let target_squares = self.move_gen.get_knight_attacks(initial_square) & !friendly_pieces;
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
-
-            if target_squares & target_square_bit == 0 {
-                continue;
-            }
-
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
@@ -532,23 +706,17 @@ impl Game<'_> {
 
     pub fn enumerate_black_knight_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let target_squares = self.move_gen.get_knight_attacks(initial_square) & !friendly_pieces;
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
-
-            if target_squares & target_square_bit == 0 {
-                continue;
-            }
-
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
@@ -562,23 +730,17 @@ impl Game<'_> {
 
     pub fn enumerate_white_king_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let target_squares = self.move_gen.get_king_attacks(initial_square) & !friendly_pieces;
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            if target_squares & target_square_bit == 0 {
-                continue;
-            }
-
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
-
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
@@ -592,13 +754,13 @@ impl Game<'_> {
         let enemy_attacks = self.get_attacks(Color::Black);
 
         if self.can_castle_kingside_white(friendly_pieces | enemy_pieces, enemy_attacks) {
-            let mut mv = Move::new();
+            let mut mv = Move::from_squares(Square::E1, Square::G1);
             mv.set_kingside_castle();
             moves.push(MoveScore::new(mv));
         }
 
         if self.can_castle_queenside_white(friendly_pieces | enemy_pieces, enemy_attacks) {
-            let mut mv = Move::new();
+            let mut mv = Move::from_squares(Square::E1, Square::C1);
             mv.set_queenside_castle();
             moves.push(MoveScore::new(mv));
         }
@@ -606,23 +768,17 @@ impl Game<'_> {
 
     pub fn enumerate_black_king_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let target_squares = self.move_gen.get_king_attacks(initial_square) & !friendly_pieces;
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
-
-            if target_squares & target_square_bit == 0 {
-                continue;
-            }
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
-
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
@@ -636,13 +792,13 @@ impl Game<'_> {
         let enemy_attacks = self.get_attacks(Color::White);
 
         if self.can_castle_kingside_black(friendly_pieces | enemy_pieces, enemy_attacks) {
-            let mut mv = Move::new();
+            let mut mv = Move::from_squares(Square::E8, Square::G8);
             mv.set_kingside_castle();
             moves.push(MoveScore::new(mv));
         }
 
         if self.can_castle_queenside_black(friendly_pieces | enemy_pieces, enemy_attacks) {
-            let mut mv = Move::new();
+            let mut mv = Move::from_squares(Square::E8, Square::C8);
             mv.set_queenside_castle();
             moves.push(MoveScore::new(mv));
         }
@@ -650,25 +806,19 @@ impl Game<'_> {
 
     pub fn enumerate_white_rook_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let target_squares =
             MoveGenerator::get_rook_attacks(initial_square, !(friendly_pieces | enemy_pieces))
                 & !friendly_pieces;
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
-
-            if target_squares & target_square_bit == 0 {
-                continue;
-            }
-
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
@@ -682,25 +832,19 @@ impl Game<'_> {
 
     pub fn enumerate_black_rook_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let target_squares =
             MoveGenerator::get_rook_attacks(initial_square, !(friendly_pieces | enemy_pieces))
                 & !friendly_pieces;
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            if target_squares & target_square_bit == 0 {
-                continue;
-            }
-
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
-
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
@@ -714,25 +858,19 @@ impl Game<'_> {
 
     pub fn enumerate_white_bishop_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let target_squares =
             MoveGenerator::get_bishop_attacks(initial_square, !(friendly_pieces | enemy_pieces))
                 & !friendly_pieces;
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
-
-            if target_squares & target_square_bit == 0 {
-                continue;
-            }
-
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
@@ -746,25 +884,19 @@ impl Game<'_> {
 
     pub fn enumerate_black_bishop_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         let target_squares =
             MoveGenerator::get_bishop_attacks(initial_square, !(friendly_pieces | enemy_pieces))
                 & !friendly_pieces;
 
-        for target_square in 0..64 {
-            let target_square_bit = 1 << target_square;
-
-            if target_squares & target_square_bit == 0 {
-                continue;
-            }
-
-            let mut mv = Move::unchecked_from_squares(initial_square, target_square);
+        for target_square in target_squares {
+            let mut mv = Move::from_squares(initial_square, target_square);
 
-            if target_square_bit & enemy_pieces != 0 {
+            if enemy_pieces.contains(target_square) {
                 mv.set_capture();
             }
 
@@ -778,9 +910,9 @@ impl Game<'_> {
 
     pub fn enumerate_white_queen_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         self.enumerate_white_rook_moves(initial_square, friendly_pieces, enemy_pieces, moves);
@@ -789,44 +921,663 @@ impl Game<'_> {
 
     pub fn enumerate_black_queen_moves(
         &self,
-        initial_square: u8,
-        friendly_pieces: u64,
-        enemy_pieces: u64,
+        initial_square: Square,
+        friendly_pieces: Bitboard,
+        enemy_pieces: Bitboard,
         moves: &mut MoveList,
     ) {
         self.enumerate_black_rook_moves(initial_square, friendly_pieces, enemy_pieces, moves);
         self.enumerate_black_bishop_moves(initial_square, friendly_pieces, enemy_pieces, moves);
     }
 
+    /// Plays the move without checking that it is legal. The move isn't recorded in the
+    /// [history](Self::history()), so this is meant for throwaway copies of a game, e.g.
+    /// while searching.
     pub fn unchecked_make_move(&mut self, mv: Move) {
         self.board_state.make_move(mv);
     }
-}
 
-#[derive(Debug)]
-pub struct BoardHistory {
-    vec: Vec<BoardState>,
-}
+    /// Plays the move if it is legal in the current position.
+    ///
+    /// The move may have incomplete flags, as produced by [`Move::from_squares_str()`] or by
+    /// parsing long algebraic notation: it is resolved to the fully flagged legal move
+    /// between the same squares first. See [`resolve_move()`](Self::resolve_move()).
+    ///
+    /// # Errors
+    /// Returns an [`IllegalMoveError`] describing why the move was rejected. The game is
+    /// left untouched.
+    pub fn try_make_move(&mut self, mv: Move) -> Result<(), IllegalMoveError> {
+        let mv = self.resolve_move(mv)?;
+        let san = self.format_san(mv);
 
-impl BoardHistory {
-    pub fn new() -> Self {
-        Self {
-            vec: Vec::with_capacity(PROJECTED_GAME_LENGTH),
+        self.board_state.make_move(mv);
+        self.history.push(HistoryEntry {
+            mv,
+            san,
+            board_state: self.board_state.clone(),
+        });
+        self.update_outcome();
+
+        Ok(())
+    }
+
+    /// Returns the legal move matching the given move's squares and promotion piece.
+    ///
+    /// A move only has to carry the flags that can't be deduced from the position: a
+    /// [quiet](MoveKind::Quiet) move matches any legal non-promotion move between the same
+    /// squares (a capture, castle, en passant capture or double pawn push), and a
+    /// [promotion](MoveKind::Promotion) matches the promotion capture to the same piece.
+    ///
+    /// # Errors
+    /// Returns an [`IllegalMoveError`] describing why no legal move matches.
+    pub fn resolve_move(&self, mv: Move) -> Result<Move, IllegalMoveError> {
+        let legal_moves = self.enumerate_moves();
+        let mut same_squares = MoveListIterator::new(&legal_moves)
+            .map(|move_score| move_score.mv)
+            .filter(|legal| {
+                legal.get_initial_square() == mv.get_initial_square()
+                    && legal.get_target_square() == mv.get_target_square()
+            })
+            .peekable();
+
+        let Some(&first) = same_squares.peek() else {
+            return Err(self.diagnose_illegal_move(mv));
+        };
+
+        if let Some(legal) = same_squares.find(|&legal| flags_match(mv, legal)) {
+            return Ok(legal);
+        }
+
+        if first.is_promotion() && !mv.is_promotion() {
+            return Err(IllegalMoveError::MissingPromotion);
+        }
+
+        Err(IllegalMoveError::WrongFlags(first))
+    }
+
+    /// Works out why a move with no legal counterpart is illegal.
+    fn diagnose_illegal_move(&self, mv: Move) -> IllegalMoveError {
+        let (initial_square, target_square) = (mv.get_initial_square(), mv.get_target_square());
+        let side_to_move = self.board_state.side_to_move;
+
+        let Some((color, piece)) = self.board_state.position.piece_at(initial_square) else {
+            return IllegalMoveError::EmptySquare(initial_square);
+        };
+
+        if color != side_to_move {
+            return IllegalMoveError::WrongSide(initial_square);
+        }
+
+        let back_rank = match color {
+            Color::White => Rank::Eighth,
+            Color::Black => Rank::First,
+        };
+
+        if mv.is_promotion() && (piece != Piece::Pawn || target_square.rank() != back_rank) {
+            return IllegalMoveError::UnexpectedPromotion;
+        }
+
+        let home_rank = back_rank.flip();
+
+        if piece == Piece::King
+            && initial_square == Square::new(File::E, home_rank)
+            && target_square.rank() == home_rank
+            && matches!(target_square.file(), File::C | File::G)
+        {
+            return self.diagnose_illegal_castle(color, target_square.file() == File::G);
+        }
+
+        if !self
+            .pseudo_legal_targets(initial_square, color, piece)
+            .contains(target_square)
+        {
+            return IllegalMoveError::UnreachableSquare(piece, target_square);
+        }
+
+        if piece == Piece::King {
+            return IllegalMoveError::IntoCheck;
+        }
+
+        // The move is only illegal because of the king's safety. If playing it uncovers a new
+        // attacker, the piece was pinned.
+        let Some(king_square) = self
+            .board_state
+            .position
+            .board(color, Piece::King)
+            .to_square()
+        else {
+            return IllegalMoveError::IntoCheck;
+        };
+
+        let mut flagged = mv;
+        flagged.set_quiet();
+
+        if self.board_state.position.piece_at(target_square).is_some() {
+            flagged.set_capture();
+        } else if piece == Piece::Pawn && initial_square.file() != target_square.file() {
+            flagged.set_en_passant_capture();
+        }
+
+        let mut next_state = self.board_state.clone();
+        next_state.make_move(flagged);
+
+        let checkers_before = self.board_state.attackers_to(
+            self.move_gen,
+            king_square,
+            color.enemy(),
+            self.board_state.position.all_boards(),
+        );
+        let checkers_after = next_state.attackers_to(
+            self.move_gen,
+            king_square,
+            color.enemy(),
+            next_state.position.all_boards(),
+        );
+
+        if !(checkers_after & !checkers_before).is_empty() {
+            return IllegalMoveError::Pinned(initial_square);
+        }
+
+        IllegalMoveError::IntoCheck
+    }
+
+    /// Works out why the given castle isn't legal.
+    fn diagnose_illegal_castle(&self, color: Color, kingside: bool) -> IllegalMoveError {
+        let (has_rights, king_path, between) = match (color, kingside) {
+            (Color::White, true) => (
+                self.board_state.has_castling_rights_kingside_white(),
+                KINGSIDE_WHITE_SQUARES,
+                KINGSIDE_WHITE_SQUARES,
+            ),
+            (Color::Black, true) => (
+                self.board_state.has_castling_rights_kingside_black(),
+                KINGSIDE_BLACK_SQUARES,
+                KINGSIDE_BLACK_SQUARES,
+            ),
+            (Color::White, false) => (
+                self.board_state.has_castling_rights_queenside_white(),
+                QUEENSIDE_WHITE_SQUARES,
+                QUEENSIDE_WHITE_SQUARES | QUEENSIDE_ROOK_SQUARE_WHITE,
+            ),
+            (Color::Black, false) => (
+                self.board_state.has_castling_rights_queenside_black(),
+                QUEENSIDE_BLACK_SQUARES,
+                QUEENSIDE_BLACK_SQUARES | QUEENSIDE_ROOK_SQUARE_BLACK,
+            ),
+        };
+
+        if !has_rights {
+            return IllegalMoveError::NoCastlingRights;
+        }
+
+        let enemy_attacks = self.get_attacks(color.enemy());
+
+        if !(self.board_state.position.board(color, Piece::King) & enemy_attacks).is_empty() {
+            return IllegalMoveError::CastlingOutOfCheck;
+        }
+
+        if !(self.board_state.position.all_boards() & between).is_empty() {
+            return IllegalMoveError::CastlingBlocked;
+        }
+
+        // Walk from the king outwards so the first attacked square is reported.
+        let attacked_path = king_path & enemy_attacks;
+        let attacked_square = if kingside {
+            attacked_path.lsb()
+        } else {
+            attacked_path.msb()
+        };
+
+        match attacked_square {
+            Some(square) => IllegalMoveError::CastlingThroughCheck(square),
+            None => IllegalMoveError::IntoCheck,
+        }
+    }
+
+    /// Returns every square the piece could move to if the safety of its king didn't
+    /// matter. Castling is not included.
+    fn pseudo_legal_targets(&self, initial_square: Square, color: Color, piece: Piece) -> Bitboard {
+        let position = &self.board_state.position;
+        let occupancy = position.all_boards();
+        let (friendly_pieces, enemy_pieces) = match color {
+            Color::White => (position.white(), position.black()),
+            Color::Black => (position.black(), position.white()),
+        };
+
+        match piece {
+            Piece::Pawn => {
+                let (pushes, attacks, forward) = match color {
+                    Color::White => (
+                        self.move_gen.get_white_pawn_moves(initial_square),
+                        self.move_gen.get_white_pawn_attacks(initial_square),
+                        1,
+                    ),
+                    Color::Black => (
+                        self.move_gen.get_black_pawn_moves(initial_square),
+                        self.move_gen.get_black_pawn_attacks(initial_square),
+                        -1,
+                    ),
+                };
+
+                let blocked = initial_square
+                    .offset(0, forward)
+                    .is_some_and(|square| occupancy.contains(square));
+                let pushes = if blocked {
+                    Bitboard::EMPTY
+                } else {
+                    pushes & !occupancy
+                };
+
+                let en_passant_square = self
+                    .board_state
+                    .en_passant_square
+                    .map_or(Bitboard::EMPTY, Square::bitboard);
+
+                pushes | attacks & (enemy_pieces | en_passant_square)
+            }
+            Piece::Knight => self.move_gen.get_knight_attacks(initial_square) & !friendly_pieces,
+            Piece::King => self.move_gen.get_king_attacks(initial_square) & !friendly_pieces,
+            Piece::Rook => {
+                MoveGenerator::get_rook_attacks(initial_square, !occupancy) & !friendly_pieces
+            }
+            Piece::Bishop => {
+                MoveGenerator::get_bishop_attacks(initial_square, !occupancy) & !friendly_pieces
+            }
+            Piece::Queen => {
+                MoveGenerator::get_queen_attacks(initial_square, !occupancy) & !friendly_pieces
+            }
+        }
+    }
+
+    /// Returns the outcome of the game, or [`None`] if it is still going.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// Returns the moves played so far and the positions they led to.
+    pub fn history(&self) -> &BoardHistory {
+        &self.history
+    }
+
+    /// Returns the number of moves played to reach the current position.
+    pub fn ply(&self) -> usize {
+        self.history.ply()
+    }
+
+    /// Takes back the last move, returning it, or [`None`] if no move has been played.
+    pub fn undo(&mut self) -> Option<Move> {
+        let ply = self.history.ply().checked_sub(1)?;
+        let mv = self.history.get(ply + 1)?.mv;
+
+        self.goto_ply(ply);
+
+        Some(mv)
+    }
+
+    /// Replays the last undone move, returning it, or [`None`] if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<Move> {
+        let ply = self.history.ply() + 1;
+        let mv = self.history.get(ply)?.mv;
+
+        self.goto_ply(ply);
+
+        Some(mv)
+    }
+
+    /// Jumps to the position at the given ply (`0` is the starting position). Returns `false`
+    /// if the ply hasn't been recorded in the history.
+    pub fn goto_ply(&mut self, ply: usize) -> bool {
+        let Some(board_state) = self.history.goto_ply(ply) else {
+            return false;
+        };
+
+        self.board_state = board_state.clone();
+        self.update_outcome();
+
+        true
+    }
+
+    /// Returns how many times the current position has occurred in the game, counting
+    /// itself.
+    pub fn repetition_count(&self) -> usize {
+        self.history.repetition_count()
+    }
+
+    /// Returns `true` if the current position has occurred at least three times, and
+    /// `false` otherwise.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    /// Returns `true` if the side to move can claim a draw, by threefold repetition, by
+    /// the fifty-move rule or for insufficient material, and `false` otherwise. See
+    /// [`draw_by_rule()`](Self::draw_by_rule()).
+    pub fn can_claim_draw(&self) -> bool {
+        self.draw_by_rule().is_some()
+    }
+
+    /// Ends the game as a draw if the side to move can claim one. Returns `false` if it
+    /// can't. See [`can_claim_draw()`](Self::can_claim_draw()).
+    pub fn claim_draw(&mut self) -> bool {
+        if !self.can_claim_draw() {
+            return false;
+        }
+
+        self.outcome = Some(Outcome::Draw);
+
+        true
+    }
+
+    /// Returns the rule drawing the current position, or [`None`] if the game can go on:
+    /// threefold repetition, the fifty-move rule or insufficient material. Unlike
+    /// checkmate and stalemate, these don't end the game by themselves, as players may
+    /// claim them or play on.
+    pub fn draw_by_rule(&self) -> Option<DrawReason> {
+        if self.is_threefold_repetition() {
+            Some(DrawReason::Repetition)
+        } else if self.board_state.fifty_move_rule >= 100 {
+            Some(DrawReason::FiftyMoves)
+        } else if self.board_state.is_insufficient_material() {
+            Some(DrawReason::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+
+    /// Ends the game as a draw if it isn't over and [`draw_by_rule()`](Self::draw_by_rule())
+    /// finds a rule drawing it, returning that rule. Games between engines call this after
+    /// every move, as they would otherwise never end in a drawn position, while human
+    /// players [claim](Self::claim_draw()) draws themselves.
+    pub fn adjudicate_draw(&mut self) -> Option<DrawReason> {
+        if self.outcome.is_some() {
+            return None;
+        }
+
+        let reason = self.draw_by_rule()?;
+        self.agree_draw();
+
+        Some(reason)
+    }
+
+    /// Ends the game as a draw agreed by both players.
+    pub fn agree_draw(&mut self) {
+        self.outcome = Some(Outcome::Draw);
+    }
+
+    /// Ends the game as a win for the opponent of the resigning side.
+    pub fn resign(&mut self, color: Color) {
+        self.outcome = Some(Outcome::Win(color.enemy()));
+    }
+
+    /// Returns `true` if the side to move is in check, and `false` otherwise.
+    pub fn is_in_check(&self) -> bool {
+        !self.checkers().is_empty()
+    }
+
+    /// Sets the outcome to a win for the side that just moved on checkmate, a draw on
+    /// stalemate, and clears it otherwise.
+    fn update_outcome(&mut self) {
+        self.outcome = if !self.enumerate_moves().is_empty() {
+            None
+        } else if self.is_in_check() {
+            Some(Outcome::Win(self.board_state.side_to_move.enemy()))
+        } else {
+            Some(Outcome::Draw)
+        };
+    }
+
+    /// Returns the move in standard algebraic notation (SAN), e.g. `Nbd7`, `exd6` or
+    /// `e8=Q#`.
+    ///
+    /// # Errors
+    /// Returns an [`IllegalMoveError`] if the move isn't legal. See
+    /// [`resolve_move()`](Self::resolve_move()).
+    pub fn to_san(&self, mv: Move) -> Result<String, IllegalMoveError> {
+        Ok(self.format_san(self.resolve_move(mv)?))
+    }
+
+    /// Returns the legal move written in standard algebraic notation. Check and annotation
+    /// suffixes (`+`, `#`, `!`, `?`) are ignored, castling may be written with zeros
+    /// (`0-0`), and the `=` of a promotion may be left out (`e8Q`).
+    ///
+    /// # Errors
+    /// Returns a [`SanError`] if the string isn't SAN, or if it doesn't match exactly one
+    /// legal move.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let bad_notation = || SanError::BadNotation(san.to_string());
+        let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+
+        let legal_moves = self.enumerate_moves();
+        let legal_moves = MoveListIterator::new(&legal_moves).map(|move_score| move_score.mv);
+
+        let candidates: Vec<Move> = match trimmed {
+            "O-O" | "0-0" => legal_moves.filter(Move::is_kingside_castle).collect(),
+            "O-O-O" | "0-0-0" => legal_moves.filter(Move::is_queenside_castle).collect(),
+            _ => {
+                if !trimmed.is_ascii() {
+                    return Err(bad_notation());
+                }
+
+                let (piece, rest) = match trimmed.as_bytes().first() {
+                    Some(b'N') => (Piece::Knight, &trimmed[1..]),
+                    Some(b'B') => (Piece::Bishop, &trimmed[1..]),
+                    Some(b'R') => (Piece::Rook, &trimmed[1..]),
+                    Some(b'Q') => (Piece::Queen, &trimmed[1..]),
+                    Some(b'K') => (Piece::King, &trimmed[1..]),
+                    _ => (Piece::Pawn, trimmed),
+                };
+
+                let (rest, promotion) = match rest.as_bytes().last() {
+                    Some(b'N') => (&rest[..rest.len() - 1], Some(Piece::Knight)),
+                    Some(b'B') => (&rest[..rest.len() - 1], Some(Piece::Bishop)),
+                    Some(b'R') => (&rest[..rest.len() - 1], Some(Piece::Rook)),
+                    Some(b'Q') => (&rest[..rest.len() - 1], Some(Piece::Queen)),
+                    _ => (rest, None),
+                };
+                let rest = match promotion {
+                    Some(_) => rest.strip_suffix('=').unwrap_or(rest),
+                    None => rest,
+                };
+
+                if rest.len() < 2 {
+                    return Err(bad_notation());
+                }
+
+                let (disambiguation, target) = rest.split_at(rest.len() - 2);
+                let target_square: Square = target.parse().map_err(|_| bad_notation())?;
+                let disambiguation = disambiguation.strip_suffix('x').unwrap_or(disambiguation);
+
+                let (mut file, mut rank) = (None, None);
+
+                for c in disambiguation.chars() {
+                    match c {
+                        'a'..='h' if file.is_none() => file = c.to_string().parse::<File>().ok(),
+                        '1'..='8' if rank.is_none() => rank = c.to_string().parse::<Rank>().ok(),
+                        _ => return Err(bad_notation()),
+                    }
+                }
+
+                let position = &self.board_state.position;
+
+                legal_moves
+                    .filter(|mv| {
+                        let initial_square = mv.get_initial_square();
+
+                        mv.get_target_square() == target_square
+                            && !mv.is_kingside_castle()
+                            && !mv.is_queenside_castle()
+                            && mv.promotion_piece() == promotion
+                            && position.piece_at(initial_square).map(|(_, piece)| piece)
+                                == Some(piece)
+                            && file.is_none_or(|file| initial_square.file() == file)
+                            && rank.is_none_or(|rank| initial_square.rank() == rank)
+                    })
+                    .collect()
+            }
+        };
+
+        match candidates[..] {
+            [mv] => Ok(mv),
+            [] => Err(SanError::NoMatch(san.to_string())),
+            _ => Err(SanError::Ambiguous(san.to_string())),
         }
     }
 
-    pub fn get(&self, index: usize) -> Option<&BoardState> {
-        self.vec.get(index)
+    /// Formats a fully flagged legal move in SAN.
+    fn format_san(&self, mv: Move) -> String {
+        let mut san = String::with_capacity(8);
+        let (initial_square, target_square) = (mv.get_initial_square(), mv.get_target_square());
+        let position = &self.board_state.position;
+        let piece_at = |square| position.piece_at(square).map(|(_, piece)| piece);
+
+        if mv.is_kingside_castle() {
+            san.push_str("O-O");
+        } else if mv.is_queenside_castle() {
+            san.push_str("O-O-O");
+        } else {
+            let piece = piece_at(initial_square).unwrap_or(Piece::Pawn);
+
+            if piece == Piece::Pawn {
+                if mv.is_capture() {
+                    san.push(initial_square.file().to_char());
+                }
+            } else {
+                san.push(piece.to_char());
+
+                let legal_moves = self.enumerate_moves();
+                let rivals: Bitboard = MoveListIterator::new(&legal_moves)
+                    .map(|move_score| move_score.mv)
+                    .filter(|other| {
+                        other.get_target_square() == target_square
+                            && other.get_initial_square() != initial_square
+                            && piece_at(other.get_initial_square()) == Some(piece)
+                    })
+                    .map(|other| other.get_initial_square())
+                    .collect();
+
+                if !rivals.is_empty() {
+                    let file = initial_square.file().bitboard();
+                    let rank = initial_square.rank().bitboard();
+
+                    if (rivals & file).is_empty() {
+                        san.push(initial_square.file().to_char());
+                    } else if (rivals & rank).is_empty() {
+                        san.push(initial_square.rank().to_char());
+                    } else {
+                        san.push_str(&initial_square.to_string());
+                    }
+                }
+            }
+
+            if mv.is_capture() {
+                san.push('x');
+            }
+
+            san.push_str(&target_square.to_string());
+
+            if let Some(piece) = mv.promotion_piece() {
+                san.push('=');
+                san.push(piece.to_char());
+            }
+        }
+
+        let mut next_turn = self.clone();
+        next_turn.board_state.make_move(mv);
+
+        if next_turn.is_in_check() {
+            if next_turn.enumerate_moves().is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+
+        san
     }
 
-    // TODO: Consider adding validation logic so it is guaranteed turns
-    // are all sequential. This logic may be applied elsewhere instead.
-    pub fn push(&mut self, board_state: BoardState) {
-        self.vec.push(board_state);
+    /// Returns the moves leading to the current position as a PGN string, with the Seven Tag
+    /// Roster and, if the game didn't start from the initial position, its starting FEN.
+    pub fn to_pgn(&self) -> String {
+        self.to_pgn_with_tags(&[])
+    }
+
+    /// Like [`to_pgn()`](Self::to_pgn()), but with the given tag pairs. Tags of the Seven Tag
+    /// Roster replace the `?` placeholders, except for `Result`, and other tags are written
+    /// after the roster in the given order.
+    pub fn to_pgn_with_tags(&self, extra_tags: &[(&str, &str)]) -> String {
+        let result = match self.outcome {
+            Some(Outcome::Win(Color::White)) => "1-0",
+            Some(Outcome::Win(Color::Black)) => "0-1",
+            Some(Outcome::Draw) => "1/2-1/2",
+            None => "*",
+        };
+
+        let mut pgn = String::new();
+        let tags = [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", result),
+        ];
+
+        for (name, value) in tags {
+            let value = match extra_tags.iter().find(|&&(extra, _)| extra == name) {
+                Some(&(_, extra_value)) if name != "Result" => extra_value,
+                _ => value,
+            };
+
+            pgn::write_tag(&mut pgn, name, value);
+        }
+
+        for &(name, value) in extra_tags {
+            if !tags.iter().any(|&(roster, _)| roster == name) {
+                pgn::write_tag(&mut pgn, name, value);
+            }
+        }
+
+        let start = self.history.start();
+
+        if *start != BoardState::default() {
+            pgn::write_tag(&mut pgn, "SetUp", "1");
+            pgn::write_tag(&mut pgn, "FEN", &start.to_fen());
+        }
+
+        pgn.push('\n');
+
+        let mut tokens = Vec::with_capacity(self.history.ply() * 3 / 2 + 1);
+        let mut before = start;
+
+        for (i, entry) in self.history.entries().enumerate() {
+            match before.side_to_move {
+                Color::White => tokens.push(format!("{}.", before.turn_count)),
+                Color::Black if i == 0 => tokens.push(format!("{}...", before.turn_count)),
+                Color::Black => (),
+            }
+
+            tokens.push(entry.san.clone());
+            before = &entry.board_state;
+        }
+
+        tokens.push(result.to_string());
+        pgn::write_movetext(&mut pgn, tokens);
+
+        pgn
     }
+}
 
-    pub fn pop(&mut self) -> Option<BoardState> {
-        self.vec.pop()
+/// Returns `true` if `mv` is the legal move `legal`, possibly with incomplete flags. See
+/// [`Game::resolve_move()`].
+fn flags_match(mv: Move, legal: Move) -> bool {
+    match (mv.kind(), legal.kind()) {
+        (kind, legal_kind) if kind == legal_kind => true,
+        (MoveKind::Quiet, legal_kind) => legal_kind.promotion_piece().is_none(),
+        (MoveKind::Promotion(piece), MoveKind::PromotionCapture(legal_piece)) => {
+            piece == legal_piece
+        }
+        _ => false,
     }
 }
 
@@ -840,7 +1591,7 @@ mod tests {
         let game = Game::new(&move_gen);
         let enemy_attacks = game.get_attacks(game.board_state.side_to_move.enemy());
 
-        assert_eq!(enemy_attacks.count_ones(), 22);
+        assert_eq!(enemy_attacks.count(), 22);
     }
 
     // TODO: Add more test cases
@@ -944,4 +1695,283 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_try_make_move() {
+        let move_gen = MoveGenerator::new();
+        let mut game = Game::new(&move_gen);
+        let mv = |s: &str| s.parse::<Move>().unwrap();
+
+        assert_eq!(game.try_make_move(mv("e2e4")), Ok(()));
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+
+        assert_eq!(
+            game.try_make_move(mv("e4e5")),
+            Err(IllegalMoveError::WrongSide(Square::E4))
+        );
+        assert_eq!(
+            game.try_make_move(mv("e5e4")),
+            Err(IllegalMoveError::EmptySquare(Square::E5))
+        );
+        assert_eq!(
+            game.try_make_move(mv("b8d7")),
+            Err(IllegalMoveError::UnreachableSquare(
+                Piece::Knight,
+                Square::D7
+            ))
+        );
+        assert_eq!(
+            game.try_make_move(mv("e7e8q")),
+            Err(IllegalMoveError::UnexpectedPromotion)
+        );
+
+        let mut capture = Move::from_squares(Square::E7, Square::E5);
+        capture.set_capture();
+        assert!(matches!(
+            game.try_make_move(capture),
+            Err(IllegalMoveError::WrongFlags(legal)) if legal.is_double_pawn_push()
+        ));
+
+        // Rejected moves leave the game untouched.
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+    }
+
+    #[test]
+    fn test_try_make_move_king_safety() {
+        let move_gen = MoveGenerator::new();
+        let mv = |s: &str| s.parse::<Move>().unwrap();
+
+        let fen = "4k3/4r3/8/8/8/8/4B3/4K2R w K - 0 1";
+        let mut game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+
+        assert_eq!(
+            game.try_make_move(mv("e2d3")),
+            Err(IllegalMoveError::Pinned(Square::E2))
+        );
+        assert_eq!(game.try_make_move(mv("e1f2")), Ok(()));
+
+        let fen = "4k3/8/8/8/8/8/3r4/4K3 w - - 0 1";
+        let mut game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+
+        assert_eq!(
+            game.try_make_move(mv("e1d1")),
+            Err(IllegalMoveError::IntoCheck)
+        );
+        assert_eq!(game.try_make_move(mv("e1d2")), Ok(()));
+    }
+
+    #[test]
+    fn test_try_make_move_castling_and_promotion() {
+        let move_gen = MoveGenerator::new();
+        let mv = |s: &str| s.parse::<Move>().unwrap();
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let mut game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+        assert_eq!(game.try_make_move(mv("e1g1")), Ok(()));
+        assert_eq!(game.try_make_move(mv("e8c8")), Ok(()));
+        assert_eq!(game.to_fen(), "2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2");
+
+        let fen = "r3k2r/8/8/8/8/5q2/8/R3K2R w KQkq - 0 1";
+        let mut game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+        assert_eq!(
+            game.try_make_move(mv("e1g1")),
+            Err(IllegalMoveError::CastlingThroughCheck(Square::F1))
+        );
+
+        let fen = "r3k2r/8/8/8/8/8/8/RN2K2R w Kkq - 0 1";
+        let mut game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+        assert_eq!(
+            game.try_make_move(mv("e1c1")),
+            Err(IllegalMoveError::NoCastlingRights)
+        );
+
+        let fen = "r3k2r/8/8/8/8/8/8/RN2K2R w KQkq - 0 1";
+        let mut game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+        assert_eq!(
+            game.try_make_move(mv("e1c1")),
+            Err(IllegalMoveError::CastlingBlocked)
+        );
+
+        let fen = "1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        let mut game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+        assert_eq!(
+            game.try_make_move(mv("a7b8")),
+            Err(IllegalMoveError::MissingPromotion)
+        );
+        assert_eq!(game.try_make_move(mv("a7b8n")), Ok(()));
+        assert_eq!(game.to_fen(), "1N2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    fn play(game: &mut Game, moves: &str) {
+        for mv in moves.split_whitespace() {
+            game.try_make_move(mv.parse().unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let move_gen = MoveGenerator::new();
+        let mut game = Game::new(&move_gen);
+        play(&mut game, "e2e4 e7e5 g1f3");
+
+        assert_eq!(game.ply(), 3);
+        assert_eq!(game.undo(), Some("g1f3".parse().unwrap()));
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+        assert_eq!(game.redo(), Some("g1f3".parse().unwrap()));
+        assert_eq!(game.redo(), None);
+
+        assert!(game.goto_ply(0));
+        assert_eq!(game.to_fen(), BoardState::default().to_fen());
+        assert_eq!(game.undo(), None);
+        assert!(!game.goto_ply(4));
+
+        // Playing a different move drops the undone moves.
+        play(&mut game, "d2d4");
+        assert_eq!(game.history().len(), 1);
+        assert_eq!(
+            game.history()
+                .iter()
+                .map(|(ply, mv, _)| (ply, mv.to_string()))
+                .collect::<Vec<_>>(),
+            [(1, "d2d4".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_to_san() {
+        let move_gen = MoveGenerator::new();
+        let mv = |s: &str| s.parse::<Move>().unwrap();
+
+        let fen = "r3k2r/1P6/8/3pP3/8/2N5/8/R3K1NR w KQkq d6 0 1";
+        let game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+
+        assert_eq!(game.to_san(mv("e5d6")), Ok("exd6".to_string()));
+        assert_eq!(game.to_san(mv("g1e2")), Ok("Nge2".to_string()));
+        assert_eq!(game.to_san(mv("e1c1")), Ok("O-O-O".to_string()));
+        assert_eq!(game.to_san(mv("b7a8q")), Ok("bxa8=Q+".to_string()));
+        assert_eq!(game.to_san(mv("a1a8")), Ok("Rxa8+".to_string()));
+        assert!(game.to_san(mv("e1g1")).is_err());
+
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+        assert_eq!(game.to_san(mv("a1a8")), Ok("Ra8#".to_string()));
+    }
+
+    #[test]
+    fn test_to_pgn() {
+        let move_gen = MoveGenerator::new();
+        let mut game = Game::new(&move_gen);
+        play(&mut game, "f2f3 e7e5 g2g4 d8h4");
+
+        assert_eq!(game.outcome(), Some(Outcome::Win(Color::Black)));
+        assert!(game.to_pgn().ends_with("\n\n1. f3 e5 2. g4 Qh4# 0-1\n"));
+        assert!(game.to_pgn().contains("[Result \"0-1\"]"));
+
+        game.undo();
+        assert_eq!(game.outcome(), None);
+
+        let fen = "4k3/8/8/8/8/8/8/4K2R b K - 0 10";
+        let mut game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+        play(&mut game, "e8d7 e1g1");
+
+        let pgn = game.to_pgn();
+        assert!(pgn.contains(&format!("[FEN \"{fen}\"]")));
+        assert!(pgn.ends_with("\n\n10... Kd7 11. O-O *\n"));
+
+        let pgn = game.to_pgn_with_tags(&[("White", "poob"), ("Result", "1-0"), ("Ply", "2")]);
+        assert!(pgn.starts_with("[Event \"?\"]\n"));
+        assert!(pgn.contains("[White \"poob\"]\n[Black \"?\"]\n[Result \"*\"]\n[Ply \"2\"]\n"));
+    }
+
+    #[test]
+    fn test_repetition() {
+        let move_gen = MoveGenerator::new();
+        let mut game = Game::new(&move_gen);
+
+        play(&mut game, "g1f3 g8f6 f3g1 f6g8");
+        assert_eq!(game.repetition_count(), 2);
+
+        play(&mut game, "g1f3 g8f6 f3g1");
+        assert!(!game.is_threefold_repetition());
+
+        play(&mut game, "f6g8");
+        assert!(game.is_threefold_repetition());
+
+        game.undo();
+        assert_eq!(game.repetition_count(), 2);
+    }
+
+    #[test]
+    fn test_from_fen_strict() {
+        let move_gen = MoveGenerator::new();
+        let fen = "4k3/8/8/8/8/8/8/4KR2 b - - 0 1";
+
+        assert_eq!(
+            Game::from_fen_strict(fen, &move_gen)
+                .unwrap()
+                .get_board_state(),
+            &BoardState::from_fen(fen).unwrap()
+        );
+        assert!(matches!(
+            Game::from_fen_strict("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1", &move_gen),
+            Err(BoardStateCreationError::IllegalPosition(_))
+        ));
+    }
+
+    #[test]
+    fn test_draw_by_rule() {
+        let move_gen = MoveGenerator::new();
+        let mut game = Game::new(&move_gen);
+
+        play(&mut game, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1");
+        assert_eq!(game.draw_by_rule(), None);
+
+        play(&mut game, "f6g8");
+        assert_eq!(game.draw_by_rule(), Some(DrawReason::Repetition));
+        assert_eq!(game.adjudicate_draw(), Some(DrawReason::Repetition));
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+        assert_eq!(game.adjudicate_draw(), None);
+
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 99 80";
+        let mut game = Game::from_fen(fen, &move_gen).unwrap();
+        assert_eq!(game.draw_by_rule(), None);
+
+        play(&mut game, "a1a2");
+        assert_eq!(game.draw_by_rule(), Some(DrawReason::FiftyMoves));
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4KB2 w - - 0 1", &move_gen).unwrap();
+        assert_eq!(game.draw_by_rule(), Some(DrawReason::InsufficientMaterial));
+        assert!(game.can_claim_draw());
+
+        // Mate is still possible, if not forced.
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1", &move_gen).unwrap();
+        assert_eq!(game.draw_by_rule(), None);
+    }
+
+    #[test]
+    fn test_parse_san() {
+        let move_gen = MoveGenerator::new();
+        let fen = "r3k2r/1P6/8/3pP3/8/2N5/8/R3K1NR w KQkq d6 0 1";
+        let game = Game::from_fen(fen, &move_gen).expect("Invalid FEN");
+        let san = |s: &str| game.parse_san(s).map(|mv| mv.to_string());
+
+        assert_eq!(san("exd6"), Ok("e5d6".to_string()));
+        assert_eq!(san("Nge2"), Ok("g1e2".to_string()));
+        assert_eq!(san("N1e2"), Ok("g1e2".to_string()));
+        assert_eq!(san("0-0-0"), Ok("e1c1".to_string()));
+        assert_eq!(san("bxa8=Q+"), Ok("b7a8q".to_string()));
+        assert_eq!(san("bxa8N!?"), Ok("b7a8n".to_string()));
+        assert_eq!(san("Ne2"), Err(SanError::Ambiguous("Ne2".to_string())));
+        assert_eq!(san("Ke3"), Err(SanError::NoMatch("Ke3".to_string())));
+        assert_eq!(san("Zz9"), Err(SanError::BadNotation("Zz9".to_string())));
+    }
 }
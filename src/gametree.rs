@@ -0,0 +1,893 @@
+use crate::boardstate::BoardState;
+use crate::game::{Game, IllegalMoveError, SanError};
+use crate::movegen::MoveGenerator;
+use crate::moves::Move;
+use crate::pgn::{self, PgnError, Token, Tokenizer};
+use crate::{Color, Square};
+use std::time::Duration;
+
+/// Identifies a node of a [`GameTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// The root of every tree, holding the starting position.
+    pub const ROOT: NodeId = NodeId(0);
+}
+
+/// The colors available for arrows and highlighted squares in `[%cal]` and `[%csl]`
+/// comment commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarkColor {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
+
+impl MarkColor {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'R' => Some(Self::Red),
+            'G' => Some(Self::Green),
+            'B' => Some(Self::Blue),
+            'Y' => Some(Self::Yellow),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Self::Red => 'R',
+            Self::Green => 'G',
+            Self::Blue => 'B',
+            Self::Yellow => 'Y',
+        }
+    }
+}
+
+/// An arrow drawn from one square to another, written as e.g. `Ge2e4` in `[%cal]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Arrow {
+    pub color: MarkColor,
+    pub from: Square,
+    pub to: Square,
+}
+
+/// A highlighted square, written as e.g. `Rd4` in `[%csl]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Highlight {
+    pub color: MarkColor,
+    pub square: Square,
+}
+
+/// The annotations attached to a node, written in PGN after the node's move.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Annotations {
+    pub comment: Option<String>,
+    /// Numeric annotation glyphs, e.g. `1` for `!` or `14` for "white is slightly better".
+    pub nags: Vec<u8>,
+    pub arrows: Vec<Arrow>,
+    pub highlights: Vec<Highlight>,
+    /// The clock time remaining after the move, from `[%clk]`.
+    pub clock: Option<Duration>,
+}
+
+impl Annotations {
+    /// Returns `true` if there are no annotations, and `false` otherwise.
+    pub fn is_empty(&self) -> bool {
+        *self == Annotations::default()
+    }
+
+    /// Reads the text of a PGN comment, pulling out the `[%cal]`, `[%csl]` and `[%clk]`
+    /// commands. Any other text, including unknown commands, is kept in the comment.
+    fn add_comment(&mut self, text: &str) {
+        let mut remaining = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find("[%") {
+            let Some(length) = rest[start..].find(']') else {
+                break;
+            };
+
+            let command = &rest[start + 2..start + length];
+            let (name, args) = command.trim().split_once(' ').unwrap_or((command, ""));
+            let args = args.split(',').map(str::trim);
+
+            let known = match name {
+                "cal" => args
+                    .map(|arg| {
+                        let color = MarkColor::from_char(arg.chars().next()?)?;
+                        let from = arg.get(1..3)?.parse().ok()?;
+                        let to = arg.get(3..5)?.parse().ok()?;
+
+                        self.arrows.push(Arrow { color, from, to });
+                        Some(())
+                    })
+                    .all(|arrow| arrow.is_some()),
+                "csl" => args
+                    .map(|arg| {
+                        let color = MarkColor::from_char(arg.chars().next()?)?;
+                        let square = arg.get(1..3)?.parse().ok()?;
+
+                        self.highlights.push(Highlight { color, square });
+                        Some(())
+                    })
+                    .all(|highlight| highlight.is_some()),
+                "clk" => {
+                    self.clock = parse_clock(command[3..].trim());
+                    self.clock.is_some()
+                }
+                _ => false,
+            };
+
+            remaining.push_str(&rest[..start]);
+
+            if !known {
+                remaining.push_str(&rest[start..=start + length]);
+            }
+
+            rest = &rest[start + length + 1..];
+        }
+
+        remaining.push_str(rest);
+
+        let remaining = remaining.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        if remaining.is_empty() {
+            return;
+        }
+
+        match &mut self.comment {
+            Some(comment) => {
+                comment.push(' ');
+                comment.push_str(&remaining);
+            }
+            None => self.comment = Some(remaining),
+        }
+    }
+
+    /// Returns the annotations as PGN tokens: the NAGs, followed by a comment holding the
+    /// text and the commands.
+    fn to_tokens(&self) -> Vec<String> {
+        let mut tokens: Vec<String> = self.nags.iter().map(|nag| format!("${nag}")).collect();
+        let mut comment: Vec<String> = self.comment.iter().cloned().collect();
+
+        if !self.highlights.is_empty() {
+            let highlights: Vec<String> = self
+                .highlights
+                .iter()
+                .map(|highlight| format!("{}{}", highlight.color.to_char(), highlight.square))
+                .collect();
+
+            comment.push(format!("[%csl {}]", highlights.join(",")));
+        }
+
+        if !self.arrows.is_empty() {
+            let arrows: Vec<String> = self
+                .arrows
+                .iter()
+                .map(|arrow| format!("{}{}{}", arrow.color.to_char(), arrow.from, arrow.to))
+                .collect();
+
+            comment.push(format!("[%cal {}]", arrows.join(",")));
+        }
+
+        if let Some(clock) = self.clock {
+            comment.push(format!("[%clk {}]", format_clock(clock)));
+        }
+
+        if !comment.is_empty() {
+            tokens.push(format!("{{{}}}", comment.join(" ")));
+        }
+
+        tokens
+    }
+}
+
+/// Parses a clock time of the form `h:mm:ss`, where the seconds may have a fraction.
+fn parse_clock(clock: &str) -> Option<Duration> {
+    let mut fields = clock.split(':');
+    let (hours, minutes, seconds) = (fields.next()?, fields.next()?, fields.next()?);
+
+    if fields.next().is_some() {
+        return None;
+    }
+
+    let hours: u64 = hours.parse().ok()?;
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.parse().ok()?;
+
+    if !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+
+    let millis = (hours * 60 + minutes) * 60_000 + (seconds * 1000.0).round() as u64;
+
+    Some(Duration::from_millis(millis))
+}
+
+/// Formats a clock time as `h:mm:ss`, adding a fraction of a second only when needed.
+fn format_clock(clock: Duration) -> String {
+    let seconds = clock.as_secs();
+    let mut formatted = format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+
+    let millis = clock.subsec_millis();
+
+    if millis != 0 {
+        let fraction = format!("{millis:03}");
+        formatted.push('.');
+        formatted.push_str(fraction.trim_end_matches('0'));
+    }
+
+    formatted
+}
+
+/// A position in a [`GameTree`], reached by playing the node's move from its parent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    mv: Option<Move>,
+    san: String,
+    board_state: BoardState,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    pub annotations: Annotations,
+}
+
+impl Node {
+    /// Returns the move leading to this node, or [`None`] for the root.
+    pub fn mv(&self) -> Option<Move> {
+        self.mv
+    }
+
+    /// Returns the SAN of the move leading to this node, or an empty string for the root.
+    pub fn san(&self) -> &str {
+        &self.san
+    }
+
+    /// Returns the position after the node's move.
+    pub fn board_state(&self) -> &BoardState {
+        &self.board_state
+    }
+
+    /// Returns the node this node's move was played from, or [`None`] for the root.
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    /// Returns the moves played from this node. The first one continues the line this node
+    /// is on, and the others are variations of it.
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// A game with a main line, nested variations and annotations, as used for studies and
+/// analysis.
+///
+/// The tree keeps a cursor on one of its nodes, which can be moved around the tree and is
+/// where new moves are [played](Self::play()).
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameTree {
+    tags: Vec<(String, String)>,
+    nodes: Vec<Option<Node>>,
+    cursor: NodeId,
+}
+
+impl GameTree {
+    /// Returns a tree without any moves, starting from the given position.
+    pub fn new(start: BoardState) -> Self {
+        let root = Node {
+            mv: None,
+            san: String::new(),
+            board_state: start,
+            parent: None,
+            children: Vec::new(),
+            annotations: Annotations::default(),
+        };
+
+        Self {
+            tags: Vec::new(),
+            nodes: vec![Some(root)],
+            cursor: NodeId::ROOT,
+        }
+    }
+
+    /// Returns the node with the given id, or [`None`] if it has been deleted.
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0)?.as_ref()
+    }
+
+    /// Returns the node with the given id, or [`None`] if it has been deleted.
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.0)?.as_mut()
+    }
+
+    /// Returns the value of the tag with the given name.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of a tag, adding it if it isn't there yet.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Returns every tag, in the order they were added.
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Adds a move played from the given node, returning the new node. If the move has
+    /// already been played from there, the existing node is returned instead.
+    ///
+    /// # Panics
+    /// Panics if the parent node has been deleted.
+    ///
+    /// # Errors
+    /// Returns an [`IllegalMoveError`] if the move isn't legal in the parent's position.
+    pub fn add_move(
+        &mut self,
+        parent: NodeId,
+        mv: Move,
+        move_gen: &MoveGenerator,
+    ) -> Result<NodeId, IllegalMoveError> {
+        let parent_node = self.node(parent).expect("parent node has been deleted");
+        let mut game = Game::from_board_state(parent_node.board_state.clone(), move_gen);
+        let mv = game.resolve_move(mv)?;
+
+        if let Some(&existing) = parent_node
+            .children
+            .iter()
+            .find(|&&child| self.node(child).and_then(Node::mv) == Some(mv))
+        {
+            return Ok(existing);
+        }
+
+        game.try_make_move(mv)?;
+
+        let san = game
+            .history()
+            .get(1)
+            .map(|entry| entry.san.clone())
+            .unwrap_or_default();
+
+        let id = NodeId(self.nodes.len());
+
+        self.nodes.push(Some(Node {
+            mv: Some(mv),
+            san,
+            board_state: game.get_board_state().clone(),
+            parent: Some(parent),
+            children: Vec::new(),
+            annotations: Annotations::default(),
+        }));
+
+        if let Some(parent_node) = self.node_mut(parent) {
+            parent_node.children.push(id);
+        }
+
+        Ok(id)
+    }
+
+    /// Like [`add_move()`](Self::add_move()), but takes the move in SAN.
+    ///
+    /// # Errors
+    /// Returns a [`SanError`] if the SAN doesn't match exactly one legal move.
+    pub fn add_san(
+        &mut self,
+        parent: NodeId,
+        san: &str,
+        move_gen: &MoveGenerator,
+    ) -> Result<NodeId, SanError> {
+        let board_state = &self
+            .node(parent)
+            .expect("parent node has been deleted")
+            .board_state;
+        let mv = Game::from_board_state(board_state.clone(), move_gen).parse_san(san)?;
+
+        self.add_move(parent, mv, move_gen)
+            .map_err(|_| SanError::NoMatch(san.to_string()))
+    }
+
+    /// Returns the node the cursor is on.
+    pub fn cursor(&self) -> NodeId {
+        self.cursor
+    }
+
+    /// Moves the cursor to the given node. Returns `false` if the node has been deleted.
+    pub fn set_cursor(&mut self, id: NodeId) -> bool {
+        if self.node(id).is_none() {
+            return false;
+        }
+
+        self.cursor = id;
+
+        true
+    }
+
+    /// Moves the cursor to the parent node. Returns `false` if the cursor is on the root.
+    pub fn go_back(&mut self) -> bool {
+        match self.node(self.cursor).and_then(Node::parent) {
+            Some(parent) => self.set_cursor(parent),
+            None => false,
+        }
+    }
+
+    /// Moves the cursor to the first child, continuing the current line. Returns `false` if
+    /// the line ends here.
+    pub fn go_forward(&mut self) -> bool {
+        self.enter_variation(0)
+    }
+
+    /// Moves the cursor to the child with the given index, where `0` continues the current
+    /// line and the others are variations. Returns `false` if there is no such child.
+    pub fn enter_variation(&mut self, index: usize) -> bool {
+        let child = self
+            .node(self.cursor)
+            .and_then(|node| node.children.get(index).copied());
+
+        match child {
+            Some(child) => self.set_cursor(child),
+            None => false,
+        }
+    }
+
+    /// Moves the cursor to the root.
+    pub fn go_to_start(&mut self) {
+        self.cursor = NodeId::ROOT;
+    }
+
+    /// Moves the cursor to the end of the current line.
+    pub fn go_to_end(&mut self) {
+        while self.go_forward() {}
+    }
+
+    /// Plays a move from the cursor and moves the cursor to the resulting node.
+    ///
+    /// # Errors
+    /// Returns an [`IllegalMoveError`] if the move isn't legal at the cursor.
+    pub fn play(&mut self, mv: Move, move_gen: &MoveGenerator) -> Result<NodeId, IllegalMoveError> {
+        let id = self.add_move(self.cursor, mv, move_gen)?;
+        self.cursor = id;
+
+        Ok(id)
+    }
+
+    /// Returns the nodes from the root to the given node, root first.
+    pub fn path_to(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path = Vec::new();
+        let mut current = Some(id);
+
+        while let Some(id) = current {
+            let Some(node) = self.node(id) else {
+                break;
+            };
+
+            path.push(id);
+            current = node.parent;
+        }
+
+        path.reverse();
+
+        path
+    }
+
+    /// Returns the nodes of the main line, root first.
+    pub fn main_line(&self) -> Vec<NodeId> {
+        let mut line = vec![NodeId::ROOT];
+
+        while let Some(&next) = line
+            .last()
+            .and_then(|&id| self.node(id))
+            .and_then(|node| node.children.first())
+        {
+            line.push(next);
+        }
+
+        line
+    }
+
+    /// Replays the moves leading to the given node through a [`Game`], so the game has the
+    /// full history of the line. Returns [`None`] if the node has been deleted.
+    pub fn game_at<'a>(&self, id: NodeId, move_gen: &'a MoveGenerator) -> Option<Game<'a>> {
+        self.node(id)?;
+
+        let root = self.node(NodeId::ROOT)?;
+        let mut game = Game::from_board_state(root.board_state.clone(), move_gen);
+
+        for node in self.path_to(id).into_iter().filter_map(|id| self.node(id)) {
+            if let Some(mv) = node.mv {
+                game.try_make_move(mv).ok()?;
+            }
+        }
+
+        Some(game)
+    }
+
+    /// Returns the sibling list containing the node and the node's index in it.
+    fn siblings_mut(&mut self, id: NodeId) -> Option<(&mut Vec<NodeId>, usize)> {
+        let parent = self.node(id)?.parent?;
+        let siblings = &mut self.node_mut(parent)?.children;
+        let index = siblings.iter().position(|&sibling| sibling == id)?;
+
+        Some((siblings, index))
+    }
+
+    /// Swaps the variation starting at the node with the one above it, making it the main
+    /// continuation if it was the first variation. Returns `false` if it is already first.
+    pub fn promote_variation(&mut self, id: NodeId) -> bool {
+        match self.siblings_mut(id) {
+            Some((siblings, index)) if index > 0 => {
+                siblings.swap(index, index - 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Swaps the variation starting at the node with the one below it. Returns `false` if it
+    /// is already last.
+    pub fn demote_variation(&mut self, id: NodeId) -> bool {
+        match self.siblings_mut(id) {
+            Some((siblings, index)) if index + 1 < siblings.len() => {
+                siblings.swap(index, index + 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Makes the line through the node the main line, promoting it at every branch on the
+    /// way from the root.
+    pub fn promote_to_main_line(&mut self, id: NodeId) {
+        for node in self.path_to(id) {
+            if let Some((siblings, index)) = self.siblings_mut(node) {
+                let node = siblings.remove(index);
+                siblings.insert(0, node);
+            }
+        }
+    }
+
+    /// Deletes the node and everything after it. If the cursor was on a deleted node, it
+    /// moves to the deleted node's parent. Returns `false` if the node is the root or has
+    /// already been deleted.
+    pub fn delete_variation(&mut self, id: NodeId) -> bool {
+        let Some((siblings, index)) = self.siblings_mut(id) else {
+            return false;
+        };
+
+        siblings.remove(index);
+
+        let parent = self.node(id).and_then(Node::parent);
+
+        if self.path_to(self.cursor).contains(&id) {
+            self.cursor = parent.unwrap_or(NodeId::ROOT);
+        }
+
+        let mut deleted = vec![id];
+
+        while let Some(id) = deleted.pop() {
+            if let Some(node) = self.nodes[id.0].take() {
+                deleted.extend(node.children);
+            }
+        }
+
+        true
+    }
+
+    /// Returns the tree as a PGN string with nested variations. The Seven Tag Roster is
+    /// always written, followed by any other tags.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        let result = self.tag("Result").unwrap_or("*");
+
+        for name in ["Event", "Site", "Date", "Round", "White", "Black", "Result"] {
+            let default = match name {
+                "Date" => "????.??.??",
+                "Result" => "*",
+                _ => "?",
+            };
+
+            pgn::write_tag(&mut pgn, name, self.tag(name).unwrap_or(default));
+        }
+
+        let roster = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+        for (name, value) in self.tags.iter() {
+            if !roster.contains(&name.as_str()) {
+                pgn::write_tag(&mut pgn, name, value);
+            }
+        }
+
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+
+        if let Some(root) = self.node(NodeId::ROOT) {
+            tokens.extend(root.annotations.to_tokens());
+        }
+
+        self.write_line(NodeId::ROOT, true, &mut tokens);
+        tokens.push(result.to_string());
+        pgn::write_movetext(&mut pgn, tokens);
+
+        pgn
+    }
+
+    /// Writes the line continuing from the given node, with the variations branching off it.
+    fn write_line(&self, mut parent: NodeId, mut force_number: bool, tokens: &mut Vec<String>) {
+        while let Some(parent_node) = self.node(parent) {
+            let Some(&main) = parent_node.children.first() else {
+                return;
+            };
+
+            self.write_move(main, force_number, tokens);
+
+            for &variation in &parent_node.children[1..] {
+                tokens.push("(".to_string());
+                self.write_move(variation, true, tokens);
+                self.write_line(variation, false, tokens);
+                tokens.push(")".to_string());
+            }
+
+            // Black's moves get their number again after anything interrupting the line.
+            force_number = parent_node.children.len() > 1
+                || self
+                    .node(main)
+                    .is_some_and(|node| !node.annotations.is_empty());
+            parent = main;
+        }
+    }
+
+    /// Writes a node's move, preceded by its number if needed, and its annotations.
+    fn write_move(&self, id: NodeId, force_number: bool, tokens: &mut Vec<String>) {
+        let Some(node) = self.node(id) else {
+            return;
+        };
+        let Some(before) = node.parent.and_then(|parent| self.node(parent)) else {
+            return;
+        };
+
+        let turn = before.board_state.turn_count;
+
+        match before.board_state.side_to_move {
+            Color::White => tokens.push(format!("{turn}.")),
+            Color::Black if force_number => tokens.push(format!("{turn}...")),
+            Color::Black => (),
+        }
+
+        tokens.push(node.san.clone());
+        tokens.extend(node.annotations.to_tokens());
+    }
+
+    /// Reads the first game of a PGN string, with its variations, comments and NAGs. The
+    /// game starts from the `FEN` tag if there is one.
+    ///
+    /// # Errors
+    /// Returns a [`PgnError`] if the PGN is malformed or contains an illegal move.
+    pub fn from_pgn(pgn: &str, move_gen: &MoveGenerator) -> Result<Self, PgnError> {
+        let (tags, movetext) = pgn::parse_tags(pgn)?;
+
+        let start = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => BoardState::from_fen_lenient(fen).map_err(PgnError::BadFen)?,
+            None => BoardState::default(),
+        };
+
+        let mut tree = GameTree::new(start);
+        tree.tags = tags;
+
+        let mut current = NodeId::ROOT;
+        let mut variation_starts = Vec::new();
+
+        for token in Tokenizer::new(movetext) {
+            match token? {
+                Token::Move(san, suffix) => {
+                    if !san.is_empty() {
+                        current = tree.add_san(current, san, move_gen)?;
+                    }
+
+                    if let Some(nag) = suffix.and_then(pgn::suffix_nag)
+                        && let Some(node) = tree.node_mut(current)
+                    {
+                        node.annotations.nags.push(nag);
+                    }
+                }
+                Token::Comment(text) => {
+                    if let Some(node) = tree.node_mut(current) {
+                        node.annotations.add_comment(text);
+                    }
+                }
+                Token::Nag(nag) => {
+                    if let Some(node) = tree.node_mut(current) {
+                        node.annotations.nags.push(nag);
+                    }
+                }
+                Token::StartVariation => {
+                    // A variation replaces the last move, so it starts from that move's parent.
+                    let parent = tree
+                        .node(current)
+                        .and_then(Node::parent)
+                        .ok_or(PgnError::UnbalancedVariation)?;
+
+                    variation_starts.push(current);
+                    current = parent;
+                }
+                Token::EndVariation => {
+                    current = variation_starts
+                        .pop()
+                        .ok_or(PgnError::UnbalancedVariation)?;
+                }
+                Token::Result(result) => {
+                    if tree.tag("Result").is_none() {
+                        tree.set_tag("Result", result);
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        if !variation_starts.is_empty() {
+            return Err(PgnError::UnbalancedVariation);
+        }
+
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Piece;
+
+    const PGN: &str = r#"[Event "Study"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "*"]
+[Annotator "poob"]
+
+{Start} 1. e4 $1 {Best by test [%csl Ge4] [%cal Gd2d4,Rg1f3] [%clk 0:01:30.5]}
+1... e5 (1... c5 2. Nf3 (2. c3) 2... d6) 2. Nf3 Nc6 *
+"#;
+
+    #[test]
+    fn test_pgn_round_trip() {
+        let move_gen = MoveGenerator::new();
+        let tree = GameTree::from_pgn(PGN, &move_gen).unwrap();
+
+        assert_eq!(tree.to_pgn(), PGN);
+        assert_eq!(tree.tag("Annotator"), Some("poob"));
+
+        let main_line: Vec<&str> = tree
+            .main_line()
+            .into_iter()
+            .filter_map(|id| tree.node(id))
+            .map(Node::san)
+            .collect();
+        assert_eq!(main_line, ["", "e4", "e5", "Nf3", "Nc6"]);
+
+        let e4 = tree.node(tree.main_line()[1]).unwrap();
+        assert_eq!(e4.annotations.comment.as_deref(), Some("Best by test"));
+        assert_eq!(e4.annotations.nags, [1]);
+        assert_eq!(e4.annotations.clock, Some(Duration::from_millis(90_500)));
+        assert_eq!(
+            e4.annotations.arrows[1],
+            Arrow {
+                color: MarkColor::Red,
+                from: Square::G1,
+                to: Square::F3
+            }
+        );
+        assert_eq!(
+            e4.annotations.highlights,
+            [Highlight {
+                color: MarkColor::Green,
+                square: Square::E4
+            }]
+        );
+    }
+
+    #[test]
+    fn test_from_pgn_zero_castling() {
+        let move_gen = MoveGenerator::new();
+        let tree =
+            GameTree::from_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 *", &move_gen).unwrap();
+
+        let main_line: Vec<&str> = tree
+            .main_line()
+            .into_iter()
+            .filter_map(|id| tree.node(id))
+            .map(Node::san)
+            .collect();
+        assert_eq!(
+            main_line,
+            ["", "e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "O-O", "Nf6"]
+        );
+    }
+
+    #[test]
+    fn test_from_pgn_errors() {
+        let move_gen = MoveGenerator::new();
+
+        assert!(matches!(
+            GameTree::from_pgn("1. e4 e5 2. Ke3 *", &move_gen),
+            Err(PgnError::BadMove(SanError::NoMatch(_)))
+        ));
+        assert_eq!(
+            GameTree::from_pgn("1. e4 (1. d4 *", &move_gen),
+            Err(PgnError::UnbalancedVariation)
+        );
+        assert_eq!(
+            GameTree::from_pgn("(1. d4) 1. e4 *", &move_gen),
+            Err(PgnError::UnbalancedVariation)
+        );
+        assert!(matches!(
+            GameTree::from_pgn("[FEN \"8/8/8\"]\n\n*", &move_gen),
+            Err(PgnError::BadFen(_))
+        ));
+    }
+
+    #[test]
+    fn test_cursor_and_variations() {
+        let move_gen = MoveGenerator::new();
+        let mut tree = GameTree::new(BoardState::default());
+        let mv = |s: &str| s.parse::<Move>().unwrap();
+
+        let e4 = tree.play(mv("e2e4"), &move_gen).unwrap();
+        let e5 = tree.play(mv("e7e5"), &move_gen).unwrap();
+        tree.go_back();
+        let c5 = tree.play(mv("c7c5"), &move_gen).unwrap();
+        let nf3 = tree.play(mv("g1f3"), &move_gen).unwrap();
+
+        assert_eq!(tree.node(e4).unwrap().children(), [e5, c5]);
+        assert_eq!(
+            tree.play(mv("d8d1"), &move_gen),
+            Err(IllegalMoveError::UnreachableSquare(Piece::Queen, Square::D1))
+        );
+
+        // Playing an existing move follows it instead of adding a duplicate.
+        tree.go_to_start();
+        assert_eq!(tree.play(mv("e2e4"), &move_gen), Ok(e4));
+        assert!(tree.enter_variation(1));
+        assert_eq!(tree.cursor(), c5);
+        tree.go_to_end();
+        assert_eq!(tree.cursor(), nf3);
+
+        assert!(tree.promote_variation(c5));
+        assert!(!tree.promote_variation(c5));
+        assert_eq!(tree.main_line(), [NodeId::ROOT, e4, c5, nf3]);
+        assert!(tree.demote_variation(c5));
+        assert!(!tree.demote_variation(c5));
+
+        tree.promote_to_main_line(nf3);
+        assert_eq!(tree.main_line(), [NodeId::ROOT, e4, c5, nf3]);
+
+        let game = tree.game_at(nf3, &move_gen).unwrap();
+        assert_eq!(game.ply(), 3);
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        assert!(tree.delete_variation(c5));
+        assert_eq!(tree.cursor(), e4);
+        assert_eq!(tree.node(nf3), None);
+        assert_eq!(tree.main_line(), [NodeId::ROOT, e4, e5]);
+        assert!(!tree.delete_variation(NodeId::ROOT));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::boardstate::BoardState;
+use crate::moves::Move;
+
+const PROJECTED_GAME_LENGTH: usize = 80;
+
+/// A move played in a [`Game`](crate::game::Game), along with its SAN and the position
+/// it led to.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryEntry {
+    pub mv: Move,
+    pub san: String,
+    pub board_state: BoardState,
+}
+
+/// The moves played in a [`Game`](crate::game::Game) and the positions they led to.
+///
+/// The history keeps a cursor on the current ply. Undoing a move only moves the cursor back,
+/// so undone moves can be redone until a different move is played from an earlier ply.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawBoardHistory"))]
+pub struct BoardHistory {
+    start: BoardState,
+    entries: Vec<HistoryEntry>,
+    ply: usize,
+}
+
+impl BoardHistory {
+    /// Returns an empty history starting from the given position.
+    pub fn new(start: BoardState) -> Self {
+        Self {
+            start,
+            entries: Vec::with_capacity(PROJECTED_GAME_LENGTH),
+            ply: 0,
+        }
+    }
+
+    /// Returns the position the history starts from.
+    pub fn start(&self) -> &BoardState {
+        &self.start
+    }
+
+    /// Returns the number of moves played to reach the current position.
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    /// Returns the number of recorded moves, including undone moves that can be redone.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no moves have been recorded, and `false` otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entry of the move that reached the given ply (`1` is the first move), or
+    /// [`None`] if the ply hasn't been recorded.
+    pub fn get(&self, ply: usize) -> Option<&HistoryEntry> {
+        self.entries.get(ply.checked_sub(1)?)
+    }
+
+    /// Returns the position at the given ply (`0` is the starting position), or [`None`] if
+    /// the ply hasn't been recorded.
+    pub fn position(&self, ply: usize) -> Option<&BoardState> {
+        match ply {
+            0 => Some(&self.start),
+            _ => self.get(ply).map(|entry| &entry.board_state),
+        }
+    }
+
+    /// Returns the position at the current ply.
+    pub fn current(&self) -> &BoardState {
+        match self.ply {
+            0 => &self.start,
+            ply => &self.entries[ply - 1].board_state,
+        }
+    }
+
+    /// Records a move played from the current ply and advances the cursor. Any undone moves
+    /// are discarded, unless the move is the one that would have been redone.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self
+            .entries
+            .get(self.ply)
+            .is_some_and(|next| next.mv == entry.mv)
+        {
+            self.ply += 1;
+            return;
+        }
+
+        self.entries.truncate(self.ply);
+        self.entries.push(entry);
+        self.ply += 1;
+    }
+
+    /// Moves the cursor to the given ply, returning the position there, or [`None`] if the
+    /// ply hasn't been recorded.
+    pub fn goto_ply(&mut self, ply: usize) -> Option<&BoardState> {
+        if ply > self.entries.len() {
+            return None;
+        }
+
+        self.ply = ply;
+
+        Some(self.current())
+    }
+
+    /// Returns an iterator over the moves leading to the current ply, as
+    /// `(ply, move, position after the move)`.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Move, &BoardState)> {
+        self.entries[..self.ply]
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (i + 1, entry.mv, &entry.board_state))
+    }
+
+    /// Returns an iterator over the entries of the moves leading to the current ply.
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries[..self.ply].iter()
+    }
+
+    /// Returns how many times the current position has occurred on the way to the current
+    /// ply, counting itself. Only positions since the last capture or pawn move can repeat.
+    pub fn repetition_count(&self) -> usize {
+        let current = self.current();
+        let reversible_plies = (current.fifty_move_rule as usize).min(self.ply);
+
+        (self.ply - reversible_plies..=self.ply)
+            .filter_map(|ply| self.position(ply))
+            .filter(|board_state| board_state.is_same_position(current))
+            .count()
+    }
+}
+
+/// The fields of a deserialised [`BoardHistory`], before checking that they make up a
+/// game.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawBoardHistory {
+    start: BoardState,
+    entries: Vec<HistoryEntry>,
+    ply: usize,
+}
+
+/// Checks that each move is legal and leads to the next position, and that the cursor is
+/// on a recorded ply. Moves read from UCI notation get their missing flags back.
+#[cfg(feature = "serde")]
+impl TryFrom<RawBoardHistory> for BoardHistory {
+    type Error = String;
+
+    fn try_from(raw: RawBoardHistory) -> Result<Self, Self::Error> {
+        let move_gen = crate::movegen::MoveGenerator::shared();
+        let mut entries = raw.entries;
+        let mut before = raw.start.clone();
+
+        for (i, entry) in entries.iter_mut().enumerate() {
+            let game = crate::game::Game::from_board_state(before, move_gen);
+            let mv = game
+                .resolve_move(entry.mv)
+                .map_err(|err| format!("move {} ({}) is illegal: {err}", i + 1, entry.mv))?;
+            let mut after = game.get_board_state().clone();
+
+            after.make_move(mv);
+
+            if after != entry.board_state {
+                return Err(format!(
+                    "move {} ({mv}) doesn't lead to the recorded position",
+                    i + 1
+                ));
+            }
+
+            entry.mv = mv;
+            before = after;
+        }
+
+        if raw.ply > entries.len() {
+            return Err(format!(
+                "ply {} is past the {} recorded moves",
+                raw.ply,
+                entries.len()
+            ));
+        }
+
+        Ok(Self {
+            start: raw.start,
+            entries,
+            ply: raw.ply,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    fn entry(mv: Move, fen: &str) -> HistoryEntry {
+        HistoryEntry {
+            mv,
+            san: String::new(),
+            board_state: BoardState::from_fen(fen).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_push_and_goto_ply() {
+        let mut history = BoardHistory::new(BoardState::default());
+        let e4 = Move::from_squares(Square::E2, Square::E4);
+        let d4 = Move::from_squares(Square::D2, Square::D4);
+
+        history.push(entry(
+            e4,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        ));
+        assert_eq!(history.ply(), 1);
+        assert_eq!(history.goto_ply(2), None);
+        assert_eq!(history.goto_ply(0), Some(&BoardState::default()));
+
+        // Replaying the undone move keeps the history.
+        history.push(entry(
+            e4,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        ));
+        assert_eq!(history.len(), 1);
+
+        history.goto_ply(0);
+        history.push(entry(
+            d4,
+            "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1",
+        ));
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.get(1).map(|entry| entry.mv), Some(d4));
+        assert_eq!(
+            history.iter().map(|(ply, _, _)| ply).collect::<Vec<_>>(),
+            [1]
+        );
+    }
+}
@@ -1,16 +1,42 @@
 pub mod bitboards;
 pub mod boardstate;
+pub mod datagen;
+pub mod encoding;
+pub mod eval;
 pub mod game;
+pub mod gametree;
+pub mod history;
 pub mod movegen;
 pub mod moves;
+pub mod nnue;
+pub mod pawns;
+pub mod pgn;
+pub mod play;
 pub mod rende;
+pub mod search;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod sprt;
+pub mod square;
+pub mod tablebase;
+pub mod tactics;
+pub mod tournament;
+pub mod tuner;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod uci_client;
 pub mod util;
+pub mod xboard;
 pub mod perft;
 
+pub use bitboards::Bitboard;
+pub use square::{File, Rank, Square};
+
 use crate::bitboards::bitboard_constants::bitboard_indices::*;
 
 /// Represents one of the two playable colors (white or black).
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Color {
     Black,
     White,
@@ -36,7 +62,8 @@ impl Color {
 }
 
 /// Represents the possible piece types.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Piece {
     Pawn,
     Rook,
@@ -59,4 +86,16 @@ impl Piece {
             Piece::King => KING,
         }
     }
+
+    /// Returns the uppercase letter of the piece, as used in SAN and FEN strings.
+    pub fn to_char(&self) -> char {
+        match self {
+            Piece::Pawn => 'P',
+            Piece::Rook => 'R',
+            Piece::Knight => 'N',
+            Piece::Bishop => 'B',
+            Piece::Queen => 'Q',
+            Piece::King => 'K',
+        }
+    }
 }
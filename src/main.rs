@@ -1,19 +1,565 @@
-//use chess::bitboards::*;
-//use chess::boardstate::*;
-//use chess::rende::*;
-use chess::game::Game;
-//use chess::moves::*;
-use chess::movegen::*;
-use chess::perft::*;
+//use poob::bitboards::*;
+//use poob::boardstate::*;
+//use poob::rende::*;
+use poob::datagen::{self, DatagenConfig};
+use poob::game::Game;
+//use poob::moves::*;
+use poob::movegen::*;
+use poob::nnue::Network;
+use poob::perft::*;
+use poob::play::{DEFAULT_LEVEL, PlaySession, Player};
+use poob::search::SearchLimits;
+use poob::sprt::{self, DEFAULT_MAX_PAIRS, SprtParams};
+use poob::tournament::{self, DrawRule, GameRecord, MatchConfig, ResignRule};
+use poob::tuner::{self, Dataset, EvalParams, TunerConfig};
+use poob::xboard::XBoard;
 
+use poob::Color;
+use poob::game::Outcome;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::{
+    env,
     fs::{self, File},
     io,
 };
 
-fn main() {
+const USAGE: &str = "\
+Usage: poob play [--white <player>] [--black <player>] [--fen <fen>]
+       poob tui [--white <player>] [--black <player>] [--fen <fen>]
+       poob xboard [--nnue <network>]
+       poob match --engine <engine> --engine <engine> [<match option>...]
+       poob sprt --engine <new> --engine <base> [<sprt option>...] [<match option>...]
+       poob tune --data <file> [--data <file>...] [<tune option>...]
+       poob datagen --out <file> [<datagen option>...]
+       poob serve [--port <port>]
 
+Players are `human`, `engine` or `engine:<level>` with a level from 1 to 10.
+By default, a human plays white against the engine. The `tui` command needs the
+`tui` feature. The `xboard` command speaks the XBoard/WinBoard protocol (CECP) on
+standard input and output, evaluating with the NNUE network file if one is given.
+
+The `match` command plays engines against each other and prints their standings.
+Engines are `poob`, `poob:<level>` or `uci:<path>[,<option>=<value>...]`. Options:
+  --tc <time control>    [<moves>/]<seconds>[+<increment>] or st=<seconds> (10+0.1)
+  --rounds <n>           rounds, each playing the next opening (1)
+  --openings <file>      EPD or PGN opening suite
+  --no-repeat            play each opening once instead of with both colors
+  --schedule <schedule>  `round-robin` or `gauntlet` of the first engine
+  --concurrency <n>      games played at once (1)
+  --resign <cp>/<moves>  resign after <moves> moves scored at or below -<cp>
+  --draw <cp>/<moves>/<from move>  draw after <moves> moves each within <cp> of 0
+  --tablebases <dir>     adjudicate positions covered by the tablebases
+  --pgn <file>           append the games to the file
+  --event <name>         the PGN Event tag
+
+The `sprt` command tests the first engine against the second with game pairs until
+the log-likelihood ratio accepts H1 (at least <elo1> stronger) or H0 (at most <elo0>).
+It plays at most the given rounds, one pair each (50000). Options:
+  --elo0 <elo> --elo1 <elo>    the hypotheses (0 and 5)
+  --alpha <p> --beta <p>       the false positive and negative rates (0.05 each)
+  --state <file>               save the progress to the file and resume from it
+
+The `tune` command fits the evaluation weights to labelled positions, one per line:
+a FEN followed by the result for white, as `c9 \"1-0\";`, `[0.5]` or `0-1`. Options:
+  --quiet                 skip positions in check or with captures pending
+  --optimizer <name>      `adam` or `gd` for plain gradient descent (adam)
+  --learning-rate <rate>  the step size (1 for adam, 1000 for gd)
+  --epochs <n>            passes over the dataset (1000)
+  --threads <n>           threads computing the gradient (all cores)
+  --k <k>                 the sigmoid scale, fitted to the dataset by default
+  --init <file>           start from the weights of a config file
+  --config <file>         write the tuned weights as a config file
+  --rust <file>           write the tuned weights as Rust constants (default: print them)
+
+The `datagen` command plays self-play games with a fixed node budget per move and writes
+their quiet positions with scores and results, ready for `tune` or NNUE trainers. The
+same seed and thread count reproduce the same data. Options:
+  --games <n>             games to play (100)
+  --nodes <n>             nodes searched per move (5000)
+  --random-plies <n>      random plies played at the start of each game (8)
+  --max-plies <n>         plies after which a game is a draw (400)
+  --openings <file>       EPD or PGN openings to start from instead of the start position
+  --threads <n>           games played at once (1)
+  --seed <n>              the random seed (0)
+  --format <format>       `text` or `binary` (text)
+  --nnue <file>           evaluate with the NNUE network
+
+The `serve` command answers JSON requests about positions over HTTP on localhost (port
+7878 by default): legal moves, making a move, perft, evaluation, search and FEN or PGN
+validation. It needs the `serve` feature.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.split_first() {
+        Some((command, options)) if command == "play" => play(options),
+        #[cfg(feature = "tui")]
+        Some((command, options)) if command == "tui" => tui(options),
+        Some((command, options)) if command == "xboard" => xboard(options),
+        Some((command, options)) if command == "match" => play_match(options),
+        Some((command, options)) if command == "sprt" => sprt(options),
+        Some((command, options)) if command == "tune" => tune(options),
+        Some((command, options)) if command == "datagen" => datagen(options),
+        #[cfg(feature = "serve")]
+        Some((command, options)) if command == "serve" => serve(options),
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The options shared by the `play` and `tui` commands.
+struct PlayOptions<'o> {
+    white: Player,
+    black: Player,
+    fen: Option<&'o str>,
+}
+
+impl<'o> PlayOptions<'o> {
+    fn parse(options: &'o [String]) -> Result<Self, String> {
+        let mut play_options = Self {
+            white: Player::Human,
+            black: Player::Engine(SearchLimits::from_level(DEFAULT_LEVEL)),
+            fen: None,
+        };
+
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            let value = options
+                .next()
+                .ok_or_else(|| format!("missing value for `{option}`\n\n{USAGE}"))?;
+
+            match option.as_str() {
+                "--white" => play_options.white = value.parse().map_err(|err| format!("{err}"))?,
+                "--black" => play_options.black = value.parse().map_err(|err| format!("{err}"))?,
+                "--fen" => play_options.fen = Some(value),
+                _ => return Err(format!("unknown option `{option}`\n\n{USAGE}")),
+            }
+        }
+
+        Ok(play_options)
+    }
+
+    fn game<'a>(&self, move_gen: &'a MoveGenerator) -> Result<Game<'a>, String> {
+        match self.fen {
+            Some(fen) => Game::from_fen(fen, move_gen).map_err(|err| format!("{err}")),
+            None => Ok(Game::new(move_gen)),
+        }
+    }
+}
+
+/// Runs an interactive game with the options of the `play` command.
+fn play(options: &[String]) -> Result<(), String> {
+    let options = PlayOptions::parse(options)?;
+    let move_gen = MoveGenerator::new();
+    let game = options.game(&move_gen)?;
+
+    PlaySession::new(game, options.white, options.black).run();
+
+    Ok(())
+}
+
+/// Runs a full-screen game with the options of the `tui` command.
+#[cfg(feature = "tui")]
+fn tui(options: &[String]) -> Result<(), String> {
+    let options = PlayOptions::parse(options)?;
+    let move_gen = MoveGenerator::new();
+    let game = options.game(&move_gen)?;
+
+    poob::tui::Tui::new(game, options.white, options.black)
+        .run()
+        .map_err(|err| format!("terminal error: {err}"))
+}
+
+/// Runs the analysis server with the options of the `serve` command.
+#[cfg(feature = "serve")]
+fn serve(options: &[String]) -> Result<(), String> {
+    let port = match options {
+        [] => poob::serve::DEFAULT_PORT,
+        [option, port] if option == "--port" => port
+            .parse()
+            .map_err(|_| format!("bad value `{port}` for `--port`"))?,
+        _ => return Err(USAGE.to_string()),
+    };
+    let server = poob::serve::Server::bind(("127.0.0.1", port))
+        .map_err(|err| format!("can't listen on port {port}: {err}"))?;
+    let addr = server.local_addr().map_err(|err| format!("{err}"))?;
+
+    println!("Listening on http://{addr}");
+
+    server.run()
+}
+
+/// Runs the engine as a CECP engine on standard input and output.
+fn xboard(options: &[String]) -> Result<(), String> {
+    let network = match options {
+        [] => None,
+        [option, path] if option == "--nnue" => Some(
+            Network::load(path.as_ref()).map_err(|err| format!("can't load `{path}`: {err}"))?,
+        ),
+        _ => return Err(USAGE.to_string()),
+    };
+    let move_gen = MoveGenerator::new();
+    let input = io::BufReader::new(io::stdin());
+    let mut xboard = XBoard::new(Game::new(&move_gen), io::stdout());
+
+    xboard.set_network(network);
+    xboard
+        .run(input)
+        .map_err(|err| format!("output error: {err}"))
+}
+
+/// The options shared by the `match` and `sprt` commands.
+struct MatchOptions<'o> {
+    config: MatchConfig,
+    pgn: Option<&'o str>,
+    sprt: SprtParams,
+    state: Option<&'o str>,
+}
+
+impl<'o> MatchOptions<'o> {
+    /// Parses the options, accepting the SPRT ones only if `sprt` is set.
+    fn parse(options: &'o [String], move_gen: &MoveGenerator, sprt: bool) -> Result<Self, String> {
+        let mut match_options = Self {
+            config: MatchConfig {
+                time_control: tournament::parse_time_control("10+0.1").unwrap_or_default(),
+                rounds: if sprt { DEFAULT_MAX_PAIRS } else { 1 },
+                ..MatchConfig::default()
+            },
+            pgn: None,
+            sprt: SprtParams::default(),
+            state: None,
+        };
+        let config = &mut match_options.config;
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            if option == "--no-repeat" {
+                config.repeat = false;
+                continue;
+            }
+
+            let value = options
+                .next()
+                .ok_or_else(|| format!("missing value for `{option}`\n\n{USAGE}"))?;
+            let bad_value = || format!("bad value `{value}` for `{option}`");
+
+            match option.as_str() {
+                "--engine" => config
+                    .engines
+                    .push(value.parse().map_err(|err| format!("{err}"))?),
+                "--tc" => {
+                    config.time_control =
+                        tournament::parse_time_control(value).ok_or_else(bad_value)?;
+                }
+                "--rounds" => config.rounds = value.parse().map_err(|_| bad_value())?,
+                "--openings" => {
+                    config.openings = tournament::load_openings(value.as_ref(), move_gen)
+                        .map_err(|err| format!("{err}"))?;
+                }
+                "--schedule" => config.schedule = value.parse()?,
+                "--concurrency" => config.concurrency = value.parse().map_err(|_| bad_value())?,
+                "--resign" => {
+                    let (score, moves) = value.split_once('/').ok_or_else(bad_value)?;
+
+                    config.resign = Some(ResignRule {
+                        score: score.parse().map_err(|_| bad_value())?,
+                        moves: moves.parse().map_err(|_| bad_value())?,
+                    });
+                }
+                "--draw" => {
+                    let fields: Vec<&str> = value.split('/').collect();
+                    let [score, moves, from_move] = fields[..] else {
+                        return Err(bad_value());
+                    };
+
+                    config.draw = Some(DrawRule {
+                        score: score.parse().map_err(|_| bad_value())?,
+                        moves: moves.parse().map_err(|_| bad_value())?,
+                        from_move: from_move.parse().map_err(|_| bad_value())?,
+                    });
+                }
+                "--tablebases" => config.tablebases = Some(PathBuf::from(value)),
+                "--pgn" => match_options.pgn = Some(value),
+                "--event" => config.event.clone_from(value),
+                "--elo0" if sprt => {
+                    match_options.sprt.elo0 = value.parse().map_err(|_| bad_value())?;
+                }
+                "--elo1" if sprt => {
+                    match_options.sprt.elo1 = value.parse().map_err(|_| bad_value())?;
+                }
+                "--alpha" if sprt => {
+                    match_options.sprt.alpha = value.parse().map_err(|_| bad_value())?;
+                }
+                "--beta" if sprt => {
+                    match_options.sprt.beta = value.parse().map_err(|_| bad_value())?;
+                }
+                "--state" if sprt => match_options.state = Some(value),
+                _ => return Err(format!("unknown option `{option}`\n\n{USAGE}")),
+            }
+        }
+
+        Ok(match_options)
+    }
+
+    /// Opens the PGN file the games are appended to, if any.
+    fn pgn_file(&self) -> Result<Mutex<Option<File>>, String> {
+        let file = match self.pgn {
+            Some(path) => Some(
+                File::options()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|err| format!("can't open `{path}`: {err}"))?,
+            ),
+            None => None,
+        };
+
+        Ok(Mutex::new(file))
+    }
+}
+
+/// Prints a finished game and appends it to the PGN file.
+fn report_game(game: &GameRecord, names: &[String], pgn: &Mutex<Option<File>>) {
+    let result = match game.outcome {
+        Outcome::Win(Color::White) => "1-0",
+        Outcome::Win(Color::Black) => "0-1",
+        Outcome::Draw => "1/2-1/2",
+    };
+
+    println!(
+        "Finished game {} ({} vs {}): {result} {{{}}}",
+        game.number, names[game.pairing.white], names[game.pairing.black], game.reason
+    );
+
+    if let Ok(mut pgn) = pgn.lock()
+        && let Some(file) = pgn.as_mut()
+        && let Err(err) = writeln!(file, "{}", game.pgn)
+    {
+        eprintln!("can't write the game: {err}");
+    }
+}
+
+/// Plays a match between engines with the options of the `match` command.
+fn play_match(options: &[String]) -> Result<(), String> {
+    let move_gen = MoveGenerator::new();
+    let options = MatchOptions::parse(options, &move_gen, false)?;
+    let pgn = options.pgn_file()?;
+    let names = tournament::engine_names(&options.config.engines);
+
+    let report = tournament::run_match(&options.config, &move_gen, |game| {
+        report_game(game, &names, &pgn);
+    })
+    .map_err(|err| format!("{err}"))?;
+
+    println!("\n{report}");
+
+    Ok(())
+}
+
+/// Runs a sequential probability ratio test with the options of the `sprt` command.
+fn sprt(options: &[String]) -> Result<(), String> {
+    let move_gen = MoveGenerator::new();
+    let options = MatchOptions::parse(options, &move_gen, true)?;
+    let pgn = options.pgn_file()?;
+    let names = tournament::engine_names(&options.config.engines);
+    let state_path = options.state.map(Path::new);
+    let counted = AtomicU32::new(0);
+
+    let state = sprt::run_sprt(
+        &options.config,
+        options.sprt,
+        state_path,
+        &move_gen,
+        |game, state| {
+            report_game(game, &names, &pgn);
+
+            if counted.swap(state.pairs(), Ordering::Relaxed) != state.pairs() {
+                println!("{state}");
+            }
+        },
+    )
+    .map_err(|err| format!("{err}"))?;
+
+    match state.verdict() {
+        Some(verdict) => println!("\n{state}\nThe change is {verdict}."),
+        None => println!("\n{state}\nNo verdict after {} pairs.", state.pairs()),
+    }
+
+    Ok(())
+}
+
+/// Tunes the evaluation weights with the options of the `tune` command.
+fn tune(options: &[String]) -> Result<(), String> {
+    let move_gen = MoveGenerator::new();
+    let mut config = TunerConfig::default();
+    let mut data = Vec::new();
+    let mut quiet_only = false;
+    let mut params = EvalParams::default();
+    let mut config_path = None;
+    let mut rust_path = None;
+    let mut options = options.iter();
+
+    while let Some(option) = options.next() {
+        if option == "--quiet" {
+            quiet_only = true;
+            continue;
+        }
+
+        let value = options
+            .next()
+            .ok_or_else(|| format!("missing value for `{option}`\n\n{USAGE}"))?;
+        let bad_value = || format!("bad value `{value}` for `{option}`");
+
+        match option.as_str() {
+            "--data" => data.push(value),
+            "--optimizer" => config.optimizer = value.parse()?,
+            "--learning-rate" => {
+                config.learning_rate = Some(value.parse().map_err(|_| bad_value())?);
+            }
+            "--epochs" => config.epochs = value.parse().map_err(|_| bad_value())?,
+            "--threads" => config.threads = value.parse().map_err(|_| bad_value())?,
+            "--k" => config.k = Some(value.parse().map_err(|_| bad_value())?),
+            "--init" => {
+                let text = fs::read_to_string(value)
+                    .map_err(|err| format!("can't read `{value}`: {err}"))?;
+                params = EvalParams::from_config(&text).map_err(|err| format!("{err}"))?;
+            }
+            "--config" => config_path = Some(value),
+            "--rust" => rust_path = Some(value),
+            _ => return Err(format!("unknown option `{option}`\n\n{USAGE}")),
+        }
+    }
+
+    if data.is_empty() {
+        return Err(format!("missing `--data`\n\n{USAGE}"));
+    }
+
+    let mut dataset = Dataset::default();
+
+    for path in data {
+        let file = Dataset::load(path.as_ref(), quiet_only, config.threads, &move_gen)
+            .map_err(|err| format!("{path}: {err}"))?;
+        dataset.append(file);
+    }
+
+    println!(
+        "Loaded {} positions, skipped {} that aren't quiet.",
+        dataset.len(),
+        dataset.skipped()
+    );
+
+    let tuning = tuner::tune(&dataset, params, &config, |epoch, error| {
+        if epoch % 10 == 0 {
+            println!("Epoch {epoch}: error {error:.6}");
+        }
+    })
+    .map_err(|err| format!("{err}"))?;
+
+    println!(
+        "K = {:.4}, error {:.6} -> {:.6}",
+        tuning.k, tuning.initial_error, tuning.error
+    );
+
+    if let Some(path) = config_path {
+        fs::write(path, tuning.params.to_config())
+            .map_err(|err| format!("can't write `{path}`: {err}"))?;
+    }
+
+    match rust_path {
+        Some(path) => fs::write(path, tuning.params.to_rust())
+            .map_err(|err| format!("can't write `{path}`: {err}"))?,
+        None => print!("\n{}", tuning.params.to_rust()),
+    }
+
+    Ok(())
+}
+
+/// Generates self-play data with the options of the `datagen` command.
+fn datagen(options: &[String]) -> Result<(), String> {
+    let move_gen = MoveGenerator::new();
+    let mut config = DatagenConfig::default();
+    let mut out = None;
+    let mut network = None;
+    let mut options = options.iter();
+
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or_else(|| format!("missing value for `{option}`\n\n{USAGE}"))?;
+        let bad_value = || format!("bad value `{value}` for `{option}`");
+
+        match option.as_str() {
+            "--out" => out = Some(value),
+            "--games" => config.games = value.parse().map_err(|_| bad_value())?,
+            "--nodes" => config.nodes = value.parse().map_err(|_| bad_value())?,
+            "--random-plies" => config.random_plies = value.parse().map_err(|_| bad_value())?,
+            "--max-plies" => config.max_plies = value.parse().map_err(|_| bad_value())?,
+            "--openings" => {
+                config.openings = tournament::load_openings(value.as_ref(), &move_gen)
+                    .map_err(|err| format!("{err}"))?;
+            }
+            "--threads" => config.threads = value.parse().map_err(|_| bad_value())?,
+            "--seed" => config.seed = value.parse().map_err(|_| bad_value())?,
+            "--format" => config.format = value.parse()?,
+            "--nnue" => {
+                network = Some(
+                    Network::load(value.as_ref())
+                        .map_err(|err| format!("can't load `{value}`: {err}"))?,
+                );
+            }
+            _ => return Err(format!("unknown option `{option}`\n\n{USAGE}")),
+        }
+    }
+
+    let path = out.ok_or_else(|| format!("missing `--out`\n\n{USAGE}"))?;
+    let file = File::create(path).map_err(|err| format!("can't create `{path}`: {err}"))?;
+    // Games finished out of order are written together, so the count can skip over a
+    // multiple of 10.
+    let reported_tens = AtomicU32::new(0);
+
+    let stats = datagen::generate(
+        &config,
+        &move_gen,
+        network.as_ref(),
+        io::BufWriter::new(file),
+        |stats| {
+            let tens = stats.games / 10;
+
+            if reported_tens.fetch_max(tens, Ordering::Relaxed) < tens {
+                println!("{} games, {} positions", stats.games, stats.positions);
+            }
+        },
+    )
+    .map_err(|err| match err.kind() {
+        io::ErrorKind::InvalidInput => format!("bad openings: {err}"),
+        _ => format!("can't write `{path}`: {err}"),
+    })?;
+
+    println!(
+        "Wrote {} positions from {} games to `{path}`.",
+        stats.positions, stats.games
+    );
+
+    if stats.skipped > 0 {
+        println!(
+            "{} games found no start that doesn't end the game and are empty.",
+            stats.skipped
+        );
+    }
+
+    Ok(())
 }
 
 #[allow(dead_code)]
@@ -41,9 +587,16 @@ fn perft_suite(path: &str) {
     let (mut successes, mut failures) = (0, 0);
 
     for (i, line) in test_cases.split('\n').enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
         let test_case = match PerftCase::from_str(line, &move_gen) {
-            Some(case) => case,
-            None => break,
+            Ok(case) => case,
+            Err(err) => {
+                eprintln!("Skipping case {}: {err}", i + 1);
+                continue;
+            }
         };
 
         println!("Testing case {}...", i + 1);
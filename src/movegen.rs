@@ -1,4 +1,6 @@
+use crate::bitboards::Bitboard;
 use crate::bitboards::bitboard_constants::rank_file::*;
+use crate::square::Square;
 
 /// Constants used in move generation.
 pub mod movegen_constants {
@@ -9,18 +11,18 @@ pub mod movegen_constants {
     pub mod rank_file_exclusions {
         use super::*;
 
-        pub const NOT_A: u64 = !FILE_A;
-        pub const NOT_H: u64 = !FILE_H;
-        pub const NOT_AB: u64 = !(FILE_A | FILE_B);
-        pub const NOT_GH: u64 = !(FILE_G | FILE_H);
+        pub const NOT_A: Bitboard = Bitboard(!FILE_A.0);
+        pub const NOT_H: Bitboard = Bitboard(!FILE_H.0);
+        pub const NOT_AB: Bitboard = Bitboard(!(FILE_A.0 | FILE_B.0));
+        pub const NOT_GH: Bitboard = Bitboard(!(FILE_G.0 | FILE_H.0));
 
-        pub const NOT_1: u64 = !RANK_1;
-        pub const NOT_8: u64 = !RANK_8;
+        pub const NOT_1: Bitboard = Bitboard(!RANK_1.0);
+        pub const NOT_8: Bitboard = Bitboard(!RANK_8.0);
 
-        pub const NOT_A1: u64 = !(FILE_A | RANK_1);
-        pub const NOT_H8: u64 = !(FILE_H | RANK_8);
-        pub const NOT_A8: u64 = !(FILE_A | RANK_8);
-        pub const NOT_H1: u64 = !(FILE_H | RANK_1);
+        pub const NOT_A1: Bitboard = Bitboard(!(FILE_A.0 | RANK_1.0));
+        pub const NOT_H8: Bitboard = Bitboard(!(FILE_H.0 | RANK_8.0));
+        pub const NOT_A8: Bitboard = Bitboard(!(FILE_A.0 | RANK_8.0));
+        pub const NOT_H1: Bitboard = Bitboard(!(FILE_H.0 | RANK_1.0));
     }
 }
 
@@ -30,15 +32,26 @@ use movegen_constants::rank_file_exclusions::*;
 /// for all pieces.
 #[derive(Debug)]
 pub struct MoveGenerator {
-    white_pawn_moves: [u64; 64],
-    black_pawn_moves: [u64; 64],
-    white_pawn_attacks: [u64; 64],
-    black_pawn_attacks: [u64; 64],
-    knight_attacks: [u64; 64],
-    king_attacks: [u64; 64],
+    white_pawn_moves: [Bitboard; 64],
+    black_pawn_moves: [Bitboard; 64],
+    white_pawn_attacks: [Bitboard; 64],
+    black_pawn_attacks: [Bitboard; 64],
+    knight_attacks: [Bitboard; 64],
+    king_attacks: [Bitboard; 64],
+    between: Box<[[Bitboard; 64]; 64]>,
+    line: Box<[[Bitboard; 64]; 64]>,
 }
 
 impl MoveGenerator {
+    /// Returns a generator shared by the whole process and built on first use, for code
+    /// that can't be handed one, such as deserialisation.
+    #[cfg(feature = "serde")]
+    pub(crate) fn shared() -> &'static Self {
+        static MOVE_GEN: std::sync::OnceLock<MoveGenerator> = std::sync::OnceLock::new();
+
+        MOVE_GEN.get_or_init(Self::new)
+    }
+
     pub fn new() -> Self {
         Self {
             white_pawn_moves: compute_white_pawn_moves(),
@@ -47,38 +60,52 @@ impl MoveGenerator {
             black_pawn_attacks: compute_black_pawn_attacks(),
             knight_attacks: compute_knight_attacks(),
             king_attacks: compute_king_attacks(),
+            between: compute_between(),
+            line: compute_line(),
         }
     }
 
-    pub fn get_white_pawn_moves(&self, square: u8) -> u64 {
-        self.white_pawn_moves[square as usize]
+    pub fn get_white_pawn_moves(&self, square: Square) -> Bitboard {
+        self.white_pawn_moves[square.index()]
     }
 
-    pub fn get_black_pawn_moves(&self, square: u8) -> u64 {
-        self.black_pawn_moves[square as usize]
+    pub fn get_black_pawn_moves(&self, square: Square) -> Bitboard {
+        self.black_pawn_moves[square.index()]
     }
 
-    pub fn get_white_pawn_attacks(&self, square: u8) -> u64 {
-        self.white_pawn_attacks[square as usize]
+    pub fn get_white_pawn_attacks(&self, square: Square) -> Bitboard {
+        self.white_pawn_attacks[square.index()]
     }
 
-    pub fn get_black_pawn_attacks(&self, square: u8) -> u64 {
-        self.black_pawn_attacks[square as usize]
+    pub fn get_black_pawn_attacks(&self, square: Square) -> Bitboard {
+        self.black_pawn_attacks[square.index()]
     }
 
-    pub fn get_knight_attacks(&self, square: u8) -> u64 {
-        self.knight_attacks[square as usize]
+    pub fn get_knight_attacks(&self, square: Square) -> Bitboard {
+        self.knight_attacks[square.index()]
     }
 
-    pub fn get_king_attacks(&self, square: u8) -> u64 {
-        self.king_attacks[square as usize]
+    pub fn get_king_attacks(&self, square: Square) -> Bitboard {
+        self.king_attacks[square.index()]
+    }
+
+    /// Returns the squares strictly between the two squares if they share a rank, file or
+    /// diagonal, and an empty bitboard otherwise.
+    pub fn between(&self, a: Square, b: Square) -> Bitboard {
+        self.between[a.index()][b.index()]
+    }
+
+    /// Returns every square of the rank, file or diagonal going through both squares,
+    /// from edge to edge, or an empty bitboard if they don't share one.
+    pub fn line(&self, a: Square, b: Square) -> Bitboard {
+        self.line[a.index()][b.index()]
     }
 
     /// Calculates all squares a [rook](crate::Piece::Rook) is attacking from the given square.
     /// Current implementation uses [dumb7fill](https://www.chessprogramming.org/Dumb7Fill).
-    pub fn get_rook_attacks(square: u8, open_squares: u64) -> u64 {
-        let rook = 1 << square;
-        let mut attacks = 0;
+    pub fn get_rook_attacks(square: Square, open_squares: Bitboard) -> Bitboard {
+        let rook = square.bitboard();
+        let mut attacks = Bitboard::EMPTY;
 
         {
             let mut north_fill = rook & NOT_8;
@@ -129,9 +156,9 @@ impl MoveGenerator {
 
     /// Calculates all squares a [bishop](crate::Piece::Bishop) is attacking from the given square.
     /// Current implementation uses [dumb7fill](https://www.chessprogramming.org/Dumb7Fill).
-    pub fn get_bishop_attacks(square: u8, open_squares: u64) -> u64 {
-        let bishop = 1 << square;
-        let mut attacks = 0;
+    pub fn get_bishop_attacks(square: Square, open_squares: Bitboard) -> Bitboard {
+        let bishop = square.bitboard();
+        let mut attacks = Bitboard::EMPTY;
 
         {
             let mut ne_fill = bishop & NOT_H8;
@@ -159,7 +186,7 @@ impl MoveGenerator {
             let mut sw_fill = bishop & NOT_A1;
 
             for _ in 0..8 {
-                sw_fill >>= 7;
+                sw_fill >>= 9;
                 attacks |= sw_fill;
 
                 sw_fill &= open_squares & NOT_A1;
@@ -170,7 +197,7 @@ impl MoveGenerator {
             let mut se_fill = bishop & NOT_H1;
 
             for _ in 0..8 {
-                se_fill >>= 9;
+                se_fill >>= 7;
                 attacks |= se_fill;
 
                 se_fill &= open_squares & NOT_H1;
@@ -182,7 +209,7 @@ impl MoveGenerator {
 
     /// Calculates all squares a [queen](crate::Piece::Queen) is attacking from the given square.
     /// Current implementation uses [dumb7fill](https://www.chessprogramming.org/Dumb7Fill).
-    pub fn get_queen_attacks(square: u8, open_squares: u64) -> u64 {
+    pub fn get_queen_attacks(square: Square, open_squares: Bitboard) -> Bitboard {
         MoveGenerator::get_bishop_attacks(square, open_squares)
             | MoveGenerator::get_rook_attacks(square, open_squares)
     }
@@ -190,16 +217,16 @@ impl MoveGenerator {
 
 /// Generates and returns a lookup table of every non-capture move a
 /// [white](crate::Color::White) [pawn](crate::Piece::Pawn) can make from each square on the board.
-pub fn compute_white_pawn_moves() -> [u64; 64] {
-    let mut moves = [0; 64];
+pub fn compute_white_pawn_moves() -> [Bitboard; 64] {
+    let mut moves = [Bitboard::EMPTY; 64];
 
-    for square in 0..64 {
-        let pawn = 1 << square;
+    for square in Square::ALL {
+        let pawn = square.bitboard();
 
-        moves[square] = pawn << 8;
+        moves[square.index()] = pawn << 8;
 
-        if pawn & RANK_2 != 0 {
-            moves[square] |= pawn << 16;
+        if !(pawn & RANK_2).is_empty() {
+            moves[square.index()] |= pawn << 16;
         }
     }
 
@@ -208,16 +235,16 @@ pub fn compute_white_pawn_moves() -> [u64; 64] {
 
 /// Generates and returns a lookup table of every non-capture move a
 /// [black](crate::Color::Black) [pawn](crate::Piece::Pawn) can make from each square on the board.
-pub fn compute_black_pawn_moves() -> [u64; 64] {
-    let mut moves = [0; 64];
+pub fn compute_black_pawn_moves() -> [Bitboard; 64] {
+    let mut moves = [Bitboard::EMPTY; 64];
 
-    for square in 0..64 {
-        let pawn = 1 << square;
+    for square in Square::ALL {
+        let pawn = square.bitboard();
 
-        moves[square] = pawn >> 8;
+        moves[square.index()] = pawn >> 8;
 
-        if pawn & RANK_7 != 0 {
-            moves[square] |= pawn >> 16;
+        if !(pawn & RANK_7).is_empty() {
+            moves[square.index()] |= pawn >> 16;
         }
     }
 
@@ -226,13 +253,13 @@ pub fn compute_black_pawn_moves() -> [u64; 64] {
 
 /// Generates and returns a lookup table of every square a [white](crate::Color::White) [pawn](crate::Piece::Pawn)
 /// is attacking from each square on the board.
-pub fn compute_white_pawn_attacks() -> [u64; 64] {
-    let mut attacks = [0; 64];
+pub fn compute_white_pawn_attacks() -> [Bitboard; 64] {
+    let mut attacks = [Bitboard::EMPTY; 64];
 
-    for square in 0..64 {
-        let pawn = 1 << square;
+    for square in Square::ALL {
+        let pawn = square.bitboard();
 
-        attacks[square] = ((pawn << 9) & NOT_A) | ((pawn << 7) & NOT_H);
+        attacks[square.index()] = ((pawn << 9) & NOT_A) | ((pawn << 7) & NOT_H);
     }
 
     attacks
@@ -240,13 +267,13 @@ pub fn compute_white_pawn_attacks() -> [u64; 64] {
 
 /// Generates and returns a lookup table of every square a [black](crate::Color::Black) [pawn](crate::Piece::Pawn)
 /// is attacking from each square on the board.
-pub fn compute_black_pawn_attacks() -> [u64; 64] {
-    let mut attacks = [0; 64];
+pub fn compute_black_pawn_attacks() -> [Bitboard; 64] {
+    let mut attacks = [Bitboard::EMPTY; 64];
 
-    for square in 0..64 {
-        let pawn = 1 << square;
+    for square in Square::ALL {
+        let pawn = square.bitboard();
 
-        attacks[square] = ((pawn >> 9) & NOT_H) | ((pawn >> 7) & NOT_A);
+        attacks[square.index()] = ((pawn >> 9) & NOT_H) | ((pawn >> 7) & NOT_A);
     }
 
     attacks
@@ -255,13 +282,13 @@ pub fn compute_black_pawn_attacks() -> [u64; 64] {
 /// Generates and returns a lookup table of every move a [knight](crate::Piece::Knight) can make
 /// (and therefore every square it is attacking) from each square on the
 /// board.
-pub fn compute_knight_attacks() -> [u64; 64] {
-    let mut attacks = [0; 64];
+pub fn compute_knight_attacks() -> [Bitboard; 64] {
+    let mut attacks = [Bitboard::EMPTY; 64];
 
-    for square in 0..64 {
-        let knight = 1 << square;
+    for square in Square::ALL {
+        let knight = square.bitboard();
 
-        attacks[square] = ((knight << 17) & NOT_A)
+        attacks[square.index()] = ((knight << 17) & NOT_A)
             | ((knight << 15) & NOT_H)
             | ((knight << 10) & NOT_AB)
             | ((knight << 6) & NOT_GH)
@@ -277,23 +304,79 @@ pub fn compute_knight_attacks() -> [u64; 64] {
 /// Generates and returns a lookup table of every move a [king](crate::Piece::King) can make
 /// (and therefore every square it is attacking) from each square on the
 /// board.
-pub fn compute_king_attacks() -> [u64; 64] {
-    let mut attacks = [0; 64];
+pub fn compute_king_attacks() -> [Bitboard; 64] {
+    let mut attacks = [Bitboard::EMPTY; 64];
 
-    for square in 0..64 {
-        let king = 1 << square;
+    for square in Square::ALL {
+        let king = square.bitboard();
 
         let horizontal_attacks = ((king << 1) & NOT_A) | ((king >> 1) & NOT_H);
-        attacks[square] = horizontal_attacks;
-        attacks[square] |= horizontal_attacks << 8;
-        attacks[square] |= horizontal_attacks >> 8;
+        attacks[square.index()] = horizontal_attacks;
+        attacks[square.index()] |= horizontal_attacks << 8;
+        attacks[square.index()] |= horizontal_attacks >> 8;
 
-        attacks[square] |= (king << 8) | (king >> 8);
+        attacks[square.index()] |= (king << 8) | (king >> 8);
     }
 
     attacks
 }
 
+/// Generates and returns a lookup table of the squares strictly between every two squares
+/// sharing a rank, file or diagonal.
+pub fn compute_between() -> Box<[[Bitboard; 64]; 64]> {
+    let mut between = Box::new([[Bitboard::EMPTY; 64]; 64]);
+
+    for a in Square::ALL {
+        let rook_rays = MoveGenerator::get_rook_attacks(a, Bitboard::FULL);
+        let bishop_rays = MoveGenerator::get_bishop_attacks(a, Bitboard::FULL);
+
+        // Each square blocks the rays coming from the other one, so the rays only overlap
+        // between them.
+        for b in Square::ALL {
+            let (a_blocked, b_blocked) = (!a.bitboard(), !b.bitboard());
+
+            between[a.index()][b.index()] = if rook_rays.contains(b) {
+                MoveGenerator::get_rook_attacks(a, b_blocked)
+                    & MoveGenerator::get_rook_attacks(b, a_blocked)
+            } else if bishop_rays.contains(b) {
+                MoveGenerator::get_bishop_attacks(a, b_blocked)
+                    & MoveGenerator::get_bishop_attacks(b, a_blocked)
+            } else {
+                Bitboard::EMPTY
+            };
+        }
+    }
+
+    between
+}
+
+/// Generates and returns a lookup table of the full rank, file or diagonal going through
+/// every two squares that share one.
+pub fn compute_line() -> Box<[[Bitboard; 64]; 64]> {
+    let mut line = Box::new([[Bitboard::EMPTY; 64]; 64]);
+
+    for a in Square::ALL {
+        let rook_rays = MoveGenerator::get_rook_attacks(a, Bitboard::FULL);
+        let bishop_rays = MoveGenerator::get_bishop_attacks(a, Bitboard::FULL);
+
+        for b in Square::ALL {
+            line[a.index()][b.index()] = if rook_rays.contains(b) {
+                (rook_rays & MoveGenerator::get_rook_attacks(b, Bitboard::FULL))
+                    | a.bitboard()
+                    | b.bitboard()
+            } else if bishop_rays.contains(b) {
+                (bishop_rays & MoveGenerator::get_bishop_attacks(b, Bitboard::FULL))
+                    | a.bitboard()
+                    | b.bitboard()
+            } else {
+                Bitboard::EMPTY
+            };
+        }
+    }
+
+    line
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -302,99 +385,137 @@ mod tests {
     fn test_compute_white_pawn_moves() {
         let white_pawn_moves = compute_white_pawn_moves();
 
-        assert_eq!(white_pawn_moves[0].count_ones(), 1);
-        assert_eq!(white_pawn_moves[8].count_ones(), 2);
-        assert_eq!(white_pawn_moves[63].count_ones(), 0);
+        assert_eq!(white_pawn_moves[0].count(), 1);
+        assert_eq!(white_pawn_moves[8].count(), 2);
+        assert_eq!(white_pawn_moves[63].count(), 0);
     }
 
     #[test]
     fn test_compute_black_pawn_moves() {
         let black_pawn_moves = compute_black_pawn_moves();
 
-        assert_eq!(black_pawn_moves[0].count_ones(), 0);
-        assert_eq!(black_pawn_moves[28].count_ones(), 1);
-        assert_eq!(black_pawn_moves[54].count_ones(), 2);
+        assert_eq!(black_pawn_moves[0].count(), 0);
+        assert_eq!(black_pawn_moves[28].count(), 1);
+        assert_eq!(black_pawn_moves[54].count(), 2);
     }
 
     #[test]
     fn test_compute_white_pawn_attacks() {
         let white_pawn_attacks = compute_white_pawn_attacks();
 
-        assert_eq!(white_pawn_attacks[0].count_ones(), 1);
-        assert_eq!(white_pawn_attacks[28].count_ones(), 2);
-        assert_eq!(white_pawn_attacks[60].count_ones(), 0);
+        assert_eq!(white_pawn_attacks[0].count(), 1);
+        assert_eq!(white_pawn_attacks[28].count(), 2);
+        assert_eq!(white_pawn_attacks[60].count(), 0);
     }
 
     #[test]
     fn test_compute_black_pawn_attacks() {
         let black_pawn_attacks = compute_black_pawn_attacks();
 
-        assert_eq!(black_pawn_attacks[0].count_ones(), 0);
-        assert_eq!(black_pawn_attacks[28].count_ones(), 2);
-        assert_eq!(black_pawn_attacks[63].count_ones(), 1);
+        assert_eq!(black_pawn_attacks[0].count(), 0);
+        assert_eq!(black_pawn_attacks[28].count(), 2);
+        assert_eq!(black_pawn_attacks[63].count(), 1);
     }
 
     #[test]
     fn test_compute_knight_attacks() {
         let knight_attacks = compute_knight_attacks();
 
-        assert_eq!(knight_attacks[0].count_ones(), 2);
-        assert_eq!(knight_attacks[1].count_ones(), 3);
-        assert_eq!(knight_attacks[28].count_ones(), 8);
+        assert_eq!(knight_attacks[0].count(), 2);
+        assert_eq!(knight_attacks[1].count(), 3);
+        assert_eq!(knight_attacks[28].count(), 8);
     }
 
     #[test]
     fn test_compute_king_attacks() {
         let king_attacks = compute_king_attacks();
 
-        assert_eq!(king_attacks[0].count_ones(), 3);
-        assert_eq!(king_attacks[4].count_ones(), 5);
-        assert_eq!(king_attacks[28].count_ones(), 8);
+        assert_eq!(king_attacks[0].count(), 3);
+        assert_eq!(king_attacks[4].count(), 5);
+        assert_eq!(king_attacks[28].count(), 8);
     }
 
     #[test]
     fn test_get_rook_attacks() {
-        let attacks_1 = MoveGenerator::get_rook_attacks(0, !0);
-        assert_eq!(attacks_1.count_ones(), 14);
+        let attacks_1 = MoveGenerator::get_rook_attacks(Square::A1, Bitboard::FULL);
+        assert_eq!(attacks_1.count(), 14);
 
-        let attacks_2 = MoveGenerator::get_rook_attacks(28, !0);
-        assert_eq!(attacks_2.count_ones(), 14);
+        let attacks_2 = MoveGenerator::get_rook_attacks(Square::E4, Bitboard::FULL);
+        assert_eq!(attacks_2.count(), 14);
 
-        let mut bitboard = 0b00010000;
-        let attacks_3 = MoveGenerator::get_rook_attacks(3, !bitboard);
-        assert_eq!(attacks_3.count_ones(), 11);
+        let mut bitboard = Bitboard(0b00010000);
+        let attacks_3 = MoveGenerator::get_rook_attacks(Square::D1, !bitboard);
+        assert_eq!(attacks_3.count(), 11);
 
-        bitboard = 0b00010100 << 24 | 0b00001000 << 40;
-        let attacks_4 = MoveGenerator::get_rook_attacks(27, !bitboard);
-        assert_eq!(attacks_4.count_ones(), 7);
+        bitboard = Bitboard(0b00010100 << 24 | 0b00001000 << 40);
+        let attacks_4 = MoveGenerator::get_rook_attacks(Square::D4, !bitboard);
+        assert_eq!(attacks_4.count(), 7);
     }
 
     #[test]
     fn test_get_bishop_attacks() {
-        let attacks_1 = MoveGenerator::get_bishop_attacks(0, !0);
-        assert_eq!(attacks_1.count_ones(), 7);
-
-        let attacks_2 = MoveGenerator::get_bishop_attacks(28, !0);
-        assert_eq!(attacks_2.count_ones(), 13);
-
-        let bitboard = 0b00010000 << 8;
-        let attacks_3 = MoveGenerator::get_bishop_attacks(3, !bitboard);
-        assert_eq!(attacks_3.count_ones(), 4);
+        let attacks_1 = MoveGenerator::get_bishop_attacks(Square::A1, Bitboard::FULL);
+        assert_eq!(attacks_1.count(), 7);
+
+        let attacks_2 = MoveGenerator::get_bishop_attacks(Square::E4, Bitboard::FULL);
+        assert_eq!(attacks_2.count(), 13);
+
+        let bitboard = Bitboard(0b00010000 << 8);
+        let attacks_3 = MoveGenerator::get_bishop_attacks(Square::D1, !bitboard);
+        assert_eq!(attacks_3.count(), 4);
+
+        let attacks_4 = MoveGenerator::get_bishop_attacks(Square::H2, Bitboard::FULL);
+        assert_eq!(
+            attacks_4,
+            [
+                Square::G1,
+                Square::G3,
+                Square::F4,
+                Square::E5,
+                Square::D6,
+                Square::C7,
+                Square::B8
+            ]
+            .into_iter()
+            .collect()
+        );
     }
 
     #[test]
     fn test_get_queen_attacks() {
-        let attacks_1 = MoveGenerator::get_queen_attacks(0, !0);
-        assert_eq!(attacks_1.count_ones(), 21);
+        let attacks_1 = MoveGenerator::get_queen_attacks(Square::A1, Bitboard::FULL);
+        assert_eq!(attacks_1.count(), 21);
 
-        let attacks_2 = MoveGenerator::get_queen_attacks(4, !0);
-        assert_eq!(attacks_2.count_ones(), 21);
+        let attacks_2 = MoveGenerator::get_queen_attacks(Square::E1, Bitboard::FULL);
+        assert_eq!(attacks_2.count(), 21);
 
-        let attacks_3 = MoveGenerator::get_queen_attacks(28, !0);
-        assert_eq!(attacks_3.count_ones(), 27);
+        let attacks_3 = MoveGenerator::get_queen_attacks(Square::E4, Bitboard::FULL);
+        assert_eq!(attacks_3.count(), 27);
 
-        let bitboard = 0b000101000 << 24;
-        let attacks_4 = MoveGenerator::get_queen_attacks(28, !bitboard);
-        assert_eq!(attacks_4.count_ones(), 22);
+        let bitboard = Bitboard(0b000101000 << 24);
+        let attacks_4 = MoveGenerator::get_queen_attacks(Square::E4, !bitboard);
+        assert_eq!(attacks_4.count(), 22);
+    }
+
+    #[test]
+    fn test_between_and_line() {
+        let move_gen = MoveGenerator::new();
+
+        assert_eq!(
+            move_gen.between(Square::A1, Square::D4),
+            [Square::B2, Square::C3].into_iter().collect()
+        );
+        assert_eq!(
+            move_gen.between(Square::H5, Square::E5),
+            [Square::G5, Square::F5].into_iter().collect()
+        );
+        assert_eq!(move_gen.between(Square::E1, Square::E2), Bitboard::EMPTY);
+        assert_eq!(move_gen.between(Square::A1, Square::B3), Bitboard::EMPTY);
+        assert_eq!(move_gen.between(Square::C4, Square::C4), Bitboard::EMPTY);
+
+        assert_eq!(move_gen.line(Square::C3, Square::E5).count(), 8);
+        assert_eq!(move_gen.line(Square::B8, Square::A7).count(), 2);
+        assert_eq!(move_gen.line(Square::E2, Square::E7), FILE_E);
+        assert_eq!(move_gen.line(Square::A1, Square::B3), Bitboard::EMPTY);
     }
 }
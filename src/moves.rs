@@ -20,17 +20,165 @@ pub mod move_constants {
     /// Used with a bitwise AND operation to set the bitflag of
     /// a [`Move`](super::Move) to [`QUIET_MOVE`].
     pub const QUIET_MASK: u16 = !(0b1111 << 12);
+
+    /// Used with a bitwise AND operation to extract the bitflag of
+    /// a [`Move`](super::Move).
+    pub const FLAG_MASK: u16 = 0b1111 << 12;
+
+    /// Used with a bitwise AND operation to extract the promotion piece
+    /// bits of a promotion bitflag.
+    pub const PROMOTION_PIECE_MASK: u16 = 0b0011 << 12;
 }
 
 /// The maximum possible moves from any given chess position.
 const MAX_POSSIBLE_MOVES: usize = 218;
 
-use crate::util::*;
+use crate::Piece;
+use crate::square::{File, Rank, Square, SquareParseError};
 use move_constants::*;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Error variants when building a [`Move`] from its parts, from a raw [`u16`] or from a
+/// string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    /// The flag bits don't correspond to any kind of move.
+    BadFlags(u16),
+    /// The initial and target squares are the same.
+    NullMove,
+    /// Pawns can only promote to a knight, bishop, rook or queen.
+    BadPromotionPiece(Piece),
+    /// The squares don't fit the kind of move, e.g. a double pawn push that
+    /// doesn't start on the second or seventh rank.
+    BadSquares(MoveKind),
+    /// The string isn't a move in long algebraic notation, e.g. `e2e4` or `e7e8q`.
+    BadNotation,
+    BadSquare(SquareParseError),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadFlags(flags) => write!(f, "invalid move flags {:#06b}", flags >> 12),
+            Self::NullMove => write!(f, "initial and target squares are the same"),
+            Self::BadPromotionPiece(piece) => write!(f, "cannot promote to a {piece:?}"),
+            Self::BadSquares(kind) => write!(f, "squares don't fit a {kind:?} move"),
+            Self::BadNotation => write!(f, "expected a move such as `e2e4` or `e7e8q`"),
+            Self::BadSquare(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for MoveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::BadSquare(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<SquareParseError> for MoveError {
+    fn from(err: SquareParseError) -> Self {
+        Self::BadSquare(err)
+    }
+}
+
+/// The kind of a [`Move`], as encoded by its bitflag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveKind {
+    Quiet,
+    DoublePawnPush,
+    KingsideCastle,
+    QueensideCastle,
+    Capture,
+    EnPassantCapture,
+    /// A pawn promotion to the given piece.
+    Promotion(Piece),
+    /// A pawn promotion to the given piece that also captures.
+    PromotionCapture(Piece),
+}
+
+impl MoveKind {
+    /// Returns the kind of move encoded by the bitflag of a move (the four leftmost
+    /// bits), or [`None`] if the bitflag is unused.
+    pub fn from_flags(flags: u16) -> Option<Self> {
+        let kind = match flags & FLAG_MASK {
+            QUIET_MOVE => Self::Quiet,
+            DOUBLE_PAWN_PUSH => Self::DoublePawnPush,
+            KING_CASTLE => Self::KingsideCastle,
+            QUEEN_CASTLE => Self::QueensideCastle,
+            CAPTURE => Self::Capture,
+            EN_PASSANT_CAPTURE => Self::EnPassantCapture,
+            flags if flags & PROMOTION == 0 => return None,
+            flags => {
+                let piece = match (flags & PROMOTION_PIECE_MASK) >> 12 {
+                    0 => Piece::Knight,
+                    1 => Piece::Bishop,
+                    2 => Piece::Rook,
+                    _ => Piece::Queen,
+                };
+
+                if flags & CAPTURE == CAPTURE {
+                    Self::PromotionCapture(piece)
+                } else {
+                    Self::Promotion(piece)
+                }
+            }
+        };
+
+        Some(kind)
+    }
+
+    /// Returns the bitflag encoding this kind of move, or [`None`] if it
+    /// promotes to a piece other than a knight, bishop, rook or queen.
+    pub fn to_flags(self) -> Option<u16> {
+        let promotion_flags = |piece| match piece {
+            Piece::Knight => Some(KNIGHT_PROMOTION),
+            Piece::Bishop => Some(BISHOP_PROMOTION),
+            Piece::Rook => Some(ROOK_PROMOTION),
+            Piece::Queen => Some(QUEEN_PROMOTION),
+            Piece::Pawn | Piece::King => None,
+        };
+
+        match self {
+            Self::Quiet => Some(QUIET_MOVE),
+            Self::DoublePawnPush => Some(DOUBLE_PAWN_PUSH),
+            Self::KingsideCastle => Some(KING_CASTLE),
+            Self::QueensideCastle => Some(QUEEN_CASTLE),
+            Self::Capture => Some(CAPTURE),
+            Self::EnPassantCapture => Some(EN_PASSANT_CAPTURE),
+            Self::Promotion(piece) => promotion_flags(piece),
+            Self::PromotionCapture(piece) => Some(promotion_flags(piece)? | CAPTURE),
+        }
+    }
+
+    /// Returns `true` if this kind of move captures a piece.
+    pub fn is_capture(self) -> bool {
+        matches!(
+            self,
+            Self::Capture | Self::EnPassantCapture | Self::PromotionCapture(_)
+        )
+    }
+
+    /// Returns the piece a pawn promotes to, or [`None`] if this kind of move
+    /// isn't a promotion.
+    pub fn promotion_piece(self) -> Option<Piece> {
+        match self {
+            Self::Promotion(piece) | Self::PromotionCapture(piece) => Some(piece),
+            _ => None,
+        }
+    }
+}
 
 /// Struct encapsulating the logic for encoding and decoding moves.
 /// All information is stored in a [`u16`] field.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+///
+/// Bits `0..6` hold the target square, bits `6..12` the initial square and
+/// bits `12..16` the bitflag (see [`move_constants`]).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Move(u16);
 
 impl Move {
@@ -38,46 +186,127 @@ impl Move {
         Self(0)
     }
 
-    /// Returns an optional [`Move`] instance from square indices.
-    pub fn from_squares(initial_square: u8, target_square: u8) -> Option<Self> {
-        let initial_square = checked_square_u8_to_square_u16(initial_square)?;
-        let target_square = checked_square_u8_to_square_u16(target_square)?;
-
-        Some(Self((initial_square << 6) | target_square))
+    /// Returns a quiet [`Move`] between the given squares.
+    pub fn from_squares(initial_square: Square, target_square: Square) -> Self {
+        Self(((initial_square as u16) << 6) | target_square as u16)
     }
 
-    /// Returns a [`Move`] instance from square indices. Do not call this
-    /// with invalid square indices. Bad things will happen.
-    pub fn unchecked_from_squares(initial_square: u8, target_square: u8) -> Self {
-        Self(((initial_square as u16) << 6) | target_square as u16)
+    /// Returns a [`Move`] of the given kind between the given squares, checking that the
+    /// squares fit the kind of move. Only the squares are checked, not the position the
+    /// move is played in.
+    ///
+    /// # Errors
+    /// Returns a [`MoveError`] if the squares are the same, if a promotion isn't to a
+    /// knight, bishop, rook or queen, or if the squares can't belong to that kind of move
+    /// (e.g. a double pawn push from e2 to e3, or a castle from a1).
+    pub fn try_new(
+        initial_square: Square,
+        target_square: Square,
+        kind: MoveKind,
+    ) -> Result<Self, MoveError> {
+        if initial_square == target_square {
+            return Err(MoveError::NullMove);
+        }
+
+        let flags = match kind.to_flags() {
+            Some(flags) => flags,
+            None => {
+                return Err(MoveError::BadPromotionPiece(
+                    kind.promotion_piece().unwrap_or(Piece::Pawn),
+                ));
+            }
+        };
+
+        let (initial_file, target_file) = (initial_square.file(), target_square.file());
+        let (initial_rank, target_rank) = (initial_square.rank(), target_square.rank());
+        let file_distance = initial_file.index().abs_diff(target_file.index());
+
+        let fits = match kind {
+            MoveKind::Quiet | MoveKind::Capture => true,
+            MoveKind::DoublePawnPush => {
+                file_distance == 0
+                    && matches!(
+                        (initial_rank, target_rank),
+                        (Rank::Second, Rank::Fourth) | (Rank::Seventh, Rank::Fifth)
+                    )
+            }
+            MoveKind::KingsideCastle | MoveKind::QueensideCastle => {
+                let castle_file = match kind {
+                    MoveKind::KingsideCastle => File::G,
+                    _ => File::C,
+                };
+
+                initial_file == File::E
+                    && target_file == castle_file
+                    && initial_rank == target_rank
+                    && matches!(initial_rank, Rank::First | Rank::Eighth)
+            }
+            MoveKind::EnPassantCapture => {
+                file_distance == 1
+                    && matches!(
+                        (initial_rank, target_rank),
+                        (Rank::Fifth, Rank::Sixth) | (Rank::Fourth, Rank::Third)
+                    )
+            }
+            MoveKind::Promotion(_) | MoveKind::PromotionCapture(_) => {
+                let expected_distance = if kind.is_capture() { 1 } else { 0 };
+
+                file_distance == expected_distance
+                    && matches!(
+                        (initial_rank, target_rank),
+                        (Rank::Seventh, Rank::Eighth) | (Rank::Second, Rank::First)
+                    )
+            }
+        };
+
+        if !fits {
+            return Err(MoveError::BadSquares(kind));
+        }
+
+        Ok(Self(
+            Self::from_squares(initial_square, target_square).0 | flags,
+        ))
     }
 
     /// Returns an optional [`Move`] instance from square string slices.
     pub fn from_squares_str(initial_square: &str, target_square: &str) -> Option<Self> {
-        let i_square_index = square_str_to_index(initial_square)?;
-        let t_square_index = square_str_to_index(target_square)?;
+        let initial_square = initial_square.parse().ok()?;
+        let target_square = target_square.parse().ok()?;
+
+        Some(Move::from_squares(initial_square, target_square))
+    }
 
-        let new_move = Move::from_squares(i_square_index, t_square_index)?;
+    /// Returns the raw [`u16`] encoding of the move.
+    pub fn to_u16(self) -> u16 {
+        self.0
+    }
 
-        Some(new_move)
+    /// Extracts the initial square encoded in the move.
+    pub fn get_initial_square(&self) -> Square {
+        Square::ALL[((self.0 >> 6) & 0b111111) as usize]
     }
 
-    /// Extracts the initial square encoded in the move and returns
-    /// it as a [`u8`].
-    pub fn get_initial_square(&self) -> u8 {
-        ((self.0 >> 6) & 0b111111) as u8
+    /// Extracts the target square encoded in the move.
+    pub fn get_target_square(&self) -> Square {
+        Square::ALL[(self.0 & 0b111111) as usize]
     }
 
-    /// Extracts the target square encoded in the move and returns
-    /// it as a [`u8`].
-    pub fn get_target_square(&self) -> u8 {
-        (self.0 & 0b111111) as u8
+    /// Returns the kind of move encoded by the bitflag.
+    pub fn kind(&self) -> MoveKind {
+        // Every way of building a move rejects the two unused bitflags.
+        MoveKind::from_flags(self.0).expect("move has an unused bitflag")
     }
 
-    /// Returns `true` if the [`QUIET_MOVE`] bitflag is set, and `false`
+    /// Returns the piece the move promotes a pawn to, or [`None`] if the move isn't a
+    /// promotion.
+    pub fn promotion_piece(&self) -> Option<Piece> {
+        self.kind().promotion_piece()
+    }
+
+    /// Returns `true` if the bitflag is exactly [`QUIET_MOVE`], and `false`
     /// otherwise.
     pub fn is_quiet(&self) -> bool {
-        (self.0 >> 12) & QUIET_MOVE == QUIET_MOVE
+        self.0 & FLAG_MASK == QUIET_MOVE
     }
 
     /// Sets the bitflag of the [`Move`] to [`QUIET_MOVE`].
@@ -85,10 +314,10 @@ impl Move {
         self.0 &= QUIET_MASK;
     }
 
-    /// Returns `true` if the [`DOUBLE_PAWN_PUSH`] bitflag is set, and `false`
+    /// Returns `true` if the bitflag is exactly [`DOUBLE_PAWN_PUSH`], and `false`
     /// otherwise.
     pub fn is_double_pawn_push(&self) -> bool {
-        self.0 & DOUBLE_PAWN_PUSH == DOUBLE_PAWN_PUSH
+        self.0 & FLAG_MASK == DOUBLE_PAWN_PUSH
     }
 
     /// Sets the bitflag of the [`Move`] to [`DOUBLE_PAWN_PUSH`].
@@ -97,10 +326,10 @@ impl Move {
         self.0 |= DOUBLE_PAWN_PUSH;
     }
 
-    /// Returns `true` if the [`KING_CASTLE`] bitflag is set, and `false`
+    /// Returns `true` if the bitflag is exactly [`KING_CASTLE`], and `false`
     /// otherwise.
     pub fn is_kingside_castle(&self) -> bool {
-        self.0 & KING_CASTLE == KING_CASTLE
+        self.0 & FLAG_MASK == KING_CASTLE
     }
 
     /// Sets the bitflag of the [`Move`] to [KING_CASTLE].
@@ -109,10 +338,10 @@ impl Move {
         self.0 |= KING_CASTLE;
     }
 
-    /// Returns `true` if the [`QUEEN_CASTLE`] bitflag is set, and `false`
+    /// Returns `true` if the bitflag is exactly [`QUEEN_CASTLE`], and `false`
     /// otherwise.
     pub fn is_queenside_castle(&self) -> bool {
-        self.0 & QUEEN_CASTLE == QUEEN_CASTLE
+        self.0 & FLAG_MASK == QUEEN_CASTLE
     }
 
     /// Sets the bitflag of the [`Move`] to [`QUEEN_CASTLE`].
@@ -121,10 +350,10 @@ impl Move {
         self.0 |= QUEEN_CASTLE;
     }
 
-    /// Returns `true` if the [`CAPTURE`] bitflag is set, and `false`
-    /// otherwise.
+    /// Returns `true` if the move captures a piece (including en passant and
+    /// promotion captures), and `false` otherwise.
     pub fn is_capture(&self) -> bool {
-        self.0 & CAPTURE == CAPTURE
+        self.kind().is_capture()
     }
 
     /// Sets the bitflag of the [`Move`] to [`CAPTURE`].
@@ -135,14 +364,24 @@ impl Move {
 
     /// Sets the [`CAPTURE`] bit of the [`Move`] bitflag without altering
     /// the other bits.
+    ///
+    /// # Panics
+    /// Panics if the move is not quiet, a capture or a promotion, since the
+    /// capture bit would turn it into a different kind of move.
     pub fn add_capture(&mut self) {
+        assert!(
+            matches!(self.0 & FLAG_MASK, QUIET_MOVE | CAPTURE) || self.is_promotion(),
+            "cannot add a capture to a {:?} move",
+            self.kind()
+        );
+
         self.0 |= CAPTURE;
     }
 
-    /// Returns `true` if the [`EN_PASSANT_CAPTURE`] bitflag is set, and `false`
+    /// Returns `true` if the bitflag is exactly [`EN_PASSANT_CAPTURE`], and `false`
     /// otherwise.
     pub fn is_en_passant_capture(&self) -> bool {
-        self.0 & EN_PASSANT_CAPTURE == EN_PASSANT_CAPTURE
+        self.0 & FLAG_MASK == EN_PASSANT_CAPTURE
     }
 
     /// Sets the bitflag of the [`Move`] to [`EN_PASSANT_CAPTURE`].
@@ -151,16 +390,30 @@ impl Move {
         self.0 |= EN_PASSANT_CAPTURE;
     }
 
-    /// Returns `true` if the [`PROMOTION`] bitflag is set, and `false`
+    /// Returns `true` if the [`PROMOTION`] bit is set, and `false`
     /// otherwise.
     pub fn is_promotion(&self) -> bool {
         self.0 & PROMOTION == PROMOTION
     }
 
-    /// Returns `true` if the [`KNIGHT_PROMOTION`] bitflag is set, and `false`
-    /// otherwise.
+    /// Sets the promotion bits of the bitflag to the given flag, keeping the capture bit.
+    ///
+    /// # Panics
+    /// Panics if the move is not quiet or a (non en passant) capture.
+    fn add_promotion(&mut self, promotion: u16) {
+        assert!(
+            self.is_quiet() || self.0 & FLAG_MASK == CAPTURE,
+            "cannot add a promotion to a {:?} move",
+            self.kind()
+        );
+
+        self.0 |= promotion;
+    }
+
+    /// Returns `true` if the move promotes a pawn to a knight, with or without
+    /// a capture, and `false` otherwise.
     pub fn is_knight_promotion(&self) -> bool {
-        self.0 & KNIGHT_PROMOTION == KNIGHT_PROMOTION
+        self.promotion_piece() == Some(Piece::Knight)
     }
 
     /// Sets the bitflag of the [`Move`] to [`KNIGHT_PROMOTION`].
@@ -170,15 +423,18 @@ impl Move {
     }
 
     /// Sets the [`KNIGHT_PROMOTION`] bits of the [`Move`] bitflag without
-    /// altering the other bits.
+    /// altering the capture bit.
+    ///
+    /// # Panics
+    /// Panics if the move is not quiet or a (non en passant) capture.
     pub fn add_knight_promotion(&mut self) {
-        self.0 |= KNIGHT_PROMOTION;
+        self.add_promotion(KNIGHT_PROMOTION);
     }
 
-    /// Returns `true` if the [`BISHOP_PROMOTION`] bitflag is set, and `false`
-    /// otherwise.
+    /// Returns `true` if the move promotes a pawn to a bishop, with or without
+    /// a capture, and `false` otherwise.
     pub fn is_bishop_promotion(&self) -> bool {
-        self.0 & BISHOP_PROMOTION == BISHOP_PROMOTION
+        self.promotion_piece() == Some(Piece::Bishop)
     }
 
     /// Sets the bitflag of the [`Move`] to [`BISHOP_PROMOTION`].
@@ -188,15 +444,18 @@ impl Move {
     }
 
     /// Sets the [`BISHOP_PROMOTION`] bits of the [`Move`] bitflag without
-    /// altering the other bits.
+    /// altering the capture bit.
+    ///
+    /// # Panics
+    /// Panics if the move is not quiet or a (non en passant) capture.
     pub fn add_bishop_promotion(&mut self) {
-        self.0 |= BISHOP_PROMOTION;
+        self.add_promotion(BISHOP_PROMOTION);
     }
 
-    /// Returns `true` if the [`ROOK_PROMOTION`] bitflag is set, and `false`
-    /// otherwise.
+    /// Returns `true` if the move promotes a pawn to a rook, with or without
+    /// a capture, and `false` otherwise.
     pub fn is_rook_promotion(&self) -> bool {
-        self.0 & ROOK_PROMOTION == ROOK_PROMOTION
+        self.promotion_piece() == Some(Piece::Rook)
     }
 
     /// Sets the bitflag of the [`Move`] to [`ROOK_PROMOTION`].
@@ -206,15 +465,18 @@ impl Move {
     }
 
     /// Sets the [`ROOK_PROMOTION`] bits of the [`Move`] bitflag without
-    /// altering the other bits.
+    /// altering the capture bit.
+    ///
+    /// # Panics
+    /// Panics if the move is not quiet or a (non en passant) capture.
     pub fn add_rook_promotion(&mut self) {
-        self.0 |= ROOK_PROMOTION;
+        self.add_promotion(ROOK_PROMOTION);
     }
 
-    /// Returns `true` if the [`QUEEN_PROMOTION`] bitflag is set, and `false`
-    /// otherwise.
+    /// Returns `true` if the move promotes a pawn to a queen, with or without
+    /// a capture, and `false` otherwise.
     pub fn is_queen_promotion(&self) -> bool {
-        self.0 & QUEEN_PROMOTION == QUEEN_PROMOTION
+        self.promotion_piece() == Some(Piece::Queen)
     }
 
     /// Sets the bitflag of the [`Move`] to [`QUEEN_PROMOTION`].
@@ -224,15 +486,18 @@ impl Move {
     }
 
     /// Sets the [`QUEEN_PROMOTION`] bits of the [`Move`] bitflag without
-    /// altering the other bits.
+    /// altering the capture bit.
+    ///
+    /// # Panics
+    /// Panics if the move is not quiet or a (non en passant) capture.
     pub fn add_queen_promotion(&mut self) {
-        self.0 |= QUEEN_PROMOTION;
+        self.add_promotion(QUEEN_PROMOTION);
     }
 
-    /// Returns `true` if the [`KNIGHT_PROMOTION_CAPTURE`] bitflag is set, and `false`
+    /// Returns `true` if the bitflag is exactly [`KNIGHT_PROMOTION_CAPTURE`], and `false`
     /// otherwise.
     pub fn is_knight_promotion_capture(&self) -> bool {
-        self.0 & KNIGHT_PROMOTION_CAPTURE == KNIGHT_PROMOTION_CAPTURE
+        self.0 & FLAG_MASK == KNIGHT_PROMOTION_CAPTURE
     }
 
     /// Sets the bitflag of the [`Move`] to [`KNIGHT_PROMOTION_CAPTURE`].
@@ -241,10 +506,10 @@ impl Move {
         self.0 |= KNIGHT_PROMOTION_CAPTURE;
     }
 
-    /// Returns `true` if the [`BISHOP_PROMOTION_CAPTURE`] bitflag is set, and `false`
+    /// Returns `true` if the bitflag is exactly [`BISHOP_PROMOTION_CAPTURE`], and `false`
     /// otherwise.
     pub fn is_bishop_promotion_capture(&self) -> bool {
-        self.0 & BISHOP_PROMOTION_CAPTURE == BISHOP_PROMOTION_CAPTURE
+        self.0 & FLAG_MASK == BISHOP_PROMOTION_CAPTURE
     }
 
     /// Sets the bitflag of the [`Move`] to [`BISHOP_PROMOTION_CAPTURE`].
@@ -253,10 +518,10 @@ impl Move {
         self.0 |= BISHOP_PROMOTION_CAPTURE;
     }
 
-    /// Returns `true` if the [`ROOK_PROMOTION_CAPTURE`] bitflag is set, and `false`
+    /// Returns `true` if the bitflag is exactly [`ROOK_PROMOTION_CAPTURE`], and `false`
     /// otherwise.
     pub fn is_rook_promotion_capture(&self) -> bool {
-        self.0 & ROOK_PROMOTION_CAPTURE == ROOK_PROMOTION_CAPTURE
+        self.0 & FLAG_MASK == ROOK_PROMOTION_CAPTURE
     }
 
     /// Sets the bitflag of the [`Move`] to [`ROOK_PROMOTION_CAPTURE`].
@@ -265,10 +530,10 @@ impl Move {
         self.0 |= ROOK_PROMOTION_CAPTURE;
     }
 
-    /// Returns `true` if the [`QUEEN_PROMOTION_CAPTURE`] bitflag is set, and `false`
+    /// Returns `true` if the bitflag is exactly [`QUEEN_PROMOTION_CAPTURE`], and `false`
     /// otherwise.
     pub fn is_queen_promotion_capture(&self) -> bool {
-        self.0 & QUEEN_PROMOTION_CAPTURE == QUEEN_PROMOTION_CAPTURE
+        self.0 & FLAG_MASK == QUEEN_PROMOTION_CAPTURE
     }
 
     /// Sets the bitflag of the [`Move`] to [`QUEEN_PROMOTION_CAPTURE`].
@@ -278,6 +543,113 @@ impl Move {
     }
 }
 
+impl TryFrom<u16> for Move {
+    type Error = MoveError;
+
+    /// Decodes a raw [`u16`] move, rejecting the two unused bitflags.
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match MoveKind::from_flags(value) {
+            Some(_) => Ok(Self(value)),
+            None => Err(MoveError::BadFlags(value & FLAG_MASK)),
+        }
+    }
+}
+
+/// Serialises the move in long algebraic (UCI) notation in human-readable formats, and as
+/// its raw [`u16`] in the others. Only the raw form keeps every flag: moves read from the
+/// notation have to be resolved against their position, e.g. with
+/// [`Game::resolve_move()`](crate::game::Game::resolve_move()).
+#[cfg(feature = "serde")]
+impl serde::Serialize for Move {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u16(self.0)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Move {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error as _;
+
+        if deserializer.is_human_readable() {
+            String::deserialize(deserializer)?
+                .parse()
+                .map_err(D::Error::custom)
+        } else {
+            Self::try_from(u16::deserialize(deserializer)?).map_err(D::Error::custom)
+        }
+    }
+}
+
+/// Formats the move in long algebraic (UCI) notation, e.g. `e2e4` or `e7e8q`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            self.get_initial_square(),
+            self.get_target_square()
+        )?;
+
+        match self.promotion_piece() {
+            Some(Piece::Knight) => write!(f, "n"),
+            Some(Piece::Bishop) => write!(f, "b"),
+            Some(Piece::Rook) => write!(f, "r"),
+            Some(_) => write!(f, "q"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match MoveKind::from_flags(self.0) {
+            Some(kind) => write!(f, "Move({self}, {kind:?})"),
+            None => write!(f, "Move({:#018b})", self.0),
+        }
+    }
+}
+
+/// Parses a move in long algebraic (UCI) notation, e.g. `e2e4` or `e7e8q`.
+///
+/// The notation doesn't say whether a move captures, castles or pushes a pawn two
+/// squares, so the parsed move is either [quiet](MoveKind::Quiet) or a
+/// [promotion](MoveKind::Promotion). The remaining flags have to be filled in from
+/// the position the move is played in.
+impl FromStr for Move {
+    type Err = MoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return Err(MoveError::BadNotation);
+        }
+
+        let initial_square: Square = s[0..2].parse()?;
+        let target_square: Square = s[2..4].parse()?;
+
+        if initial_square == target_square {
+            return Err(MoveError::NullMove);
+        }
+
+        let mut mv = Move::from_squares(initial_square, target_square);
+
+        match s.as_bytes().get(4) {
+            Some(b'n') => mv.set_knight_promotion(),
+            Some(b'b') => mv.set_bishop_promotion(),
+            Some(b'r') => mv.set_rook_promotion(),
+            Some(b'q') => mv.set_queen_promotion(),
+            Some(_) => return Err(MoveError::BadNotation),
+            None => (),
+        }
+
+        Ok(mv)
+    }
+}
+
 /// Struct representing a [`Move`] and its corresponding score.
 #[derive(Debug, Clone, Copy)]
 pub struct MoveScore {
@@ -432,12 +804,146 @@ mod tests {
 
     #[test]
     fn test_from_squares() {
-        let i_square = 8;
-        let t_square = 24;
+        let i_square = Square::A2;
+        let t_square = Square::A4;
 
         assert_eq!(
             Move::from_squares(i_square, t_square),
-            Some(Move(0b00000010_00011000))
+            Move(0b00000010_00011000)
+        );
+        assert_eq!(
+            Move::from_squares(i_square, t_square).get_initial_square(),
+            i_square
+        );
+        assert_eq!(
+            Move::from_squares(i_square, t_square).get_target_square(),
+            t_square
+        );
+        assert_eq!(
+            Move::from_squares_str("a2", "a4"),
+            Some(Move::from_squares(i_square, t_square))
+        );
+        assert_eq!(Move::from_squares_str("a2", "a9"), None);
+    }
+
+    #[test]
+    fn test_flag_predicates() {
+        let queen_castle = Move(QUEEN_CASTLE);
+        assert!(queen_castle.is_queenside_castle());
+        assert!(!queen_castle.is_kingside_castle());
+
+        let rook_promotion = Move(ROOK_PROMOTION);
+        assert!(!rook_promotion.is_kingside_castle());
+        assert!(rook_promotion.is_rook_promotion());
+        assert!(!rook_promotion.is_knight_promotion());
+
+        let en_passant = Move(EN_PASSANT_CAPTURE);
+        assert!(!en_passant.is_double_pawn_push());
+        assert!(en_passant.is_capture());
+
+        let bishop_promotion_capture = Move(BISHOP_PROMOTION_CAPTURE);
+        assert!(!bishop_promotion_capture.is_en_passant_capture());
+        assert!(bishop_promotion_capture.is_bishop_promotion());
+        assert!(bishop_promotion_capture.is_capture());
+
+        assert!(Move::new().is_quiet());
+        assert!(!Move(CAPTURE).is_quiet());
+    }
+
+    #[test]
+    fn test_move_kind() {
+        let mut mv = Move::from_squares(Square::B7, Square::A8);
+        assert_eq!(mv.kind(), MoveKind::Quiet);
+        assert_eq!(mv.promotion_piece(), None);
+
+        mv.set_capture();
+        mv.add_rook_promotion();
+        assert_eq!(mv.kind(), MoveKind::PromotionCapture(Piece::Rook));
+        assert_eq!(mv.promotion_piece(), Some(Piece::Rook));
+
+        for flags in 0..16 {
+            let kind = MoveKind::from_flags(flags << 12);
+            assert_eq!(kind.is_none(), flags == 6 || flags == 7);
+            assert_eq!(kind.and_then(MoveKind::to_flags), kind.map(|_| flags << 12));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_add_promotion_to_double_pawn_push() {
+        let mut mv = Move::from_squares(Square::E2, Square::E4);
+        mv.set_double_pawn_push();
+        mv.add_queen_promotion();
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert_eq!(
+            Move::try_new(Square::E2, Square::E4, MoveKind::DoublePawnPush).map(|mv| mv.kind()),
+            Ok(MoveKind::DoublePawnPush)
+        );
+        assert_eq!(
+            Move::try_new(Square::E2, Square::E3, MoveKind::DoublePawnPush),
+            Err(MoveError::BadSquares(MoveKind::DoublePawnPush))
+        );
+        assert_eq!(
+            Move::try_new(Square::E2, Square::E4, MoveKind::Promotion(Piece::Queen)),
+            Err(MoveError::BadSquares(MoveKind::Promotion(Piece::Queen)))
+        );
+        assert_eq!(
+            Move::try_new(Square::E7, Square::E8, MoveKind::Promotion(Piece::King)),
+            Err(MoveError::BadPromotionPiece(Piece::King))
+        );
+        assert!(Move::try_new(Square::E8, Square::C8, MoveKind::QueensideCastle).is_ok());
+        assert!(Move::try_new(Square::A1, Square::C1, MoveKind::QueensideCastle).is_err());
+        assert!(Move::try_new(Square::D5, Square::E6, MoveKind::EnPassantCapture).is_ok());
+        assert!(
+            Move::try_new(
+                Square::D2,
+                Square::C1,
+                MoveKind::PromotionCapture(Piece::Knight)
+            )
+            .is_ok()
+        );
+        assert_eq!(
+            Move::try_new(Square::D4, Square::D4, MoveKind::Quiet),
+            Err(MoveError::NullMove)
+        );
+    }
+
+    #[test]
+    fn test_try_from_u16() {
+        let mv = Move::try_new(
+            Square::G7,
+            Square::H8,
+            MoveKind::PromotionCapture(Piece::Queen),
+        )
+        .unwrap();
+
+        assert_eq!(Move::try_from(mv.to_u16()), Ok(mv));
+        assert_eq!(
+            Move::try_from(0b0110 << 12),
+            Err(MoveError::BadFlags(0b0110 << 12))
+        );
+    }
+
+    #[test]
+    fn test_move_display_from_str() {
+        let mv: Move = "e7e8n".parse().unwrap();
+        assert_eq!(mv.kind(), MoveKind::Promotion(Piece::Knight));
+        assert_eq!(mv.to_string(), "e7e8n");
+        assert_eq!(format!("{mv:?}"), "Move(e7e8n, Promotion(Knight))");
+
+        let mv: Move = "g1f3".parse().unwrap();
+        assert_eq!(mv, Move::from_squares(Square::G1, Square::F3));
+        assert_eq!(mv.to_string(), "g1f3");
+
+        assert_eq!("e7e8k".parse::<Move>(), Err(MoveError::BadNotation));
+        assert_eq!("e2".parse::<Move>(), Err(MoveError::BadNotation));
+        assert_eq!("e2e2".parse::<Move>(), Err(MoveError::NullMove));
+        assert_eq!(
+            "e2i4".parse::<Move>(),
+            Err(MoveError::BadSquare(SquareParseError::BadFile))
         );
     }
 }
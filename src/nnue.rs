@@ -0,0 +1,822 @@
+//! NNUE evaluation: a neural network whose first layer is updated incrementally as moves
+//! are made, so that evaluating a position only costs the small output layer.
+//!
+//! Each side has an accumulator holding the first layer from its own perspective: the
+//! feature biases plus the weight rows of the features of the pieces on the board, seen
+//! from that side's king. Both accumulators go through a clipped ReLU, the side to move's
+//! first, and a single output neuron turns them into a score in centipawns. Everything runs
+//! on integers, with AVX2 used when the CPU has it and a scalar fallback otherwise.
+//!
+//! # Network files
+//!
+//! A network file holds the quantised weights, all numbers little-endian:
+//!
+//! - the magic bytes `POOBNNUE`
+//! - the format version, [`NETWORK_VERSION`], as a `u32`
+//! - the feature set as a `u32`: `0` for [HalfKP](FeatureSet::HalfKp) and `1` for
+//!   [HalfKA](FeatureSet::HalfKa)
+//! - the hidden layer size `H` as a `u32`, a multiple of 16 up to [`MAX_HIDDEN`]
+//! - the output scale as an `i32`
+//! - the feature weights as `i16`s, the `H` weights of each input feature in turn
+//! - the `H` feature biases as `i16`s
+//! - the `2 * H` output weights as `i8`s, those of the side to move's accumulator first
+//! - the output bias as an `i32`
+//!
+//! The first layer is quantised by [`QA`], so that an activation of `1.0` is stored as
+//! `QA`, and the output weights by [`QB`]. The evaluation is the output neuron times the
+//! output scale divided by `QA * QB`.
+
+use crate::boardstate::BoardState;
+use crate::datagen;
+use crate::eval::ALL_PIECES;
+use crate::game::Game;
+use crate::pawns::PawnHashTable;
+use crate::search::SearchLimits;
+use crate::{Color, Piece, Square};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The magic bytes at the start of every network file.
+pub const NETWORK_MAGIC: [u8; 8] = *b"POOBNNUE";
+
+/// The version of the network file format.
+pub const NETWORK_VERSION: u32 = 1;
+
+/// The largest hidden layer a network file may have.
+pub const MAX_HIDDEN: usize = 4096;
+
+/// The quantisation factor of the first layer, and the upper bound of the clipped ReLU.
+pub const QA: i16 = 255;
+
+/// The quantisation factor of the output weights.
+pub const QB: i32 = 64;
+
+/// Evaluations are clamped to this many centipawns, well away from mate scores.
+const MAX_SCORE: i32 = 20_000;
+
+/// The errors that can occur when reading a network.
+#[derive(Debug)]
+pub enum NnueError {
+    Io(io::Error),
+    /// The file doesn't start with [`NETWORK_MAGIC`].
+    BadMagic,
+    UnsupportedVersion(u32),
+    UnknownFeatureSet(u32),
+    /// The hidden layer size isn't a non-zero multiple of 16 up to [`MAX_HIDDEN`].
+    BadHiddenSize(u32),
+    /// The file ends before the last weight.
+    Truncated,
+    /// The file goes on after the last weight.
+    TrailingData,
+}
+
+impl fmt::Display for NnueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::BadMagic => write!(f, "not a poob network file"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported network file version {version}")
+            }
+            Self::UnknownFeatureSet(id) => write!(f, "unknown feature set {id}"),
+            Self::BadHiddenSize(size) => write!(
+                f,
+                "bad hidden layer size {size}, expected a multiple of 16 up to {MAX_HIDDEN}"
+            ),
+            Self::Truncated => write!(f, "the network file is truncated"),
+            Self::TrailingData => write!(f, "unexpected data after the network weights"),
+        }
+    }
+}
+
+impl Error for NnueError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NnueError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// The input features of a network. Both index a piece by the square of the king of the
+/// perspective's side, the piece's kind and color relative to that side, and its square,
+/// with the board flipped vertically for black.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureSet {
+    /// Every piece but the kings: `64 * 10 * 64` features. A king move changes every
+    /// feature of its side's perspective.
+    HalfKp,
+    /// Every piece, the kings included: `64 * 12 * 64` features.
+    HalfKa,
+}
+
+impl FeatureSet {
+    /// Returns the number of input features.
+    pub fn inputs(self) -> usize {
+        64 * self.kinds() * 64
+    }
+
+    /// Returns the number of piece kinds, counting each color separately.
+    fn kinds(self) -> usize {
+        match self {
+            Self::HalfKp => 10,
+            Self::HalfKa => 12,
+        }
+    }
+
+    fn id(self) -> u32 {
+        match self {
+            Self::HalfKp => 0,
+            Self::HalfKa => 1,
+        }
+    }
+
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::HalfKp),
+            1 => Some(Self::HalfKa),
+            _ => None,
+        }
+    }
+
+    /// Returns the feature of a piece from the given perspective, or [`None`] if the
+    /// feature set ignores the piece.
+    fn index(
+        self,
+        perspective: Color,
+        king: Square,
+        (color, piece): (Color, Piece),
+        square: Square,
+    ) -> Option<usize> {
+        let orient = |square: Square| match perspective {
+            Color::White => square.index(),
+            Color::Black => square.flip_rank().index(),
+        };
+        let per_side = self.kinds() / 2;
+        let piece = piece.to_index();
+
+        if piece >= per_side {
+            return None;
+        }
+
+        let kind = if color == perspective {
+            piece
+        } else {
+            per_side + piece
+        };
+
+        Some((orient(king) * self.kinds() + kind) * 64 + orient(square))
+    }
+}
+
+/// The implementation of the vector operations, picked once for the running CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kernel {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+}
+
+impl Kernel {
+    fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            return Self::Avx2;
+        }
+
+        Self::Scalar
+    }
+
+    /// Adds a weight row to an accumulator.
+    fn add(self, accumulator: &mut [i16], row: &[i16]) {
+        match self {
+            Self::Scalar => {
+                for (value, weight) in accumulator.iter_mut().zip(row) {
+                    *value = value.wrapping_add(*weight);
+                }
+            }
+            // SAFETY: the AVX2 kernel is only picked when the CPU supports AVX2.
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => unsafe { avx2::add(accumulator, row) },
+        }
+    }
+
+    /// Subtracts a weight row from an accumulator.
+    fn sub(self, accumulator: &mut [i16], row: &[i16]) {
+        match self {
+            Self::Scalar => {
+                for (value, weight) in accumulator.iter_mut().zip(row) {
+                    *value = value.wrapping_sub(*weight);
+                }
+            }
+            // SAFETY: as in `add`.
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => unsafe { avx2::sub(accumulator, row) },
+        }
+    }
+
+    /// Returns the dot product of the clipped activations and the weights.
+    fn output(self, activations: &[i16], weights: &[i8]) -> i32 {
+        match self {
+            Self::Scalar => activations
+                .iter()
+                .zip(weights)
+                .map(|(&value, &weight)| i32::from(value.clamp(0, QA)) * i32::from(weight))
+                .sum(),
+            // SAFETY: as in `add`.
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => unsafe { avx2::output(activations, weights) },
+        }
+    }
+}
+
+/// The AVX2 kernel, working on 16 values at a time. Every slice length is a multiple of 16.
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use super::QA;
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn add(accumulator: &mut [i16], row: &[i16]) {
+        for (values, weights) in accumulator.chunks_exact_mut(16).zip(row.chunks_exact(16)) {
+            // SAFETY: each chunk is 16 `i16`s, 32 bytes, and the loads and stores are
+            // unaligned.
+            unsafe {
+                let sum = _mm256_add_epi16(
+                    _mm256_loadu_si256(values.as_ptr().cast()),
+                    _mm256_loadu_si256(weights.as_ptr().cast()),
+                );
+                _mm256_storeu_si256(values.as_mut_ptr().cast(), sum);
+            }
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn sub(accumulator: &mut [i16], row: &[i16]) {
+        for (values, weights) in accumulator.chunks_exact_mut(16).zip(row.chunks_exact(16)) {
+            // SAFETY: as in `add`.
+            unsafe {
+                let difference = _mm256_sub_epi16(
+                    _mm256_loadu_si256(values.as_ptr().cast()),
+                    _mm256_loadu_si256(weights.as_ptr().cast()),
+                );
+                _mm256_storeu_si256(values.as_mut_ptr().cast(), difference);
+            }
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn output(activations: &[i16], weights: &[i8]) -> i32 {
+        let zero = _mm256_setzero_si256();
+        let max = _mm256_set1_epi16(QA);
+        let mut sum = _mm256_setzero_si256();
+
+        for (values, weights) in activations.chunks_exact(16).zip(weights.chunks_exact(16)) {
+            // SAFETY: each chunk of activations is 32 bytes and each chunk of weights 16
+            // bytes, and the loads are unaligned.
+            let (values, weights) = unsafe {
+                (
+                    _mm256_loadu_si256(values.as_ptr().cast()),
+                    _mm_loadu_si128(weights.as_ptr().cast()),
+                )
+            };
+            let clipped = _mm256_min_epi16(_mm256_max_epi16(values, zero), max);
+
+            sum = _mm256_add_epi32(
+                sum,
+                _mm256_madd_epi16(clipped, _mm256_cvtepi8_epi16(weights)),
+            );
+        }
+
+        let mut lanes = [0i32; 8];
+        // SAFETY: `lanes` is 32 bytes, and the store is unaligned.
+        unsafe { _mm256_storeu_si256(lanes.as_mut_ptr().cast(), sum) };
+
+        lanes.iter().sum()
+    }
+}
+
+/// A quantised network. See the [module documentation](self) for its file format.
+#[derive(Clone)]
+pub struct Network {
+    feature_set: FeatureSet,
+    hidden: usize,
+    scale: i32,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i8>,
+    output_bias: i32,
+    kernel: Kernel,
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Network")
+            .field("feature_set", &self.feature_set)
+            .field("hidden", &self.hidden)
+            .field("scale", &self.scale)
+            .field("kernel", &self.kernel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Network {
+    /// Reads a network file.
+    ///
+    /// # Errors
+    /// Returns an [`NnueError`] if the file can't be read or isn't a valid network.
+    pub fn load(path: &Path) -> Result<Self, NnueError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Writes the network to a file.
+    ///
+    /// # Errors
+    /// Returns an [`io::Error`] if the file can't be written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    /// Parses a network in the file format.
+    ///
+    /// # Errors
+    /// Returns an [`NnueError`] if the bytes aren't a valid network.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, NnueError> {
+        let bytes = &mut bytes;
+
+        if take(bytes, NETWORK_MAGIC.len())? != NETWORK_MAGIC {
+            return Err(NnueError::BadMagic);
+        }
+
+        let version = read_u32(bytes)?;
+
+        if version != NETWORK_VERSION {
+            return Err(NnueError::UnsupportedVersion(version));
+        }
+
+        let id = read_u32(bytes)?;
+        let feature_set = FeatureSet::from_id(id).ok_or(NnueError::UnknownFeatureSet(id))?;
+        let size = read_u32(bytes)?;
+        let hidden = size as usize;
+
+        if hidden == 0 || !hidden.is_multiple_of(16) || hidden > MAX_HIDDEN {
+            return Err(NnueError::BadHiddenSize(size));
+        }
+
+        let scale = read_i32(bytes)?;
+        let feature_weights = read_i16s(bytes, feature_set.inputs() * hidden)?;
+        let feature_biases = read_i16s(bytes, hidden)?;
+        let output_weights = take(bytes, 2 * hidden)?
+            .iter()
+            .map(|&byte| byte as i8)
+            .collect();
+        let output_bias = read_i32(bytes)?;
+
+        if !bytes.is_empty() {
+            return Err(NnueError::TrailingData);
+        }
+
+        Ok(Self {
+            feature_set,
+            hidden,
+            scale,
+            feature_weights,
+            feature_biases,
+            output_weights,
+            output_bias,
+            kernel: Kernel::detect(),
+        })
+    }
+
+    /// Returns the network in the file format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.feature_weights.len() * 2 + 64);
+
+        bytes.extend_from_slice(&NETWORK_MAGIC);
+        bytes.extend_from_slice(&NETWORK_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.feature_set.id().to_le_bytes());
+        bytes.extend_from_slice(&(self.hidden as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.scale.to_le_bytes());
+
+        for weight in self.feature_weights.iter().chain(&self.feature_biases) {
+            bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+
+        bytes.extend(self.output_weights.iter().map(|&weight| weight as u8));
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+
+        bytes
+    }
+
+    /// Returns the input features of the network.
+    pub fn feature_set(&self) -> FeatureSet {
+        self.feature_set
+    }
+
+    /// Returns the size of the hidden layer, the length of each accumulator.
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    /// Returns the evaluation of the position in centipawns from the point of view of the
+    /// side to move, computing the accumulators from scratch.
+    pub fn evaluate(&self, board_state: &BoardState) -> i32 {
+        self.output(
+            &Accumulator::new(self, board_state),
+            board_state.side_to_move,
+        )
+    }
+
+    /// Returns the evaluation in centipawns from the point of view of the side to move,
+    /// given the accumulators of the position.
+    pub fn output(&self, accumulator: &Accumulator, side_to_move: Color) -> i32 {
+        let us = &accumulator.values[side_to_move.to_index()];
+        let them = &accumulator.values[side_to_move.enemy().to_index()];
+        let (our_weights, their_weights) = self.output_weights.split_at(self.hidden);
+        let output = self.kernel.output(us, our_weights)
+            + self.kernel.output(them, their_weights)
+            + self.output_bias;
+        let score = i64::from(output) * i64::from(self.scale) / i64::from(i32::from(QA) * QB);
+
+        score.clamp(-i64::from(MAX_SCORE), i64::from(MAX_SCORE)) as i32
+    }
+
+    /// Returns the weight row of an input feature.
+    fn row(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+}
+
+fn take<'b>(bytes: &mut &'b [u8], len: usize) -> Result<&'b [u8], NnueError> {
+    if bytes.len() < len {
+        return Err(NnueError::Truncated);
+    }
+
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+
+    Ok(head)
+}
+
+fn read_u32(bytes: &mut &[u8]) -> Result<u32, NnueError> {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(take(bytes, 4)?);
+
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_i32(bytes: &mut &[u8]) -> Result<i32, NnueError> {
+    read_u32(bytes).map(|value| value as i32)
+}
+
+fn read_i16s(bytes: &mut &[u8], count: usize) -> Result<Vec<i16>, NnueError> {
+    Ok(take(bytes, count * 2)?
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect())
+}
+
+/// Returns the square of the king of the given color, or `a1` in positions without one.
+fn king_square(board_state: &BoardState, color: Color) -> Square {
+    board_state
+        .position
+        .board(color, Piece::King)
+        .into_iter()
+        .next()
+        .unwrap_or(Square::A1)
+}
+
+/// The first layer of a network for a position, from each side's perspective.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator {
+    /// The accumulators indexed by [`Color::to_index()`].
+    values: [Vec<i16>; 2],
+}
+
+impl Accumulator {
+    /// Computes the accumulators of the position from scratch.
+    pub fn new(network: &Network, board_state: &BoardState) -> Self {
+        let mut accumulator = Self {
+            values: [Vec::new(), Vec::new()],
+        };
+
+        for perspective in [Color::White, Color::Black] {
+            accumulator.refresh(network, board_state, perspective);
+        }
+
+        accumulator
+    }
+
+    fn refresh(&mut self, network: &Network, board_state: &BoardState, perspective: Color) {
+        let values = &mut self.values[perspective.to_index()];
+        let king = king_square(board_state, perspective);
+
+        values.clone_from(&network.feature_biases);
+
+        for color in [Color::White, Color::Black] {
+            for piece in ALL_PIECES {
+                for square in board_state.position.board(color, piece) {
+                    if let Some(feature) =
+                        network
+                            .feature_set
+                            .index(perspective, king, (color, piece), square)
+                    {
+                        network.kernel.add(values, network.row(feature));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Updates the accumulators of `before` to those of `after`, a position a move away.
+    /// Only the pieces that changed are updated, unless a king moved and its side's
+    /// perspective has to be computed from scratch.
+    pub fn update(&mut self, network: &Network, before: &BoardState, after: &BoardState) {
+        for perspective in [Color::White, Color::Black] {
+            let king = king_square(after, perspective);
+
+            if king != king_square(before, perspective) {
+                self.refresh(network, after, perspective);
+                continue;
+            }
+
+            let values = &mut self.values[perspective.to_index()];
+
+            for color in [Color::White, Color::Black] {
+                for piece in ALL_PIECES {
+                    let old = before.position.board(color, piece);
+                    let new = after.position.board(color, piece);
+                    let feature = |square| {
+                        network
+                            .feature_set
+                            .index(perspective, king, (color, piece), square)
+                    };
+
+                    for feature in (old & !new).into_iter().filter_map(feature) {
+                        network.kernel.sub(values, network.row(feature));
+                    }
+
+                    for feature in (new & !old).into_iter().filter_map(feature) {
+                        network.kernel.add(values, network.row(feature));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The accumulators of the positions along a search path, pushed as moves are made and
+/// popped as they are taken back.
+#[derive(Debug)]
+pub struct AccumulatorStack<'n> {
+    network: &'n Network,
+    /// The accumulators of the path, followed by spare ones kept to avoid allocating.
+    stack: Vec<Accumulator>,
+    len: usize,
+}
+
+impl<'n> AccumulatorStack<'n> {
+    /// Creates a stack holding the accumulators of the root position.
+    pub fn new(network: &'n Network, board_state: &BoardState) -> Self {
+        Self {
+            network,
+            stack: vec![Accumulator::new(network, board_state)],
+            len: 1,
+        }
+    }
+
+    /// Pushes the accumulators of `after`, reached by a move from `before`, the position
+    /// of the accumulators on top of the stack.
+    pub fn push(&mut self, before: &BoardState, after: &BoardState) {
+        if self.len == self.stack.len() {
+            self.stack.push(self.stack[self.len - 1].clone());
+        } else {
+            let (path, spare) = self.stack.split_at_mut(self.len);
+            spare[0].clone_from(&path[self.len - 1]);
+        }
+
+        self.stack[self.len].update(self.network, before, after);
+        self.len += 1;
+    }
+
+    /// Pops the accumulators of the last position pushed. The root position is never
+    /// popped.
+    pub fn pop(&mut self) {
+        self.len = (self.len - 1).max(1);
+    }
+
+    /// Returns the evaluation of the position on top of the stack from the point of view
+    /// of the side to move.
+    pub fn evaluate(&self, side_to_move: Color) -> i32 {
+        self.network.output(&self.stack[self.len - 1], side_to_move)
+    }
+}
+
+/// A position of a self-play game, for training networks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingRecord {
+    pub fen: String,
+    /// The search score in centipawns, from white's point of view.
+    pub score: i32,
+    /// The result of the game from white's point of view: `1` for a win, `0.5` for a draw
+    /// and `0` for a loss.
+    pub result: f32,
+}
+
+impl fmt::Display for TrainingRecord {
+    /// Writes the record as `<fen> | <score> | <result>`, the text format read by common
+    /// NNUE trainers.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} | {} | {:.1}", self.fen, self.score, self.result)
+    }
+}
+
+/// Plays a game of the engine against itself from the given position, with the given
+/// search limits and network, and returns its quiet positions labelled with their scores
+/// and the result. See [`datagen`] to generate whole datasets.
+pub fn self_play(
+    start: &Game,
+    limits: SearchLimits,
+    network: Option<&Network>,
+) -> Vec<TrainingRecord> {
+    let (samples, result) = datagen::play_game(
+        start,
+        limits,
+        datagen::MAX_GAME_PLIES,
+        network,
+        &mut PawnHashTable::default(),
+    );
+
+    samples
+        .into_iter()
+        .map(|sample| TrainingRecord {
+            fen: sample.board_state.to_fen(),
+            score: sample.score,
+            result,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+    use crate::search;
+    use std::sync::atomic::AtomicBool;
+
+    /// Returns a network with pseudo-random weights.
+    fn random_network(feature_set: FeatureSet, hidden: usize) -> Network {
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = move |range: i64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % (2 * range as u64 + 1)) as i64 - range
+        };
+
+        Network {
+            feature_set,
+            hidden,
+            scale: 400,
+            feature_weights: (0..feature_set.inputs() * hidden)
+                .map(|_| next(40) as i16)
+                .collect(),
+            feature_biases: (0..hidden).map(|_| next(100) as i16).collect(),
+            output_weights: (0..2 * hidden).map(|_| next(127) as i8).collect(),
+            output_bias: next(1000) as i32,
+            kernel: Kernel::detect(),
+        }
+    }
+
+    #[test]
+    fn test_network_file() {
+        let network = random_network(FeatureSet::HalfKp, 16);
+        let bytes = network.to_bytes();
+        let loaded = Network::from_bytes(&bytes).unwrap();
+
+        assert_eq!(loaded.to_bytes(), bytes);
+        assert_eq!(loaded.feature_set(), FeatureSet::HalfKp);
+        assert_eq!(loaded.hidden_size(), 16);
+        assert_eq!(bytes.len(), 24 + 40960 * 16 * 2 + 16 * 2 + 32 + 4);
+
+        assert!(matches!(
+            Network::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(NnueError::Truncated)
+        ));
+        assert!(matches!(
+            Network::from_bytes(&[&bytes[..], &[0]].concat()),
+            Err(NnueError::TrailingData)
+        ));
+        assert!(matches!(
+            Network::from_bytes(b"NOTANNUE"),
+            Err(NnueError::BadMagic)
+        ));
+
+        let mut bad_size = bytes.clone();
+        bad_size[16..20].copy_from_slice(&24u32.to_le_bytes());
+
+        assert!(matches!(
+            Network::from_bytes(&bad_size),
+            Err(NnueError::BadHiddenSize(24))
+        ));
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let move_gen = MoveGenerator::new();
+
+        for feature_set in [FeatureSet::HalfKp, FeatureSet::HalfKa] {
+            let network = random_network(feature_set, 32);
+            // Castling both ways, an en passant capture, promotions and king moves.
+            let mut game =
+                Game::from_fen("r3k2r/6P1/8/3pP3/8/8/2p5/R3K2R w KQkq d6 0 1", &move_gen).unwrap();
+            let mut stack = AccumulatorStack::new(&network, game.get_board_state());
+
+            for mv in ["e5d6", "e8c8", "e1g1", "c2c1q", "g7h8n", "c8b8", "g1h1"] {
+                let before = game.get_board_state().clone();
+                game.try_make_move(mv.parse().unwrap()).unwrap();
+                let after = game.get_board_state();
+
+                stack.push(&before, after);
+
+                assert_eq!(
+                    stack.stack[stack.len - 1],
+                    Accumulator::new(&network, after),
+                    "{feature_set:?} after {mv}"
+                );
+                assert_eq!(stack.evaluate(after.side_to_move), network.evaluate(after));
+            }
+
+            for _ in 0..10 {
+                stack.pop();
+            }
+
+            assert_eq!(stack.len, 1);
+        }
+    }
+
+    #[test]
+    fn test_kernels_agree() {
+        let mut network = random_network(FeatureSet::HalfKa, 64);
+        let board_state = BoardState::from_fen(
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R b KQ - 0 8",
+        )
+        .unwrap();
+        let detected = network.evaluate(&board_state);
+
+        network.kernel = Kernel::Scalar;
+
+        assert_eq!(network.evaluate(&board_state), detected);
+        assert_ne!(detected, 0);
+
+        // The perspectives are symmetric: the same position with colors swapped scores the
+        // same for the side to move.
+        let mirrored = BoardState::from_fen(
+            "r2qkb1r/pp2bppp/2n2n2/2pp4/3P4/2N1PN2/PP2BPPP/R1BQ1RK1 w kq - 0 8",
+        )
+        .unwrap();
+
+        assert_eq!(network.evaluate(&mirrored), detected);
+    }
+
+    #[test]
+    fn test_search_and_self_play() {
+        let move_gen = MoveGenerator::new();
+        let network = random_network(FeatureSet::HalfKp, 16);
+
+        // The network knows nothing, but the search still finds the mate.
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &move_gen).unwrap();
+        let stop = AtomicBool::new(false);
+        let result = search::search_with_network(
+            &game,
+            SearchLimits::depth(3),
+            &stop,
+            Some(&network),
+            |_| (),
+        );
+
+        assert_eq!(search::mate_in(result.score), Some(1));
+
+        let records = self_play(&Game::new(&move_gen), SearchLimits::depth(1), None);
+        let result = records[0].result;
+
+        assert!([0.0, 0.5, 1.0].contains(&result));
+        assert!(records.iter().all(|record| record.result == result));
+        assert_eq!(
+            records[0].to_string(),
+            format!(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 | {} | {result:.1}",
+                records[0].score
+            )
+        );
+    }
+}
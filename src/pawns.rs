@@ -0,0 +1,499 @@
+use crate::Color;
+use crate::bitboards::{BitBoards, Bitboard, bitboard_constants::rank_file::*};
+
+/// The bonus for a passed pawn, indexed by its rank from its own side's point of view.
+pub(crate) const PASSED_PAWN_BONUS: [i32; 8] = [0, 5, 10, 20, 35, 60, 100, 0];
+pub(crate) const CANDIDATE_PAWN_BONUS: i32 = 10;
+pub(crate) const CONNECTED_PAWN_BONUS: i32 = 5;
+pub(crate) const ISOLATED_PAWN_PENALTY: i32 = -15;
+pub(crate) const DOUBLED_PAWN_PENALTY: i32 = -10;
+pub(crate) const BACKWARD_PAWN_PENALTY: i32 = -8;
+/// The penalty for every pawn island beyond the first.
+pub(crate) const PAWN_ISLAND_PENALTY: i32 = -5;
+
+/// The ranks on which a white piece can find an outpost. Black outposts are on the
+/// mirrored ranks.
+const WHITE_OUTPOST_RANKS: Bitboard = Bitboard(RANK_4.0 | RANK_5.0 | RANK_6.0);
+const BLACK_OUTPOST_RANKS: Bitboard = Bitboard(RANK_3.0 | RANK_4.0 | RANK_5.0);
+
+/// The default number of entries of a [`PawnHashTable`].
+pub const DEFAULT_PAWN_TABLE_SIZE: usize = 1 << 14;
+
+/// The Zobrist keys of a pawn of each color on each square, generated at compile time with
+/// a fixed seed so that pawn keys are stable between runs.
+const PAWN_KEYS: [[u64; 64]; 2] = {
+    let mut keys = [[0; 64]; 2];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut index = 0;
+
+    while index < 128 {
+        // xorshift64*
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        keys[index / 64][index % 64] = state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        index += 1;
+    }
+
+    keys
+};
+
+/// Returns the Zobrist hash of the pawns alone, used as the key of the [`PawnHashTable`].
+pub fn pawn_key(pawns_white: Bitboard, pawns_black: Bitboard) -> u64 {
+    let white = pawns_white
+        .into_iter()
+        .fold(0, |key, square| key ^ PAWN_KEYS[0][square.index()]);
+
+    pawns_black
+        .into_iter()
+        .fold(white, |key, square| key ^ PAWN_KEYS[1][square.index()])
+}
+
+/// The classification of every pawn on the board, along with the file and outpost
+/// information that only depends on the pawns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PawnStructure {
+    pawns: [Bitboard; 2],
+    passed: [Bitboard; 2],
+    candidate: [Bitboard; 2],
+    isolated: [Bitboard; 2],
+    doubled: [Bitboard; 2],
+    backward: [Bitboard; 2],
+    connected: [Bitboard; 2],
+    islands: [u32; 2],
+    half_open_files: [Bitboard; 2],
+    outposts: [Bitboard; 2],
+}
+
+impl PawnStructure {
+    /// Analyses the structure of the given pawns.
+    pub fn new(pawns_white: Bitboard, pawns_black: Bitboard) -> Self {
+        let mut structure = Self {
+            pawns: [Bitboard::EMPTY; 2],
+            passed: [Bitboard::EMPTY; 2],
+            candidate: [Bitboard::EMPTY; 2],
+            isolated: [Bitboard::EMPTY; 2],
+            doubled: [Bitboard::EMPTY; 2],
+            backward: [Bitboard::EMPTY; 2],
+            connected: [Bitboard::EMPTY; 2],
+            islands: [0; 2],
+            half_open_files: [Bitboard::EMPTY; 2],
+            outposts: [Bitboard::EMPTY; 2],
+        };
+
+        structure.pawns[Color::White.to_index()] = pawns_white;
+        structure.pawns[Color::Black.to_index()] = pawns_black;
+
+        for color in [Color::White, Color::Black] {
+            structure.classify(color);
+        }
+
+        structure
+    }
+
+    /// Analyses the structure of the pawns of the position.
+    pub fn from_position(position: &BitBoards) -> Self {
+        Self::new(position.pawns_white(), position.pawns_black())
+    }
+
+    fn classify(&mut self, color: Color) {
+        let index = color.to_index();
+        let own = self.pawns[index];
+        let enemy = self.pawns[color.enemy().to_index()];
+        let enemy_attacks = pawn_attacks(enemy, color.enemy());
+        let own_attacks = pawn_attacks(own, color);
+
+        for square in own {
+            let pawn = square.bitboard();
+            let front = front_span(pawn, color);
+            let adjacent = adjacent_files(file_fill(pawn));
+            let ahead = front | adjacent & front_span(adjacent & rank_fill(pawn), color);
+            // The squares on adjacent files level with or behind the pawn, from which
+            // friendly pawns could support it.
+            let support = adjacent & !front_span(adjacent & rank_fill(pawn), color);
+
+            let passed = (ahead & enemy).is_empty();
+            let isolated = (adjacent & own).is_empty();
+
+            if passed {
+                self.passed[index] |= pawn;
+            } else if (front & enemy).is_empty() {
+                // A pawn on a half-open file becomes passed if its supporters outnumber
+                // the enemy pawns in its way.
+                let sentries = (ahead & !front & enemy).count();
+                let supporters = (support & own).count();
+
+                if supporters >= sentries {
+                    self.candidate[index] |= pawn;
+                }
+            }
+
+            if isolated {
+                self.isolated[index] |= pawn;
+            }
+
+            if !(front & own).is_empty() {
+                self.doubled[index] |= pawn;
+            }
+
+            let phalanx = adjacent & rank_fill(pawn) & own;
+
+            if !phalanx.is_empty() || own_attacks.contains(square) {
+                self.connected[index] |= pawn;
+            }
+
+            let stop = push(pawn, color);
+
+            if !isolated
+                && !passed
+                && (support & own).is_empty()
+                && !(stop & enemy_attacks).is_empty()
+            {
+                self.backward[index] |= pawn;
+            }
+        }
+
+        let files = file_fill(own) & RANK_1;
+        self.islands[index] = (files & !(files << 1)).count();
+        self.half_open_files[index] = file_fill(enemy) & !file_fill(own);
+
+        let (outpost_ranks, enemy_span) = match color {
+            Color::White => (WHITE_OUTPOST_RANKS, south_fill(enemy_attacks)),
+            Color::Black => (BLACK_OUTPOST_RANKS, north_fill(enemy_attacks)),
+        };
+        self.outposts[index] =
+            outpost_ranks & own_attacks & !enemy_span & !(self.pawns[0] | self.pawns[1]);
+    }
+
+    /// Returns the pawns of the given color with no enemy pawns in front of them or on the
+    /// adjacent files ahead.
+    pub fn passed(&self, color: Color) -> Bitboard {
+        self.passed[color.to_index()]
+    }
+
+    /// Returns the pawns of the given color on a half-open file which have at least as many
+    /// supporting pawns as enemy pawns in their way, and so could become passed.
+    pub fn candidate(&self, color: Color) -> Bitboard {
+        self.candidate[color.to_index()]
+    }
+
+    /// Returns the pawns of the given color with no friendly pawns on the adjacent files.
+    pub fn isolated(&self, color: Color) -> Bitboard {
+        self.isolated[color.to_index()]
+    }
+
+    /// Returns the pawns of the given color with a friendly pawn in front of them on the
+    /// same file.
+    pub fn doubled(&self, color: Color) -> Bitboard {
+        self.doubled[color.to_index()]
+    }
+
+    /// Returns the pawns of the given color which can't be supported by friendly pawns and
+    /// whose stop square is controlled by an enemy pawn.
+    pub fn backward(&self, color: Color) -> Bitboard {
+        self.backward[color.to_index()]
+    }
+
+    /// Returns the pawns of the given color defended by a friendly pawn or standing next to
+    /// one on the same rank.
+    pub fn connected(&self, color: Color) -> Bitboard {
+        self.connected[color.to_index()]
+    }
+
+    /// Returns the number of groups of pawns of the given color on adjacent files.
+    pub fn islands(&self, color: Color) -> u32 {
+        self.islands[color.to_index()]
+    }
+
+    /// Returns the squares of the files without any pawns.
+    pub fn open_files(&self) -> Bitboard {
+        !file_fill(self.pawns[0] | self.pawns[1])
+    }
+
+    /// Returns the squares of the files with enemy pawns but none of the given color.
+    pub fn half_open_files(&self, color: Color) -> Bitboard {
+        self.half_open_files[color.to_index()]
+    }
+
+    /// Returns the empty squares in the enemy half of the board which are defended by a pawn
+    /// of the given color and can never be attacked by an enemy pawn.
+    pub fn outposts(&self, color: Color) -> Bitboard {
+        self.outposts[color.to_index()]
+    }
+
+    /// Returns the evaluation of the pawn structure in centipawns, from white's point of
+    /// view.
+    pub fn score(&self) -> i32 {
+        self.side_score(Color::White) - self.side_score(Color::Black)
+    }
+
+    fn side_score(&self, color: Color) -> i32 {
+        let index = color.to_index();
+
+        let passed = self.passed[index]
+            .into_iter()
+            .map(|square| {
+                let rank = match color {
+                    Color::White => square.rank(),
+                    Color::Black => square.rank().flip(),
+                };
+
+                PASSED_PAWN_BONUS[rank.index()]
+            })
+            .sum::<i32>();
+
+        passed
+            + self.candidate[index].count() as i32 * CANDIDATE_PAWN_BONUS
+            + self.connected[index].count() as i32 * CONNECTED_PAWN_BONUS
+            + self.isolated[index].count() as i32 * ISOLATED_PAWN_PENALTY
+            + self.doubled[index].count() as i32 * DOUBLED_PAWN_PENALTY
+            + self.backward[index].count() as i32 * BACKWARD_PAWN_PENALTY
+            + self.islands[index].saturating_sub(1) as i32 * PAWN_ISLAND_PENALTY
+    }
+}
+
+/// A cache of pawn structure scores, keyed by [`pawn_key`]. Pawn structures change rarely
+/// during a search, so most lookups hit.
+#[derive(Debug, Clone)]
+pub struct PawnHashTable {
+    entries: Vec<Option<PawnEntry>>,
+    hits: u64,
+    probes: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PawnEntry {
+    key: u64,
+    score: i32,
+}
+
+impl PawnHashTable {
+    /// Creates a table with the given number of entries, rounded up to a power of two.
+    pub fn new(entries: usize) -> Self {
+        Self {
+            entries: vec![None; entries.max(1).next_power_of_two()],
+            hits: 0,
+            probes: 0,
+        }
+    }
+
+    /// Returns the score of the pawn structure, from white's point of view, analysing it
+    /// and storing the result if it isn't already in the table.
+    pub fn probe(&mut self, pawns_white: Bitboard, pawns_black: Bitboard) -> i32 {
+        let key = pawn_key(pawns_white, pawns_black);
+        let slot = (key as usize) & (self.entries.len() - 1);
+
+        self.probes += 1;
+
+        if let Some(entry) = self.entries[slot]
+            && entry.key == key
+        {
+            self.hits += 1;
+
+            return entry.score;
+        }
+
+        let score = PawnStructure::new(pawns_white, pawns_black).score();
+        self.entries[slot] = Some(PawnEntry { key, score });
+
+        score
+    }
+
+    /// Removes every entry from the table.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.hits = 0;
+        self.probes = 0;
+    }
+
+    /// Returns the number of probes answered from the table, and the total number of
+    /// probes.
+    pub fn stats(&self) -> (u64, u64) {
+        (self.hits, self.probes)
+    }
+}
+
+impl Default for PawnHashTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_PAWN_TABLE_SIZE)
+    }
+}
+
+fn north_fill(mut bitboard: Bitboard) -> Bitboard {
+    bitboard |= bitboard << 8;
+    bitboard |= bitboard << 16;
+    bitboard | bitboard << 32
+}
+
+fn south_fill(mut bitboard: Bitboard) -> Bitboard {
+    bitboard |= bitboard >> 8;
+    bitboard |= bitboard >> 16;
+    bitboard | bitboard >> 32
+}
+
+/// Returns every square of the files with a set square.
+fn file_fill(bitboard: Bitboard) -> Bitboard {
+    north_fill(bitboard) | south_fill(bitboard)
+}
+
+/// Returns every square of the ranks with a set square.
+fn rank_fill(bitboard: Bitboard) -> Bitboard {
+    [
+        RANK_1, RANK_2, RANK_3, RANK_4, RANK_5, RANK_6, RANK_7, RANK_8,
+    ]
+    .into_iter()
+    .filter(|&rank| !(rank & bitboard).is_empty())
+    .fold(Bitboard::EMPTY, |ranks, rank| ranks | rank)
+}
+
+/// Returns the files on either side of the given files.
+fn adjacent_files(files: Bitboard) -> Bitboard {
+    ((files & !FILE_H) << 1 | (files & !FILE_A) >> 1) & !files
+}
+
+/// Returns the squares in front of the set squares on the same files, from the given
+/// color's point of view.
+fn front_span(bitboard: Bitboard, color: Color) -> Bitboard {
+    match color {
+        Color::White => north_fill(bitboard << 8),
+        Color::Black => south_fill(bitboard >> 8),
+    }
+}
+
+/// Returns the squares one step in front of the set squares.
+fn push(bitboard: Bitboard, color: Color) -> Bitboard {
+    match color {
+        Color::White => bitboard << 8,
+        Color::Black => bitboard >> 8,
+    }
+}
+
+/// Returns the squares attacked by the given pawns.
+fn pawn_attacks(pawns: Bitboard, color: Color) -> Bitboard {
+    let forward = push(pawns, color);
+
+    (forward & !FILE_H) << 1 | (forward & !FILE_A) >> 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    fn squares(squares: &[Square]) -> Bitboard {
+        squares.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_pawn_structure() {
+        // White: a2 b3 c2 c4 e4 h5, black: a7 b7 d5 f6 g6 h6.
+        let white = squares(&[
+            Square::A2,
+            Square::B3,
+            Square::C2,
+            Square::C4,
+            Square::E4,
+            Square::H5,
+        ]);
+        let black = squares(&[
+            Square::A7,
+            Square::B7,
+            Square::D5,
+            Square::F6,
+            Square::G6,
+            Square::H6,
+        ]);
+        let structure = PawnStructure::new(white, black);
+
+        assert_eq!(structure.passed(Color::White), Bitboard::EMPTY);
+        assert_eq!(structure.passed(Color::Black), Bitboard::EMPTY);
+        assert_eq!(
+            structure.isolated(Color::White),
+            squares(&[Square::E4, Square::H5])
+        );
+        assert_eq!(structure.isolated(Color::Black), squares(&[Square::D5]));
+        assert_eq!(structure.doubled(Color::White), squares(&[Square::C2]));
+        assert_eq!(
+            structure.connected(Color::White),
+            squares(&[Square::B3, Square::C4])
+        );
+        assert_eq!(
+            structure.connected(Color::Black),
+            squares(&[Square::A7, Square::B7, Square::F6, Square::G6, Square::H6])
+        );
+        assert_eq!(structure.backward(Color::Black), Bitboard::EMPTY);
+        assert_eq!(structure.islands(Color::White), 3);
+        assert_eq!(structure.islands(Color::Black), 3);
+        assert_eq!(structure.open_files(), Bitboard::EMPTY);
+        assert!(structure.half_open_files(Color::White).contains(Square::D1));
+        assert!(structure.half_open_files(Color::Black).contains(Square::C8));
+        assert_eq!(structure.outposts(Color::White), Bitboard::EMPTY);
+        assert_eq!(
+            structure.outposts(Color::Black),
+            squares(&[Square::E5, Square::G5])
+        );
+    }
+
+    #[test]
+    fn test_passed_backward_and_candidate() {
+        // White: a5 b4 c3 d4 f4 g4, black: b7 d5 e6 g5 h6.
+        let white = squares(&[
+            Square::A5,
+            Square::B4,
+            Square::C3,
+            Square::D4,
+            Square::F4,
+            Square::G4,
+        ]);
+        let black = squares(&[Square::B7, Square::D5, Square::E6, Square::G5, Square::H6]);
+        let structure = PawnStructure::new(white, black);
+
+        assert_eq!(structure.passed(Color::White), Bitboard::EMPTY);
+        assert_eq!(structure.candidate(Color::White), squares(&[Square::A5]));
+        assert_eq!(structure.backward(Color::White), squares(&[Square::C3]));
+        assert_eq!(
+            structure.backward(Color::Black),
+            squares(&[Square::E6, Square::H6])
+        );
+        assert!(structure.outposts(Color::White).contains(Square::E5));
+        assert!(!structure.outposts(Color::White).contains(Square::C5));
+
+        let structure = PawnStructure::new(squares(&[Square::E6]), squares(&[Square::A7]));
+
+        assert_eq!(structure.passed(Color::White), squares(&[Square::E6]));
+        assert_eq!(structure.passed(Color::Black), squares(&[Square::A7]));
+        assert!(structure.score() > 0);
+        assert_eq!(
+            PawnStructure::new(Bitboard::EMPTY, Bitboard::EMPTY).open_files(),
+            Bitboard::FULL
+        );
+    }
+
+    #[test]
+    fn test_pawn_hash_table() {
+        let position = BitBoards::default();
+        let mut table = PawnHashTable::new(1000);
+
+        assert_eq!(table.entries.len(), 1024);
+        assert_eq!(
+            pawn_key(position.pawns_white(), position.pawns_black()),
+            pawn_key(position.pawns_white(), position.pawns_black())
+        );
+        assert_ne!(
+            pawn_key(position.pawns_white(), Bitboard::EMPTY),
+            pawn_key(Bitboard::EMPTY, position.pawns_white())
+        );
+
+        let score = table.probe(position.pawns_white(), position.pawns_black());
+
+        assert_eq!(score, 0);
+        assert_eq!(
+            table.probe(position.pawns_white(), position.pawns_black()),
+            score
+        );
+        assert_eq!(table.stats(), (1, 2));
+
+        table.clear();
+        assert_eq!(table.stats(), (0, 0));
+    }
+}
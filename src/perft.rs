@@ -1,6 +1,9 @@
+use crate::boardstate::BoardStateCreationError;
 use crate::game::Game;
 use crate::movegen::*;
 use crate::moves::*;
+use std::error::Error;
+use std::fmt;
 use std::time::Instant;
 
 pub fn perft(depth: usize, game: Game) -> usize {
@@ -28,19 +31,54 @@ pub struct PerftCase<'a> {
     case: Vec<DepthNode>,
 }
 
+/// An error encountered while parsing a line of a perft suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PerftCaseError {
+    BadFen(BoardStateCreationError),
+    BadDepthNode(String),
+}
+
+impl fmt::Display for PerftCaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadFen(err) => write!(f, "{err}"),
+            Self::BadDepthNode(chunk) => {
+                write!(
+                    f,
+                    "bad depth entry `{}`, expected e.g. `D3 8902`",
+                    chunk.trim()
+                )
+            }
+        }
+    }
+}
+
+impl Error for PerftCaseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::BadFen(err) => Some(err),
+            Self::BadDepthNode(_) => None,
+        }
+    }
+}
+
 impl<'a> PerftCase<'a> {
-    pub fn from_str(s: &str, move_gen: &'a MoveGenerator) -> Option<Self> {
+    /// Parses a perft suite line of the form `<fen> ;D1 20 ;D2 400 ...`.
+    pub fn from_str(s: &str, move_gen: &'a MoveGenerator) -> Result<Self, PerftCaseError> {
         let mut chunks = s.split(';');
-        let fen = chunks.next()?;
+        let fen = chunks.next().unwrap_or_default();
         let mut depth_nodes = Vec::new();
 
         for chunk in chunks {
-            depth_nodes.push(DepthNode::from_str(chunk)?);
+            let depth_node = DepthNode::from_str(chunk)
+                .ok_or_else(|| PerftCaseError::BadDepthNode(chunk.to_string()))?;
+
+            depth_nodes.push(depth_node);
         }
 
-        let game = Game::from_fen(fen, move_gen).ok()?;
+        let game = Game::from_fen_lenient(fen, move_gen).map_err(PerftCaseError::BadFen)?;
 
-        Some(Self {
+        Ok(Self {
             game,
             case: depth_nodes,
         })
@@ -101,3 +139,67 @@ impl DepthNode {
         Some(Self { depth, nodes })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boardstate::FenStringError;
+
+    #[test]
+    fn test_perft_case_from_str() {
+        let move_gen = MoveGenerator::new();
+
+        let case = PerftCase::from_str(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400",
+            &move_gen,
+        )
+        .unwrap();
+        assert_eq!(case.case.len(), 2);
+        assert!(case.test().1);
+
+        // EPD-style lines without move counters are accepted.
+        assert!(
+            PerftCase::from_str(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ;D1 20",
+                &move_gen
+            )
+            .is_ok()
+        );
+
+        assert!(matches!(
+            PerftCase::from_str(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ;D1 twenty",
+                &move_gen
+            ),
+            Err(PerftCaseError::BadDepthNode(_))
+        ));
+
+        match PerftCase::from_str(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - ;D1 20",
+            &move_gen,
+        ) {
+            Err(PerftCaseError::BadFen(BoardStateCreationError::BadFenString(err))) => {
+                assert_eq!(err.kind, FenStringError::BadColor);
+                assert_eq!(err.offset, 44);
+            }
+            _ => panic!("expected a FEN error"),
+        }
+    }
+
+    #[test]
+    fn test_perft() {
+        let move_gen = MoveGenerator::new();
+        let cases = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ;D1 48 ;D2 2039",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ;D1 14 ;D2 191 ;D3 2812",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1 ;D1 6 ;D2 264",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8 ;D1 44 ;D2 1486",
+        ];
+
+        for case in cases {
+            let (results, success) = PerftCase::from_str(case, &move_gen).unwrap().test();
+            assert!(success, "{results}");
+        }
+    }
+}
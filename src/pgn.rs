@@ -0,0 +1,374 @@
+use crate::boardstate::BoardStateCreationError;
+use crate::game::SanError;
+use std::error::Error;
+use std::fmt::{self, Write};
+
+/// The PGN export format keeps movetext lines under this many characters.
+const MAX_LINE_LENGTH: usize = 80;
+
+/// Error variants when reading a PGN string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    /// A tag pair isn't of the form `[Name "value"]`.
+    BadTag(String),
+    /// A `{` comment is never closed.
+    UnterminatedComment,
+    /// The `FEN` tag doesn't hold a valid position.
+    BadFen(BoardStateCreationError),
+    /// A move isn't legal in its position.
+    BadMove(SanError),
+    /// A `(` variation is never closed, a `)` has no matching `(`, or a variation starts
+    /// before the first move.
+    UnbalancedVariation,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadTag(tag) => write!(f, "bad tag pair `{tag}`"),
+            Self::UnterminatedComment => write!(f, "comment is missing its closing `}}`"),
+            Self::BadFen(err) => write!(f, "bad FEN tag: {err}"),
+            Self::BadMove(err) => write!(f, "{err}"),
+            Self::UnbalancedVariation => write!(f, "unbalanced variation parentheses"),
+        }
+    }
+}
+
+impl Error for PgnError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::BadFen(err) => Some(err),
+            Self::BadMove(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<SanError> for PgnError {
+    fn from(err: SanError) -> Self {
+        Self::BadMove(err)
+    }
+}
+
+/// Writes a tag pair on its own line, escaping quotes and backslashes in the value.
+pub(crate) fn write_tag(pgn: &mut String, name: &str, value: &str) {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+
+    let _ = writeln!(pgn, "[{name} \"{value}\"]");
+}
+
+/// Writes movetext tokens separated by spaces, wrapping lines before they reach
+/// [`MAX_LINE_LENGTH`]. No space is written after `(` or before `)`.
+pub(crate) fn write_movetext(pgn: &mut String, tokens: impl IntoIterator<Item = String>) {
+    let mut line_length = 0;
+    let mut after_open = false;
+
+    for token in tokens {
+        let needs_space = line_length > 0 && !after_open && token != ")";
+
+        if line_length > 0
+            && line_length + usize::from(needs_space) + token.len() >= MAX_LINE_LENGTH
+        {
+            pgn.push('\n');
+            line_length = 0;
+        } else if needs_space {
+            pgn.push(' ');
+            line_length += 1;
+        }
+
+        after_open = token == "(";
+        line_length += token.len();
+        pgn.push_str(&token);
+    }
+
+    pgn.push('\n');
+}
+
+/// Returns the NAG equivalent to a move suffix annotation such as `!?`.
+pub(crate) fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+/// Tag pairs as `(name, value)`, in the order they appear.
+pub(crate) type Tags = Vec<(String, String)>;
+
+/// Splits the tag pairs off the front of a PGN string, returning them along with the
+/// movetext that follows.
+pub(crate) fn parse_tags(pgn: &str) -> Result<(Tags, &str), PgnError> {
+    let mut tags = Vec::new();
+    let mut rest = pgn.trim_start();
+
+    while let Some(tag) = rest.strip_prefix('[') {
+        let bad_tag = || PgnError::BadTag(tag.lines().next().unwrap_or_default().to_string());
+
+        let name_end = tag.find(|c: char| c.is_whitespace()).ok_or_else(bad_tag)?;
+        let name = &tag[..name_end];
+        let quoted = tag[name_end..]
+            .trim_start()
+            .strip_prefix('"')
+            .ok_or_else(bad_tag)?;
+
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        let mut value_end = None;
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                '"' => {
+                    value_end = Some(i);
+                    break;
+                }
+                _ => value.push(c),
+            }
+        }
+
+        let after_value = &quoted[value_end.ok_or_else(bad_tag)? + 1..];
+        let closing = after_value
+            .trim_start()
+            .strip_prefix(']')
+            .ok_or_else(bad_tag)?;
+
+        if name.is_empty() {
+            return Err(bad_tag());
+        }
+
+        tags.push((name.to_string(), value));
+        rest = closing.trim_start();
+    }
+
+    Ok((tags, rest))
+}
+
+/// Splits a PGN database into its games. A game starts at a tag pair following the
+/// movetext of the previous one, so the games don't need to be separated by blank lines.
+pub fn split_games(pgn: &str) -> Vec<&str> {
+    let mut games = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut in_movetext = false;
+
+    for line in pgn.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with('[') {
+            if in_movetext {
+                games.push(&pgn[start..offset]);
+                start = offset;
+            }
+
+            in_movetext = false;
+        } else if !trimmed.is_empty() {
+            in_movetext = true;
+        }
+
+        offset += line.len();
+    }
+
+    if !pgn[start..].trim().is_empty() {
+        games.push(&pgn[start..]);
+    }
+
+    games
+}
+
+/// A token of PGN movetext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token<'a> {
+    /// A move in SAN, along with its suffix annotation (e.g. `!?`), if any.
+    Move(&'a str, Option<&'a str>),
+    Comment(&'a str),
+    Nag(u8),
+    StartVariation,
+    EndVariation,
+    /// A game termination marker such as `1-0` or `*`.
+    Result(&'a str),
+}
+
+/// Iterates over the tokens of PGN movetext, skipping move numbers.
+pub(crate) struct Tokenizer<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub(crate) fn new(movetext: &'a str) -> Self {
+        Self { rest: movetext }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token<'a>, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.rest = self.rest.trim_start();
+            let c = self.rest.chars().next()?;
+
+            match c {
+                '{' => {
+                    let Some(end) = self.rest.find('}') else {
+                        self.rest = "";
+                        return Some(Err(PgnError::UnterminatedComment));
+                    };
+
+                    let comment = &self.rest[1..end];
+                    self.rest = &self.rest[end + 1..];
+
+                    return Some(Ok(Token::Comment(comment)));
+                }
+                ';' => {
+                    let end = self.rest.find('\n').unwrap_or(self.rest.len());
+                    let comment = &self.rest[1..end];
+                    self.rest = &self.rest[end..];
+
+                    return Some(Ok(Token::Comment(comment)));
+                }
+                '(' | ')' => {
+                    self.rest = &self.rest[1..];
+
+                    return Some(Ok(match c {
+                        '(' => Token::StartVariation,
+                        _ => Token::EndVariation,
+                    }));
+                }
+                _ => (),
+            }
+
+            let end = self
+                .rest
+                .find(|c: char| c.is_whitespace() || "{};()".contains(c))
+                .unwrap_or(self.rest.len());
+            let symbol = &self.rest[..end];
+            self.rest = &self.rest[end..];
+
+            if let Some(nag) = symbol.strip_prefix('$') {
+                match nag.parse() {
+                    Ok(nag) => return Some(Ok(Token::Nag(nag))),
+                    Err(_) => continue,
+                }
+            }
+
+            if matches!(symbol, "1-0" | "0-1" | "1/2-1/2" | "*") {
+                return Some(Ok(Token::Result(symbol)));
+            }
+
+            // Move numbers (`12.` or `12...`) may be attached to the move that follows them.
+            // Digits not followed by a dot are part of the move, as in `0-0`.
+            let symbol = match symbol.split_once('.') {
+                Some((number, san)) if number.bytes().all(|b| b.is_ascii_digit()) => {
+                    san.trim_start_matches('.')
+                }
+                _ => symbol,
+            };
+            let san_end = symbol.trim_end_matches(['!', '?']).len();
+
+            match symbol.split_at(san_end) {
+                ("", "") => continue,
+                ("", suffix) => return Some(Ok(Token::Move("", Some(suffix)))),
+                (san, "") => return Some(Ok(Token::Move(san, None))),
+                (san, suffix) => return Some(Ok(Token::Move(san, Some(suffix)))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags() {
+        let pgn = "[Event \"A \\\"quoted\\\" event\"]\n[Site \"?\"]\n\n1. e4 *";
+        let (tags, movetext) = parse_tags(pgn).unwrap();
+
+        assert_eq!(
+            tags,
+            [
+                ("Event".to_string(), "A \"quoted\" event".to_string()),
+                ("Site".to_string(), "?".to_string())
+            ]
+        );
+        assert_eq!(movetext, "1. e4 *");
+        assert!(matches!(parse_tags("[Event ?]"), Err(PgnError::BadTag(_))));
+
+        let mut written = String::new();
+        write_tag(&mut written, "Event", "A \"quoted\" event");
+        assert_eq!(written, "[Event \"A \\\"quoted\\\" event\"]\n");
+    }
+
+    #[test]
+    fn test_split_games() {
+        let pgn = "[Event \"1\"]\n\n1. e4 *\n[Event \"2\"]\n[Site \"?\"]\n1. d4\n1-0\n\n\n";
+
+        assert_eq!(
+            split_games(pgn),
+            [
+                "[Event \"1\"]\n\n1. e4 *\n",
+                "[Event \"2\"]\n[Site \"?\"]\n1. d4\n1-0\n\n\n"
+            ]
+        );
+        assert_eq!(split_games("1. e4 e5 *"), ["1. e4 e5 *"]);
+        assert!(split_games("\n \n").is_empty());
+    }
+
+    #[test]
+    fn test_tokenizer() {
+        let tokens: Result<Vec<_>, _> =
+            Tokenizer::new("1.e4 {Best by test} e5?! (1... c5 $1) ; line\n2. Nf3 1-0").collect();
+
+        assert_eq!(
+            tokens.unwrap(),
+            [
+                Token::Move("e4", None),
+                Token::Comment("Best by test"),
+                Token::Move("e5", Some("?!")),
+                Token::StartVariation,
+                Token::Move("c5", None),
+                Token::Nag(1),
+                Token::EndVariation,
+                Token::Comment(" line"),
+                Token::Move("Nf3", None),
+                Token::Result("1-0"),
+            ]
+        );
+
+        let tokens: Result<Vec<_>, _> = Tokenizer::new("4.0-0 0-0-0 5...0-0").collect();
+
+        assert_eq!(
+            tokens.unwrap(),
+            [
+                Token::Move("0-0", None),
+                Token::Move("0-0-0", None),
+                Token::Move("0-0", None),
+            ]
+        );
+
+        assert_eq!(
+            Tokenizer::new("1. e4 {oops").nth(1),
+            Some(Err(PgnError::UnterminatedComment))
+        );
+    }
+
+    #[test]
+    fn test_write_movetext() {
+        let mut pgn = String::new();
+        let tokens = ["1.", "e4", "(", "1.", "d4", ")", "1...", "e5", "*"];
+        write_movetext(&mut pgn, tokens.map(String::from));
+
+        assert_eq!(pgn, "1. e4 (1. d4) 1... e5 *\n");
+
+        let mut pgn = String::new();
+        write_movetext(&mut pgn, std::iter::repeat_n("Nf3".to_string(), 30));
+
+        assert!(pgn.lines().all(|line| line.len() < MAX_LINE_LENGTH));
+        assert_eq!(pgn.split_whitespace().count(), 30);
+    }
+}
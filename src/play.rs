@@ -0,0 +1,559 @@
+use crate::Color;
+use crate::game::{Game, IllegalMoveError, Outcome, SanError};
+use crate::moves::Move;
+use crate::rende::{RenderOptions, Renderer, UnicodeRenderer};
+use crate::search::{self, MAX_LEVEL, SearchLimits};
+use crate::util::read_input;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The engine level used when a player is given as `engine` without a level.
+pub const DEFAULT_LEVEL: u8 = 5;
+
+/// The engine level used to search for hints.
+const HINT_LEVEL: u8 = 4;
+
+/// How much worse off than a draw the engine has to be, in centipawns, before it accepts a
+/// draw offer.
+const DRAW_ACCEPT_MARGIN: i32 = 50;
+
+const HELP: &str = "\
+Moves can be written in SAN (Nf3, exd5, O-O, e8=Q), UCI (g1f3, e7e8q) or as two squares
+(g1 f3). Other commands:
+  undo    take back your last move
+  flip    turn the board around
+  fen     print the position as FEN
+  pgn     print the game as PGN
+  resign  resign the game
+  draw    claim a draw, or offer or accept one
+  hint    suggest a move
+  help    print this message
+  quit    leave the game";
+
+/// Who makes the moves for one side of a [`PlaySession`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Player {
+    /// Moves are read from `stdin`.
+    Human,
+    /// Moves are searched by the engine within the given limits.
+    Engine(SearchLimits),
+}
+
+/// Error variants when parsing a [`Player`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerParseError {
+    /// The player is neither `human` nor `engine`.
+    BadPlayer(String),
+    /// The engine level isn't a number from `1` to [`MAX_LEVEL`].
+    BadLevel(String),
+}
+
+impl fmt::Display for PlayerParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadPlayer(player) => {
+                write!(f, "unknown player `{player}`, expected `human` or `engine`")
+            }
+            Self::BadLevel(level) => {
+                write!(f, "bad engine level `{level}`, expected 1 to {MAX_LEVEL}")
+            }
+        }
+    }
+}
+
+impl Error for PlayerParseError {}
+
+impl FromStr for Player {
+    type Err = PlayerParseError;
+
+    /// Parses `human`, `engine`, or `engine:<level>` with a level from `1` to [`MAX_LEVEL`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (player, level) = match s.split_once(':') {
+            Some((player, level)) => (player, Some(level)),
+            None => (s, None),
+        };
+
+        match (player, level) {
+            ("human", None) => Ok(Self::Human),
+            ("engine", None) => Ok(Self::Engine(SearchLimits::from_level(DEFAULT_LEVEL))),
+            ("engine", Some(level)) => match level.parse() {
+                Ok(level @ 1..=MAX_LEVEL) => Ok(Self::Engine(SearchLimits::from_level(level))),
+                _ => Err(PlayerParseError::BadLevel(level.to_string())),
+            },
+            _ => Err(PlayerParseError::BadPlayer(s.to_string())),
+        }
+    }
+}
+
+/// A line of input in a [`PlaySession`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// A move, in any notation accepted by [`parse_move()`].
+    Move(String),
+    Undo,
+    Flip,
+    Fen,
+    Pgn,
+    Resign,
+    Draw,
+    Hint,
+    Help,
+    Quit,
+}
+
+impl Command {
+    /// Parses a line of input, returning [`None`] if it is blank. Anything that isn't a
+    /// command is taken to be a move.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+
+        let command = match input.to_ascii_lowercase().as_str() {
+            "" => return None,
+            "undo" => Self::Undo,
+            "flip" => Self::Flip,
+            "fen" => Self::Fen,
+            "pgn" => Self::Pgn,
+            "resign" => Self::Resign,
+            "draw" => Self::Draw,
+            "hint" => Self::Hint,
+            "help" | "?" => Self::Help,
+            "quit" | "exit" => Self::Quit,
+            _ => Self::Move(input.to_string()),
+        };
+
+        Some(command)
+    }
+}
+
+/// The reasons a move typed by a player can be rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveInputError {
+    /// The move is written in UCI or as two squares, but isn't legal.
+    Illegal(IllegalMoveError),
+    /// The move isn't valid SAN, or doesn't match a single legal move.
+    BadSan(SanError),
+}
+
+impl fmt::Display for MoveInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Illegal(err) => write!(f, "illegal move: {err}"),
+            Self::BadSan(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for MoveInputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Illegal(err) => Some(err),
+            Self::BadSan(err) => Some(err),
+        }
+    }
+}
+
+/// Returns the legal move written in UCI (`g1f3`), as two squares (`g1 f3`), or in SAN
+/// (`Nf3`).
+///
+/// # Errors
+/// Returns a [`MoveInputError`] if the input doesn't describe a legal move.
+pub fn parse_move(game: &Game, input: &str) -> Result<Move, MoveInputError> {
+    let input = input.trim();
+    let mut squares = input.split_ascii_whitespace();
+
+    let mv = match (squares.next(), squares.next(), squares.next()) {
+        (Some(initial), Some(target), None) => Move::from_squares_str(initial, target),
+        _ => input.parse().ok(),
+    };
+
+    match mv {
+        Some(mv) => game.resolve_move(mv).map_err(MoveInputError::Illegal),
+        None => game.parse_san(input).map_err(MoveInputError::BadSan),
+    }
+}
+
+/// Formats a search score for players: in pawns from white's point of view (`+0.35`), or
+/// as the number of moves until mate (`#3`, `#-2`).
+pub fn format_score(score: i32, side_to_move: Color) -> String {
+    let score = match side_to_move {
+        Color::White => score,
+        Color::Black => -score,
+    };
+
+    match search::mate_in(score) {
+        Some(moves) => format!("#{moves}"),
+        None => format!("{:+.2}", f64::from(score) / 100.0),
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+/// An interactive game on the terminal, with each side played by a human or the engine.
+#[derive(Debug, Clone)]
+pub struct PlaySession<'a> {
+    game: Game<'a>,
+    /// The players, indexed by their color's bitboard index.
+    players: [Player; 2],
+    flipped: bool,
+    /// The side that offered a draw, until the offer is accepted or declined.
+    draw_offer: Option<Color>,
+    quit: bool,
+}
+
+impl<'a> PlaySession<'a> {
+    /// Starts a session continuing the given game.
+    pub fn new(game: Game<'a>, white: Player, black: Player) -> Self {
+        let mut players = [Player::Human; 2];
+        players[Color::White.to_index()] = white;
+        players[Color::Black.to_index()] = black;
+
+        Self {
+            game,
+            players,
+            flipped: false,
+            draw_offer: None,
+            quit: false,
+        }
+    }
+
+    pub fn game(&self) -> &Game<'a> {
+        &self.game
+    }
+
+    pub fn into_game(self) -> Game<'a> {
+        self.game
+    }
+
+    /// Returns the player of the given side.
+    pub fn player(&self, color: Color) -> Player {
+        self.players[color.to_index()]
+    }
+
+    /// Returns the side the board is drawn from: the side to move, unless the board has
+    /// been [flipped](Command::Flip).
+    pub fn perspective(&self) -> Color {
+        let side_to_move = self.game.get_board_state().side_to_move;
+
+        if self.flipped {
+            side_to_move.enemy()
+        } else {
+            side_to_move
+        }
+    }
+
+    /// Plays the game until it ends, or until a player quits or closes `stdin`.
+    pub fn run(&mut self) {
+        let has_human = self.players.contains(&Player::Human);
+
+        println!("Type `help` for the list of commands.");
+
+        while !self.quit {
+            // Humans claim draws by rule themselves, or play on.
+            if !has_human && let Some(reason) = self.game.adjudicate_draw() {
+                println!("Draw by {reason}.");
+            }
+
+            if let Some(outcome) = self.game.outcome() {
+                self.print_board();
+
+                match outcome {
+                    Outcome::Win(color) => println!("{} wins!", color_name(color)),
+                    Outcome::Draw => println!("Draw!"),
+                }
+
+                return;
+            }
+
+            let side_to_move = self.game.get_board_state().side_to_move;
+
+            match self.player(side_to_move) {
+                Player::Engine(limits) => {
+                    println!("{}", self.play_engine_move(limits));
+
+                    if !has_human {
+                        self.print_board();
+                    }
+                }
+                Player::Human => {
+                    self.print_board();
+
+                    let prompt = format!("{} to move: ", color_name(side_to_move));
+                    let Some(input) = read_input(&prompt) else {
+                        return;
+                    };
+
+                    if let Some(command) = Command::parse(&input) {
+                        println!("{}", self.execute(command));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Carries out a command for the side to move, returning the message to show.
+    pub fn execute(&mut self, command: Command) -> String {
+        let side_to_move = self.game.get_board_state().side_to_move;
+        let name = color_name(side_to_move);
+
+        match command {
+            Command::Move(input) => {
+                let mv = match parse_move(&self.game, &input) {
+                    Ok(mv) => mv,
+                    Err(err) => return format!("Can't play `{input}`: {err}"),
+                };
+
+                self.play_move(mv)
+            }
+            Command::Undo => {
+                if self.game.undo().is_none() {
+                    return "There is no move to take back.".to_string();
+                }
+
+                // Take back the engine's replies too, so that it is a human's turn again.
+                while self.game.ply() > 0
+                    && self.player(self.game.get_board_state().side_to_move) != Player::Human
+                {
+                    self.game.undo();
+                }
+
+                self.draw_offer = None;
+
+                format!("Took back the moves up to ply {}.", self.game.ply())
+            }
+            Command::Flip => {
+                self.flipped = !self.flipped;
+
+                "Flipped the board.".to_string()
+            }
+            Command::Fen => self.game.to_fen(),
+            Command::Pgn => self.game.to_pgn().trim_end().to_string(),
+            Command::Resign => {
+                self.game.resign(side_to_move);
+
+                format!("{name} resigns.")
+            }
+            Command::Draw => self.draw(side_to_move),
+            Command::Hint => {
+                let result = search::search(&self.game, SearchLimits::from_level(HINT_LEVEL));
+
+                match result.best_move.and_then(|mv| self.game.to_san(mv).ok()) {
+                    Some(san) => {
+                        format!("Hint: {san} ({})", format_score(result.score, side_to_move))
+                    }
+                    None => "There is no move to play.".to_string(),
+                }
+            }
+            Command::Help => HELP.to_string(),
+            Command::Quit => {
+                self.quit = true;
+
+                "Leaving the game.".to_string()
+            }
+        }
+    }
+
+    /// Plays a legal move for the side to move, declining any draw offer from the opponent.
+    fn play_move(&mut self, mv: Move) -> String {
+        let side_to_move = self.game.get_board_state().side_to_move;
+
+        if let Err(err) = self.game.try_make_move(mv) {
+            return format!("Can't play {mv}: {err}");
+        }
+
+        if self.draw_offer == Some(side_to_move.enemy()) {
+            self.draw_offer = None;
+        }
+
+        let san = self
+            .game
+            .history()
+            .get(self.game.ply())
+            .map_or_else(|| mv.to_string(), |entry| entry.san.clone());
+
+        format!("{} plays {san}.", color_name(side_to_move))
+    }
+
+    /// Searches and plays the engine's move for the side to move.
+    fn play_engine_move(&mut self, limits: SearchLimits) -> String {
+        let side_to_move = self.game.get_board_state().side_to_move;
+        let result = search::search(&self.game, limits);
+
+        let Some(mv) = result.best_move else {
+            return format!("{} has no move to play.", color_name(side_to_move));
+        };
+
+        format!(
+            "{} ({}, depth {})",
+            self.play_move(mv),
+            format_score(result.score, side_to_move),
+            result.depth
+        )
+    }
+
+    /// Claims a draw for the side to move if it can, and otherwise accepts the opponent's
+    /// draw offer or makes one. The engine accepts an offer when it is losing.
+    fn draw(&mut self, side_to_move: Color) -> String {
+        let name = color_name(side_to_move);
+
+        if self.draw_offer == Some(side_to_move.enemy()) {
+            self.game.agree_draw();
+
+            return format!("{name} accepts the draw offer.");
+        }
+
+        if self.game.claim_draw() {
+            return format!("{name} claims a draw.");
+        }
+
+        let opponent = color_name(side_to_move.enemy());
+
+        match self.player(side_to_move.enemy()) {
+            Player::Human => {
+                self.draw_offer = Some(side_to_move);
+
+                format!("{name} offers a draw. {opponent} can accept it with `draw`.")
+            }
+            Player::Engine(limits) => {
+                // The score is from the side to move's point of view, not the engine's.
+                let score = search::search(&self.game, limits).score;
+
+                if score >= DRAW_ACCEPT_MARGIN {
+                    self.game.agree_draw();
+
+                    format!("{opponent} accepts the draw offer.")
+                } else {
+                    format!("{opponent} declines the draw offer.")
+                }
+            }
+        }
+    }
+
+    fn print_board(&self) {
+        let options = RenderOptions {
+            perspective: self.perspective(),
+            ..RenderOptions::for_game(&self.game)
+        };
+
+        print!(
+            "{}",
+            UnicodeRenderer.render(self.game.get_board_state(), &options)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+
+    #[test]
+    fn test_parse_player_and_command() {
+        assert_eq!("human".parse(), Ok(Player::Human));
+        assert_eq!(
+            "engine:3".parse(),
+            Ok(Player::Engine(SearchLimits::from_level(3)))
+        );
+        assert_eq!(
+            "engine:11".parse::<Player>(),
+            Err(PlayerParseError::BadLevel("11".to_string()))
+        );
+        assert!(matches!(
+            "robot".parse::<Player>(),
+            Err(PlayerParseError::BadPlayer(_))
+        ));
+
+        assert_eq!(Command::parse(" Undo\n"), Some(Command::Undo));
+        assert_eq!(
+            Command::parse("Nf3"),
+            Some(Command::Move("Nf3".to_string()))
+        );
+        assert_eq!(Command::parse("  "), None);
+    }
+
+    #[test]
+    fn test_parse_move() {
+        let move_gen = MoveGenerator::new();
+        let game = Game::new(&move_gen);
+
+        let knight_move = game.parse_san("Nf3").unwrap();
+
+        assert_eq!(parse_move(&game, "Nf3"), Ok(knight_move));
+        assert_eq!(parse_move(&game, "g1f3"), Ok(knight_move));
+        assert_eq!(parse_move(&game, "g1 f3"), Ok(knight_move));
+        assert!(matches!(
+            parse_move(&game, "g1g3"),
+            Err(MoveInputError::Illegal(_))
+        ));
+        assert!(matches!(
+            parse_move(&game, "Nf4"),
+            Err(MoveInputError::BadSan(_))
+        ));
+    }
+
+    #[test]
+    fn test_execute() {
+        let move_gen = MoveGenerator::new();
+        let engine = Player::Engine(SearchLimits::depth(1));
+        let mut session = PlaySession::new(Game::new(&move_gen), Player::Human, engine);
+
+        assert_eq!(
+            session.execute(Command::Move("e4".to_string())),
+            "White plays e4."
+        );
+        assert_eq!(session.perspective(), Color::Black);
+
+        session.execute(Command::Flip);
+        assert_eq!(session.perspective(), Color::White);
+
+        // Undoing against the engine takes back its reply as well.
+        session.execute(Command::Move("e7e5".to_string()));
+        session.execute(Command::Undo);
+        assert_eq!(session.game().ply(), 0);
+
+        session.execute(Command::Resign);
+        assert_eq!(session.game().outcome(), Some(Outcome::Win(Color::Black)));
+
+        // Between humans, a draw offer stands until the opponent accepts it or moves.
+        let mut session = PlaySession::new(Game::new(&move_gen), Player::Human, Player::Human);
+
+        session.execute(Command::Draw);
+        session.execute(Command::Move("e4".to_string()));
+        session.execute(Command::Move("e5".to_string()));
+        session.execute(Command::Move("Nf3".to_string()));
+        assert_eq!(session.draw_offer, None);
+
+        session.execute(Command::Draw);
+        assert_eq!(session.draw_offer, Some(Color::Black));
+        session.execute(Command::Move("Nc6".to_string()));
+        session.execute(Command::Draw);
+        assert_eq!(session.game().outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn test_engine_game_ends_in_drawn_position() {
+        let move_gen = MoveGenerator::new();
+        let game = Game::from_fen("8/8/4k3/8/8/4K3/8/8 w - - 0 1", &move_gen).unwrap();
+        let engine = Player::Engine(SearchLimits::depth(1));
+        let mut session = PlaySession::new(game, engine, engine);
+
+        session.run();
+        assert_eq!(session.game().outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn test_human_claims_drawn_position() {
+        let move_gen = MoveGenerator::new();
+        let game = Game::from_fen("8/8/4k3/8/8/4K3/8/8 w - - 0 1", &move_gen).unwrap();
+        let engine = Player::Engine(SearchLimits::depth(1));
+        let mut session = PlaySession::new(game, Player::Human, engine);
+
+        assert_eq!(session.execute(Command::Draw), "White claims a draw.");
+        assert_eq!(session.game().outcome(), Some(Outcome::Draw));
+    }
+}
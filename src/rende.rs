@@ -1,68 +1,607 @@
-use crate::Color;
 use crate::bitboards::BitBoards;
+use crate::boardstate::BoardState;
+use crate::game::Game;
+use crate::gametree::{Arrow, MarkColor};
+use crate::moves::Move;
+use crate::{Color, File, Piece, Rank, Square};
+use std::io::{self, Write};
 
-pub fn print_bitboard(bitboard: &BitBoards, perspective: Color) {
-    let mut board_arr = [[' '; 8]; 8];
-
-    set_pieces(&mut board_arr, bitboard.pawns_white(), '♟');
-    set_pieces(&mut board_arr, bitboard.pawns_black(), '♙');
-    set_pieces(&mut board_arr, bitboard.rooks_white(), '♜');
-    set_pieces(&mut board_arr, bitboard.rooks_black(), '♖');
-    set_pieces(&mut board_arr, bitboard.knights_white(), '♞');
-    set_pieces(&mut board_arr, bitboard.knights_black(), '♘');
-    set_pieces(&mut board_arr, bitboard.bishops_white(), '♝');
-    set_pieces(&mut board_arr, bitboard.bishops_black(), '♗');
-    set_pieces(&mut board_arr, bitboard.queens_white(), '♛');
-    set_pieces(&mut board_arr, bitboard.queens_black(), '♕');
-    set_pieces(&mut board_arr, bitboard.king_white(), '♚');
-    set_pieces(&mut board_arr, bitboard.king_black(), '♔');
+/// What to draw on top of the pieces, and from which side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// The side drawn at the bottom of the board.
+    pub perspective: Color,
+    /// Whether to label the files and ranks.
+    pub coordinates: bool,
+    /// The move to highlight as the last one played.
+    pub last_move: Option<Move>,
+    /// The square of the king in check, to highlight.
+    pub check: Option<Square>,
+    pub arrows: Vec<Arrow>,
+}
 
-    print!("  ");
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            perspective: Color::White,
+            coordinates: true,
+            last_move: None,
+            check: None,
+            arrows: Vec::new(),
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Returns the options to draw the current position of the game from the side to move,
+    /// highlighting the last move and the king in check.
+    pub fn for_game(game: &Game) -> Self {
+        let board_state = game.get_board_state();
+        let side_to_move = board_state.side_to_move;
 
-    for c in 'a'..='h' {
-        print!(" {c} ");
+        Self {
+            perspective: side_to_move,
+            last_move: game.history().get(game.ply()).map(|entry| entry.mv),
+            check: game
+                .is_in_check()
+                .then(|| {
+                    board_state
+                        .position
+                        .board(side_to_move, Piece::King)
+                        .to_square()
+                })
+                .flatten(),
+            ..Self::default()
+        }
     }
+}
 
-    print!("\n");
+/// Draws a position as text or an image.
+pub trait Renderer {
+    /// Writes the drawing of the position.
+    ///
+    /// # Errors
+    /// Returns any error encountered while writing.
+    fn render_to(
+        &self,
+        board_state: &BoardState,
+        options: &RenderOptions,
+        out: &mut dyn Write,
+    ) -> io::Result<()>;
 
-    match perspective {
-        Color::White => {
-            for (rank, name) in board_arr.iter().rev().zip((1..=8).rev()) {
-                print!("{name} ");
+    /// Returns the drawing of the position.
+    fn render(&self, board_state: &BoardState, options: &RenderOptions) -> String {
+        let mut buf = Vec::new();
+
+        self.render_to(board_state, options, &mut buf)
+            .expect("writing to a Vec doesn't fail");
+
+        String::from_utf8(buf).expect("renderers only write UTF-8")
+    }
+}
 
-                for square in rank.iter() {
-                    print!("[{}]", square);
-                }
+/// Draws the board with the letters used in FEN strings (`PNBRQK` for white and
+/// `pnbrqk` for black) and `.` for empty squares. Highlights and arrows aren't drawn.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsciiRenderer;
 
-                print!("\n");
+impl Renderer for AsciiRenderer {
+    fn render_to(
+        &self,
+        board_state: &BoardState,
+        options: &RenderOptions,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        for row in rows(options.perspective) {
+            if options.coordinates {
+                write!(out, "{} ", row[0].rank().to_char())?;
             }
+
+            let line: Vec<String> = row
+                .iter()
+                .map(|&square| match board_state.position.piece_at(square) {
+                    Some((color, piece)) => letter(color, piece).to_string(),
+                    None => ".".to_string(),
+                })
+                .collect();
+
+            writeln!(out, "{}", line.join(" "))?;
         }
-        Color::Black => {
-            for (rank, name) in board_arr.iter().zip(1..=8) {
-                print!("{name} ");
 
-                for square in rank.iter() {
-                    print!("[{}]", square);
-                }
+        if options.coordinates {
+            let files: Vec<String> = file_order(options.perspective)
+                .map(|file| file.to_char().to_string())
+                .collect();
 
-                print!("\n");
+            writeln!(out, "  {}", files.join(" "))?;
+        }
+
+        writeln!(out, "{}", side_to_move_line(board_state))
+    }
+}
+
+/// Draws the board with Unicode chess glyphs on squares coloured with ANSI escape codes,
+/// highlighting the last move and the king in check. Arrows aren't drawn.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnicodeRenderer;
+
+impl UnicodeRenderer {
+    const LIGHT_SQUARE: (u8, u8, u8) = (240, 217, 181);
+    const DARK_SQUARE: (u8, u8, u8) = (181, 136, 99);
+    const LAST_MOVE_SQUARE: (u8, u8, u8) = (205, 210, 106);
+    const CHECK_SQUARE: (u8, u8, u8) = (224, 80, 80);
+}
+
+impl Renderer for UnicodeRenderer {
+    fn render_to(
+        &self,
+        board_state: &BoardState,
+        options: &RenderOptions,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        for row in rows(options.perspective) {
+            if options.coordinates {
+                write!(out, "{} ", row[0].rank().to_char())?;
+            }
+
+            for square in row {
+                let (r, g, b) = match square_highlight(square, options) {
+                    Some(Highlight::Check) => Self::CHECK_SQUARE,
+                    Some(Highlight::LastMove) => Self::LAST_MOVE_SQUARE,
+                    None if is_light(square) => Self::LIGHT_SQUARE,
+                    None => Self::DARK_SQUARE,
+                };
+                let glyph = board_state
+                    .position
+                    .piece_at(square)
+                    .map_or(' ', |(color, piece)| unicode_glyph(color, piece));
+
+                write!(out, "\x1b[48;2;{r};{g};{b}m\x1b[38;2;0;0;0m {glyph} ")?;
+            }
+
+            writeln!(out, "\x1b[0m")?;
+        }
+
+        if options.coordinates {
+            write!(out, " ")?;
+
+            for file in file_order(options.perspective) {
+                write!(out, "  {}", file.to_char())?;
             }
+
+            writeln!(out)?;
         }
-    };
+
+        writeln!(out, "{}", side_to_move_line(board_state))
+    }
+}
+
+/// Draws the board as a standalone SVG image, with coordinates around the board, the last
+/// move and the king in check highlighted, and arrows.
+#[derive(Debug, Clone, Copy)]
+pub struct SvgRenderer {
+    /// The width of a square, in pixels.
+    pub square_size: u32,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self { square_size: 45 }
+    }
+}
+
+impl SvgRenderer {
+    const LIGHT_SQUARE: &str = "#f0d9b5";
+    const DARK_SQUARE: &str = "#b58863";
+    const LAST_MOVE_SQUARE: &str = "#cdd26a";
+    const CHECK_SQUARE: &str = "#e05050";
+    const MARK_COLORS: [MarkColor; 4] = [
+        MarkColor::Red,
+        MarkColor::Green,
+        MarkColor::Blue,
+        MarkColor::Yellow,
+    ];
+
+    fn mark_color(color: MarkColor) -> (&'static str, &'static str) {
+        match color {
+            MarkColor::Red => ("red", "#d03030"),
+            MarkColor::Green => ("green", "#15781b"),
+            MarkColor::Blue => ("blue", "#003088"),
+            MarkColor::Yellow => ("yellow", "#e68f00"),
+        }
+    }
 }
 
-fn set_pieces(board: &mut [[char; 8]; 8], bitboard: u64, c: char) {
-    for i in 0..64 {
-        if bitboard & 1 << i == 0 {
-            continue;
+impl Renderer for SvgRenderer {
+    fn render_to(
+        &self,
+        board_state: &BoardState,
+        options: &RenderOptions,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let square_size = self.square_size;
+        let margin = if options.coordinates {
+            square_size / 2
+        } else {
+            0
+        };
+        let size = 8 * square_size + 2 * margin;
+
+        // Returns the top left corner of the square as seen on the board.
+        let corner = |screen_file: u32, screen_rank: u32| {
+            (
+                margin + screen_file * square_size,
+                margin + screen_rank * square_size,
+            )
+        };
+        let center = |square: Square| {
+            let (screen_file, screen_rank) = screen_position(square, options.perspective);
+            let (x, y) = corner(screen_file, screen_rank);
+
+            (
+                f64::from(x) + f64::from(square_size) / 2.0,
+                f64::from(y) + f64::from(square_size) / 2.0,
+            )
+        };
+
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" \
+             viewBox=\"0 0 {size} {size}\">"
+        )?;
+        writeln!(out, "<defs>")?;
+
+        for color in Self::MARK_COLORS {
+            let (name, fill) = Self::mark_color(color);
+
+            writeln!(
+                out,
+                "<marker id=\"arrowhead-{name}\" viewBox=\"0 0 10 10\" refX=\"5\" refY=\"5\" \
+                 markerWidth=\"3\" markerHeight=\"3\" orient=\"auto\">\
+                 <path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"{fill}\"/></marker>"
+            )?;
+        }
+
+        writeln!(out, "</defs>")?;
+
+        for square in Square::ALL {
+            let (screen_file, screen_rank) = screen_position(square, options.perspective);
+            let (x, y) = corner(screen_file, screen_rank);
+            let fill = match square_highlight(square, options) {
+                Some(Highlight::Check) => Self::CHECK_SQUARE,
+                Some(Highlight::LastMove) => Self::LAST_MOVE_SQUARE,
+                None if is_light(square) => Self::LIGHT_SQUARE,
+                None => Self::DARK_SQUARE,
+            };
+
+            writeln!(
+                out,
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{square_size}\" height=\"{square_size}\" \
+                 fill=\"{fill}\"/>"
+            )?;
+        }
+
+        if options.coordinates {
+            let font_size = square_size * 3 / 10;
+            let half_margin = f64::from(margin) / 2.0;
+            let label_end = f64::from(size) - half_margin;
+
+            for (i, file) in file_order(options.perspective).enumerate() {
+                let (x, _) = center(Square::new(file, Rank::First));
+
+                writeln!(
+                    out,
+                    "<text x=\"{x:.1}\" y=\"{label_end:.1}\" font-size=\"{font_size}\" \
+                     text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+                    file.to_char()
+                )?;
+
+                let rank = match options.perspective {
+                    Color::White => Rank::from_index(7 - i as u8),
+                    Color::Black => Rank::from_index(i as u8),
+                };
+                let y = f64::from(corner(0, i as u32).1) + f64::from(square_size) / 2.0;
+
+                writeln!(
+                    out,
+                    "<text x=\"{half_margin:.1}\" y=\"{y:.1}\" font-size=\"{font_size}\" \
+                     text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+                    rank.map_or(' ', Rank::to_char)
+                )?;
+            }
+        }
+
+        let font_size = square_size * 4 / 5;
+
+        for square in Square::ALL {
+            let Some((color, piece)) = board_state.position.piece_at(square) else {
+                continue;
+            };
+            let (x, y) = center(square);
+
+            writeln!(
+                out,
+                "<text x=\"{x:.1}\" y=\"{y:.1}\" font-size=\"{font_size}\" \
+                 text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+                unicode_glyph(color, piece)
+            )?;
         }
 
-        let (x, y) = bit_to_2d_indices(i);
+        let stroke_width = f64::from(square_size) / 6.0;
 
-        board[x][y] = c;
+        for arrow in &options.arrows {
+            let (name, stroke) = Self::mark_color(arrow.color);
+            let (x1, y1) = center(arrow.from);
+            let (x2, y2) = center(arrow.to);
+
+            // Stop the line short, so that the tip of the arrowhead lands on the center.
+            let length = (x2 - x1).hypot(y2 - y1);
+            let shortening = 1.5 * stroke_width / length.max(1.0);
+            let (x2, y2) = (x2 - (x2 - x1) * shortening, y2 - (y2 - y1) * shortening);
+
+            writeln!(
+                out,
+                "<line x1=\"{x1:.1}\" y1=\"{y1:.1}\" x2=\"{x2:.1}\" y2=\"{y2:.1}\" \
+                 stroke=\"{stroke}\" stroke-width=\"{stroke_width:.1}\" \
+                 stroke-opacity=\"0.8\" marker-end=\"url(#arrowhead-{name})\"/>"
+            )?;
+        }
+
+        writeln!(out, "</svg>")
+    }
+}
+
+/// Prints the pieces of the position to stdout in brackets, from the given side.
+pub fn print_bitboard(bitboard: &BitBoards, perspective: Color) {
+    print!("  ");
+
+    for file in file_order(perspective) {
+        print!(" {} ", file.to_char());
+    }
+
+    println!();
+
+    for row in rows(perspective) {
+        print!("{} ", row[0].rank().to_char());
+
+        for square in row {
+            let glyph = bitboard
+                .piece_at(square)
+                .map_or(' ', |(color, piece)| unicode_glyph(color, piece));
+
+            print!("[{glyph}]");
+        }
+
+        println!();
+    }
+}
+
+/// Returns the solid glyph of the piece, for renderers that tell the colors apart some
+/// other way, e.g. with terminal colors.
+pub fn piece_glyph(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => '♟',
+        Piece::Rook => '♜',
+        Piece::Knight => '♞',
+        Piece::Bishop => '♝',
+        Piece::Queen => '♛',
+        Piece::King => '♚',
+    }
+}
+
+/// Returns the Unicode glyph of the piece: outlined for white and solid for black.
+pub fn unicode_glyph(color: Color, piece: Piece) -> char {
+    match (color, piece) {
+        (Color::White, Piece::Pawn) => '♙',
+        (Color::White, Piece::Rook) => '♖',
+        (Color::White, Piece::Knight) => '♘',
+        (Color::White, Piece::Bishop) => '♗',
+        (Color::White, Piece::Queen) => '♕',
+        (Color::White, Piece::King) => '♔',
+        (Color::Black, piece) => piece_glyph(piece),
+    }
+}
+
+/// Returns the FEN letter of the piece: uppercase for white and lowercase for black.
+fn letter(color: Color, piece: Piece) -> char {
+    match color {
+        Color::White => piece.to_char(),
+        Color::Black => piece.to_char().to_ascii_lowercase(),
+    }
+}
+
+fn side_to_move_line(board_state: &BoardState) -> &'static str {
+    match board_state.side_to_move {
+        Color::White => "White to move",
+        Color::Black => "Black to move",
+    }
+}
+
+/// The highlights a square can have, by priority.
+enum Highlight {
+    Check,
+    LastMove,
+}
+
+fn square_highlight(square: Square, options: &RenderOptions) -> Option<Highlight> {
+    if options.check == Some(square) {
+        Some(Highlight::Check)
+    } else if options
+        .last_move
+        .is_some_and(|mv| mv.get_initial_square() == square || mv.get_target_square() == square)
+    {
+        Some(Highlight::LastMove)
+    } else {
+        None
+    }
+}
+
+fn is_light(square: Square) -> bool {
+    (square.file().index() + square.rank().index()) % 2 == 1
+}
+
+/// Returns the files from left to right as seen from the given side.
+fn file_order(perspective: Color) -> impl Iterator<Item = File> {
+    (0..8).filter_map(move |i| match perspective {
+        Color::White => File::from_index(i),
+        Color::Black => File::from_index(7 - i),
+    })
+}
+
+/// Returns the rows of squares from top to bottom as seen from the given side, each from
+/// left to right.
+fn rows(perspective: Color) -> impl Iterator<Item = [Square; 8]> {
+    (0..8).filter_map(move |i| {
+        let rank = match perspective {
+            Color::White => Rank::from_index(7 - i)?,
+            Color::Black => Rank::from_index(i)?,
+        };
+        let mut files = file_order(perspective);
+
+        Some([(); 8].map(|()| Square::new(files.next().expect("there are eight files"), rank)))
+    })
+}
+
+/// Returns the column and row of the square as seen from the given side, counting from the
+/// top left corner.
+fn screen_position(square: Square, perspective: Color) -> (u32, u32) {
+    let (file, rank) = (square.file().index() as u32, square.rank().index() as u32);
+
+    match perspective {
+        Color::White => (file, 7 - rank),
+        Color::Black => (7 - file, rank),
     }
 }
 
-fn bit_to_2d_indices(bit: u64) -> (usize, usize) {
-    ((bit / 8) as usize, (bit % 8) as usize)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+
+    #[test]
+    fn test_ascii_renderer() {
+        let board_state = BoardState::default();
+
+        assert_eq!(
+            AsciiRenderer.render(&board_state, &RenderOptions::default()),
+            "\
+8 r n b q k b n r
+7 p p p p p p p p
+6 . . . . . . . .
+5 . . . . . . . .
+4 . . . . . . . .
+3 . . . . . . . .
+2 P P P P P P P P
+1 R N B Q K B N R
+  a b c d e f g h
+White to move
+"
+        );
+
+        let board_state = BoardState::from_fen("4k3/8/8/8/8/8/1P6/R3K3 b Q - 0 1").unwrap();
+        let options = RenderOptions {
+            perspective: Color::Black,
+            ..RenderOptions::default()
+        };
+
+        assert_eq!(
+            AsciiRenderer.render(&board_state, &options),
+            "\
+1 . . . K . . . R
+2 . . . . . . P .
+3 . . . . . . . .
+4 . . . . . . . .
+5 . . . . . . . .
+6 . . . . . . . .
+7 . . . . . . . .
+8 . . . k . . . .
+  h g f e d c b a
+Black to move
+"
+        );
+
+        let options = RenderOptions {
+            coordinates: false,
+            ..RenderOptions::default()
+        };
+
+        assert!(
+            AsciiRenderer
+                .render(&board_state, &options)
+                .starts_with(". . . . k . . .\n")
+        );
+    }
+
+    #[test]
+    fn test_unicode_renderer() {
+        let move_gen = MoveGenerator::new();
+        let mut game = Game::new(&move_gen);
+
+        for san in ["f3", "e5", "g4", "Qh4"] {
+            game.try_make_move(game.parse_san(san).unwrap()).unwrap();
+        }
+
+        let options = RenderOptions::for_game(&game);
+        let rendered = UnicodeRenderer.render(game.get_board_state(), &options);
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(options.check, Some(Square::E1));
+        assert_eq!(
+            options.last_move.map(|mv| mv.to_string()).as_deref(),
+            Some("d8h4")
+        );
+        assert_eq!(lines.len(), 10);
+        assert_eq!(
+            lines[7],
+            "1 \x1b[48;2;181;136;99m\x1b[38;2;0;0;0m ♖ \x1b[48;2;240;217;181m\x1b[38;2;0;0;0m ♘ \
+             \x1b[48;2;181;136;99m\x1b[38;2;0;0;0m ♗ \x1b[48;2;240;217;181m\x1b[38;2;0;0;0m ♕ \
+             \x1b[48;2;224;80;80m\x1b[38;2;0;0;0m ♔ \x1b[48;2;240;217;181m\x1b[38;2;0;0;0m ♗ \
+             \x1b[48;2;181;136;99m\x1b[38;2;0;0;0m ♘ \x1b[48;2;240;217;181m\x1b[38;2;0;0;0m ♖ \
+             \x1b[0m"
+        );
+        assert_eq!(lines[8], "   a  b  c  d  e  f  g  h");
+        assert_eq!(lines[9], "White to move");
+        assert!(lines[4].contains("\x1b[48;2;205;210;106m\x1b[38;2;0;0;0m ♛ "));
+    }
+
+    #[test]
+    fn test_svg_renderer() {
+        let board_state = BoardState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let options = RenderOptions {
+            perspective: Color::Black,
+            coordinates: false,
+            arrows: vec![Arrow {
+                color: MarkColor::Green,
+                from: Square::E1,
+                to: Square::E2,
+            }],
+            ..RenderOptions::default()
+        };
+        let svg = SvgRenderer { square_size: 10 }.render(&board_state, &options);
+
+        assert!(svg.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"80\" height=\"80\" \
+             viewBox=\"0 0 80 80\">\n"
+        ));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), 64);
+        // From black's side, h8 is in the bottom left corner and e1 near the top right.
+        assert!(
+            svg.contains("<rect x=\"0\" y=\"70\" width=\"10\" height=\"10\" fill=\"#b58863\"/>")
+        );
+        assert!(svg.contains(
+            "<text x=\"35.0\" y=\"5.0\" font-size=\"8\" text-anchor=\"middle\" \
+             dominant-baseline=\"central\">♔</text>"
+        ));
+        assert!(svg.contains(
+            "<line x1=\"35.0\" y1=\"5.0\" x2=\"35.0\" y2=\"12.5\" stroke=\"#15781b\" \
+             stroke-width=\"1.7\" stroke-opacity=\"0.8\" marker-end=\"url(#arrowhead-green)\"/>"
+        ));
+
+        let svg = SvgRenderer::default().render(&board_state, &RenderOptions::default());
+
+        assert!(svg.contains("width=\"404\""));
+        assert!(svg.contains(">a</text>"));
+        assert!(svg.contains(">8</text>"));
+    }
 }
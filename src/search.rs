@@ -0,0 +1,475 @@
+use crate::boardstate::BoardState;
+use crate::eval::{self, PIECE_VALUES};
+use crate::game::Game;
+use crate::moves::*;
+use crate::nnue::{AccumulatorStack, Network};
+use crate::pawns::PawnHashTable;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// The score of a checkmate at the root. Mates further away score closer to `0`, so the
+/// search prefers the fastest mate and the slowest loss.
+pub const MATE_SCORE: i32 = 30_000;
+
+/// Scores beyond this are mate scores.
+const MATE_THRESHOLD: i32 = MATE_SCORE - 1_000;
+
+/// A bound larger than any score.
+const INFINITY: i32 = MATE_SCORE + 1;
+
+/// The deepest the iterative deepening goes when no depth is given.
+pub const MAX_DEPTH: u8 = 64;
+
+/// The strongest engine level accepted by [`SearchLimits::from_level()`].
+pub const MAX_LEVEL: u8 = 10;
+
+/// The search checks its node and time limits every this many nodes.
+const LIMIT_CHECK_INTERVAL: u64 = 1024;
+
+/// Limits on how long a search may run. The search stops as soon as any of the limits is
+/// reached, but always completes at least a depth `1` search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+}
+
+impl SearchLimits {
+    /// Returns limits that only cap the search depth.
+    pub fn depth(depth: u8) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the limits of an engine playing at the given strength, from `1` (weakest)
+    /// to [`MAX_LEVEL`]. Each level searches one ply deeper and for half a second longer.
+    pub fn from_level(level: u8) -> Self {
+        let level = level.clamp(1, MAX_LEVEL);
+
+        Self {
+            depth: Some(level),
+            nodes: None,
+            movetime: Some(Duration::from_millis(500 * u64::from(level))),
+        }
+    }
+}
+
+/// The result of a search, or of one iteration of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The best move found, or [`None`] if the side to move has no legal moves.
+    pub best_move: Option<Move>,
+    /// The score of the best move in centipawns, from the point of view of the side to move.
+    /// See [`mate_in()`].
+    pub score: i32,
+    /// The depth of the last completed iteration.
+    pub depth: u8,
+    /// The number of positions visited.
+    pub nodes: u64,
+    /// The principal variation, starting with the best move.
+    pub pv: Vec<Move>,
+}
+
+/// Returns the number of moves until mate if the score is a mate score: positive if the
+/// side to move mates, and negative if it gets mated.
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() < MATE_THRESHOLD {
+        return None;
+    }
+
+    let plies = MATE_SCORE - score.abs();
+    let moves = (plies + 1) / 2;
+
+    Some(if score > 0 { moves } else { -moves })
+}
+
+/// Searches the current position of the game for the best move.
+pub fn search(game: &Game, limits: SearchLimits) -> SearchResult {
+    search_with_info(game, limits, |_| ())
+}
+
+/// Searches the current position of the game for the best move, calling `info` with the
+/// result of every completed iteration of the iterative deepening.
+pub fn search_with_info(
+    game: &Game,
+    limits: SearchLimits,
+    info: impl FnMut(&SearchResult),
+) -> SearchResult {
+    search_with_stop(game, limits, &AtomicBool::new(false), info)
+}
+
+/// Returns the score of a quiescence search of the current position, which only plays out
+/// captures, from the point of view of the side to move. A position is quiet when the
+/// score matches its static evaluation. The pawn structure scores are cached in
+/// `pawn_table`.
+pub fn quiescence(game: &Game, pawn_table: &mut PawnHashTable) -> i32 {
+    let stop = AtomicBool::new(false);
+    let table = mem::replace(pawn_table, PawnHashTable::new(1));
+    let mut searcher = Searcher::new(game, SearchLimits::default(), &stop, table, None);
+    let score = searcher.quiescence(game, -INFINITY, INFINITY);
+
+    *pawn_table = searcher.pawn_table;
+
+    score
+}
+
+/// Searches like [`search_with_info()`], but also stops as soon as `stop` is set, e.g. by
+/// another thread reading user input. Like the other limits, `stop` is only honored once
+/// the first iteration has completed.
+pub fn search_with_stop(
+    game: &Game,
+    limits: SearchLimits,
+    stop: &AtomicBool,
+    info: impl FnMut(&SearchResult),
+) -> SearchResult {
+    search_with_network(game, limits, stop, None, info)
+}
+
+/// Searches like [`search_with_stop()`], evaluating positions with the given NNUE network
+/// instead of the handcrafted evaluation if there is one.
+pub fn search_with_network(
+    game: &Game,
+    limits: SearchLimits,
+    stop: &AtomicBool,
+    network: Option<&Network>,
+    mut info: impl FnMut(&SearchResult),
+) -> SearchResult {
+    let mut searcher = Searcher::new(game, limits, stop, PawnHashTable::default(), network);
+    let root = Game::from_board_state(game.get_board_state().clone(), game.move_gen());
+
+    let mut result = SearchResult {
+        best_move: root.enumerate_moves().get_move(0),
+        score: 0,
+        depth: 0,
+        nodes: 0,
+        pv: Vec::new(),
+    };
+
+    if result.best_move.is_none() {
+        result.score = if root.is_in_check() { -MATE_SCORE } else { 0 };
+
+        return result;
+    }
+
+    for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH).max(1) {
+        let mut pv = Vec::new();
+        let score = searcher.negamax(&root, depth, 0, -INFINITY, INFINITY, &mut pv);
+
+        if searcher.stopped {
+            break;
+        }
+
+        result = SearchResult {
+            best_move: pv.first().copied(),
+            score,
+            depth,
+            nodes: searcher.nodes,
+            pv,
+        };
+        searcher.completed_depth = depth;
+        searcher.pv.clone_from(&result.pv);
+
+        info(&result);
+
+        if mate_in(score).is_some() {
+            break;
+        }
+    }
+
+    result.nodes = searcher.nodes;
+    result
+}
+
+/// Holds the state of a negamax alpha-beta search.
+struct Searcher<'s> {
+    limits: SearchLimits,
+    stop: &'s AtomicBool,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+    completed_depth: u8,
+    /// The principal variation of the last completed iteration, searched first.
+    pv: Vec<Move>,
+    /// The positions played since the last capture or pawn move, followed by the positions
+    /// on the current search path, for repetition detection.
+    positions: Vec<BoardState>,
+    pawn_table: PawnHashTable,
+    /// The NNUE accumulators along the search path, when evaluating with a network.
+    nnue: Option<AccumulatorStack<'s>>,
+}
+
+impl<'s> Searcher<'s> {
+    fn new(
+        game: &Game,
+        limits: SearchLimits,
+        stop: &'s AtomicBool,
+        pawn_table: PawnHashTable,
+        network: Option<&'s Network>,
+    ) -> Self {
+        let history = game.history();
+        let reversible_plies = (game.get_board_state().fifty_move_rule as usize).min(history.ply());
+
+        Self {
+            limits,
+            stop,
+            start: Instant::now(),
+            nodes: 0,
+            stopped: false,
+            completed_depth: 0,
+            pv: Vec::new(),
+            positions: (history.ply() - reversible_plies..history.ply())
+                .filter_map(|ply| history.position(ply).cloned())
+                .collect(),
+            pawn_table,
+            nnue: network.map(|network| AccumulatorStack::new(network, game.get_board_state())),
+        }
+    }
+
+    /// Makes a move from `game` to `child`, updating the NNUE accumulators.
+    fn push(&mut self, game: &Game, child: &Game) {
+        if let Some(nnue) = &mut self.nnue {
+            nnue.push(game.get_board_state(), child.get_board_state());
+        }
+    }
+
+    /// Takes back the last move [pushed](Self::push()).
+    fn pop(&mut self) {
+        if let Some(nnue) = &mut self.nnue {
+            nnue.pop();
+        }
+    }
+
+    /// Returns the static evaluation of the position from the point of view of the side to
+    /// move.
+    fn evaluate(&mut self, board_state: &BoardState) -> i32 {
+        match &self.nnue {
+            Some(nnue) => nnue.evaluate(board_state.side_to_move),
+            None => eval::evaluate_cached(board_state, &mut self.pawn_table),
+        }
+    }
+
+    /// Stops the search if it has reached its limits. The first iteration always completes,
+    /// so that there is a move to play.
+    fn check_limits(&mut self) {
+        if self.completed_depth == 0 || !self.nodes.is_multiple_of(LIMIT_CHECK_INTERVAL) {
+            return;
+        }
+
+        let out_of_nodes = self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes);
+        let out_of_time = self
+            .limits
+            .movetime
+            .is_some_and(|movetime| self.start.elapsed() >= movetime);
+
+        self.stopped = out_of_nodes || out_of_time || self.stop.load(Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the position is a draw by the fifty-move rule or by repetition.
+    /// Any earlier occurrence of the position counts as a draw, since the side repeating it
+    /// could repeat it again.
+    fn is_draw(&self, board_state: &BoardState) -> bool {
+        if board_state.fifty_move_rule >= 100 {
+            return true;
+        }
+
+        let reversible_plies = (board_state.fifty_move_rule as usize).min(self.positions.len());
+
+        self.positions[self.positions.len() - reversible_plies..]
+            .iter()
+            .any(|earlier| earlier.is_same_position(board_state))
+    }
+
+    fn negamax(
+        &mut self,
+        game: &Game,
+        depth: u8,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        self.nodes += 1;
+        self.check_limits();
+
+        if self.stopped {
+            return 0;
+        }
+
+        if ply > 0 && self.is_draw(game.get_board_state()) {
+            return 0;
+        }
+
+        if depth == 0 {
+            return self.quiescence(game, alpha, beta);
+        }
+
+        let moves = self.order_moves(game, self.pv.get(ply).copied(), false);
+
+        if moves.is_empty() {
+            return if game.is_in_check() {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        self.positions.push(game.get_board_state().clone());
+
+        for mv in moves {
+            let mut child = game.clone();
+            let mut child_pv = Vec::new();
+
+            child.unchecked_make_move(mv);
+            self.push(game, &child);
+
+            let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+
+            self.pop();
+
+            if self.stopped {
+                break;
+            }
+
+            if score > alpha {
+                alpha = score;
+
+                pv.clear();
+                pv.push(mv);
+                pv.append(&mut child_pv);
+
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        self.positions.pop();
+
+        alpha
+    }
+
+    /// Searches captures until the position is quiet, so that the static evaluation isn't
+    /// taken in the middle of an exchange.
+    fn quiescence(&mut self, game: &Game, mut alpha: i32, beta: i32) -> i32 {
+        let stand_pat = self.evaluate(game.get_board_state());
+
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+
+        alpha = alpha.max(stand_pat);
+
+        for mv in self.order_moves(game, None, true) {
+            let mut child = game.clone();
+
+            child.unchecked_make_move(mv);
+            self.push(game, &child);
+            self.nodes += 1;
+            self.check_limits();
+
+            let score = -self.quiescence(&child, -beta, -alpha);
+
+            self.pop();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                return score;
+            }
+
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+
+    /// Returns the legal moves of the position, best candidates first: the principal
+    /// variation move, then captures by most valuable victim and least valuable attacker,
+    /// then promotions and quiet moves.
+    fn order_moves(&self, game: &Game, pv_move: Option<Move>, captures_only: bool) -> Vec<Move> {
+        let position = &game.get_board_state().position;
+        let legal_moves = game.enumerate_moves();
+
+        let mut moves: Vec<MoveScore> = MoveListIterator::new(&legal_moves)
+            .filter(|move_score| !captures_only || move_score.mv.is_capture())
+            .map(|MoveScore { mv, .. }| {
+                let value = |square| {
+                    position
+                        .piece_at(square)
+                        .map_or(PIECE_VALUES[0], |(_, piece)| PIECE_VALUES[piece.to_index()])
+                };
+
+                let mut score = 0;
+
+                if Some(mv) == pv_move {
+                    score += 100_000;
+                }
+
+                if mv.is_capture() {
+                    // En passant captures find no piece on the target square and count as
+                    // capturing a pawn.
+                    score += 10_000 + 10 * value(mv.get_target_square())
+                        - value(mv.get_initial_square());
+                }
+
+                if let Some(piece) = mv.promotion_piece() {
+                    score += PIECE_VALUES[piece.to_index()];
+                }
+
+                MoveScore::with_score(mv, score)
+            })
+            .collect();
+
+        moves.sort_by_key(|move_score| -move_score.score);
+        moves.into_iter().map(|move_score| move_score.mv).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+    use crate::square::Square;
+
+    #[test]
+    fn test_search() {
+        let move_gen = MoveGenerator::new();
+
+        // Back rank mate.
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &move_gen).unwrap();
+        let result = search(&game, SearchLimits::depth(3));
+
+        assert_eq!(
+            result.best_move.map(|mv| mv.get_target_square()),
+            Some(Square::A8)
+        );
+        assert_eq!(mate_in(result.score), Some(1));
+
+        // Taking the hanging queen.
+        let game = Game::from_fen("4k3/8/8/3q4/8/2N5/8/4K3 w - - 0 1", &move_gen).unwrap();
+        let result = search(&game, SearchLimits::depth(2));
+
+        assert_eq!(game.to_san(result.best_move.unwrap()).unwrap(), "Nxd5");
+        assert_eq!(result.pv.first(), result.best_move.as_ref());
+
+        // The quiescence search sees the capture the static evaluation misses.
+        let mut pawn_table = PawnHashTable::new(1);
+        let static_eval = eval::evaluate(game.get_board_state());
+
+        assert!(quiescence(&game, &mut pawn_table) > static_eval + 500);
+        assert_eq!(quiescence(&Game::new(&move_gen), &mut pawn_table), 0);
+
+        // Checkmated, with no move to play.
+        let game = Game::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", &move_gen).unwrap();
+        let result = search(&game, SearchLimits::from_level(1));
+
+        assert_eq!(result.best_move, None);
+        assert_eq!(mate_in(result.score), Some(0));
+    }
+}
@@ -0,0 +1,760 @@
+//! A local HTTP server answering JSON requests about positions, for programs that can't
+//! link the crate, such as web apps and notebooks.
+//!
+//! Every endpoint takes a `POST` request with a JSON object body and answers with a JSON
+//! object. Positions are FEN strings, checked like by [`BoardState::from_fen_strict()`],
+//! and moves are `{"uci": ..., "san": ...}` objects:
+//!
+//! - `/moves` takes `{"fen"}` and returns the legal `moves`, whether the side to move is in
+//!   `check`, and the `result` (`"1-0"`, `"0-1"`, `"1/2-1/2"` or `null`)
+//! - `/play` takes `{"fen", "move"}`, with the move in UCI notation or SAN, and returns the
+//!   `fen` after it, the `move`, `check` and `result`
+//! - `/perft` takes `{"fen", "depth"}`, with a depth up to [`MAX_PERFT_DEPTH`], and returns
+//!   the number of leaf `nodes` and the `divide` list of each move with its own `nodes`
+//! - `/eval` takes `{"fen"}` and returns the terms of the static evaluation: see
+//!   [`EvalBreakdown`]
+//! - `/search` takes `{"fen"}` and at least one of `depth`, `nodes` and `movetime` (in
+//!   milliseconds), all positive, and returns the `best_move`, the `score` in centipawns from the side to
+//!   move's point of view, `mate` in moves if the score is a mate score, the `depth`
+//!   reached, the `nodes` searched and the `pv`
+//! - `/validate` takes `{"fen"}` or `{"pgn"}` and returns whether it is `valid` and the
+//!   `errors` found
+//!
+//! Malformed requests get a `4xx` status and an `{"error"}` object. Each connection is
+//! served on its own thread and closed after the response. Responses allow any origin, so
+//! that pages served elsewhere can call the server.
+
+use crate::Color;
+use crate::boardstate::{BoardState, BoardStateCreationError};
+use crate::eval::{self, EvalBreakdown};
+use crate::game::Game;
+use crate::gametree::GameTree;
+use crate::movegen::MoveGenerator;
+use crate::moves::{Move, MoveListIterator};
+use crate::perft::perft;
+use crate::pgn;
+use crate::search::{self, MAX_DEPTH, SearchLimits};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+/// The port the `serve` command listens on by default.
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// The deepest perft the server runs.
+pub const MAX_PERFT_DEPTH: usize = 6;
+
+/// Requests with a larger body are rejected.
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// Requests with more header lines are rejected.
+const MAX_HEADERS: usize = 100;
+
+/// Connections idle for longer are dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the server waits after failing to accept a connection, so that running out of
+/// file descriptors doesn't spin.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A server listening for analysis requests.
+pub struct Server {
+    listener: TcpListener,
+    move_gen: MoveGenerator,
+}
+
+/// An HTTP request, reduced to what the endpoints need.
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// A request that can't be answered, with its HTTP status.
+#[derive(Debug)]
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(400, message)
+    }
+}
+
+impl From<serde_json::Error> for HttpError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::bad_request(format!("bad request body: {err}"))
+    }
+}
+
+/// The body of the requests that only need a position.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PositionRequest {
+    fen: BoardState,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlayRequest {
+    fen: BoardState,
+    #[serde(rename = "move")]
+    mv: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PerftRequest {
+    fen: BoardState,
+    depth: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SearchRequest {
+    fen: BoardState,
+    depth: Option<u8>,
+    nodes: Option<u64>,
+    movetime: Option<u64>,
+}
+
+/// The FEN or PGN is kept as a string, so that its problems can be reported rather than
+/// rejected.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ValidateRequest {
+    fen: Option<String>,
+    pgn: Option<String>,
+}
+
+impl Server {
+    /// Binds a server to the given address. Bind to port `0` to let the OS pick a free
+    /// port, see [`local_addr()`](Self::local_addr()).
+    ///
+    /// # Errors
+    /// Returns an error if the address can't be bound.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            move_gen: MoveGenerator::new(),
+        })
+    }
+
+    /// Returns the address the server listens on.
+    ///
+    /// # Errors
+    /// Returns an error if the OS can't tell.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves connections, each on its own thread, for as long as the process runs. Failing
+    /// to accept a connection is logged to stderr and doesn't stop the server.
+    pub fn run(&self) -> ! {
+        thread::scope(|scope| {
+            loop {
+                let stream = match self.listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        eprintln!("can't accept a connection: {err}");
+                        thread::sleep(ACCEPT_BACKOFF);
+                        continue;
+                    }
+                };
+
+                // A client hanging up early only concerns its own connection.
+                scope.spawn(move || {
+                    let _ = self.serve_connection(stream);
+                });
+            }
+        })
+    }
+
+    /// Reads a request from the connection and writes the response.
+    fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let (status, body) = match read_request(&mut BufReader::new(&stream)) {
+            Ok(request) => match self.handle(&request) {
+                Ok(body) => (if body.is_null() { 204 } else { 200 }, body),
+                Err(err) => (err.status, json!({ "error": err.message })),
+            },
+            Err(err) => (err.status, json!({ "error": err.message })),
+        };
+
+        write_response(&stream, status, &body)
+    }
+
+    /// Routes a request to its endpoint, returning [`Value::Null`] for an empty response.
+    fn handle(&self, request: &Request) -> Result<Value, HttpError> {
+        let body = &request.body;
+        let endpoint = match request.path.as_str() {
+            "/moves" => Self::moves,
+            "/play" => Self::play,
+            "/perft" => Self::perft,
+            "/eval" => Self::eval,
+            "/search" => Self::search,
+            "/validate" => Self::validate,
+            path => return Err(HttpError::new(404, format!("no endpoint at `{path}`"))),
+        };
+
+        match request.method.as_str() {
+            "POST" => endpoint(self, body),
+            // The preflight request of browsers, answered by the headers of every response.
+            "OPTIONS" => Ok(Value::Null),
+            method => Err(HttpError::new(
+                405,
+                format!("`{method}` isn't allowed, use `POST`"),
+            )),
+        }
+    }
+
+    fn game(&self, board_state: BoardState) -> Game<'_> {
+        Game::from_board_state(board_state, &self.move_gen)
+    }
+
+    fn moves(&self, body: &[u8]) -> Result<Value, HttpError> {
+        let request: PositionRequest = serde_json::from_slice(body)?;
+        let game = self.game(request.fen);
+        let legal_moves = game.enumerate_moves();
+        let moves: Vec<Value> = MoveListIterator::new(&legal_moves)
+            .map(|move_score| move_json(&game, move_score.mv))
+            .collect();
+
+        Ok(json!({
+            "fen": game.to_fen(),
+            "moves": moves,
+            "check": game.is_in_check(),
+            "result": result(&game),
+        }))
+    }
+
+    fn play(&self, body: &[u8]) -> Result<Value, HttpError> {
+        let request: PlayRequest = serde_json::from_slice(body)?;
+        let mut game = self.game(request.fen);
+        let illegal = |err: &dyn std::fmt::Display| {
+            HttpError::bad_request(format!("illegal move `{}`: {err}", request.mv))
+        };
+
+        // UCI notation first, as SAN can't be mistaken for it.
+        let mv = match request.mv.parse::<Move>() {
+            Ok(mv) => game.resolve_move(mv).map_err(|err| illegal(&err))?,
+            Err(_) => game.parse_san(&request.mv).map_err(|err| illegal(&err))?,
+        };
+        let mv_json = move_json(&game, mv);
+
+        game.try_make_move(mv).map_err(|err| illegal(&err))?;
+
+        Ok(json!({
+            "fen": game.to_fen(),
+            "move": mv_json,
+            "check": game.is_in_check(),
+            "result": result(&game),
+        }))
+    }
+
+    fn perft(&self, body: &[u8]) -> Result<Value, HttpError> {
+        let request: PerftRequest = serde_json::from_slice(body)?;
+
+        if request.depth > MAX_PERFT_DEPTH {
+            return Err(HttpError::bad_request(format!(
+                "depth {} is past the maximum of {MAX_PERFT_DEPTH}",
+                request.depth
+            )));
+        }
+
+        let game = self.game(request.fen);
+
+        if request.depth == 0 {
+            return Ok(json!({ "nodes": 1, "divide": [] }));
+        }
+
+        let legal_moves = game.enumerate_moves();
+        let mut nodes = 0;
+        let divide: Vec<Value> = MoveListIterator::new(&legal_moves)
+            .map(|move_score| {
+                let mut next = game.clone();
+                next.unchecked_make_move(move_score.mv);
+
+                let move_nodes = perft(request.depth - 1, next);
+                nodes += move_nodes;
+
+                let mut entry = move_json(&game, move_score.mv);
+                entry["nodes"] = move_nodes.into();
+                entry
+            })
+            .collect();
+
+        Ok(json!({ "nodes": nodes, "divide": divide }))
+    }
+
+    fn eval(&self, body: &[u8]) -> Result<Value, HttpError> {
+        let request: PositionRequest = serde_json::from_slice(body)?;
+        let EvalBreakdown {
+            material,
+            placement,
+            pawns,
+            phase,
+            drawn_material,
+            opposite_bishops,
+            total,
+        } = eval::breakdown(&request.fen);
+        let by_color = |terms: [i32; 2]| {
+            json!({
+                "white": terms[Color::White.to_index()],
+                "black": terms[Color::Black.to_index()],
+            })
+        };
+
+        Ok(json!({
+            "total": total,
+            "material": by_color(material),
+            "placement": by_color(placement),
+            "pawns": pawns,
+            "phase": phase,
+            "max_phase": eval::MAX_PHASE,
+            "drawn_material": drawn_material,
+            "opposite_bishops": opposite_bishops,
+        }))
+    }
+
+    fn search(&self, body: &[u8]) -> Result<Value, HttpError> {
+        let request: SearchRequest = serde_json::from_slice(body)?;
+        let limits = SearchLimits {
+            depth: request.depth,
+            nodes: request.nodes,
+            movetime: request.movetime.map(Duration::from_millis),
+        };
+
+        if limits == SearchLimits::default() {
+            return Err(HttpError::bad_request(
+                "give a `depth`, `nodes` or `movetime` limit",
+            ));
+        }
+
+        if request.depth == Some(0) || request.nodes == Some(0) || request.movetime == Some(0) {
+            return Err(HttpError::bad_request("search limits must be positive"));
+        }
+
+        if request.depth.is_some_and(|depth| depth > MAX_DEPTH) {
+            return Err(HttpError::bad_request(format!(
+                "depth is past the maximum of {MAX_DEPTH}"
+            )));
+        }
+
+        let mut game = self.game(request.fen);
+        let result = search::search(&game, limits);
+        let best_move = result.best_move.map(|mv| move_json(&game, mv));
+        let mut pv = Vec::with_capacity(result.pv.len());
+
+        for &mv in &result.pv {
+            pv.push(move_json(&game, mv));
+            game.unchecked_make_move(mv);
+        }
+
+        Ok(json!({
+            "best_move": best_move,
+            "score": result.score,
+            "mate": search::mate_in(result.score),
+            "depth": result.depth,
+            "nodes": result.nodes,
+            "pv": pv,
+        }))
+    }
+
+    fn validate(&self, body: &[u8]) -> Result<Value, HttpError> {
+        let request: ValidateRequest = serde_json::from_slice(body)?;
+
+        match (request.fen, request.pgn) {
+            (Some(fen), None) => {
+                let errors: Vec<String> = match BoardState::from_fen(&fen) {
+                    Ok(board_state) => match board_state.validate_with(&self.move_gen) {
+                        Ok(()) => {
+                            return Ok(json!({
+                                "valid": true,
+                                "fen": board_state.to_fen(),
+                                "errors": [],
+                            }));
+                        }
+                        Err(errors) => errors.iter().map(ToString::to_string).collect(),
+                    },
+                    Err(BoardStateCreationError::IllegalPosition(errors)) => {
+                        errors.iter().map(ToString::to_string).collect()
+                    }
+                    Err(err) => vec![err.to_string()],
+                };
+
+                Ok(json!({ "valid": false, "errors": errors }))
+            }
+            (None, Some(pgn)) => {
+                let games = pgn::split_games(&pgn);
+                let mut errors: Vec<Value> = games
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, game)| {
+                        let err = GameTree::from_pgn(game, &self.move_gen).err()?;
+                        Some(json!({ "game": i + 1, "error": err.to_string() }))
+                    })
+                    .collect();
+
+                if games.is_empty() {
+                    errors.push(json!({ "game": null, "error": "no games found" }));
+                }
+
+                Ok(json!({
+                    "valid": errors.is_empty(),
+                    "games": games.len(),
+                    "errors": errors,
+                }))
+            }
+            _ => Err(HttpError::bad_request("give either a `fen` or a `pgn`")),
+        }
+    }
+}
+
+/// Returns the legal move as a JSON object with its UCI notation and SAN.
+fn move_json(game: &Game, mv: Move) -> Value {
+    let san = game.to_san(mv).expect("the move is legal");
+
+    json!({ "uci": mv.to_string(), "san": san })
+}
+
+/// Returns the result of the game if the side to move is mated or stalemated.
+fn result(game: &Game) -> Option<&'static str> {
+    if !game.enumerate_moves().is_empty() {
+        return None;
+    }
+
+    Some(
+        match (game.is_in_check(), game.get_board_state().side_to_move) {
+            (false, _) => "1/2-1/2",
+            (true, Color::White) => "0-1",
+            (true, Color::Black) => "1-0",
+        },
+    )
+}
+
+/// Reads the request line, the headers and the body of a request.
+fn read_request(reader: &mut impl BufRead) -> Result<Request, HttpError> {
+    let mut read_line = || {
+        let mut line = String::new();
+
+        match reader.read_line(&mut line) {
+            Ok(0) => Err(HttpError::bad_request("the request ends early")),
+            Ok(_) => Ok(line.trim_end_matches(['\r', '\n']).to_string()),
+            Err(err) => Err(HttpError::bad_request(format!(
+                "can't read the request: {err}"
+            ))),
+        }
+    };
+
+    let request_line = read_line()?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(_version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(HttpError::bad_request(format!(
+            "bad request line `{request_line}`"
+        )));
+    };
+    let path = target.split('?').next().unwrap_or(target).to_string();
+    let method = method.to_string();
+    let mut content_length = 0;
+
+    for count in 0.. {
+        let line = read_line()?;
+
+        if line.is_empty() {
+            break;
+        }
+
+        if count == MAX_HEADERS {
+            return Err(HttpError::new(431, "too many headers"));
+        }
+
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value
+                .trim()
+                .parse()
+                .map_err(|_| HttpError::bad_request(format!("bad content length `{value}`")))?;
+        }
+    }
+
+    if content_length > MAX_BODY_SIZE {
+        return Err(HttpError::new(
+            413,
+            format!("the body is larger than {MAX_BODY_SIZE} bytes"),
+        ));
+    }
+
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|err| HttpError::bad_request(format!("can't read the body: {err}")))?;
+
+    Ok(Request { method, path, body })
+}
+
+/// Writes a response with a JSON body, or no body for [`Value::Null`].
+fn write_response(mut stream: &TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Error",
+    };
+    let body = match body {
+        Value::Null => String::new(),
+        body => body.to_string(),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    )?;
+
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    /// Starts a server on a free port, left running until the tests exit.
+    fn start() -> SocketAddr {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.run());
+
+        addr
+    }
+
+    /// Sends a request and returns the status and the JSON body of the response.
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        let body = match body {
+            "" => Value::Null,
+            body => serde_json::from_str(body).unwrap(),
+        };
+
+        (status, body)
+    }
+
+    fn post(addr: SocketAddr, path: &str, body: Value) -> (u16, Value) {
+        request(addr, "POST", path, &body.to_string())
+    }
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_moves_and_play() {
+        let addr = start();
+
+        let (status, body) = post(addr, "/moves", json!({ "fen": START }));
+        assert_eq!(status, 200);
+        assert_eq!(body["moves"].as_array().unwrap().len(), 20);
+        assert!(
+            body["moves"]
+                .as_array()
+                .unwrap()
+                .contains(&json!({ "uci": "g1f3", "san": "Nf3" }))
+        );
+        assert_eq!(body["check"], false);
+        assert_eq!(body["result"], Value::Null);
+
+        // Fool's mate.
+        let mated = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        let (_, body) = post(addr, "/moves", json!({ "fen": mated }));
+        assert_eq!(body["moves"], json!([]));
+        assert_eq!(body["check"], true);
+        assert_eq!(body["result"], "0-1");
+
+        // Moves in UCI notation or SAN.
+        for mv in ["e2e4", "e4"] {
+            let (status, body) = post(addr, "/play", json!({ "fen": START, "move": mv }));
+            assert_eq!(status, 200);
+            assert_eq!(
+                body["fen"],
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+            );
+            assert_eq!(body["move"], json!({ "uci": "e2e4", "san": "e4" }));
+        }
+
+        let before_mate = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        let (_, body) = post(addr, "/play", json!({ "fen": before_mate, "move": "Qh4" }));
+        assert_eq!(body["move"]["san"], "Qh4#");
+        assert_eq!(body["result"], "0-1");
+
+        // Positions reached by /play are accepted back, even past the fifty-move point.
+        let fen = "4k3/8/8/8/8/8/8/4K2R w - - 99 80";
+        let (_, body) = post(addr, "/play", json!({ "fen": fen, "move": "h1h2" }));
+        assert_eq!(body["fen"], "4k3/8/8/8/8/8/7R/4K3 b - - 100 80");
+        let (status, body) = post(addr, "/moves", json!({ "fen": body["fen"] }));
+        assert_eq!(status, 200);
+        assert_eq!(body["moves"].as_array().unwrap().len(), 5);
+
+        let (status, body) = post(addr, "/play", json!({ "fen": START, "move": "e2e5" }));
+        assert_eq!(status, 400);
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("illegal move `e2e5`")
+        );
+
+        // Positions are validated.
+        let (status, _) = post(
+            addr,
+            "/moves",
+            json!({ "fen": "8/8/8/8/8/8/8/8 w - - 0 1" }),
+        );
+        assert_eq!(status, 400);
+
+        let (_, body) = post(addr, "/validate", json!({ "fen": START }));
+        assert_eq!(body, json!({ "valid": true, "fen": START, "errors": [] }));
+
+        let (_, body) = post(
+            addr,
+            "/validate",
+            json!({ "fen": "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1" }),
+        );
+        assert_eq!(body["valid"], false);
+        assert_eq!(body["errors"].as_array().unwrap().len(), 1);
+
+        let (_, body) = post(addr, "/validate", json!({ "fen": "not a fen" }));
+        assert_eq!(body["valid"], false);
+
+        let pgn = "[Event \"a\"]\n\n1. e4 e5 2. Nf3 *\n\n[Event \"b\"]\n\n1. e4 Ke7 2. Ke3 *\n";
+        let (_, body) = post(addr, "/validate", json!({ "pgn": pgn }));
+        assert_eq!(body["valid"], false);
+        assert_eq!(body["games"], 2);
+        assert_eq!(body["errors"][0]["game"], 2);
+
+        let (status, _) = post(addr, "/validate", json!({ "fen": START, "pgn": pgn }));
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn test_analysis() {
+        let addr = start();
+
+        let (status, body) = post(addr, "/perft", json!({ "fen": START, "depth": 3 }));
+        assert_eq!(status, 200);
+        assert_eq!(body["nodes"], 8902);
+        assert_eq!(body["divide"].as_array().unwrap().len(), 20);
+        assert!(
+            body["divide"]
+                .as_array()
+                .unwrap()
+                .contains(&json!({ "uci": "b1c3", "san": "Nc3", "nodes": 440 }))
+        );
+
+        let (status, _) = post(addr, "/perft", json!({ "fen": START, "depth": 7 }));
+        assert_eq!(status, 400);
+
+        let fen = "4k3/pp6/2n5/8/3Q4/8/8/4K3 b - - 0 1";
+        let (_, body) = post(addr, "/eval", json!({ "fen": fen }));
+        assert_eq!(
+            body["total"],
+            eval::evaluate(&BoardState::from_fen(fen).unwrap())
+        );
+        assert_eq!(body["material"]["black"], 520);
+        assert_eq!(body["material"]["white"], 900);
+
+        // Mate in one with the back rank.
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let (status, body) = post(addr, "/search", json!({ "fen": fen, "depth": 3 }));
+        assert_eq!(status, 200);
+        assert_eq!(body["best_move"], json!({ "uci": "a1a8", "san": "Ra8#" }));
+        assert_eq!(body["mate"], 1);
+        assert_eq!(body["pv"][0], body["best_move"]);
+
+        let (status, body) = post(addr, "/search", json!({ "fen": START, "nodes": 2000 }));
+        assert_eq!(status, 200);
+        assert!(!body["pv"].as_array().unwrap().is_empty());
+
+        let (status, _) = post(addr, "/search", json!({ "fen": START }));
+        assert_eq!(status, 400);
+
+        for limit in ["depth", "nodes", "movetime"] {
+            let (status, body) = post(addr, "/search", json!({ "fen": START, limit: 0 }));
+            assert_eq!(status, 400);
+            assert_eq!(body["error"], "search limits must be positive");
+        }
+    }
+
+    #[test]
+    fn test_http() {
+        let addr = start();
+
+        let (status, body) = request(addr, "GET", "/moves", "");
+        assert_eq!(status, 405);
+        assert!(body["error"].is_string());
+
+        let (status, body) = request(addr, "OPTIONS", "/moves", "");
+        assert_eq!(status, 204);
+        assert_eq!(body, Value::Null);
+
+        let (status, _) = post(addr, "/nowhere", json!({}));
+        assert_eq!(status, 404);
+
+        let (status, _) = request(addr, "POST", "/moves", "{");
+        assert_eq!(status, 400);
+
+        let (status, body) = post(addr, "/eval", json!({ "fen": START, "depth": 1 }));
+        assert_eq!(status, 400);
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap()
+                .contains("unknown field `depth`")
+        );
+
+        // Query strings are ignored.
+        let (status, _) = post(addr, "/eval?verbose=1", json!({ "fen": START }));
+        assert_eq!(status, 200);
+    }
+}
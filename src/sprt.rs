@@ -0,0 +1,615 @@
+use crate::Color;
+use crate::game::Outcome;
+use crate::movegen::MoveGenerator;
+use crate::tournament::{self, EloEstimate, GameRecord, MatchConfig, TournamentError};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// The number of game pairs a test plays at most, unless the match config sets fewer
+/// rounds.
+pub const DEFAULT_MAX_PAIRS: u32 = 50_000;
+
+/// Stands in for the empty pentanomial counts when computing the LLR, so that a test whose
+/// pairs all ended the same way, with no variance, still reaches a verdict.
+const EMPTY_COUNT: f64 = 1e-3;
+
+/// The first line of a state file.
+const STATE_HEADER: &str = "# poob SPRT state";
+
+/// The errors that can stop a test.
+#[derive(Debug)]
+pub enum SprtError {
+    /// The match couldn't start.
+    Match(TournamentError),
+    /// The state file couldn't be read or written.
+    Io(io::Error),
+    /// A line of the state file is missing or malformed.
+    BadState(String),
+    /// The state file belongs to a test with other bounds.
+    StateMismatch,
+    /// The bounds aren't valid: `elo0` must be below `elo1`, and `alpha` and `beta`
+    /// strictly between `0` and `1`.
+    BadParams,
+    /// The match doesn't play game pairs: a test needs exactly two engines playing each
+    /// opening with both colors.
+    Unpaired,
+}
+
+impl fmt::Display for SprtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Match(err) => write!(f, "{err}"),
+            Self::Io(err) => write!(f, "state file error: {err}"),
+            Self::BadState(line) => write!(f, "bad state file line `{line}`"),
+            Self::StateMismatch => write!(f, "the state file is for a test with other bounds"),
+            Self::BadParams => write!(
+                f,
+                "bad bounds, expected elo0 < elo1 and alpha and beta between 0 and 1"
+            ),
+            Self::Unpaired => write!(
+                f,
+                "a test needs two engines playing each opening with both colors"
+            ),
+        }
+    }
+}
+
+impl Error for SprtError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Match(err) => Some(err),
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<TournamentError> for SprtError {
+    fn from(err: TournamentError) -> Self {
+        Self::Match(err)
+    }
+}
+
+impl From<io::Error> for SprtError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// The hypotheses of a test, `elo0` (H0) and `elo1` (H1), and the probabilities of a false
+/// positive (`alpha`) and a false negative (`beta`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SprtParams {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for SprtParams {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl SprtParams {
+    /// Returns the log-likelihood ratios at which H0 and H1 are accepted.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    fn is_valid(&self) -> bool {
+        let probability = |p: f64| p > 0.0 && p < 1.0;
+
+        self.elo0 < self.elo1 && probability(self.alpha) && probability(self.beta)
+    }
+}
+
+/// The conclusion of a test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// H1 is accepted: the tested engine is at least `elo1` stronger.
+    Accept,
+    /// H0 is accepted: the tested engine is at most `elo0` stronger.
+    Reject,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Accept => write!(f, "accepted (H1)"),
+            Self::Reject => write!(f, "rejected (H0)"),
+        }
+    }
+}
+
+/// The progress of a test: the game pairs counted so far and the round to resume from.
+///
+/// The pairs are counted with the pentanomial model: `pentanomial[i]` is the number of
+/// pairs in which the tested engine scored `i` half points out of `4`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SprtState {
+    pub params: SprtParams,
+    pub pentanomial: [u32; 5],
+    /// The first round whose pair hasn't been counted.
+    pub next_round: u32,
+}
+
+impl SprtState {
+    pub fn new(params: SprtParams) -> Self {
+        Self {
+            params,
+            pentanomial: [0; 5],
+            next_round: 0,
+        }
+    }
+
+    /// Returns the number of pairs counted.
+    pub fn pairs(&self) -> u32 {
+        self.pentanomial.iter().sum()
+    }
+
+    /// Returns the mean score per game of the tested engine and its variance per pair, or
+    /// [`None`] if no pair has been counted. With `regularize`, the empty counts are
+    /// replaced by [`EMPTY_COUNT`].
+    fn score(&self, regularize: bool) -> Option<(f64, f64)> {
+        if self.pairs() == 0 {
+            return None;
+        }
+
+        let counts = self.pentanomial.map(|count| match count {
+            0 if regularize => EMPTY_COUNT,
+            count => f64::from(count),
+        });
+        let pairs: f64 = counts.iter().sum();
+        let frequencies = counts.map(|count| count / pairs);
+        let points = |i: usize| i as f64 / 4.0;
+
+        let mean: f64 = (0..5).map(|i| frequencies[i] * points(i)).sum();
+        let variance = (0..5)
+            .map(|i| frequencies[i] * (points(i) - mean).powi(2))
+            .sum();
+
+        Some((mean, variance))
+    }
+
+    /// Returns the log-likelihood ratio of H1 against H0, using the normal approximation
+    /// of the generalized SPRT.
+    pub fn llr(&self) -> f64 {
+        let Some((mean, variance)) = self.score(true) else {
+            return 0.0;
+        };
+
+        let score0 = tournament::elo_to_score(self.params.elo0);
+        let score1 = tournament::elo_to_score(self.params.elo1);
+
+        f64::from(self.pairs()) * (score1 - score0) * (2.0 * mean - score0 - score1)
+            / (2.0 * variance)
+    }
+
+    /// Returns the verdict once the log-likelihood ratio has crossed a bound, and [`None`]
+    /// while the test goes on.
+    pub fn verdict(&self) -> Option<Verdict> {
+        let (lower, upper) = self.params.bounds();
+        let llr = self.llr();
+
+        if llr >= upper {
+            Some(Verdict::Accept)
+        } else if llr <= lower {
+            Some(Verdict::Reject)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the Elo difference of the tested engine, or [`None`] if it has scored `0%`
+    /// or `100%` so far.
+    pub fn elo(&self) -> Option<EloEstimate> {
+        let (mean, variance) = self.score(false)?;
+
+        (mean > 0.0 && mean < 1.0)
+            .then(|| EloEstimate::new(mean, (variance / f64::from(self.pairs())).sqrt()))
+    }
+
+    /// Returns the state in the format of a state file.
+    pub fn to_state_file(&self) -> String {
+        let SprtParams {
+            elo0,
+            elo1,
+            alpha,
+            beta,
+        } = self.params;
+        let [ll, ld, dd, wd, ww] = self.pentanomial;
+
+        let mut state = String::new();
+        let _ = writeln!(state, "{STATE_HEADER}");
+        let _ = writeln!(
+            state,
+            "elo0 {elo0}\nelo1 {elo1}\nalpha {alpha}\nbeta {beta}"
+        );
+        let _ = writeln!(state, "next_round {}", self.next_round);
+        let _ = writeln!(state, "pentanomial {ll} {ld} {dd} {wd} {ww}");
+
+        state
+    }
+
+    /// Parses a state file written by [`to_state_file()`](Self::to_state_file()).
+    ///
+    /// # Errors
+    /// Returns [`SprtError::BadState`] with the first line that can't be parsed, or the
+    /// name of the first missing field.
+    pub fn from_state_file(text: &str) -> Result<Self, SprtError> {
+        let mut params = [None; 4];
+        let mut next_round = None;
+        let mut pentanomial = None;
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let bad_state = || SprtError::BadState(line.to_string());
+            let (name, value) = line.split_once(' ').ok_or_else(bad_state)?;
+
+            match name {
+                "elo0" | "elo1" | "alpha" | "beta" => {
+                    let index = ["elo0", "elo1", "alpha", "beta"]
+                        .iter()
+                        .position(|&param| param == name)
+                        .unwrap_or_default();
+
+                    params[index] = Some(value.trim().parse().map_err(|_| bad_state())?);
+                }
+                "next_round" => next_round = Some(value.trim().parse().map_err(|_| bad_state())?),
+                "pentanomial" => {
+                    let counts = value
+                        .split_ascii_whitespace()
+                        .map(|count| count.parse().ok())
+                        .collect::<Option<Vec<u32>>>()
+                        .ok_or_else(bad_state)?;
+
+                    pentanomial = Some(<[u32; 5]>::try_from(counts).map_err(|_| bad_state())?);
+                }
+                _ => return Err(bad_state()),
+            }
+        }
+
+        let missing = |name: &str| SprtError::BadState(format!("missing {name}"));
+        let [elo0, elo1, alpha, beta] = params;
+
+        Ok(Self {
+            params: SprtParams {
+                elo0: elo0.ok_or_else(|| missing("elo0"))?,
+                elo1: elo1.ok_or_else(|| missing("elo1"))?,
+                alpha: alpha.ok_or_else(|| missing("alpha"))?,
+                beta: beta.ok_or_else(|| missing("beta"))?,
+            },
+            pentanomial: pentanomial.ok_or_else(|| missing("pentanomial"))?,
+            next_round: next_round.ok_or_else(|| missing("next_round"))?,
+        })
+    }
+
+    /// Reads a state file.
+    ///
+    /// # Errors
+    /// Returns a [`SprtError`] if the file can't be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, SprtError> {
+        Self::from_state_file(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the state file, replacing the old one only once the new one is complete.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        fs::write(&temporary, self.to_state_file())?;
+        fs::rename(&temporary, path)
+    }
+}
+
+impl fmt::Display for SprtState {
+    /// Writes the LLR with its bounds, the pentanomial counts and the Elo estimate.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (lower, upper) = self.params.bounds();
+
+        write!(
+            f,
+            "LLR {:.2} ({lower:.2}, {upper:.2}) [{:.1}, {:.1}], pairs {} {:?}",
+            self.llr(),
+            self.params.elo0,
+            self.params.elo1,
+            self.pairs(),
+            self.pentanomial
+        )?;
+
+        match self.elo() {
+            Some(elo) => write!(f, ", Elo {elo}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Counts finished games into the state, pair by pair and in round order, so that the
+/// state can be resumed from its next round whatever order the games ended in.
+#[derive(Debug)]
+struct Tally {
+    state: SprtState,
+    /// The games and half points of the tested engine in rounds past the next one.
+    pending: BTreeMap<u32, (u8, usize)>,
+}
+
+impl Tally {
+    /// Adds a finished game, returning `true` if it completed pairs up to the next round.
+    fn add(&mut self, game: &GameRecord) -> bool {
+        let tested = match game.pairing.white {
+            0 => Color::White,
+            _ => Color::Black,
+        };
+        let half_points = match game.outcome {
+            Outcome::Win(winner) if winner == tested => 2,
+            Outcome::Win(_) => 0,
+            Outcome::Draw => 1,
+        };
+
+        let pair = self.pending.entry(game.pairing.round).or_default();
+        pair.0 += 1;
+        pair.1 += half_points;
+
+        let mut counted = false;
+
+        while let Some(&(2, half_points)) = self.pending.get(&self.state.next_round) {
+            self.pending.remove(&self.state.next_round);
+            self.state.pentanomial[half_points] += 1;
+            self.state.next_round += 1;
+            counted = true;
+        }
+
+        counted
+    }
+}
+
+/// Runs a sequential probability ratio test of the first engine of the match against the
+/// second, and returns the final state, with a verdict unless the match ran out of rounds.
+///
+/// Each round is a pair of games on the same opening with the colors reversed. With a
+/// `state_path`, the state is read from the file if it exists and written to it after
+/// every pair, and the test resumes from the first round not counted yet. `progress` is
+/// called with every finished game and the state at that time.
+///
+/// # Errors
+/// Returns a [`SprtError`] if the parameters or the state file aren't valid, the match
+/// doesn't play game pairs, or it can't start.
+pub fn run_sprt(
+    config: &MatchConfig,
+    params: SprtParams,
+    state_path: Option<&Path>,
+    move_gen: &MoveGenerator,
+    progress: impl Fn(&GameRecord, &SprtState) + Sync,
+) -> Result<SprtState, SprtError> {
+    if !params.is_valid() {
+        return Err(SprtError::BadParams);
+    }
+
+    if config.engines.len() != 2 || !config.repeat {
+        return Err(SprtError::Unpaired);
+    }
+
+    let state = match state_path {
+        Some(path) if path.exists() => SprtState::load(path)?,
+        _ => SprtState::new(params),
+    };
+
+    if state.params != params {
+        return Err(SprtError::StateMismatch);
+    }
+
+    if state.verdict().is_some() {
+        return Ok(state);
+    }
+
+    let config = MatchConfig {
+        first_round: state.next_round,
+        ..config.clone()
+    };
+    let tally = Mutex::new(Tally {
+        state,
+        pending: BTreeMap::new(),
+    });
+    let save_error = Mutex::new(None);
+    let stop = AtomicBool::new(false);
+
+    tournament::run_match_with_stop(&config, move_gen, &stop, |game| {
+        let mut tally = tally.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if tally.add(game) {
+            if let Some(path) = state_path
+                && let Err(err) = tally.state.save(path)
+            {
+                *save_error.lock().unwrap_or_else(PoisonError::into_inner) = Some(err);
+                stop.store(true, Ordering::Relaxed);
+            }
+
+            if tally.state.verdict().is_some() {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+
+        progress(game, &tally.state);
+    })?;
+
+    if let Some(err) = save_error
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner)
+    {
+        return Err(SprtError::Io(err));
+    }
+
+    Ok(tally
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner)
+        .state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament::{EngineConfig, Pairing};
+    use crate::xboard::TimeControl;
+    use std::time::Duration;
+
+    fn state(pentanomial: [u32; 5]) -> SprtState {
+        SprtState {
+            pentanomial,
+            ..SprtState::new(SprtParams::default())
+        }
+    }
+
+    #[test]
+    fn test_llr() {
+        let (lower, upper) = SprtParams::default().bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+
+        assert_eq!(state([0; 5]).llr(), 0.0);
+        assert_eq!(state([0, 0, 3, 0, 0]).verdict(), None);
+        assert_eq!(state([20, 0, 0, 0, 0]).verdict(), Some(Verdict::Reject));
+        assert_eq!(state([0, 0, 0, 0, 20]).verdict(), Some(Verdict::Accept));
+
+        // A score of 55.2%, well above the 50.7% expected under H1.
+        let winning = state([100, 400, 1000, 700, 200]);
+        assert!((winning.llr() - 14.64).abs() < 0.01);
+        assert_eq!(winning.verdict(), Some(Verdict::Accept));
+        assert!((winning.elo().unwrap().elo - 36.32).abs() < 0.01);
+
+        let losing = state([200, 700, 1000, 400, 100]);
+        assert_eq!(losing.verdict(), Some(Verdict::Reject));
+
+        let even = state([10, 40, 100, 40, 10]);
+        assert_eq!(even.verdict(), None);
+        assert_eq!(even.elo().unwrap().elo, 0.0);
+    }
+
+    #[test]
+    fn test_state_file() {
+        let mut state = state([1, 2, 3, 4, 5]);
+        state.next_round = 15;
+        state.params.elo1 = 2.5;
+
+        let text = state.to_state_file();
+        assert!(text.contains("next_round 15\npentanomial 1 2 3 4 5\n"));
+        assert_eq!(SprtState::from_state_file(&text).unwrap(), state);
+
+        let truncated = text.replace("pentanomial 1 2 3 4 5\n", "");
+        assert!(matches!(
+            SprtState::from_state_file(&truncated),
+            Err(SprtError::BadState(missing)) if missing == "missing pentanomial"
+        ));
+        assert!(matches!(
+            SprtState::from_state_file(&text.replace("3 4 5", "3 4")),
+            Err(SprtError::BadState(_))
+        ));
+    }
+
+    #[test]
+    fn test_tally() {
+        let game = |round, white, outcome| GameRecord {
+            number: 0,
+            pairing: Pairing {
+                round,
+                white,
+                black: 1 - white,
+                opening: 0,
+            },
+            outcome,
+            reason: String::new(),
+            pgn: String::new(),
+        };
+        let mut tally = Tally {
+            state: SprtState::new(SprtParams::default()),
+            pending: BTreeMap::new(),
+        };
+
+        // The pair of round 1 ends first, but is only counted after the one of round 0.
+        assert!(!tally.add(&game(1, 0, Outcome::Win(Color::White))));
+        assert!(!tally.add(&game(1, 1, Outcome::Draw)));
+        assert!(!tally.add(&game(0, 1, Outcome::Win(Color::White))));
+        assert_eq!(tally.state.pairs(), 0);
+
+        assert!(tally.add(&game(0, 0, Outcome::Win(Color::Black))));
+        assert_eq!(tally.state.pentanomial, [1, 0, 0, 1, 0]);
+        assert_eq!(tally.state.next_round, 2);
+        assert!(tally.pending.is_empty());
+    }
+
+    #[test]
+    fn test_run_sprt() {
+        let move_gen = MoveGenerator::new();
+        let path = std::env::temp_dir().join(format!("poob-sprt-{}.state", std::process::id()));
+        let mut config = MatchConfig {
+            engines: vec![EngineConfig::Poob(Some(1)), EngineConfig::Poob(Some(2))],
+            rounds: 2,
+            time_control: TimeControl::Fixed(Duration::from_secs(1)),
+            openings: tournament::parse_epd("8/8/4k3/8/8/4K3/8/8 w - -").unwrap(),
+            ..MatchConfig::default()
+        };
+        let params = SprtParams::default();
+        let run = |config: &MatchConfig, params| {
+            let games = Mutex::new(Vec::new());
+            let state = run_sprt(config, params, Some(&path), &move_gen, |game, _| {
+                games.lock().unwrap().push(game.number);
+            });
+
+            (state, games.into_inner().unwrap())
+        };
+
+        let (state, games) = run(&config, params);
+        assert_eq!(state.unwrap(), SprtState::load(&path).unwrap());
+        assert_eq!(games.len(), 4);
+
+        // Resuming only plays the rounds not counted yet.
+        config.rounds = 3;
+        let (state, games) = run(&config, params);
+        let state = state.unwrap();
+        assert_eq!((state.next_round, state.pentanomial), (3, [0, 0, 3, 0, 0]));
+        assert_eq!(games, [5, 6]);
+
+        let other = SprtParams {
+            elo1: 10.0,
+            ..params
+        };
+        assert!(matches!(
+            run(&config, other).0,
+            Err(SprtError::StateMismatch)
+        ));
+
+        // A decided test plays no more games.
+        let mut decided = SprtState::new(params);
+        decided.pentanomial = [0, 0, 100, 0, 100];
+        decided.save(&path).unwrap();
+        let (state, games) = run(&config, params);
+        assert_eq!(state.unwrap().verdict(), Some(Verdict::Accept));
+        assert!(games.is_empty());
+
+        let _ = fs::remove_file(&path);
+
+        config.repeat = false;
+        assert!(matches!(run(&config, params).0, Err(SprtError::Unpaired)));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::bitboards::Bitboard;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Error variants when parsing a [`Square`], [`File`], [`Rank`] or [`Bitboard`] from a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SquareParseError {
+    BadFile,
+    BadRank,
+    BadSquare,
+    BadBitboard,
+}
+
+impl fmt::Display for SquareParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadFile => write!(f, "expected a file from `a` to `h`"),
+            Self::BadRank => write!(f, "expected a rank from `1` to `8`"),
+            Self::BadSquare => write!(f, "expected a square such as `e4`"),
+            Self::BadBitboard => write!(f, "expected 8 rows of 8 `.` or `x` characters"),
+        }
+    }
+}
+
+impl Error for SquareParseError {}
+
+/// Represents one of the eight files (columns) of the board, from the queenside (a) to the
+/// kingside (h).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum File {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+}
+
+impl File {
+    /// Every file, ordered from a to h.
+    pub const ALL: [File; 8] = [
+        File::A,
+        File::B,
+        File::C,
+        File::D,
+        File::E,
+        File::F,
+        File::G,
+        File::H,
+    ];
+
+    /// Returns the file with the given index (`0` is the a-file), or [`None`] if the index is
+    /// not in the range `0..8`.
+    pub fn from_index(index: u8) -> Option<Self> {
+        File::ALL.get(index as usize).copied()
+    }
+
+    /// Returns the index of the file, from `0` (a-file) to `7` (h-file).
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the bitboard of every square on this file.
+    pub const fn bitboard(self) -> Bitboard {
+        Bitboard(0x0101_0101_0101_0101 << self as u8)
+    }
+
+    /// Returns the file mirrored across the middle of the board (a ↔ h).
+    pub const fn flip(self) -> Self {
+        File::ALL[7 - self as usize]
+    }
+
+    /// Returns the lowercase letter of the file.
+    pub const fn to_char(self) -> char {
+        (b'a' + self as u8) as char
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl FromStr for File {
+    type Err = SquareParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[c @ b'a'..=b'h'] => Ok(File::ALL[(c - b'a') as usize]),
+            _ => Err(SquareParseError::BadFile),
+        }
+    }
+}
+
+/// Represents one of the eight ranks (rows) of the board, from [white's](crate::Color::White)
+/// side (first) to [black's](crate::Color::Black) side (eighth).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Fifth,
+    Sixth,
+    Seventh,
+    Eighth,
+}
+
+impl Rank {
+    /// Every rank, ordered from first to eighth.
+    pub const ALL: [Rank; 8] = [
+        Rank::First,
+        Rank::Second,
+        Rank::Third,
+        Rank::Fourth,
+        Rank::Fifth,
+        Rank::Sixth,
+        Rank::Seventh,
+        Rank::Eighth,
+    ];
+
+    /// Returns the rank with the given index (`0` is the first rank), or [`None`] if the
+    /// index is not in the range `0..8`.
+    pub fn from_index(index: u8) -> Option<Self> {
+        Rank::ALL.get(index as usize).copied()
+    }
+
+    /// Returns the index of the rank, from `0` (first rank) to `7` (eighth rank).
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the bitboard of every square on this rank.
+    pub const fn bitboard(self) -> Bitboard {
+        Bitboard(0xff << (self as u8 * 8))
+    }
+
+    /// Returns the rank mirrored across the middle of the board (first ↔ eighth).
+    pub const fn flip(self) -> Self {
+        Rank::ALL[7 - self as usize]
+    }
+
+    /// Returns the digit of the rank.
+    pub const fn to_char(self) -> char {
+        (b'1' + self as u8) as char
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl FromStr for Rank {
+    type Err = SquareParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[c @ b'1'..=b'8'] => Ok(Rank::ALL[(c - b'1') as usize]),
+            _ => Err(SquareParseError::BadRank),
+        }
+    }
+}
+
+/// Represents one of the 64 squares of the board. Squares are numbered from `0` (a1) to `63`
+/// (h8), rank by rank, matching the bit order of a [`Bitboard`].
+#[rustfmt::skip]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Square {
+    A1, B1, C1, D1, E1, F1, G1, H1,
+    A2, B2, C2, D2, E2, F2, G2, H2,
+    A3, B3, C3, D3, E3, F3, G3, H3,
+    A4, B4, C4, D4, E4, F4, G4, H4,
+    A5, B5, C5, D5, E5, F5, G5, H5,
+    A6, B6, C6, D6, E6, F6, G6, H6,
+    A7, B7, C7, D7, E7, F7, G7, H7,
+    A8, B8, C8, D8, E8, F8, G8, H8,
+}
+
+impl Square {
+    /// Every square, ordered by index.
+    #[rustfmt::skip]
+    pub const ALL: [Square; 64] = {
+        use Square::*;
+
+        [
+            A1, B1, C1, D1, E1, F1, G1, H1,
+            A2, B2, C2, D2, E2, F2, G2, H2,
+            A3, B3, C3, D3, E3, F3, G3, H3,
+            A4, B4, C4, D4, E4, F4, G4, H4,
+            A5, B5, C5, D5, E5, F5, G5, H5,
+            A6, B6, C6, D6, E6, F6, G6, H6,
+            A7, B7, C7, D7, E7, F7, G7, H7,
+            A8, B8, C8, D8, E8, F8, G8, H8,
+        ]
+    };
+
+    /// Returns the square on the given file and rank.
+    pub const fn new(file: File, rank: Rank) -> Self {
+        Square::ALL[rank as usize * 8 + file as usize]
+    }
+
+    /// Returns the square with the given index, or [`None`] if the index is not in the range
+    /// `0..64`.
+    pub fn from_index(index: u8) -> Option<Self> {
+        Square::ALL.get(index as usize).copied()
+    }
+
+    /// Returns the index of the square, from `0` (a1) to `63` (h8).
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the file of the square.
+    pub const fn file(self) -> File {
+        File::ALL[self as usize % 8]
+    }
+
+    /// Returns the rank of the square.
+    pub const fn rank(self) -> Rank {
+        Rank::ALL[self as usize / 8]
+    }
+
+    /// Returns a bitboard with only this square set.
+    pub const fn bitboard(self) -> Bitboard {
+        Bitboard(1 << self as u8)
+    }
+
+    /// Returns the square the given number of files and ranks away, or [`None`] if it would be
+    /// off the board.
+    pub fn offset(self, files: i8, ranks: i8) -> Option<Self> {
+        let file = (self.file() as i8).checked_add(files)?;
+        let rank = (self.rank() as i8).checked_add(ranks)?;
+
+        if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+            return None;
+        }
+
+        Some(Square::ALL[(rank * 8 + file) as usize])
+    }
+
+    /// Returns the square mirrored across the middle rank (a1 ↔ a8).
+    pub const fn flip_rank(self) -> Self {
+        Square::ALL[self as usize ^ 56]
+    }
+
+    /// Returns the square mirrored across the middle file (a1 ↔ h1).
+    pub const fn flip_file(self) -> Self {
+        Square::ALL[self as usize ^ 7]
+    }
+
+    /// Returns the square reflected along the a1-h8 diagonal (b1 ↔ a2).
+    pub const fn transpose(self) -> Self {
+        Square::ALL[(self as usize % 8) * 8 + self as usize / 8]
+    }
+
+    /// Returns the number of king moves between two squares.
+    pub fn distance(self, other: Square) -> u8 {
+        let rank_distance = (self.rank() as u8).abs_diff(other.rank() as u8);
+        let file_distance = (self.file() as u8).abs_diff(other.file() as u8);
+
+        rank_distance.max(file_distance)
+    }
+}
+
+impl TryFrom<u8> for Square {
+    type Error = SquareParseError;
+
+    fn try_from(index: u8) -> Result<Self, Self::Error> {
+        Square::from_index(index).ok_or(SquareParseError::BadSquare)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
+
+impl FromStr for Square {
+    type Err = SquareParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 2 || !s.is_char_boundary(1) {
+            return Err(SquareParseError::BadSquare);
+        }
+
+        let (file, rank) = s.split_at(1);
+
+        Ok(Square::new(file.parse()?, rank.parse()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_from_str() {
+        assert_eq!("lmao".parse::<Square>(), Err(SquareParseError::BadSquare));
+        assert_eq!("i3".parse::<Square>(), Err(SquareParseError::BadFile));
+        assert_eq!("a9".parse::<Square>(), Err(SquareParseError::BadRank));
+        assert_eq!("h3".parse(), Ok(Square::H3));
+        assert_eq!("a1".parse(), Ok(Square::A1));
+        assert_eq!("h8".parse(), Ok(Square::H8));
+    }
+
+    #[test]
+    fn test_square_display() {
+        assert_eq!(Square::H3.to_string(), "h3");
+        assert_eq!(Square::A1.to_string(), "a1");
+        assert_eq!(Square::H8.to_string(), "h8");
+    }
+
+    #[test]
+    fn test_square_index() {
+        assert_eq!(Square::from_index(69), None);
+        assert_eq!(Square::from_index(23), Some(Square::H3));
+        assert_eq!(Square::H3.index(), 23);
+        assert_eq!(Square::new(File::E, Rank::Fourth), Square::E4);
+        assert_eq!(Square::E4.file(), File::E);
+        assert_eq!(Square::E4.rank(), Rank::Fourth);
+
+        for (i, square) in Square::ALL.into_iter().enumerate() {
+            assert_eq!(square.index(), i);
+        }
+    }
+
+    #[test]
+    fn test_square_geometry() {
+        assert_eq!(Square::E4.offset(1, 2), Some(Square::F6));
+        assert_eq!(Square::H4.offset(1, 0), None);
+        assert_eq!(Square::A1.offset(0, -1), None);
+        assert_eq!(Square::B1.flip_rank(), Square::B8);
+        assert_eq!(Square::B1.flip_file(), Square::G1);
+        assert_eq!(Square::B1.transpose(), Square::A2);
+        assert_eq!(Square::A1.distance(Square::H8), 7);
+        assert_eq!(Square::E4.distance(Square::F6), 2);
+    }
+
+    #[test]
+    fn test_file_rank() {
+        assert_eq!(File::C.to_string(), "c");
+        assert_eq!(Rank::Third.to_string(), "3");
+        assert_eq!("c".parse(), Ok(File::C));
+        assert_eq!("3".parse(), Ok(Rank::Third));
+        assert_eq!(File::A.flip(), File::H);
+        assert_eq!(Rank::Second.flip(), Rank::Seventh);
+        assert_eq!(File::B.bitboard().count(), 8);
+        assert!(Rank::Eighth.bitboard().contains(Square::D8));
+    }
+}
@@ -0,0 +1,1274 @@
+/// Constants used when generating, storing and probing tablebases.
+pub mod tablebase_constants {
+    /// The maximum number of men (kings included) a generated table may contain.
+    pub const MAX_MEN: usize = 4;
+
+    /// The magic bytes at the start of every table file.
+    pub const TABLE_MAGIC: [u8; 4] = *b"PTB1";
+
+    /// The file extension used by [`Tablebase::save()`](super::Tablebase::save()).
+    pub const TABLE_EXTENSION: &str = "ptb";
+
+    /// Entry value of a drawn position.
+    pub const DRAW: u8 = 0;
+
+    /// Entry value of an illegal or non-canonical position.
+    pub const INVALID: u8 = 255;
+
+    /// The longest distance to mate (in plies) a table entry can encode.
+    pub const MAX_PLIES: u8 = 252;
+}
+
+use crate::bitboards::{BitBoards, Bitboard};
+use crate::boardstate::BoardState;
+use crate::movegen::MoveGenerator;
+use crate::{Color, Piece, Rank, Square};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use tablebase_constants::*;
+
+/// Entry value of a position which has not been resolved yet during generation.
+const UNKNOWN: u8 = 254;
+
+/// Every piece type, ordered by its bitboard index.
+const PIECES: [Piece; 6] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+/// Squares the [white](Color::White) king is reduced to in tables without pawns (the a1-d1-d4
+/// triangle).
+#[rustfmt::skip]
+const PAWNLESS_KING_SQUARES: [Square; 10] = {
+    use Square::*;
+
+    [
+        A1, B1, C1, D1,
+            B2, C2, D2,
+                C3, D3,
+                    D4,
+    ]
+};
+
+/// Squares the [white](Color::White) king is reduced to in tables with pawns (files a-d).
+#[rustfmt::skip]
+const PAWN_KING_SQUARES: [Square; 32] = {
+    use Square::*;
+
+    [
+        A1, B1, C1, D1,
+        A2, B2, C2, D2,
+        A3, B3, C3, D3,
+        A4, B4, C4, D4,
+        A5, B5, C5, D5,
+        A6, B6, C6, D6,
+        A7, B7, C7, D7,
+        A8, B8, C8, D8,
+    ]
+};
+
+/// Non-king piece types in the order they appear in a material signature.
+const SIGNATURE_ORDER: [Piece; 5] = [
+    Piece::Queen,
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Knight,
+    Piece::Pawn,
+];
+
+/// Error variants when generating, loading or probing tablebases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TablebaseError {
+    BadMaterial,
+    TooManyMen,
+    MissingTable,
+    PliesOverflow,
+    BadFile,
+    Io(io::ErrorKind),
+}
+
+impl From<io::Error> for TablebaseError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err.kind())
+    }
+}
+
+/// The distance to mate of a position, in plies, from the perspective of the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dtm {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+impl Dtm {
+    /// Decodes a table entry. Returns [`None`] for [`INVALID`] entries.
+    fn from_entry(entry: u8) -> Option<Self> {
+        match entry {
+            DRAW => Some(Dtm::Draw),
+            INVALID | UNKNOWN => None,
+            _ if (entry - 1) % 2 == 1 => Some(Dtm::Win(entry - 1)),
+            _ => Some(Dtm::Loss(entry - 1)),
+        }
+    }
+
+    /// Returns the distance to mate in full moves, or [`None`] if the position is drawn.
+    pub fn moves(&self) -> Option<u8> {
+        match self {
+            Dtm::Win(plies) => Some(plies.div_ceil(2)),
+            Dtm::Loss(plies) => Some(plies / 2),
+            Dtm::Draw => None,
+        }
+    }
+}
+
+/// The material signature of a position, such as `KRvKP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Material {
+    counts: [[u8; 6]; 2],
+}
+
+impl Material {
+    /// Returns the [`Material`] present in the given bitboards.
+    pub fn from_bitboards(position: &BitBoards) -> Self {
+        let mut counts = [[0; 6]; 2];
+
+        for color in [Color::White, Color::Black] {
+            for piece in PIECES {
+                counts[color.to_index()][piece.to_index()] =
+                    position.board(color, piece).count() as u8;
+            }
+        }
+
+        Self { counts }
+    }
+
+    /// Returns the number of pieces of the given color and type.
+    pub fn count(&self, color: Color, piece: Piece) -> u8 {
+        self.counts[color.to_index()][piece.to_index()]
+    }
+
+    /// Returns the difference between the number of white and black pieces of the given
+    /// type: positive when white has more.
+    pub fn imbalance(&self, piece: Piece) -> i32 {
+        self.count(Color::White, piece) as i32 - self.count(Color::Black, piece) as i32
+    }
+
+    /// Returns the total number of men, kings included.
+    pub fn men(&self) -> usize {
+        self.counts
+            .iter()
+            .flatten()
+            .map(|&count| count as usize)
+            .sum()
+    }
+
+    /// Returns `true` if either side has a [pawn](Piece::Pawn), and `false` otherwise.
+    pub fn has_pawns(&self) -> bool {
+        self.count(Color::White, Piece::Pawn) + self.count(Color::Black, Piece::Pawn) != 0
+    }
+
+    /// Returns `true` if only the two kings remain, and `false` otherwise.
+    pub fn is_bare_kings(&self) -> bool {
+        self.men() == 2
+    }
+
+    /// Returns the [`Material`] with the colors swapped.
+    pub fn flipped(&self) -> Self {
+        Self {
+            counts: [self.counts[1], self.counts[0]],
+        }
+    }
+
+    /// Returns `true` if [white](Color::White) holds the stronger half of the material, which
+    /// is the orientation tables are generated and stored in.
+    pub fn is_canonical(&self) -> bool {
+        let strength = |color: Color| SIGNATURE_ORDER.map(|piece| self.count(color, piece));
+
+        strength(Color::White) >= strength(Color::Black)
+    }
+
+    /// Returns this [`Material`] in the orientation tables are stored in.
+    pub fn canonical(&self) -> Self {
+        if self.is_canonical() {
+            *self
+        } else {
+            self.flipped()
+        }
+    }
+
+    fn with(&self, color: Color, piece: Piece, delta: i8) -> Option<Self> {
+        let mut counts = self.counts;
+        let count = &mut counts[color.to_index()][piece.to_index()];
+
+        *count = count.checked_add_signed(delta)?;
+
+        Some(Self { counts })
+    }
+
+    /// Returns every material signature reachable from this one with a single capture
+    /// and/or promotion.
+    fn successors(&self) -> Vec<Self> {
+        let mut successors = Vec::new();
+
+        for color in [Color::White, Color::Black] {
+            let enemy = color.enemy();
+            let captures: Vec<Piece> = SIGNATURE_ORDER
+                .into_iter()
+                .filter(|&piece| self.count(enemy, piece) != 0)
+                .collect();
+
+            for &captured in captures.iter() {
+                successors.extend(self.with(enemy, captured, -1));
+            }
+
+            if self.count(color, Piece::Pawn) == 0 {
+                continue;
+            }
+
+            for promotion in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+                let Some(promoted) = self
+                    .with(color, Piece::Pawn, -1)
+                    .and_then(|material| material.with(color, promotion, 1))
+                else {
+                    continue;
+                };
+
+                successors.push(promoted);
+
+                for &captured in captures.iter() {
+                    successors.extend(promoted.with(enemy, captured, -1));
+                }
+            }
+        }
+
+        successors
+    }
+}
+
+impl FromStr for Material {
+    type Err = TablebaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((white, black)) = s.split_once('v') else {
+            return Err(TablebaseError::BadMaterial);
+        };
+
+        let mut counts = [[0; 6]; 2];
+
+        for (color, side) in [(Color::White, white), (Color::Black, black)] {
+            let mut chars = side.chars();
+
+            if chars.next() != Some('K') {
+                return Err(TablebaseError::BadMaterial);
+            }
+
+            counts[color.to_index()][Piece::King.to_index()] = 1;
+
+            for c in chars {
+                let piece = match c {
+                    'Q' => Piece::Queen,
+                    'R' => Piece::Rook,
+                    'B' => Piece::Bishop,
+                    'N' => Piece::Knight,
+                    'P' => Piece::Pawn,
+                    _ => return Err(TablebaseError::BadMaterial),
+                };
+
+                counts[color.to_index()][piece.to_index()] += 1;
+            }
+        }
+
+        Ok(Self { counts })
+    }
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for color in [Color::White, Color::Black] {
+            if color == Color::Black {
+                write!(f, "v")?;
+            }
+
+            write!(f, "K")?;
+
+            for piece in SIGNATURE_ORDER {
+                let c = match piece {
+                    Piece::Queen => 'Q',
+                    Piece::Rook => 'R',
+                    Piece::Bishop => 'B',
+                    Piece::Knight => 'N',
+                    _ => 'P',
+                };
+
+                for _ in 0..self.count(color, piece) {
+                    write!(f, "{c}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A compact piece list used while generating tables, as bitboards are too expensive to
+/// transform and index millions of times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Men {
+    men: [(Color, Piece, Square); MAX_MEN],
+    len: usize,
+    side_to_move: Color,
+}
+
+impl Men {
+    fn new(side_to_move: Color) -> Self {
+        Self {
+            men: [(Color::White, Piece::King, Square::A1); MAX_MEN],
+            len: 0,
+            side_to_move,
+        }
+    }
+
+    fn from_bitboards(position: &BitBoards, side_to_move: Color) -> Option<Self> {
+        let mut men = Men::new(side_to_move);
+
+        for color in [Color::White, Color::Black] {
+            for piece in PIECES {
+                for square in position.board(color, piece) {
+                    if men.len == MAX_MEN {
+                        return None;
+                    }
+
+                    men.push(color, piece, square);
+                }
+            }
+        }
+
+        Some(men)
+    }
+
+    fn push(&mut self, color: Color, piece: Piece, square: Square) {
+        self.men[self.len] = (color, piece, square);
+        self.len += 1;
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &(Color, Piece, Square)> {
+        self.men[..self.len].iter()
+    }
+
+    fn material(&self) -> Material {
+        let mut counts = [[0; 6]; 2];
+
+        for &(color, piece, _) in self.iter() {
+            counts[color.to_index()][piece.to_index()] += 1;
+        }
+
+        Material { counts }
+    }
+
+    fn occupancy(&self) -> Bitboard {
+        self.iter().map(|&(_, _, square)| square).collect()
+    }
+
+    fn color_occupancy(&self, color: Color) -> Bitboard {
+        self.iter()
+            .filter(|&&(c, _, _)| c == color)
+            .map(|&(_, _, square)| square)
+            .collect()
+    }
+
+    fn king_square(&self, color: Color) -> Option<Square> {
+        self.iter()
+            .find(|&&(c, piece, _)| c == color && piece == Piece::King)
+            .map(|&(_, _, square)| square)
+    }
+
+    /// Returns the same position with the colors swapped and the board mirrored vertically.
+    fn flipped(&self) -> Self {
+        let mut flipped = *self;
+
+        for man in flipped.men[..flipped.len].iter_mut() {
+            *man = (man.0.enemy(), man.1, man.2.flip_rank());
+        }
+
+        flipped.side_to_move = self.side_to_move.enemy();
+
+        flipped
+    }
+
+    fn attacks(
+        move_gen: &MoveGenerator,
+        color: Color,
+        piece: Piece,
+        square: Square,
+        open: Bitboard,
+    ) -> Bitboard {
+        match (color, piece) {
+            (Color::White, Piece::Pawn) => move_gen.get_white_pawn_attacks(square),
+            (Color::Black, Piece::Pawn) => move_gen.get_black_pawn_attacks(square),
+            (_, Piece::Knight) => move_gen.get_knight_attacks(square),
+            (_, Piece::King) => move_gen.get_king_attacks(square),
+            (_, Piece::Rook) => MoveGenerator::get_rook_attacks(square, open),
+            (_, Piece::Bishop) => MoveGenerator::get_bishop_attacks(square, open),
+            (_, Piece::Queen) => MoveGenerator::get_queen_attacks(square, open),
+        }
+    }
+
+    fn is_attacked(&self, move_gen: &MoveGenerator, square: Square, by: Color) -> bool {
+        let open = !self.occupancy();
+
+        self.iter().any(|&(color, piece, from)| {
+            color == by
+                && Men::could_attack(piece, from, square)
+                && Men::attacks(move_gen, color, piece, from, open).contains(square)
+        })
+    }
+
+    /// Cheap pre-check that skips the sliding attack fills when the squares don't share a line.
+    fn could_attack(piece: Piece, from: Square, square: Square) -> bool {
+        let rank_distance = (from.rank() as u8).abs_diff(square.rank() as u8);
+        let file_distance = (from.file() as u8).abs_diff(square.file() as u8);
+        let straight = rank_distance == 0 || file_distance == 0;
+        let diagonal = rank_distance == file_distance;
+
+        match piece {
+            Piece::Rook => straight,
+            Piece::Bishop => diagonal,
+            Piece::Queen => straight || diagonal,
+            _ => true,
+        }
+    }
+
+    fn in_check(&self, move_gen: &MoveGenerator, color: Color) -> bool {
+        self.king_square(color)
+            .is_some_and(|square| self.is_attacked(move_gen, square, color.enemy()))
+    }
+
+    /// Returns `true` if the position could arise in a game: no overlapping men, no
+    /// [pawns](Piece::Pawn) on the first or eighth rank, and the side not to move not in check.
+    fn is_legal(&self, move_gen: &MoveGenerator) -> bool {
+        if self.occupancy().count() as usize != self.len {
+            return false;
+        }
+
+        if self
+            .iter()
+            .any(|&(_, piece, square)| piece == Piece::Pawn && is_back_rank(square))
+        {
+            return false;
+        }
+
+        !self.in_check(move_gen, self.side_to_move.enemy())
+    }
+
+    /// Returns every position reachable with a legal move.
+    fn children(&self, move_gen: &MoveGenerator) -> Vec<Men> {
+        let mut children = Vec::new();
+        let occupancy = self.occupancy();
+        let friendly = self.color_occupancy(self.side_to_move);
+        let enemy = occupancy & !friendly;
+
+        for (i, &(color, piece, from)) in self.iter().enumerate() {
+            if color != self.side_to_move {
+                continue;
+            }
+
+            let targets = match (color, piece) {
+                (Color::White, Piece::Pawn) => {
+                    let mut pushes = move_gen.get_white_pawn_moves(from) & !occupancy;
+
+                    if from
+                        .offset(0, 1)
+                        .is_some_and(|square| occupancy.contains(square))
+                    {
+                        pushes = Bitboard::EMPTY;
+                    }
+
+                    pushes | move_gen.get_white_pawn_attacks(from) & enemy
+                }
+                (Color::Black, Piece::Pawn) => {
+                    let mut pushes = move_gen.get_black_pawn_moves(from) & !occupancy;
+
+                    if from
+                        .offset(0, -1)
+                        .is_some_and(|square| occupancy.contains(square))
+                    {
+                        pushes = Bitboard::EMPTY;
+                    }
+
+                    pushes | move_gen.get_black_pawn_attacks(from) & enemy
+                }
+                _ => Men::attacks(move_gen, color, piece, from, !occupancy) & !friendly,
+            };
+
+            for target in targets {
+                let mut child = Men::new(self.side_to_move.enemy());
+
+                for (j, &man) in self.iter().enumerate() {
+                    if j != i && man.2 != target {
+                        child.push(man.0, man.1, man.2);
+                    }
+                }
+
+                let promotes = piece == Piece::Pawn && is_back_rank(target);
+                let promotions: &[Piece] = if promotes {
+                    &[Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight]
+                } else {
+                    &[piece]
+                };
+
+                for &promotion in promotions {
+                    let mut promoted = child;
+                    promoted.push(color, promotion, target);
+
+                    if !promoted.in_check(move_gen, color) {
+                        children.push(promoted);
+                    }
+                }
+            }
+        }
+
+        children
+    }
+
+    /// Returns every legal position from which the side not to move could have reached this
+    /// one with a non-capturing, non-promoting move.
+    fn parents(&self, move_gen: &MoveGenerator) -> Vec<Men> {
+        let mut parents = Vec::new();
+        let occupancy = self.occupancy();
+        let mover = self.side_to_move.enemy();
+
+        for (i, &(color, piece, to)) in self.iter().enumerate() {
+            if color != mover {
+                continue;
+            }
+
+            let origins = match (color, piece) {
+                (Color::White, Piece::Pawn) => pawn_origins(to, -1, Rank::Fourth, occupancy),
+                (Color::Black, Piece::Pawn) => pawn_origins(to, 1, Rank::Fifth, occupancy),
+                _ => Men::attacks(move_gen, color, piece, to, !occupancy) & !occupancy,
+            };
+
+            for origin in origins {
+                let mut parent = *self;
+                parent.men[i].2 = origin;
+                parent.side_to_move = mover;
+
+                if !parent.in_check(move_gen, self.side_to_move) {
+                    parents.push(parent);
+                }
+            }
+        }
+
+        parents
+    }
+}
+
+/// Maps a square through one of the eight symmetries of the board. Bit `0` mirrors the
+/// files, bit `1` mirrors the ranks and bit `2` reflects along the a1-h8 diagonal.
+fn transform(square: Square, symmetry: usize) -> Square {
+    let mut square = square;
+
+    if symmetry & 4 != 0 {
+        square = square.transpose();
+    }
+
+    if symmetry & 2 != 0 {
+        square = square.flip_rank();
+    }
+
+    if symmetry & 1 != 0 {
+        square = square.flip_file();
+    }
+
+    square
+}
+
+/// Returns `true` if the square is on the first or eighth rank.
+fn is_back_rank(square: Square) -> bool {
+    matches!(square.rank(), Rank::First | Rank::Eighth)
+}
+
+/// Returns the squares a [pawn](Piece::Pawn) now on `to` could have been pushed from, given the
+/// direction it came from (`-1` for [white](Color::White), `1` for [black](Color::Black)) and
+/// the rank a double push lands on.
+fn pawn_origins(
+    to: Square,
+    direction: i8,
+    double_push_rank: Rank,
+    occupancy: Bitboard,
+) -> Bitboard {
+    let mut origins = Bitboard::EMPTY;
+
+    if let Some(single) = to.offset(0, direction)
+        && !is_back_rank(single)
+        && !occupancy.contains(single)
+    {
+        origins = origins.with(single);
+
+        if to.rank() == double_push_rank
+            && let Some(double) = single.offset(0, direction)
+            && !occupancy.contains(double)
+        {
+            origins = origins.with(double);
+        }
+    }
+
+    origins
+}
+
+/// Queues a position to be resolved once the retrograde analysis reaches the given depth.
+fn schedule(buckets: &mut [Vec<usize>], plies: usize, index: usize) -> Result<(), TablebaseError> {
+    if plies > MAX_PLIES as usize {
+        return Err(TablebaseError::PliesOverflow);
+    }
+
+    buckets[plies].push(index);
+
+    Ok(())
+}
+
+/// A distance-to-mate table for a single material signature.
+///
+/// Positions are indexed by the white king's square (reduced by symmetry to the a1-d1-d4
+/// triangle, or to files a-d when pawns are present), then every other man's square and
+/// finally the side to move. Each entry takes one byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    material: Material,
+    layout: [(Color, Piece); MAX_MEN],
+    entries: Vec<u8>,
+}
+
+impl Table {
+    fn new(material: Material) -> Self {
+        let mut layout = [
+            (Color::White, Piece::King),
+            (Color::Black, Piece::King),
+            (Color::White, Piece::King),
+            (Color::White, Piece::King),
+        ];
+        let mut slot = 2;
+
+        for color in [Color::White, Color::Black] {
+            for piece in SIGNATURE_ORDER {
+                for _ in 0..material.count(color, piece) {
+                    layout[slot] = (color, piece);
+                    slot += 1;
+                }
+            }
+        }
+
+        let mut table = Self {
+            material,
+            layout,
+            entries: Vec::new(),
+        };
+
+        table.entries = vec![UNKNOWN; table.size()];
+
+        table
+    }
+
+    /// Returns the material signature of this [`Table`].
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    /// Returns the number of entries in this [`Table`].
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if this [`Table`] has no entries, and `false` otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the longest distance to mate stored in this [`Table`], in plies.
+    pub fn max_plies(&self) -> u8 {
+        self.entries
+            .iter()
+            .filter_map(|&entry| match Dtm::from_entry(entry)? {
+                Dtm::Win(plies) | Dtm::Loss(plies) => Some(plies),
+                Dtm::Draw => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn men(&self) -> usize {
+        self.material.men()
+    }
+
+    fn symmetries(&self) -> usize {
+        if self.material.has_pawns() { 2 } else { 8 }
+    }
+
+    fn king_squares(&self) -> &'static [Square] {
+        if self.material.has_pawns() {
+            &PAWN_KING_SQUARES
+        } else {
+            &PAWNLESS_KING_SQUARES
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.king_squares().len() * 64usize.pow(self.men() as u32 - 1) * 2
+    }
+
+    /// Returns the canonical index of a position with this table's material, or [`None`] if
+    /// the material does not match.
+    fn index_of(&self, men: &Men) -> Option<usize> {
+        if men.len != self.men() {
+            return None;
+        }
+
+        // The white king is the most significant part of the key, so only the symmetries
+        // that map it to its lowest square need to be compared in full.
+        let king = men.king_square(Color::White)?;
+        let lowest_king = (0..self.symmetries())
+            .map(|symmetry| transform(king, symmetry))
+            .min()?;
+        let mut best = u64::MAX;
+
+        for symmetry in 0..self.symmetries() {
+            if transform(king, symmetry) != lowest_king {
+                continue;
+            }
+
+            let mut used = [false; MAX_MEN];
+            let mut key = 0u64;
+
+            for &(color, piece) in self.layout[..self.men()].iter() {
+                let mut pick: Option<(usize, Square)> = None;
+
+                for (i, &(c, p, square)) in men.iter().enumerate() {
+                    if used[i] || c != color || p != piece {
+                        continue;
+                    }
+
+                    let square = transform(square, symmetry);
+
+                    if pick.is_none_or(|(_, picked)| square < picked) {
+                        pick = Some((i, square));
+                    }
+                }
+
+                let (i, square) = pick?;
+                used[i] = true;
+                key = key * 64 + square as u64;
+            }
+
+            best = best.min(key);
+        }
+
+        let rest_size = 64u64.pow(self.men() as u32 - 1);
+        let king = self
+            .king_squares()
+            .iter()
+            .position(|&square| square as u64 == best / rest_size)?;
+        let stm = match men.side_to_move {
+            Color::White => 0,
+            Color::Black => 1,
+        };
+
+        Some(((king as u64 * rest_size + best % rest_size) * 2 + stm) as usize)
+    }
+
+    fn decode(&self, index: usize) -> Men {
+        let side_to_move = if index.is_multiple_of(2) {
+            Color::White
+        } else {
+            Color::Black
+        };
+
+        let mut men = Men::new(side_to_move);
+        let mut rest = index / 2;
+        let mut squares = [Square::A1; MAX_MEN];
+
+        for slot in (1..self.men()).rev() {
+            squares[slot] = Square::ALL[rest % 64];
+            rest /= 64;
+        }
+
+        squares[0] = self.king_squares()[rest];
+
+        for (slot, &(color, piece)) in self.layout[..self.men()].iter().enumerate() {
+            men.push(color, piece, squares[slot]);
+        }
+
+        men
+    }
+
+    fn probe_men(&self, men: &Men) -> Option<Dtm> {
+        let index = self.index_of(men)?;
+
+        Dtm::from_entry(*self.entries.get(index)?)
+    }
+
+    /// Writes this [`Table`] in the on-disk format: the magic bytes, the twelve piece counts,
+    /// the entry count as a little-endian [`u32`], then one byte per entry.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&TABLE_MAGIC)?;
+
+        for &count in self.material.counts.iter().flatten() {
+            writer.write_all(&[count])?;
+        }
+
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        writer.write_all(&self.entries)
+    }
+
+    /// Reads a [`Table`] previously written with [`write_to()`](Self::write_to()).
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, TablebaseError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+
+        if magic != TABLE_MAGIC {
+            return Err(TablebaseError::BadFile);
+        }
+
+        let mut raw_counts = [0; 12];
+        reader.read_exact(&mut raw_counts)?;
+
+        let mut counts = [[0; 6]; 2];
+
+        for (i, &count) in raw_counts.iter().enumerate() {
+            counts[i / 6][i % 6] = count;
+        }
+
+        let material = Material { counts };
+
+        if material.men() > MAX_MEN
+            || material.count(Color::White, Piece::King) != 1
+            || material.count(Color::Black, Piece::King) != 1
+        {
+            return Err(TablebaseError::BadFile);
+        }
+
+        let mut len = [0; 4];
+        reader.read_exact(&mut len)?;
+
+        let mut table = Table::new(material);
+
+        if u32::from_le_bytes(len) as usize != table.size() {
+            return Err(TablebaseError::BadFile);
+        }
+
+        reader.read_exact(&mut table.entries)?;
+
+        Ok(table)
+    }
+}
+
+/// A collection of generated or loaded [`Table`]s, keyed by [`Material`].
+#[derive(Debug, Clone)]
+pub struct Tablebase<'a> {
+    tables: HashMap<Material, Table>,
+    move_gen: &'a MoveGenerator,
+}
+
+impl<'a> Tablebase<'a> {
+    pub fn new(move_gen: &'a MoveGenerator) -> Self {
+        Self {
+            tables: HashMap::new(),
+            move_gen,
+        }
+    }
+}
+
+impl Tablebase<'_> {
+    /// Returns the [`Table`] for the given material (in either orientation), if present.
+    pub fn get(&self, material: Material) -> Option<&Table> {
+        self.tables.get(&material.canonical())
+    }
+
+    /// Adds a [`Table`] to the collection, replacing any table with the same material.
+    pub fn insert(&mut self, table: Table) {
+        self.tables.insert(table.material, table);
+    }
+
+    /// Returns an iterator over all [`Table`]s in the collection.
+    pub fn tables(&self) -> impl Iterator<Item = &Table> {
+        self.tables.values()
+    }
+
+    /// Generates the table for the given material, along with every table it converts into
+    /// through captures and promotions.
+    pub fn generate(&mut self, material: Material) -> Result<(), TablebaseError> {
+        let material = material.canonical();
+
+        if material.count(Color::White, Piece::King) != 1
+            || material.count(Color::Black, Piece::King) != 1
+        {
+            return Err(TablebaseError::BadMaterial);
+        }
+
+        if material.men() > MAX_MEN {
+            return Err(TablebaseError::TooManyMen);
+        }
+
+        if material.is_bare_kings() || self.tables.contains_key(&material) {
+            return Ok(());
+        }
+
+        for successor in material.successors() {
+            self.generate(successor)?;
+        }
+
+        let table = self.build(material)?;
+        self.insert(table);
+
+        Ok(())
+    }
+
+    /// Runs the retrograde analysis for a single material signature. Every successor table
+    /// must already be present.
+    fn build(&self, material: Material) -> Result<Table, TablebaseError> {
+        let mut table = Table::new(material);
+        let size = table.len();
+
+        let mut remaining = vec![0u8; size];
+        let mut can_avoid_loss = vec![false; size];
+        let mut worst_loss = vec![0u8; size];
+        let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); MAX_PLIES as usize + 1];
+
+        for index in 0..size {
+            let men = table.decode(index);
+
+            if !men.is_legal(self.move_gen) || table.index_of(&men) != Some(index) {
+                table.entries[index] = INVALID;
+                continue;
+            }
+
+            let children = men.children(self.move_gen);
+
+            if children.is_empty() {
+                if men.in_check(self.move_gen, men.side_to_move) {
+                    schedule(&mut buckets, 0, index)?;
+                } else {
+                    table.entries[index] = DRAW;
+                }
+
+                continue;
+            }
+
+            let mut internal = Vec::with_capacity(children.len());
+            let mut best_win: Option<u8> = None;
+
+            for child in children.iter() {
+                if child.material() == material {
+                    internal.extend(table.index_of(child));
+                    continue;
+                }
+
+                match self.probe_men(child).ok_or(TablebaseError::MissingTable)? {
+                    Dtm::Loss(plies) => {
+                        best_win = Some(best_win.map_or(plies + 1, |best| best.min(plies + 1)));
+                    }
+                    Dtm::Win(plies) => worst_loss[index] = worst_loss[index].max(plies + 1),
+                    Dtm::Draw => can_avoid_loss[index] = true,
+                }
+            }
+
+            internal.sort_unstable();
+            internal.dedup();
+            remaining[index] = internal.len() as u8;
+
+            if let Some(plies) = best_win {
+                can_avoid_loss[index] = true;
+                schedule(&mut buckets, plies as usize, index)?;
+            }
+
+            if remaining[index] == 0 && !can_avoid_loss[index] {
+                schedule(&mut buckets, worst_loss[index] as usize, index)?;
+            }
+        }
+
+        for plies in 0..buckets.len() {
+            let bucket = std::mem::take(&mut buckets[plies]);
+
+            for index in bucket {
+                if table.entries[index] != UNKNOWN {
+                    continue;
+                }
+
+                table.entries[index] = plies as u8 + 1;
+
+                let men = table.decode(index);
+                let mut parents: Vec<usize> = men
+                    .parents(self.move_gen)
+                    .iter()
+                    .filter_map(|parent| table.index_of(parent))
+                    .collect();
+
+                parents.sort_unstable();
+                parents.dedup();
+
+                for parent in parents {
+                    if table.entries[parent] != UNKNOWN {
+                        continue;
+                    }
+
+                    if plies.is_multiple_of(2) {
+                        schedule(&mut buckets, plies + 1, parent)?;
+                        continue;
+                    }
+
+                    remaining[parent] -= 1;
+
+                    if remaining[parent] == 0 && !can_avoid_loss[parent] {
+                        let loss = (plies + 1).max(worst_loss[parent] as usize);
+                        schedule(&mut buckets, loss, parent)?;
+                    }
+                }
+            }
+        }
+
+        for entry in table.entries.iter_mut() {
+            if *entry == UNKNOWN {
+                *entry = DRAW;
+            }
+        }
+
+        Ok(table)
+    }
+
+    fn probe_men(&self, men: &Men) -> Option<Dtm> {
+        let material = men.material();
+
+        if material.is_bare_kings() {
+            return Some(Dtm::Draw);
+        }
+
+        if material.is_canonical() {
+            self.tables.get(&material)?.probe_men(men)
+        } else {
+            self.tables
+                .get(&material.flipped())?
+                .probe_men(&men.flipped())
+        }
+    }
+
+    /// Returns the distance to mate of the given position, or [`None`] if no table covers it.
+    /// Positions with castling rights or an en passant square are never covered.
+    pub fn probe(&self, board_state: &BoardState) -> Option<Dtm> {
+        if board_state.en_passant_square.is_some()
+            || board_state.has_castling_rights_kingside_white()
+            || board_state.has_castling_rights_queenside_white()
+            || board_state.has_castling_rights_kingside_black()
+            || board_state.has_castling_rights_queenside_black()
+        {
+            return None;
+        }
+
+        let men = Men::from_bitboards(&board_state.position, board_state.side_to_move)?;
+
+        if !men.is_legal(self.move_gen) {
+            return None;
+        }
+
+        self.probe_men(&men)
+    }
+
+    /// Writes every table into the given directory, one `<material>.ptb` file per table.
+    pub fn save(&self, dir: &Path) -> Result<(), TablebaseError> {
+        fs::create_dir_all(dir)?;
+
+        for table in self.tables.values() {
+            let path = dir.join(format!("{}.{TABLE_EXTENSION}", table.material));
+            let mut writer = BufWriter::new(File::create(path)?);
+
+            table.write_to(&mut writer)?;
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads every `.ptb` table file in the given directory.
+    pub fn load(&mut self, dir: &Path) -> Result<(), TablebaseError> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path
+                .extension()
+                .is_none_or(|extension| extension != TABLE_EXTENSION)
+            {
+                continue;
+            }
+
+            let mut reader = BufReader::new(File::open(path)?);
+            let table = Table::read_from(&mut reader)?;
+
+            self.insert(table);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use std::sync::OnceLock;
+
+    /// Generating `KPvK` also generates `KQvK`, `KRvK`, `KBvK` and `KNvK`, so the tests share
+    /// a single tablebase.
+    fn tablebase() -> &'static Tablebase<'static> {
+        static MOVE_GEN: OnceLock<MoveGenerator> = OnceLock::new();
+        static TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+
+        TABLEBASE.get_or_init(|| {
+            let mut tablebase = Tablebase::new(MOVE_GEN.get_or_init(MoveGenerator::new));
+
+            tablebase
+                .generate("KPvK".parse().unwrap())
+                .expect("Generation failed");
+
+            tablebase
+        })
+    }
+
+    fn probe(tablebase: &Tablebase, fen: &str) -> Option<Dtm> {
+        tablebase.probe(&BoardState::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn test_material() {
+        let material: Material = "KRvKP".parse().unwrap();
+
+        assert_eq!(material.men(), 4);
+        assert_eq!(material.to_string(), "KRvKP");
+        assert!(material.is_canonical());
+        assert_eq!(material.flipped().to_string(), "KPvKR");
+        assert_eq!(material.flipped().canonical(), material);
+        assert_eq!("KRKP".parse::<Material>(), Err(TablebaseError::BadMaterial));
+    }
+
+    #[test]
+    fn test_kqk() {
+        let tablebase = tablebase();
+
+        assert_eq!(
+            probe(&tablebase, "k7/8/1K6/8/8/8/7Q/8 w - - 0 1"),
+            Some(Dtm::Win(1))
+        );
+        assert_eq!(
+            probe(&tablebase, "k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"),
+            Some(Dtm::Loss(0))
+        );
+        assert_eq!(
+            probe(&tablebase, "k7/8/1Q6/8/8/8/8/K7 b - - 0 1"),
+            Some(Dtm::Draw)
+        );
+        assert_eq!(
+            probe(&tablebase, "8/7q/8/8/8/8/8/K1k5 b - - 0 1"),
+            Some(Dtm::Win(1))
+        );
+
+        // The longest forced mate in KQvK is mate in 10.
+        assert_eq!(
+            tablebase.get("KQvK".parse().unwrap()).unwrap().max_plies(),
+            20
+        );
+    }
+
+    #[test]
+    fn test_krk() {
+        let tablebase = tablebase();
+
+        // The longest forced mate in KRvK is mate in 16.
+        assert_eq!(
+            tablebase.get("KRvK".parse().unwrap()).unwrap().max_plies(),
+            32
+        );
+    }
+
+    #[test]
+    fn test_kpk() {
+        let tablebase = tablebase();
+
+        assert!(matches!(
+            probe(&tablebase, "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"),
+            Some(Dtm::Win(_))
+        ));
+        assert!(matches!(
+            probe(&tablebase, "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"),
+            Some(Dtm::Loss(_))
+        ));
+        assert_eq!(
+            probe(&tablebase, "k7/8/K7/P7/8/8/8/8 w - - 0 1"),
+            Some(Dtm::Draw)
+        );
+        assert!(matches!(
+            probe(&tablebase, "8/8/8/8/3p4/3k4/8/3K4 b - - 0 1"),
+            Some(Dtm::Win(_))
+        ));
+        assert_eq!(probe(&tablebase, "4k3/8/4K3/4P3/8/8/8/8 w K - 0 1"), None);
+    }
+
+    #[test]
+    fn test_write_read() {
+        let table = tablebase().get("KRvK".parse().unwrap()).unwrap();
+
+        let mut bytes = Vec::new();
+        table.write_to(&mut bytes).unwrap();
+
+        assert_eq!(bytes.len(), 4 + 12 + 4 + table.len());
+        assert_eq!(Table::read_from(&mut bytes.as_slice()).as_ref(), Ok(table));
+
+        bytes[0] = b'X';
+        assert_eq!(
+            Table::read_from(&mut bytes.as_slice()),
+            Err(TablebaseError::BadFile)
+        );
+    }
+
+    #[test]
+    fn test_move_counts_match_game() {
+        let move_gen = MoveGenerator::new();
+        let table = Table::new("KRvK".parse().unwrap());
+
+        for index in (0..table.len()).step_by(97) {
+            let men = table.decode(index);
+
+            if !men.is_legal(&move_gen) {
+                continue;
+            }
+
+            let mut boards = [[Bitboard::EMPTY; 6]; 2];
+
+            for &(color, piece, square) in men.iter() {
+                boards[color.to_index()][piece.to_index()] |= square.bitboard();
+            }
+
+            let board_state = BoardState::new(
+                men.side_to_move,
+                BitBoards::new(boards).unwrap(),
+                1,
+                0,
+                0,
+                None,
+            );
+            let game = Game::from_fen(&board_state.to_fen(), &move_gen).unwrap();
+
+            assert_eq!(
+                men.children(&move_gen).len(),
+                game.enumerate_moves().len(),
+                "{}",
+                board_state.to_fen()
+            );
+        }
+    }
+}
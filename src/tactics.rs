@@ -0,0 +1,525 @@
+use crate::bitboards::Bitboard;
+use crate::boardstate::BoardState;
+use crate::eval::PIECE_VALUES;
+use crate::game::{Game, IllegalMoveError};
+use crate::movegen::MoveGenerator;
+use crate::moves::Move;
+use crate::{Color, Piece, Rank, Square};
+
+/// The value given to kings when comparing pieces. Any piece is worth less than a king.
+const KING_VALUE: i32 = 20_000;
+
+/// A tactical pattern found in a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motif {
+    /// A piece attacks two or more enemy pieces that are either undefended or worth more
+    /// than it.
+    Fork { attacker: Square, targets: Bitboard },
+    /// A piece can't move without exposing a more valuable piece behind it to a sliding
+    /// attacker. The pin is absolute when the piece behind is the king, which makes moving
+    /// the pinned piece off the line illegal.
+    Pin {
+        pinner: Square,
+        pinned: Square,
+        behind: Square,
+        absolute: bool,
+    },
+    /// A sliding piece attacks a piece that exposes a less valuable piece behind it when it
+    /// moves out of the way.
+    Skewer {
+        attacker: Square,
+        front: Square,
+        behind: Square,
+    },
+    /// Moving a piece out of the way of a friendly sliding piece uncovers an attack on an
+    /// enemy piece, or a check if the target is the king.
+    DiscoveredAttack {
+        blocker: Square,
+        attacker: Square,
+        target: Square,
+        check: bool,
+    },
+    /// An enemy piece is attacked, and either undefended or attacked by a less valuable
+    /// piece.
+    HangingPiece { square: Square, attackers: Bitboard },
+    /// An enemy piece is the only defender of two or more attacked pieces.
+    OverloadedDefender {
+        defender: Square,
+        defended: Bitboard,
+    },
+    /// The enemy king is on its back rank and can't step off it, while a rook or queen could
+    /// give mate along it.
+    BackRankWeakness { king: Square },
+}
+
+/// A [`Motif`], along with the side that can take advantage of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tactic {
+    pub by: Color,
+    pub motif: Motif,
+}
+
+/// Returns the tactical motifs present in the current position of the game, for both
+/// sides.
+pub fn find_motifs(game: &Game) -> Vec<Tactic> {
+    let board_state = game.get_board_state();
+    let move_gen = game.move_gen();
+
+    [Color::White, Color::Black]
+        .into_iter()
+        .flat_map(|by| {
+            motifs_for(board_state, move_gen, by)
+                .into_iter()
+                .map(move |motif| Tactic { by, motif })
+        })
+        .collect()
+}
+
+/// Returns the tactical motifs created by the move for the side playing it: the motifs in
+/// the resulting position that weren't there before, and the discovered attacks the move
+/// makes.
+///
+/// # Errors
+/// Returns an [`IllegalMoveError`] if the move isn't legal.
+pub fn find_move_motifs(game: &Game, mv: Move) -> Result<Vec<Tactic>, IllegalMoveError> {
+    let by = game.get_board_state().side_to_move;
+    let move_gen = game.move_gen();
+    let mv = game.resolve_move(mv)?;
+
+    let mut after = game.clone();
+    after.try_make_move(mv)?;
+
+    let before = motifs_for(game.get_board_state(), move_gen, by);
+    let mut motifs: Vec<Motif> = motifs_for(after.get_board_state(), move_gen, by)
+        .into_iter()
+        .filter(|motif| !before.contains(motif))
+        .collect();
+
+    motifs.extend(discovered_by_move(
+        game.get_board_state(),
+        after.get_board_state(),
+        move_gen,
+        mv,
+    ));
+
+    Ok(motifs
+        .into_iter()
+        .map(|motif| Tactic { by, motif })
+        .collect())
+}
+
+/// Returns the motifs the given side can take advantage of.
+fn motifs_for(board_state: &BoardState, move_gen: &MoveGenerator, by: Color) -> Vec<Motif> {
+    let mut motifs = Vec::new();
+
+    forks(board_state, move_gen, by, &mut motifs);
+    lines(board_state, move_gen, by, &mut motifs);
+    hanging_pieces(board_state, move_gen, by, &mut motifs);
+    overloaded_defenders(board_state, move_gen, by, &mut motifs);
+    back_rank_weakness(board_state, move_gen, by, &mut motifs);
+
+    motifs
+}
+
+fn value(piece: Piece) -> i32 {
+    match piece {
+        Piece::King => KING_VALUE,
+        _ => PIECE_VALUES[piece.to_index()],
+    }
+}
+
+/// Returns the value of the piece on the square, or `0` if it is empty.
+fn value_at(board_state: &BoardState, square: Square) -> i32 {
+    board_state
+        .position
+        .piece_at(square)
+        .map_or(0, |(_, piece)| value(piece))
+}
+
+/// Returns the squares attacked by the piece on the given square.
+fn piece_attacks(
+    move_gen: &MoveGenerator,
+    color: Color,
+    piece: Piece,
+    square: Square,
+    occupancy: Bitboard,
+) -> Bitboard {
+    match (color, piece) {
+        (Color::White, Piece::Pawn) => move_gen.get_white_pawn_attacks(square),
+        (Color::Black, Piece::Pawn) => move_gen.get_black_pawn_attacks(square),
+        (_, Piece::Knight) => move_gen.get_knight_attacks(square),
+        (_, Piece::King) => move_gen.get_king_attacks(square),
+        (_, Piece::Rook) => MoveGenerator::get_rook_attacks(square, !occupancy),
+        (_, Piece::Bishop) => MoveGenerator::get_bishop_attacks(square, !occupancy),
+        (_, Piece::Queen) => MoveGenerator::get_queen_attacks(square, !occupancy),
+    }
+}
+
+/// Returns `true` if the piece slides along the line joining the two squares.
+fn slides_between(piece: Piece, a: Square, b: Square) -> bool {
+    let straight = a.file() == b.file() || a.rank() == b.rank();
+
+    match piece {
+        Piece::Rook => straight,
+        Piece::Bishop => !straight,
+        Piece::Queen => true,
+        _ => false,
+    }
+}
+
+fn forks(board_state: &BoardState, move_gen: &MoveGenerator, by: Color, motifs: &mut Vec<Motif>) {
+    let occupancy = board_state.position.all_boards();
+    let enemy_pieces = board_state.position.pieces(by.enemy());
+
+    for attacker in board_state.position.pieces(by) {
+        let Some((_, piece)) = board_state.position.piece_at(attacker) else {
+            continue;
+        };
+
+        let targets: Bitboard = (piece_attacks(move_gen, by, piece, attacker, occupancy)
+            & enemy_pieces)
+            .into_iter()
+            .filter(|&target| {
+                value_at(board_state, target) > value(piece)
+                    || board_state
+                        .attackers_to(move_gen, target, by.enemy(), occupancy)
+                        .is_empty()
+            })
+            .collect();
+
+        if targets.count() >= 2 {
+            motifs.push(Motif::Fork { attacker, targets });
+        }
+    }
+}
+
+/// Finds the pins, skewers and potential discovered attacks along the lines of the given
+/// side's sliding pieces.
+fn lines(board_state: &BoardState, move_gen: &MoveGenerator, by: Color, motifs: &mut Vec<Motif>) {
+    let occupancy = board_state.position.all_boards();
+    let friendly_pieces = board_state.position.pieces(by);
+    let enemy_pieces = board_state.position.pieces(by.enemy());
+
+    let sliders = board_state.position.board(by, Piece::Rook)
+        | board_state.position.board(by, Piece::Bishop)
+        | board_state.position.board(by, Piece::Queen);
+
+    for attacker in sliders {
+        let Some((_, piece)) = board_state.position.piece_at(attacker) else {
+            continue;
+        };
+
+        for target in enemy_pieces {
+            if !slides_between(piece, attacker, target)
+                || move_gen.line(attacker, target).is_empty()
+            {
+                continue;
+            }
+
+            let blockers = move_gen.between(attacker, target) & occupancy;
+
+            let Some(blocker) = blockers.to_square() else {
+                continue;
+            };
+
+            let (front, behind) = (
+                value_at(board_state, blocker),
+                value_at(board_state, target),
+            );
+
+            if friendly_pieces.contains(blocker) {
+                motifs.push(Motif::DiscoveredAttack {
+                    blocker,
+                    attacker,
+                    target,
+                    check: behind == KING_VALUE,
+                });
+            } else if front < behind {
+                motifs.push(Motif::Pin {
+                    pinner: attacker,
+                    pinned: blocker,
+                    behind: target,
+                    absolute: behind == KING_VALUE,
+                });
+            } else if front > behind {
+                motifs.push(Motif::Skewer {
+                    attacker,
+                    front: blocker,
+                    behind: target,
+                });
+            }
+        }
+    }
+}
+
+fn hanging_pieces(
+    board_state: &BoardState,
+    move_gen: &MoveGenerator,
+    by: Color,
+    motifs: &mut Vec<Motif>,
+) {
+    let occupancy = board_state.position.all_boards();
+    let enemy_pieces = board_state.position.pieces(by.enemy())
+        & !board_state.position.board(by.enemy(), Piece::King);
+
+    for square in enemy_pieces {
+        let attackers = board_state.attackers_to(move_gen, square, by, occupancy);
+
+        if attackers.is_empty() {
+            continue;
+        }
+
+        let defended = !board_state
+            .attackers_to(move_gen, square, by.enemy(), occupancy)
+            .is_empty();
+        let cheapest_attacker = attackers
+            .into_iter()
+            .map(|attacker| value_at(board_state, attacker))
+            .min()
+            .unwrap_or(KING_VALUE);
+
+        if !defended || cheapest_attacker < value_at(board_state, square) {
+            motifs.push(Motif::HangingPiece { square, attackers });
+        }
+    }
+}
+
+fn overloaded_defenders(
+    board_state: &BoardState,
+    move_gen: &MoveGenerator,
+    by: Color,
+    motifs: &mut Vec<Motif>,
+) {
+    let occupancy = board_state.position.all_boards();
+    let mut sole_defenders: Vec<(Square, Bitboard)> = Vec::new();
+
+    for square in board_state.position.pieces(by.enemy()) {
+        if board_state
+            .attackers_to(move_gen, square, by, occupancy)
+            .is_empty()
+        {
+            continue;
+        }
+
+        let defenders = board_state.attackers_to(move_gen, square, by.enemy(), occupancy);
+
+        let Some(defender) = (defenders.count() == 1)
+            .then(|| defenders.to_square())
+            .flatten()
+        else {
+            continue;
+        };
+
+        match sole_defenders
+            .iter_mut()
+            .find(|(sole, _)| *sole == defender)
+        {
+            Some((_, defended)) => *defended |= square.bitboard(),
+            None => sole_defenders.push((defender, square.bitboard())),
+        }
+    }
+
+    motifs.extend(
+        sole_defenders
+            .into_iter()
+            .filter(|(_, defended)| defended.count() >= 2)
+            .map(|(defender, defended)| Motif::OverloadedDefender { defender, defended }),
+    );
+}
+
+fn back_rank_weakness(
+    board_state: &BoardState,
+    move_gen: &MoveGenerator,
+    by: Color,
+    motifs: &mut Vec<Motif>,
+) {
+    let enemy = by.enemy();
+    let heavy_pieces =
+        board_state.position.board(by, Piece::Rook) | board_state.position.board(by, Piece::Queen);
+
+    let Some(king) = board_state.position.board(enemy, Piece::King).to_square() else {
+        return;
+    };
+
+    let (back_rank, forward) = match enemy {
+        Color::White => (Rank::First, 1),
+        Color::Black => (Rank::Eighth, -1),
+    };
+
+    if heavy_pieces.is_empty() || king.rank() != back_rank {
+        return;
+    }
+
+    // The squares the king could step to off its back rank.
+    let escape_squares: Bitboard = [-1, 0, 1]
+        .into_iter()
+        .filter_map(|files| king.offset(files, forward))
+        .collect();
+
+    let blocked = escape_squares.into_iter().all(|square| {
+        board_state.position.pieces(enemy).contains(square)
+            || board_state.is_square_attacked(move_gen, square, by)
+    });
+
+    if blocked {
+        motifs.push(Motif::BackRankWeakness { king });
+    }
+}
+
+/// Returns the attacks uncovered by the piece that moved off the line between a friendly
+/// sliding piece and an enemy piece.
+fn discovered_by_move(
+    before: &BoardState,
+    after: &BoardState,
+    move_gen: &MoveGenerator,
+    mv: Move,
+) -> Vec<Motif> {
+    let by = before.side_to_move;
+    let blocker = mv.get_initial_square();
+    let occupancy = after.position.all_boards();
+    let mut motifs = Vec::new();
+
+    let sliders = after.position.board(by, Piece::Rook)
+        | after.position.board(by, Piece::Bishop)
+        | after.position.board(by, Piece::Queen);
+
+    for attacker in sliders {
+        let Some((_, piece)) = after.position.piece_at(attacker) else {
+            continue;
+        };
+
+        for target in after.position.pieces(by.enemy()) {
+            let between = move_gen.between(attacker, target);
+
+            if attacker == mv.get_target_square()
+                || !slides_between(piece, attacker, target)
+                || !between.contains(blocker)
+                || !(between & occupancy).is_empty()
+            {
+                continue;
+            }
+
+            motifs.push(Motif::DiscoveredAttack {
+                blocker,
+                attacker,
+                target,
+                check: value_at(after, target) == KING_VALUE,
+            });
+        }
+    }
+
+    motifs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squares(squares: &[Square]) -> Bitboard {
+        squares.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_forks_and_hanging_pieces() {
+        let move_gen = MoveGenerator::new();
+        let game = Game::from_fen("r3k3/2N5/8/8/8/8/8/4K3 b - - 0 1", &move_gen).unwrap();
+        let motifs = find_motifs(&game);
+
+        assert!(motifs.contains(&Tactic {
+            by: Color::White,
+            motif: Motif::Fork {
+                attacker: Square::C7,
+                targets: squares(&[Square::A8, Square::E8]),
+            },
+        }));
+        assert!(motifs.contains(&Tactic {
+            by: Color::White,
+            motif: Motif::HangingPiece {
+                square: Square::A8,
+                attackers: squares(&[Square::C7]),
+            },
+        }));
+        assert!(motifs.iter().all(|tactic| tactic.by == Color::White));
+    }
+
+    #[test]
+    fn test_pins_and_skewers() {
+        let move_gen = MoveGenerator::new();
+
+        let game = Game::from_fen("7k/8/5q2/8/8/8/1B6/4K3 b - - 0 1", &move_gen).unwrap();
+        assert!(find_motifs(&game).contains(&Tactic {
+            by: Color::White,
+            motif: Motif::Pin {
+                pinner: Square::B2,
+                pinned: Square::F6,
+                behind: Square::H8,
+                absolute: true,
+            },
+        }));
+
+        let game = Game::from_fen("7q/8/5k2/8/8/8/1B6/4K3 b - - 0 1", &move_gen).unwrap();
+        assert!(find_motifs(&game).contains(&Tactic {
+            by: Color::White,
+            motif: Motif::Skewer {
+                attacker: Square::B2,
+                front: Square::F6,
+                behind: Square::H8,
+            },
+        }));
+
+        let game = Game::from_fen("3qk3/8/3n4/8/8/8/8/3RK3 w - - 0 1", &move_gen).unwrap();
+        assert!(find_motifs(&game).contains(&Tactic {
+            by: Color::White,
+            motif: Motif::Pin {
+                pinner: Square::D1,
+                pinned: Square::D6,
+                behind: Square::D8,
+                absolute: false,
+            },
+        }));
+    }
+
+    #[test]
+    fn test_overloaded_defender_and_back_rank() {
+        let move_gen = MoveGenerator::new();
+        let game = Game::from_fen("b2r2k1/5ppp/8/8/3nB3/8/8/3R2K1 w - - 0 1", &move_gen).unwrap();
+        let motifs = find_motifs(&game);
+
+        assert!(motifs.contains(&Tactic {
+            by: Color::White,
+            motif: Motif::OverloadedDefender {
+                defender: Square::D8,
+                defended: squares(&[Square::A8, Square::D4]),
+            },
+        }));
+        assert!(motifs.contains(&Tactic {
+            by: Color::White,
+            motif: Motif::BackRankWeakness { king: Square::G8 },
+        }));
+        assert!(!motifs.contains(&Tactic {
+            by: Color::Black,
+            motif: Motif::BackRankWeakness { king: Square::G1 },
+        }));
+    }
+
+    #[test]
+    fn test_find_move_motifs() {
+        let move_gen = MoveGenerator::new();
+        let game = Game::from_fen("4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1", &move_gen).unwrap();
+        let motifs = find_move_motifs(&game, "e2c3".parse().unwrap()).unwrap();
+
+        assert_eq!(
+            motifs,
+            [Tactic {
+                by: Color::White,
+                motif: Motif::DiscoveredAttack {
+                    blocker: Square::E2,
+                    attacker: Square::E1,
+                    target: Square::E8,
+                    check: true,
+                },
+            }]
+        );
+        assert!(find_move_motifs(&game, "e2e4".parse().unwrap()).is_err());
+    }
+}
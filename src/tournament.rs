@@ -0,0 +1,1438 @@
+use crate::Color;
+use crate::boardstate::BoardState;
+use crate::game::{Game, IllegalMoveError, Outcome};
+use crate::gametree::GameTree;
+use crate::movegen::MoveGenerator;
+use crate::moves::Move;
+use crate::pgn::{self, PgnError};
+use crate::search::{self, MATE_SCORE, MAX_LEVEL, SearchLimits};
+use crate::tablebase::{Dtm, Tablebase, TablebaseError};
+use crate::uci_client::{GoParams, Score, UciEngine, UciError};
+use crate::xboard::TimeControl;
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How far an engine may overrun its clock before losing on time, to make up for the
+/// latency of the pipes and the scheduler.
+const TIME_MARGIN: Duration = Duration::from_millis(100);
+
+/// The quantile of the standard normal distribution bounding a 95% confidence interval.
+const Z_95: f64 = 1.959_964;
+
+/// The errors that can stop a match before it starts.
+#[derive(Debug)]
+pub enum TournamentError {
+    /// An opening suite couldn't be read.
+    Io(io::Error),
+    /// An external engine couldn't be started.
+    Uci(UciError),
+    /// The tablebase files couldn't be loaded.
+    Tablebase(TablebaseError),
+    /// A game of a PGN opening suite couldn't be parsed.
+    Pgn(PgnError),
+    /// A position of an opening suite isn't valid, with its line or game number.
+    BadOpening(usize, String),
+    /// A match needs at least two engines.
+    TooFewEngines,
+}
+
+impl fmt::Display for TournamentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "can't read the openings: {err}"),
+            Self::Uci(err) => write!(f, "can't start the engine: {err}"),
+            Self::Tablebase(err) => write!(f, "can't load the tablebases: {err:?}"),
+            Self::Pgn(err) => write!(f, "bad opening game: {err}"),
+            Self::BadOpening(number, reason) => write!(f, "bad opening {number}: {reason}"),
+            Self::TooFewEngines => write!(f, "a match needs at least two engines"),
+        }
+    }
+}
+
+impl Error for TournamentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Uci(err) => Some(err),
+            Self::Pgn(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TournamentError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<UciError> for TournamentError {
+    fn from(err: UciError) -> Self {
+        Self::Uci(err)
+    }
+}
+
+/// An engine taking part in a match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineConfig {
+    /// The built-in engine, playing at the given level or, without one, on its clock alone.
+    Poob(Option<u8>),
+    /// An external UCI engine, with the options to set before the first game.
+    Uci {
+        path: PathBuf,
+        options: Vec<(String, String)>,
+    },
+}
+
+impl EngineConfig {
+    /// Returns the name used in the PGN tags and the standings.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Poob(None) => "poob".to_string(),
+            Self::Poob(Some(level)) => format!("poob:{level}"),
+            Self::Uci { path, .. } => path
+                .file_stem()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+
+    /// Creates a fresh instance of the engine, starting its process if it is external.
+    fn start(&self) -> Result<Box<dyn Engine>, UciError> {
+        match self {
+            &Self::Poob(level) => Ok(Box::new(PoobEngine { level })),
+            Self::Uci { path, options } => {
+                let mut engine = UciEngine::spawn(path, &[])?;
+
+                for (name, value) in options {
+                    engine.set_option(name, value)?;
+                }
+
+                Ok(Box::new(engine))
+            }
+        }
+    }
+}
+
+/// Error returned when an engine isn't of a form accepted by [`EngineConfig::from_str()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineParseError(pub String);
+
+impl fmt::Display for EngineParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bad engine `{}`, expected `poob`, `poob:<level>` or `uci:<path>[,<option>=<value>...]`",
+            self.0
+        )
+    }
+}
+
+impl Error for EngineParseError {}
+
+impl FromStr for EngineConfig {
+    type Err = EngineParseError;
+
+    /// Parses `poob`, `poob:<level>` with a level from `1` to [`MAX_LEVEL`], or
+    /// `uci:<path>` followed by comma-separated UCI options, e.g. `uci:sf,Hash=64`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad_engine = || EngineParseError(s.to_string());
+
+        match s.split_once(':') {
+            None if s == "poob" => Ok(Self::Poob(None)),
+            Some(("poob", level)) => match level.parse() {
+                Ok(level @ 1..=MAX_LEVEL) => Ok(Self::Poob(Some(level))),
+                _ => Err(bad_engine()),
+            },
+            Some(("uci", spec)) => {
+                let mut fields = spec.split(',');
+                let path = fields.next().filter(|path| !path.is_empty());
+                let options = fields
+                    .map(|option| {
+                        let (name, value) = option.split_once('=')?;
+                        Some((name.trim().to_string(), value.trim().to_string()))
+                    })
+                    .collect::<Option<_>>();
+
+                match (path, options) {
+                    (Some(path), Some(options)) => Ok(Self::Uci {
+                        path: PathBuf::from(path),
+                        options,
+                    }),
+                    _ => Err(bad_engine()),
+                }
+            }
+            _ => Err(bad_engine()),
+        }
+    }
+}
+
+/// Parses a time control as `[<moves>/]<seconds>[+<increment>]`, e.g. `40/60`, `10+0.1` or
+/// `60`, or a fixed time per move as `st=<seconds>`.
+pub fn parse_time_control(s: &str) -> Option<TimeControl> {
+    let seconds = |s: &str| Duration::try_from_secs_f64(s.parse().ok()?).ok();
+
+    if let Some(time) = s.strip_prefix("st=") {
+        return Some(TimeControl::Fixed(seconds(time)?));
+    }
+
+    let (moves, rest) = match s.split_once('/') {
+        Some((moves, rest)) => (moves.parse().ok()?, rest),
+        None => (0, s),
+    };
+    let (base, increment) = match rest.split_once('+') {
+        Some((base, increment)) => (seconds(base)?, seconds(increment)?),
+        None => (seconds(rest)?, Duration::ZERO),
+    };
+
+    Some(TimeControl::Conventional {
+        moves,
+        base,
+        increment,
+    })
+}
+
+/// Returns the time control in the format of the PGN `TimeControl` tag. A fixed time per
+/// move is written as one move per period.
+fn pgn_time_control(time_control: TimeControl) -> String {
+    match time_control {
+        TimeControl::Fixed(time) => format!("1/{}", time.as_secs_f64()),
+        TimeControl::Conventional {
+            moves,
+            base,
+            increment,
+        } => {
+            let mut tag = match moves {
+                0 => String::new(),
+                moves => format!("{moves}/"),
+            };
+            let _ = write!(tag, "{}", base.as_secs_f64());
+
+            if !increment.is_zero() {
+                let _ = write!(tag, "+{}", increment.as_secs_f64());
+            }
+
+            tag
+        }
+    }
+}
+
+/// A starting position and the moves played from it before the engines take over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opening {
+    pub start: BoardState,
+    pub moves: Vec<Move>,
+}
+
+impl Default for Opening {
+    fn default() -> Self {
+        Self {
+            start: BoardState::default(),
+            moves: Vec::new(),
+        }
+    }
+}
+
+impl Opening {
+    /// Returns a game with the opening moves played.
+    ///
+    /// # Errors
+    /// Returns an [`IllegalMoveError`] if one of the moves isn't legal.
+    pub fn game<'a>(&self, move_gen: &'a MoveGenerator) -> Result<Game<'a>, IllegalMoveError> {
+        let mut game = Game::from_board_state(self.start.clone(), move_gen);
+
+        for &mv in &self.moves {
+            game.try_make_move(mv)?;
+        }
+
+        Ok(game)
+    }
+}
+
+/// Parses an EPD opening suite, one position per line. The operations following the four
+/// position fields are ignored, but a halfmove clock and fullmove number are kept.
+///
+/// # Errors
+/// Returns [`TournamentError::BadOpening`] with the line number of the first invalid
+/// position.
+pub fn parse_epd(epd: &str) -> Result<Vec<Opening>, TournamentError> {
+    let mut openings = Vec::new();
+
+    for (i, line) in epd.lines().enumerate() {
+        let fields: Vec<&str> = line.split_ascii_whitespace().collect();
+
+        if fields.is_empty() || fields[0].starts_with('#') {
+            continue;
+        }
+
+        let fen = fields[..epd_position_len(&fields)].join(" ");
+        let start = BoardState::from_fen_lenient(&fen)
+            .map_err(|err| TournamentError::BadOpening(i + 1, err.to_string()))?;
+
+        openings.push(Opening {
+            start,
+            moves: Vec::new(),
+        });
+    }
+
+    Ok(openings)
+}
+
+/// Returns the number of fields of an EPD line making up the position: the first four
+/// fields of a FEN, followed by the move counters if they are there.
+pub(crate) fn epd_position_len(fields: &[&str]) -> usize {
+    let counters = fields
+        .iter()
+        .skip(4)
+        .take(2)
+        .take_while(|field| field.parse::<u32>().is_ok())
+        .count();
+
+    fields.len().min(4 + counters)
+}
+
+/// Parses a PGN opening suite, taking the main line of each game.
+///
+/// # Errors
+/// Returns a [`TournamentError`] if one of the games can't be parsed.
+pub fn parse_pgn_openings(
+    pgn: &str,
+    move_gen: &MoveGenerator,
+) -> Result<Vec<Opening>, TournamentError> {
+    pgn::split_games(pgn)
+        .into_iter()
+        .enumerate()
+        .map(|(i, game)| {
+            let tree = GameTree::from_pgn(game, move_gen).map_err(TournamentError::Pgn)?;
+            let line = tree.main_line();
+            let game = line
+                .last()
+                .and_then(|&id| tree.game_at(id, move_gen))
+                .ok_or_else(|| TournamentError::BadOpening(i + 1, "bad main line".to_string()))?;
+
+            Ok(Opening {
+                start: game.history().start().clone(),
+                moves: game.history().entries().map(|entry| entry.mv).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Reads an opening suite, as PGN if the file has a `.pgn` extension and as EPD otherwise.
+///
+/// # Errors
+/// Returns a [`TournamentError`] if the file can't be read or parsed.
+pub fn load_openings(
+    path: &Path,
+    move_gen: &MoveGenerator,
+) -> Result<Vec<Opening>, TournamentError> {
+    let text = std::fs::read_to_string(path)?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("pgn") => parse_pgn_openings(&text, move_gen),
+        _ => parse_epd(&text),
+    }
+}
+
+/// Who plays whom in a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Schedule {
+    /// Every engine plays every other engine.
+    #[default]
+    RoundRobin,
+    /// The first engine plays every other engine, which don't play each other.
+    Gauntlet,
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    /// Parses `round-robin` or `gauntlet`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Self::RoundRobin),
+            "gauntlet" => Ok(Self::Gauntlet),
+            _ => Err(format!(
+                "unknown schedule `{s}`, expected `round-robin` or `gauntlet`"
+            )),
+        }
+    }
+}
+
+/// A game of the schedule: the engines are indices into [`MatchConfig::engines`] and the
+/// opening an index into [`MatchConfig::openings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pairing {
+    pub round: u32,
+    pub white: usize,
+    pub black: usize,
+    pub opening: usize,
+}
+
+/// Returns the games of a match between `engines` engines. Each round uses the next
+/// opening for all its pairs. With `repeat`, every pair plays the opening twice with the
+/// colors reversed; otherwise the colors alternate from round to round.
+pub fn pairings(
+    schedule: Schedule,
+    engines: usize,
+    rounds: u32,
+    openings: usize,
+    repeat: bool,
+) -> Vec<Pairing> {
+    let pairs: Vec<(usize, usize)> = match schedule {
+        Schedule::RoundRobin => (0..engines)
+            .flat_map(|a| (a + 1..engines).map(move |b| (a, b)))
+            .collect(),
+        Schedule::Gauntlet => (1..engines).map(|b| (0, b)).collect(),
+    };
+
+    let mut pairings = Vec::new();
+
+    for round in 0..rounds {
+        let opening = round as usize % openings.max(1);
+
+        for &(a, b) in &pairs {
+            let (white, black) = if round % 2 == 0 || repeat {
+                (a, b)
+            } else {
+                (b, a)
+            };
+
+            pairings.push(Pairing {
+                round,
+                white,
+                black,
+                opening,
+            });
+
+            if repeat {
+                pairings.push(Pairing {
+                    round,
+                    white: black,
+                    black: white,
+                    opening,
+                });
+            }
+        }
+    }
+
+    pairings
+}
+
+/// Ends a game for an engine whose score stays at or below `-score` centipawns for
+/// `moves` of its moves in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResignRule {
+    pub score: i32,
+    pub moves: u32,
+}
+
+/// Ends a game as a draw once both engines' scores have stayed within `score` centipawns
+/// of `0` for `moves` moves each, from move number `from_move` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawRule {
+    pub score: i32,
+    pub moves: u32,
+    pub from_move: u16,
+}
+
+/// The settings of a match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchConfig {
+    pub engines: Vec<EngineConfig>,
+    pub schedule: Schedule,
+    pub rounds: u32,
+    /// The rounds before this one are skipped, to resume an interrupted match.
+    pub first_round: u32,
+    pub time_control: TimeControl,
+    /// The openings played in turn, or only the initial position if empty.
+    pub openings: Vec<Opening>,
+    /// Whether each opening is played twice with the colors reversed.
+    pub repeat: bool,
+    pub resign: Option<ResignRule>,
+    pub draw: Option<DrawRule>,
+    /// A directory of tablebase files to adjudicate positions they cover.
+    pub tablebases: Option<PathBuf>,
+    /// The number of games played at once.
+    pub concurrency: usize,
+    /// The name of the `Event` tag.
+    pub event: String,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            engines: Vec::new(),
+            schedule: Schedule::RoundRobin,
+            rounds: 1,
+            first_round: 0,
+            time_control: TimeControl::default(),
+            openings: Vec::new(),
+            repeat: true,
+            resign: None,
+            draw: None,
+            tablebases: None,
+            concurrency: 1,
+            event: "poob match".to_string(),
+        }
+    }
+}
+
+/// A finished game of a match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    /// The number of the game in the schedule, from `1`.
+    pub number: usize,
+    pub pairing: Pairing,
+    pub outcome: Outcome,
+    /// Why the game ended, e.g. `White mates` or `Black loses on time`.
+    pub reason: String,
+    pub pgn: String,
+}
+
+/// Wins, draws and losses from the point of view of one engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Record {
+    /// Returns the number of games played.
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns the points scored, counting a draw as half a point.
+    pub fn points(&self) -> f64 {
+        f64::from(self.wins) + f64::from(self.draws) / 2.0
+    }
+
+    /// Returns the Elo difference to the opponents suggested by the score, or [`None`] if
+    /// there are no games or the score is `0%` or `100%`, which gives no finite estimate.
+    pub fn elo(&self) -> Option<EloEstimate> {
+        let games = f64::from(self.games());
+        let score = self.points() / games;
+
+        if !(score > 0.0 && score < 1.0) {
+            return None;
+        }
+
+        let (wins, draws, losses) = (
+            f64::from(self.wins) / games,
+            f64::from(self.draws) / games,
+            f64::from(self.losses) / games,
+        );
+        let variance =
+            wins * (1.0 - score).powi(2) + draws * (0.5 - score).powi(2) + losses * score.powi(2);
+
+        Some(EloEstimate::new(score, (variance / games).sqrt()))
+    }
+}
+
+/// An Elo difference with the half-width of its 95% confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EloEstimate {
+    pub elo: f64,
+    pub margin: f64,
+}
+
+impl EloEstimate {
+    /// Returns the estimate for a mean score from `0` to `1` with the given standard
+    /// deviation.
+    pub fn new(score: f64, deviation: f64) -> Self {
+        Self {
+            elo: score_to_elo(score),
+            margin: (score_to_elo(score + Z_95 * deviation)
+                - score_to_elo(score - Z_95 * deviation))
+                / 2.0,
+        }
+    }
+}
+
+/// Returns the Elo difference at which the expected score is `score`, from `0` to `1`.
+pub fn score_to_elo(score: f64) -> f64 {
+    match score {
+        score if score <= 0.0 => f64::NEG_INFINITY,
+        score if score >= 1.0 => f64::INFINITY,
+        score => -400.0 * (1.0 / score - 1.0).log10(),
+    }
+}
+
+/// Returns the expected score, from `0` to `1`, of a player this many Elo stronger than
+/// their opponent.
+pub fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl fmt::Display for EloEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Adding zero turns `-0.0` into `0.0`, so an even score isn't written as negative.
+        write!(f, "{:+.1} +/- {:.1}", self.elo + 0.0, self.margin)
+    }
+}
+
+/// The games of a finished match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchReport {
+    /// The names of the engines, in the order of [`MatchConfig::engines`].
+    pub engines: Vec<String>,
+    /// The games in schedule order.
+    pub games: Vec<GameRecord>,
+}
+
+impl MatchReport {
+    /// Returns the record of an engine against all its opponents.
+    pub fn record(&self, engine: usize) -> Record {
+        self.games_of(engine, None)
+    }
+
+    /// Returns the record of an engine against one opponent.
+    pub fn head_to_head(&self, engine: usize, opponent: usize) -> Record {
+        self.games_of(engine, Some(opponent))
+    }
+
+    /// Returns all the games as a PGN database.
+    pub fn to_pgn(&self) -> String {
+        self.games
+            .iter()
+            .map(|game| game.pgn.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn games_of(&self, engine: usize, opponent: Option<usize>) -> Record {
+        let mut record = Record::default();
+
+        for game in &self.games {
+            let Pairing { white, black, .. } = game.pairing;
+            let color = match (white == engine, black == engine) {
+                (true, false) if opponent.is_none_or(|opponent| opponent == black) => Color::White,
+                (false, true) if opponent.is_none_or(|opponent| opponent == white) => Color::Black,
+                _ => continue,
+            };
+
+            match game.outcome {
+                Outcome::Win(winner) if winner == color => record.wins += 1,
+                Outcome::Win(_) => record.losses += 1,
+                Outcome::Draw => record.draws += 1,
+            }
+        }
+
+        record
+    }
+}
+
+impl fmt::Display for MatchReport {
+    /// Writes the standings, with each engine's Elo against the field.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .engines
+            .iter()
+            .map(String::len)
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        let mut standings: Vec<(usize, Record)> = (0..self.engines.len())
+            .map(|i| (i, self.record(i)))
+            .collect();
+
+        standings.sort_by(|(_, a), (_, b)| {
+            let share = |record: &Record| record.points() / f64::from(record.games().max(1));
+            share(b).total_cmp(&share(a))
+        });
+
+        writeln!(
+            f,
+            "Rank {:width$}  {:>18}  {:>5}  {:>6}  {:>5}  {:>5}  {:>5}",
+            "Name", "Elo", "Games", "Score", "Wins", "Draws", "Losses"
+        )?;
+
+        for (rank, (engine, record)) in standings.into_iter().enumerate() {
+            let elo = match record.elo() {
+                Some(elo) => elo.to_string(),
+                None => "-".to_string(),
+            };
+            let score = 100.0 * record.points() / f64::from(record.games().max(1));
+
+            writeln!(
+                f,
+                "{:>4} {:width$}  {elo:>18}  {:>5}  {score:>5.1}%  {:>5}  {:>5}  {:>5}",
+                rank + 1,
+                self.engines[engine],
+                record.games(),
+                record.wins,
+                record.draws,
+                record.losses
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An engine as seen by the match: it is told about new games and asked for moves.
+trait Engine {
+    fn new_game(&mut self) -> Result<(), UciError>;
+
+    /// Returns the chosen move, or [`None`] if the engine claims to have no legal move,
+    /// along with its score in centipawns from its own point of view, if it sent one.
+    fn think(
+        &mut self,
+        game: &Game,
+        clock: &Clock,
+    ) -> Result<(Option<Move>, Option<i32>), UciError>;
+}
+
+/// The state of the clocks when an engine is asked to move.
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    time_control: TimeControl,
+    /// The time left for white and black, indexed by [`Color::to_index()`].
+    remaining: [Duration; 2],
+    /// The number of moves the side to move has made since the opening.
+    played: u32,
+}
+
+/// The built-in engine, searching on the thread playing the game.
+struct PoobEngine {
+    level: Option<u8>,
+}
+
+impl Engine for PoobEngine {
+    fn new_game(&mut self) -> Result<(), UciError> {
+        Ok(())
+    }
+
+    fn think(
+        &mut self,
+        game: &Game,
+        clock: &Clock,
+    ) -> Result<(Option<Move>, Option<i32>), UciError> {
+        let color = game.get_board_state().side_to_move;
+        let time = clock
+            .time_control
+            .allocate(clock.remaining[color.to_index()], clock.played);
+        let mut limits = self
+            .level
+            .map_or_else(SearchLimits::default, SearchLimits::from_level);
+
+        limits.movetime = Some(
+            limits
+                .movetime
+                .map_or(time, |level_time| level_time.min(time)),
+        );
+
+        let result = search::search(game, limits);
+
+        Ok((result.best_move, Some(result.score)))
+    }
+}
+
+impl Engine for UciEngine {
+    fn new_game(&mut self) -> Result<(), UciError> {
+        UciEngine::new_game(self)
+    }
+
+    fn think(
+        &mut self,
+        game: &Game,
+        clock: &Clock,
+    ) -> Result<(Option<Move>, Option<i32>), UciError> {
+        let params = match clock.time_control {
+            TimeControl::Fixed(time) => GoParams {
+                movetime: Some(time),
+                ..GoParams::default()
+            },
+            TimeControl::Conventional {
+                moves, increment, ..
+            } => GoParams {
+                wtime: Some(clock.remaining[Color::White.to_index()]),
+                btime: Some(clock.remaining[Color::Black.to_index()]),
+                winc: Some(increment),
+                binc: Some(increment),
+                movestogo: (moves > 0).then(|| moves - clock.played % moves),
+                ..GoParams::default()
+            },
+        };
+
+        let reply = self.go(game, &params)?;
+        let score = reply
+            .last_scored()
+            .and_then(|info| info.score)
+            .map(|score| match score {
+                Score::Cp(cp) => cp,
+                Score::Mate(moves) if moves > 0 => MATE_SCORE - (2 * moves - 1),
+                Score::Mate(moves) => -MATE_SCORE - 2 * moves,
+            });
+
+        Ok((reply.best_move, score))
+    }
+}
+
+/// Returns the names of the engines of a match, numbering the ones sharing a name.
+pub fn engine_names(engines: &[EngineConfig]) -> Vec<String> {
+    let mut names: Vec<String> = engines.iter().map(EngineConfig::name).collect();
+
+    for i in 1..names.len() {
+        let duplicates = names[..i]
+            .iter()
+            .filter(|name| name.split(" (").next() == Some(&names[i]))
+            .count();
+
+        if duplicates > 0 {
+            names[i] = format!("{} ({})", names[i], duplicates + 1);
+        }
+    }
+
+    names
+}
+
+/// Plays the games of a match, spreading them over [`MatchConfig::concurrency`] threads,
+/// and calls `progress` as each game ends.
+///
+/// Every thread starts its own instance of each engine the first time it needs it, and
+/// restarts an external engine after it failed during a game, which the engine loses.
+///
+/// # Errors
+/// Returns a [`TournamentError`] if there are fewer than two engines, the tablebases can't
+/// be loaded, an opening is illegal, or an external engine can't be started.
+pub fn run_match(
+    config: &MatchConfig,
+    move_gen: &MoveGenerator,
+    progress: impl Fn(&GameRecord) + Sync,
+) -> Result<MatchReport, TournamentError> {
+    run_match_with_stop(config, move_gen, &AtomicBool::new(false), progress)
+}
+
+/// Plays a match like [`run_match()`], but starts no more games once `stop` is set, e.g.
+/// by `progress` when the result is clear. The games already running are finished.
+///
+/// # Errors
+/// Returns a [`TournamentError`] like [`run_match()`].
+pub fn run_match_with_stop(
+    config: &MatchConfig,
+    move_gen: &MoveGenerator,
+    stop: &AtomicBool,
+    progress: impl Fn(&GameRecord) + Sync,
+) -> Result<MatchReport, TournamentError> {
+    if config.engines.len() < 2 {
+        return Err(TournamentError::TooFewEngines);
+    }
+
+    let default_openings = [Opening::default()];
+    let openings = match config.openings.as_slice() {
+        [] => &default_openings[..],
+        openings => openings,
+    };
+
+    let starts = openings
+        .iter()
+        .enumerate()
+        .map(|(i, opening)| {
+            opening
+                .game(move_gen)
+                .map_err(|err| TournamentError::BadOpening(i + 1, err.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let tablebase = match &config.tablebases {
+        Some(dir) => {
+            let mut tablebase = Tablebase::new(move_gen);
+            tablebase.load(dir).map_err(TournamentError::Tablebase)?;
+            Some(tablebase)
+        }
+        None => None,
+    };
+
+    // Catch engines that can't start before any game is played.
+    for engine in &config.engines {
+        engine.start()?;
+    }
+
+    let names = engine_names(&config.engines);
+    let schedule: Vec<(usize, Pairing)> = pairings(
+        config.schedule,
+        config.engines.len(),
+        config.rounds,
+        openings.len(),
+        config.repeat,
+    )
+    .into_iter()
+    .enumerate()
+    .filter(|(_, pairing)| pairing.round >= config.first_round)
+    .collect();
+    let next = AtomicUsize::new(0);
+    let games = Mutex::new(Vec::with_capacity(schedule.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..config.concurrency.clamp(1, schedule.len().max(1)) {
+            scope.spawn(|| {
+                let mut engines: Vec<Option<Box<dyn Engine>>> =
+                    config.engines.iter().map(|_| None).collect();
+
+                while !stop.load(Ordering::Relaxed) {
+                    let Some(&(index, pairing)) =
+                        schedule.get(next.fetch_add(1, Ordering::Relaxed))
+                    else {
+                        break;
+                    };
+
+                    let match_game = MatchGame {
+                        config,
+                        names: &names,
+                        pairing,
+                        number: index + 1,
+                        tablebase: tablebase.as_ref(),
+                    };
+                    let record = match_game.play(starts[pairing.opening].clone(), &mut engines);
+
+                    progress(&record);
+                    games
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push(record);
+                }
+            });
+        }
+    });
+
+    let mut games = games.into_inner().unwrap_or_else(PoisonError::into_inner);
+    games.sort_by_key(|game| game.number);
+
+    Ok(MatchReport {
+        engines: names,
+        games,
+    })
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Ending {
+    /// Why the game ended, e.g. `White mates`.
+    reason: String,
+    /// The value of the PGN `Termination` tag.
+    termination: &'static str,
+    /// The side whose engine failed and should be restarted.
+    failed: Option<Color>,
+}
+
+impl Ending {
+    fn new(reason: String, termination: &'static str) -> Self {
+        Self {
+            reason,
+            termination,
+            failed: None,
+        }
+    }
+
+    fn failure(color: Color, reason: String, termination: &'static str) -> Self {
+        Self {
+            reason,
+            termination,
+            failed: Some(color),
+        }
+    }
+}
+
+/// One game of a match, with everything needed to play and record it.
+struct MatchGame<'m> {
+    config: &'m MatchConfig,
+    names: &'m [String],
+    pairing: Pairing,
+    number: usize,
+    tablebase: Option<&'m Tablebase<'m>>,
+}
+
+impl MatchGame<'_> {
+    /// Plays the game from the opening, starting the engines that aren't running yet, and
+    /// records it. An engine failing to start or answer loses the game and is restarted for
+    /// the next one.
+    fn play(&self, mut game: Game, engines: &mut [Option<Box<dyn Engine>>]) -> GameRecord {
+        let ending = match self.start_engines(engines) {
+            Some(ending) => ending,
+            None => {
+                let sides = engine_pair(engines, self.pairing.white, self.pairing.black)
+                    .expect("both engines were just started");
+
+                self.play_moves(&mut game, sides)
+            }
+        };
+
+        if let Some(color) = ending.failed {
+            engines[self.engine(color)] = None;
+
+            if game.outcome().is_none() {
+                game.resign(color);
+            }
+        }
+
+        let round = (self.pairing.round + 1).to_string();
+        let time_control = pgn_time_control(self.config.time_control);
+        let tags = [
+            ("Event", self.config.event.as_str()),
+            ("Round", round.as_str()),
+            ("White", self.names[self.pairing.white].as_str()),
+            ("Black", self.names[self.pairing.black].as_str()),
+            ("TimeControl", time_control.as_str()),
+            ("Termination", ending.termination),
+        ];
+
+        GameRecord {
+            number: self.number,
+            pairing: self.pairing,
+            outcome: game.outcome().unwrap_or(Outcome::Draw),
+            pgn: game.to_pgn_with_tags(&tags),
+            reason: ending.reason,
+        }
+    }
+
+    /// Returns the index of the engine playing the given side.
+    fn engine(&self, color: Color) -> usize {
+        match color {
+            Color::White => self.pairing.white,
+            Color::Black => self.pairing.black,
+        }
+    }
+
+    /// Starts the engines of the game if needed and tells them a new game begins, returning
+    /// the ending of the game if one of them fails to.
+    fn start_engines(&self, engines: &mut [Option<Box<dyn Engine>>]) -> Option<Ending> {
+        for color in [Color::White, Color::Black] {
+            let slot = &mut engines[self.engine(color)];
+
+            if slot.is_none() {
+                *slot = self.config.engines[self.engine(color)].start().ok();
+            }
+
+            if slot
+                .as_mut()
+                .is_none_or(|engine| engine.new_game().is_err())
+            {
+                let reason = format!("{} fails to start", color_name(color));
+
+                return Some(Ending::failure(color, reason, "abandoned"));
+            }
+        }
+
+        None
+    }
+
+    /// Asks the engines, indexed by [`Color::to_index()`], for moves until the game ends.
+    fn play_moves(&self, game: &mut Game, engines: [&mut dyn Engine; 2]) -> Ending {
+        let time_control = self.config.time_control;
+        let mut clock = Clock {
+            time_control,
+            remaining: [time_control.start_time(); 2],
+            played: 0,
+        };
+        let mut played = [0; 2];
+        let mut adjudicator = Adjudicator::default();
+
+        loop {
+            if let Some(ending) = self.game_over(game, &adjudicator) {
+                return ending;
+            }
+
+            let color = game.get_board_state().side_to_move;
+            let index = color.to_index();
+            let name = color_name(color);
+
+            clock.played = played[index];
+
+            let start = Instant::now();
+            let answer = engines[index].think(game, &clock);
+            let elapsed = start.elapsed();
+
+            let (mv, score) = match answer {
+                Ok(answer) => answer,
+                Err(UciError::IllegalMove(mv)) => {
+                    let reason = format!("{name} makes an illegal move: {mv}");
+                    return Ending::failure(color, reason, "rules infraction");
+                }
+                Err(err) => {
+                    let reason = format!("{name} disconnects: {err}");
+                    return Ending::failure(color, reason, "abandoned");
+                }
+            };
+
+            let limit = match time_control {
+                TimeControl::Fixed(time) => time,
+                TimeControl::Conventional { .. } => clock.remaining[index],
+            };
+
+            if elapsed > limit + TIME_MARGIN {
+                game.resign(color);
+                return Ending::new(format!("{name} loses on time"), "time forfeit");
+            }
+
+            played[index] += 1;
+
+            if let TimeControl::Conventional {
+                moves,
+                base,
+                increment,
+            } = time_control
+            {
+                let remaining = &mut clock.remaining[index];
+                *remaining = remaining.saturating_sub(elapsed) + increment;
+
+                if moves > 0 && played[index] % moves == 0 {
+                    *remaining += base;
+                }
+            }
+
+            match mv.map(|mv| game.try_make_move(mv)) {
+                Some(Ok(())) => adjudicator.record(color, score, self.config),
+                Some(Err(_)) | None => {
+                    let mv = mv.map_or_else(|| "(none)".to_string(), |mv| mv.to_string());
+                    let reason = format!("{name} makes an illegal move: {mv}");
+                    return Ending::failure(color, reason, "rules infraction");
+                }
+            }
+        }
+    }
+
+    /// Returns how the game ended by the rules or by adjudication, setting its outcome, or
+    /// [`None`] if it goes on.
+    fn game_over(&self, game: &mut Game, adjudicator: &Adjudicator) -> Option<Ending> {
+        if let Some(outcome) = game.outcome() {
+            let reason = match outcome {
+                Outcome::Win(winner) => format!("{} mates", color_name(winner)),
+                Outcome::Draw => "Draw by stalemate".to_string(),
+            };
+
+            return Some(Ending::new(reason, "normal"));
+        }
+
+        let board_state = game.get_board_state();
+        let color = board_state.side_to_move;
+        let probe = self
+            .tablebase
+            .and_then(|tablebase| tablebase.probe(board_state));
+
+        let (winner, how, termination) = if let Some(reason) = game.draw_by_rule() {
+            (None, format!("by {reason}"), "normal")
+        } else if board_state.is_drawn_material() {
+            // Neither side can force mate, even if the rules let the game go on.
+            (
+                None,
+                "by drawn material adjudication".to_string(),
+                "adjudication",
+            )
+        } else if let Some(dtm) = probe {
+            let winner = match dtm {
+                Dtm::Win(_) => Some(color),
+                Dtm::Loss(_) => Some(color.enemy()),
+                Dtm::Draw => None,
+            };
+
+            (
+                winner,
+                "by tablebase adjudication".to_string(),
+                "adjudication",
+            )
+        } else if let Some(loser) = adjudicator.resigns(self.config) {
+            (
+                Some(loser.enemy()),
+                "by resign adjudication".to_string(),
+                "adjudication",
+            )
+        } else if adjudicator.draws(self.config, board_state.turn_count) {
+            (None, "by draw adjudication".to_string(), "adjudication")
+        } else {
+            return None;
+        };
+
+        let reason = match winner {
+            Some(winner) => {
+                game.resign(winner.enemy());
+                format!("{} wins {how}", color_name(winner))
+            }
+            None => {
+                game.agree_draw();
+                format!("Draw {how}")
+            }
+        };
+
+        Some(Ending::new(reason, termination))
+    }
+}
+
+/// Returns the running engines of the white and black players, indexed by
+/// [`Color::to_index()`].
+fn engine_pair(
+    engines: &mut [Option<Box<dyn Engine>>],
+    white: usize,
+    black: usize,
+) -> Option<[&mut dyn Engine; 2]> {
+    let (head, tail) = engines.split_at_mut(white.max(black));
+    let first = head.get_mut(white.min(black))?.as_deref_mut()?;
+    let second = tail.first_mut()?.as_deref_mut()?;
+
+    Some(if white < black {
+        [first, second]
+    } else {
+        [second, first]
+    })
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+/// Tracks the scores reported by the engines for the resign and draw rules.
+#[derive(Debug, Default)]
+struct Adjudicator {
+    /// How many moves in a row each side's score has been below the resign threshold.
+    losing: [u32; 2],
+    /// How many moves in a row both sides' scores have been close to `0`.
+    drawish: u32,
+}
+
+impl Adjudicator {
+    fn record(&mut self, color: Color, score: Option<i32>, config: &MatchConfig) {
+        let index = color.to_index();
+
+        match (config.resign, score) {
+            (Some(rule), Some(score)) if score <= -rule.score => self.losing[index] += 1,
+            _ => self.losing[index] = 0,
+        }
+
+        match (config.draw, score) {
+            (Some(rule), Some(score)) if score.abs() <= rule.score => self.drawish += 1,
+            _ => self.drawish = 0,
+        }
+    }
+
+    /// Returns the side that should resign, if any.
+    fn resigns(&self, config: &MatchConfig) -> Option<Color> {
+        let rule = config.resign?;
+
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|color| rule.moves > 0 && self.losing[color.to_index()] >= rule.moves)
+    }
+
+    fn draws(&self, config: &MatchConfig, turn_count: u16) -> bool {
+        config.draw.is_some_and(|rule| {
+            rule.moves > 0 && turn_count >= rule.from_move && self.drawish >= 2 * rule.moves
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATE_IN_ONE: &str = "6k1/5ppp/8/8/8/8/8/R5K1 w - -";
+    const BARE_KINGS: &str = "8/8/4k3/8/8/4K3/8/8 w - -";
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!("poob".parse(), Ok(EngineConfig::Poob(None)));
+        assert_eq!("poob:3".parse(), Ok(EngineConfig::Poob(Some(3))));
+        assert_eq!(
+            "uci:/usr/bin/sf,Hash=64, Threads = 2".parse(),
+            Ok(EngineConfig::Uci {
+                path: PathBuf::from("/usr/bin/sf"),
+                options: vec![
+                    ("Hash".to_string(), "64".to_string()),
+                    ("Threads".to_string(), "2".to_string())
+                ],
+            })
+        );
+        assert!("poob:11".parse::<EngineConfig>().is_err());
+        assert!("uci:".parse::<EngineConfig>().is_err());
+        assert!("uci:sf,Hash".parse::<EngineConfig>().is_err());
+
+        let engines = ["poob", "uci:/bin/sf", "poob", "poob"].map(|s| s.parse().unwrap());
+        assert_eq!(
+            engine_names(&engines),
+            ["poob", "sf", "poob (2)", "poob (3)"]
+        );
+
+        let time_control = parse_time_control("40/60+0.5").unwrap();
+        assert_eq!(
+            time_control,
+            TimeControl::Conventional {
+                moves: 40,
+                base: Duration::from_secs(60),
+                increment: Duration::from_millis(500),
+            }
+        );
+        assert_eq!(pgn_time_control(time_control), "40/60+0.5");
+        assert_eq!(
+            parse_time_control("st=0.1"),
+            Some(TimeControl::Fixed(Duration::from_millis(100)))
+        );
+        assert_eq!(pgn_time_control(parse_time_control("90").unwrap()), "90");
+        assert_eq!(parse_time_control("1/2/3"), None);
+    }
+
+    #[test]
+    fn test_openings() {
+        let move_gen = MoveGenerator::new();
+        let epd = format!("{MATE_IN_ONE} bm Ra8#; id \"mate\";\n\n# comment\n{BARE_KINGS} 3 40\n");
+        let openings = parse_epd(&epd).unwrap();
+
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].start.to_fen(), format!("{MATE_IN_ONE} 0 1"));
+        assert_eq!(openings[1].start.to_fen(), format!("{BARE_KINGS} 3 40"));
+        assert!(matches!(
+            parse_epd("8/8/8/8 w - -"),
+            Err(TournamentError::BadOpening(1, _))
+        ));
+
+        let pgn = "[Event \"?\"]\n\n1. e4 e5 (1... c5) 2. Nf3 *\n\n[Event \"?\"]\n1. d4 *\n";
+        let openings = parse_pgn_openings(pgn, &move_gen).unwrap();
+        let moves = |opening: &Opening| {
+            opening
+                .moves
+                .iter()
+                .map(|mv| mv.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(openings.len(), 2);
+        assert_eq!(moves(&openings[0]), ["e2e4", "e7e5", "g1f3"]);
+        assert_eq!(moves(&openings[1]), ["d2d4"]);
+        assert_eq!(openings[0].game(&move_gen).unwrap().ply(), 3);
+    }
+
+    #[test]
+    fn test_pairings() {
+        let games = |schedule, repeat| {
+            pairings(schedule, 3, 2, 1, repeat)
+                .into_iter()
+                .map(|pairing| (pairing.white, pairing.black))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            games(Schedule::RoundRobin, false),
+            [(0, 1), (0, 2), (1, 2), (1, 0), (2, 0), (2, 1)]
+        );
+        assert_eq!(
+            games(Schedule::Gauntlet, true),
+            [
+                (0, 1),
+                (1, 0),
+                (0, 2),
+                (2, 0),
+                (0, 1),
+                (1, 0),
+                (0, 2),
+                (2, 0)
+            ]
+        );
+
+        let openings: Vec<usize> = pairings(Schedule::RoundRobin, 2, 3, 2, true)
+            .into_iter()
+            .map(|pairing| pairing.opening)
+            .collect();
+        assert_eq!(openings, [0, 0, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_elo() {
+        let record = Record {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+        };
+        let estimate = record.elo().unwrap();
+
+        assert!((estimate.elo - 147.2).abs() < 0.1);
+        assert!((estimate.margin - 66.0).abs() < 0.1);
+        assert_eq!(estimate.to_string(), "+147.2 +/- 66.0");
+
+        let even = Record {
+            wins: 5,
+            draws: 0,
+            losses: 5,
+        };
+        assert_eq!(even.elo().unwrap().elo, 0.0);
+        assert_eq!(Record::default().elo(), None);
+        assert_eq!(
+            Record {
+                wins: 3,
+                ..Record::default()
+            }
+            .elo(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_run_match() {
+        let move_gen = MoveGenerator::new();
+        let config = MatchConfig {
+            engines: vec![EngineConfig::Poob(Some(2)), EngineConfig::Poob(Some(2))],
+            rounds: 2,
+            time_control: TimeControl::Fixed(Duration::from_secs(1)),
+            openings: parse_epd(&format!("{MATE_IN_ONE}\n{BARE_KINGS}")).unwrap(),
+            concurrency: 2,
+            ..MatchConfig::default()
+        };
+        let finished = AtomicUsize::new(0);
+        let report = run_match(&config, &move_gen, |_| {
+            finished.fetch_add(1, Ordering::Relaxed);
+        })
+        .unwrap();
+
+        assert_eq!(finished.into_inner(), 4);
+        assert_eq!(report.engines, ["poob:2", "poob:2 (2)"]);
+
+        let reasons: Vec<&str> = report
+            .games
+            .iter()
+            .map(|game| game.reason.as_str())
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                "White mates",
+                "White mates",
+                "Draw by insufficient material",
+                "Draw by insufficient material"
+            ]
+        );
+
+        let record = Record {
+            wins: 1,
+            draws: 2,
+            losses: 1,
+        };
+        assert_eq!(report.record(0), record);
+        assert_eq!(report.head_to_head(1, 0), record);
+
+        let pgn = report.to_pgn();
+        assert_eq!(pgn::split_games(&pgn).len(), 4);
+        assert!(pgn.contains("[White \"poob:2 (2)\"]\n[Black \"poob:2\"]\n[Result \"1-0\"]"));
+        // Bare kings are drawn by the rules, not adjudicated.
+        assert!(!pgn.contains("[Termination \"adjudication\"]"));
+        assert!(report.to_string().contains("poob:2 (2)"));
+
+        let config = MatchConfig {
+            engines: config.engines[..1].to_vec(),
+            ..config
+        };
+        assert!(matches!(
+            run_match(&config, &move_gen, |_| ()),
+            Err(TournamentError::TooFewEngines)
+        ));
+    }
+}
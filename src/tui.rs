@@ -0,0 +1,793 @@
+use crate::bitboards::Bitboard;
+use crate::game::{Game, Outcome};
+use crate::moves::{Move, MoveListIterator};
+use crate::play::{Player, format_score};
+use crate::rende::piece_glyph;
+use crate::search::{self, SearchLimits, SearchResult};
+use crate::{Color, File, Piece, Rank, Square};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    MouseButton, MouseEvent, MouseEventKind,
+};
+use crossterm::style::{self, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+const BOARD_LEFT: u16 = 3;
+const BOARD_TOP: u16 = 1;
+const SQUARE_WIDTH: u16 = 3;
+const PANE_LEFT: u16 = BOARD_LEFT + 8 * SQUARE_WIDTH + 4;
+const PANE_WIDTH: usize = 48;
+const STATUS_ROW: u16 = BOARD_TOP + 10;
+
+/// The number of full moves shown in the move list. Older moves scroll out of view.
+const MOVE_LIST_ROWS: usize = 8;
+
+/// How often the screen is redrawn while waiting for input, to keep the clocks running.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The engine level used when analysing the position for a human.
+const ANALYSIS_LEVEL: u8 = 4;
+
+const LIGHT_SQUARE: style::Color = style::Color::Rgb {
+    r: 240,
+    g: 217,
+    b: 181,
+};
+const DARK_SQUARE: style::Color = style::Color::Rgb {
+    r: 181,
+    g: 136,
+    b: 99,
+};
+const LAST_MOVE_SQUARE: style::Color = style::Color::Rgb {
+    r: 205,
+    g: 210,
+    b: 106,
+};
+const SELECTED_SQUARE: style::Color = style::Color::Rgb {
+    r: 106,
+    g: 170,
+    b: 100,
+};
+const TARGET_SQUARE: style::Color = style::Color::Rgb {
+    r: 130,
+    g: 170,
+    b: 210,
+};
+const CURSOR_SQUARE: style::Color = style::Color::Rgb {
+    r: 220,
+    g: 110,
+    b: 90,
+};
+const WHITE_PIECE: style::Color = style::Color::Rgb {
+    r: 255,
+    g: 255,
+    b: 255,
+};
+const BLACK_PIECE: style::Color = style::Color::Rgb { r: 0, g: 0, b: 0 };
+
+const HELP: &str =
+    "arrows/hjkl: move  enter/space/click: select  u: undo  d: draw  f: flip  a: analyse  q: quit";
+
+/// Keeps track of the time each side has spent thinking.
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    used: [Duration; 2],
+    /// The side whose clock is running, and since when.
+    running: Option<(Color, Instant)>,
+}
+
+impl Clock {
+    fn new(side_to_move: Color) -> Self {
+        Self {
+            used: [Duration::ZERO; 2],
+            running: Some((side_to_move, Instant::now())),
+        }
+    }
+
+    /// Stops the running clock and starts the given side's, if any.
+    fn start(&mut self, color: Option<Color>) {
+        if let Some((running, since)) = self.running {
+            self.used[running.to_index()] += since.elapsed();
+        }
+
+        self.running = color.map(|color| (color, Instant::now()));
+    }
+
+    fn used(&self, color: Color) -> Duration {
+        match self.running {
+            Some((running, since)) if running == color => {
+                self.used[color.to_index()] + since.elapsed()
+            }
+            _ => self.used[color.to_index()],
+        }
+    }
+}
+
+/// The result of a search, formatted for the analysis pane.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Analysis {
+    depth: u8,
+    nodes: u64,
+    score: String,
+    /// The principal variation in SAN.
+    pv: String,
+}
+
+impl Analysis {
+    fn new(game: &Game, result: &SearchResult) -> Self {
+        let mut line = game.clone();
+        let mut pv = Vec::with_capacity(result.pv.len());
+
+        for &mv in &result.pv {
+            let Ok(san) = line.to_san(mv) else {
+                break;
+            };
+
+            pv.push(san);
+            line.unchecked_make_move(mv);
+        }
+
+        Self {
+            depth: result.depth,
+            nodes: result.nodes,
+            score: format_score(result.score, game.get_board_state().side_to_move),
+            pv: pv.join(" "),
+        }
+    }
+}
+
+/// A full-screen terminal UI to play a game, with each side played by a human or the
+/// engine. Moves are entered by moving a cursor over the board with the keyboard, or by
+/// clicking on the squares.
+pub struct Tui<'a> {
+    game: Game<'a>,
+    /// The players, indexed by their color's bitboard index.
+    players: [Player; 2],
+    flipped: bool,
+    cursor: Square,
+    selected: Option<Square>,
+    /// The squares of a promotion waiting for the player to pick a piece.
+    pending_promotion: Option<(Square, Square)>,
+    analysis: Option<Analysis>,
+    clock: Clock,
+    message: String,
+    quit: bool,
+}
+
+impl<'a> Tui<'a> {
+    /// Starts a UI continuing the given game. The board is drawn from white's side, unless
+    /// only black is played by a human.
+    pub fn new(game: Game<'a>, white: Player, black: Player) -> Self {
+        let mut players = [Player::Human; 2];
+        players[Color::White.to_index()] = white;
+        players[Color::Black.to_index()] = black;
+
+        let clock = Clock::new(game.get_board_state().side_to_move);
+
+        Self {
+            game,
+            players,
+            flipped: white != Player::Human && black == Player::Human,
+            cursor: Square::E2,
+            selected: None,
+            pending_promotion: None,
+            analysis: None,
+            clock,
+            message: String::new(),
+            quit: false,
+        }
+    }
+
+    pub fn game(&self) -> &Game<'a> {
+        &self.game
+    }
+
+    /// Returns the side drawn at the bottom of the board.
+    pub fn perspective(&self) -> Color {
+        if self.flipped {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    /// Runs the UI until the player quits. The terminal is restored even if drawing fails.
+    ///
+    /// # Errors
+    /// Returns any error encountered while reading from or writing to the terminal.
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut out = io::stdout();
+
+        terminal::enable_raw_mode()?;
+        execute!(
+            out,
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            cursor::Hide,
+            terminal::Clear(ClearType::All)
+        )?;
+
+        let result = self.event_loop(&mut out);
+
+        execute!(out, cursor::Show, DisableMouseCapture, LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+
+        result
+    }
+
+    fn event_loop(&mut self, out: &mut impl Write) -> io::Result<()> {
+        while !self.quit {
+            // Input is still polled between engine moves, so that engine games can be quit.
+            if let Some(Player::Engine(limits)) = self.engine_to_move() {
+                self.play_engine_move(out, limits)?;
+            }
+
+            self.draw(out)?;
+
+            if !event::poll(POLL_INTERVAL)? {
+                continue;
+            }
+
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_key(out, key)?,
+                Event::Mouse(mouse) => self.handle_mouse(mouse),
+                Event::Resize(_, _) => queue!(out, terminal::Clear(ClearType::All))?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the engine player of the side to move, if the game is still going and the
+    /// side to move isn't played by a human.
+    fn engine_to_move(&self) -> Option<Player> {
+        let player = self.players[self.game.get_board_state().side_to_move.to_index()];
+
+        (self.game.outcome().is_none() && player != Player::Human).then_some(player)
+    }
+
+    fn handle_key(&mut self, out: &mut impl Write, key: KeyEvent) -> io::Result<()> {
+        if self.pending_promotion.is_some() {
+            let piece = match key.code {
+                KeyCode::Char('q') => Some(Piece::Queen),
+                KeyCode::Char('r') => Some(Piece::Rook),
+                KeyCode::Char('b') => Some(Piece::Bishop),
+                KeyCode::Char('n') => Some(Piece::Knight),
+                _ => None,
+            };
+
+            match piece {
+                Some(piece) => self.promote(piece),
+                None => {
+                    self.pending_promotion = None;
+                    self.message = "Promotion cancelled.".to_string();
+                }
+            }
+
+            return Ok(());
+        }
+
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(-1, 0),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(1, 0),
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(0, 1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(0, -1),
+            KeyCode::Enter | KeyCode::Char(' ') => self.activate(self.cursor),
+            KeyCode::Esc => self.selected = None,
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('d') => self.claim_draw(),
+            KeyCode::Char('f') => self.flipped = !self.flipped,
+            KeyCode::Char('a') => self.analyse(out)?,
+            KeyCode::Char('q') => self.quit = true,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if mouse.kind != MouseEventKind::Down(MouseButton::Left) {
+            return;
+        }
+
+        if let Some(square) = self.square_at(mouse.column, mouse.row) {
+            self.cursor = square;
+            self.activate(square);
+        }
+    }
+
+    /// Returns the square drawn at the given terminal cell, if any.
+    fn square_at(&self, column: u16, row: u16) -> Option<Square> {
+        let screen_file = column.checked_sub(BOARD_LEFT)? / SQUARE_WIDTH;
+        let screen_rank = row.checked_sub(BOARD_TOP)?;
+
+        if screen_file >= 8 || screen_rank >= 8 {
+            return None;
+        }
+
+        let (file, rank) = match self.perspective() {
+            Color::White => (screen_file, 7 - screen_rank),
+            Color::Black => (7 - screen_file, screen_rank),
+        };
+
+        Some(Square::new(
+            File::from_index(file as u8)?,
+            Rank::from_index(rank as u8)?,
+        ))
+    }
+
+    /// Returns the terminal cell of the left edge of the square.
+    fn cell_of(&self, square: Square) -> (u16, u16) {
+        let (file, rank) = (square.file().index() as u16, square.rank().index() as u16);
+        let (screen_file, screen_rank) = match self.perspective() {
+            Color::White => (file, 7 - rank),
+            Color::Black => (7 - file, rank),
+        };
+
+        (
+            BOARD_LEFT + screen_file * SQUARE_WIDTH,
+            BOARD_TOP + screen_rank,
+        )
+    }
+
+    /// Moves the cursor in the given direction as seen on the screen.
+    fn move_cursor(&mut self, right: i8, up: i8) {
+        let (files, ranks) = match self.perspective() {
+            Color::White => (right, up),
+            Color::Black => (-right, -up),
+        };
+
+        if let Some(square) = self.cursor.offset(files, ranks) {
+            self.cursor = square;
+        }
+    }
+
+    /// Selects the piece on the square, or moves the selected piece there.
+    fn activate(&mut self, square: Square) {
+        if self.engine_to_move().is_some() || self.game.outcome().is_some() {
+            return;
+        }
+
+        let side_to_move = self.game.get_board_state().side_to_move;
+        let own_piece = self
+            .game
+            .get_position()
+            .piece_at(square)
+            .is_some_and(|(color, _)| color == side_to_move);
+
+        match self.selected {
+            Some(selected) if selected == square => self.selected = None,
+            _ if own_piece => self.selected = Some(square),
+            None => (),
+            Some(selected) => self.drop_piece(selected, square),
+        }
+    }
+
+    /// Plays the move of the selected piece to the target square, or asks for the piece to
+    /// promote to.
+    fn drop_piece(&mut self, initial_square: Square, target_square: Square) {
+        let mut moves = self.legal_moves_from(initial_square);
+
+        let Some(mv) = moves.find(|mv| mv.get_target_square() == target_square) else {
+            self.message = format!("{initial_square}{target_square} isn't a legal move.");
+            return;
+        };
+
+        if mv.is_promotion() {
+            self.pending_promotion = Some((initial_square, target_square));
+            self.message = "Promote to (q)ueen, (r)ook, (b)ishop or k(n)ight?".to_string();
+        } else {
+            self.play_move(mv);
+        }
+    }
+
+    fn promote(&mut self, piece: Piece) {
+        let Some((initial_square, target_square)) = self.pending_promotion.take() else {
+            return;
+        };
+
+        let mv = self.legal_moves_from(initial_square).find(|mv| {
+            mv.get_target_square() == target_square && mv.promotion_piece() == Some(piece)
+        });
+
+        if let Some(mv) = mv {
+            self.play_move(mv);
+        }
+    }
+
+    fn legal_moves_from(&self, initial_square: Square) -> impl Iterator<Item = Move> + use<> {
+        let legal_moves = self.game.enumerate_moves();
+
+        MoveListIterator::new(&legal_moves)
+            .map(|move_score| move_score.mv)
+            .filter(|mv| mv.get_initial_square() == initial_square)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Returns the squares the selected piece can move to.
+    fn legal_targets(&self) -> Bitboard {
+        match self.selected {
+            Some(selected) => self
+                .legal_moves_from(selected)
+                .map(|mv| mv.get_target_square())
+                .collect(),
+            None => Bitboard::EMPTY,
+        }
+    }
+
+    fn play_move(&mut self, mv: Move) {
+        if let Err(err) = self.game.try_make_move(mv) {
+            self.message = format!("Can't play {mv}: {err}");
+            return;
+        }
+
+        // Engines would otherwise play a drawn position forever, while humans claim draws
+        // themselves.
+        let draw = if self.players.contains(&Player::Human) {
+            None
+        } else {
+            self.game.adjudicate_draw()
+        };
+
+        self.selected = None;
+        self.message.clear();
+        self.start_clock();
+
+        if let Some(reason) = draw {
+            self.message = format!("Draw by {reason}!");
+        }
+    }
+
+    fn play_engine_move(&mut self, out: &mut impl Write, limits: SearchLimits) -> io::Result<()> {
+        self.message = "Thinking...".to_string();
+
+        let result = self.search(out, limits)?;
+
+        match result.best_move {
+            Some(mv) => self.play_move(mv),
+            None => self.message = "The engine has no move to play.".to_string(),
+        }
+
+        Ok(())
+    }
+
+    fn analyse(&mut self, out: &mut impl Write) -> io::Result<()> {
+        self.message = "Analysing...".to_string();
+        self.search(out, SearchLimits::from_level(ANALYSIS_LEVEL))?;
+        self.message.clear();
+
+        Ok(())
+    }
+
+    /// Searches the current position, updating the analysis pane after every iteration.
+    fn search(&mut self, out: &mut impl Write, limits: SearchLimits) -> io::Result<SearchResult> {
+        let game = self.game.clone();
+        let mut draw_result = Ok(());
+
+        let result = search::search_with_info(&game, limits, |result| {
+            self.analysis = Some(Analysis::new(&game, result));
+
+            if draw_result.is_ok() {
+                draw_result = self.draw(out);
+            }
+        });
+
+        draw_result.map(|()| result)
+    }
+
+    /// Takes back the last move, along with the engine's replies, so that it is a human's
+    /// turn again.
+    /// Ends the game as a draw if the human to move can claim one by the rules.
+    fn claim_draw(&mut self) {
+        if self.game.outcome().is_some() || self.engine_to_move().is_some() {
+            return;
+        }
+
+        if self.game.claim_draw() {
+            self.start_clock();
+            self.message = "Draw claimed!".to_string();
+        } else {
+            self.message = "There is no draw to claim.".to_string();
+        }
+    }
+
+    fn undo(&mut self) {
+        if self.game.undo().is_none() {
+            return;
+        }
+
+        while self.game.ply() > 0 && self.engine_to_move().is_some() {
+            self.game.undo();
+        }
+
+        self.selected = None;
+        self.analysis = None;
+        self.message.clear();
+        self.start_clock();
+    }
+
+    /// Starts the clock of the side to move, or stops the clocks once the game is over.
+    fn start_clock(&mut self) {
+        let side_to_move = self.game.get_board_state().side_to_move;
+
+        self.clock
+            .start(self.game.outcome().is_none().then_some(side_to_move));
+
+        if let Some(outcome) = self.game.outcome() {
+            self.message = match outcome {
+                Outcome::Win(Color::White) => "White wins!".to_string(),
+                Outcome::Win(Color::Black) => "Black wins!".to_string(),
+                Outcome::Draw => "Draw!".to_string(),
+            };
+        }
+    }
+
+    /// Returns the move list in SAN, one full move per line.
+    fn move_list(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut before = self.game.history().start();
+
+        for entry in self.game.history().entries() {
+            match before.side_to_move {
+                Color::White => lines.push(format!("{:>3}. {}", before.turn_count, entry.san)),
+                Color::Black if lines.is_empty() => {
+                    lines.push(format!("{:>3}... {}", before.turn_count, entry.san));
+                }
+                Color::Black => {
+                    if let Some(line) = lines.last_mut() {
+                        line.push(' ');
+                        line.push_str(&entry.san);
+                    }
+                }
+            }
+
+            before = &entry.board_state;
+        }
+
+        lines
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        self.draw_board(out)?;
+        self.draw_pane(out)?;
+
+        queue!(
+            out,
+            cursor::MoveTo(0, STATUS_ROW),
+            Print(&self.message),
+            terminal::Clear(ClearType::UntilNewLine),
+            cursor::MoveTo(0, STATUS_ROW + 1),
+            Print(HELP),
+            terminal::Clear(ClearType::UntilNewLine),
+        )?;
+
+        out.flush()
+    }
+
+    fn draw_board(&self, out: &mut impl Write) -> io::Result<()> {
+        let position = self.game.get_position();
+        let targets = self.legal_targets();
+        let last_move = self
+            .game
+            .history()
+            .get(self.game.ply())
+            .map(|entry| entry.mv);
+
+        for square in Square::ALL {
+            let (column, row) = self.cell_of(square);
+            let is_light = (square.file().index() + square.rank().index()) % 2 == 1;
+
+            let background = if square == self.cursor {
+                CURSOR_SQUARE
+            } else if Some(square) == self.selected {
+                SELECTED_SQUARE
+            } else if targets.contains(square) {
+                TARGET_SQUARE
+            } else if last_move.is_some_and(|mv| {
+                mv.get_initial_square() == square || mv.get_target_square() == square
+            }) {
+                LAST_MOVE_SQUARE
+            } else if is_light {
+                LIGHT_SQUARE
+            } else {
+                DARK_SQUARE
+            };
+
+            let (foreground, glyph) = match position.piece_at(square) {
+                Some((Color::White, piece)) => (WHITE_PIECE, piece_glyph(piece)),
+                Some((Color::Black, piece)) => (BLACK_PIECE, piece_glyph(piece)),
+                None => (BLACK_PIECE, ' '),
+            };
+
+            queue!(
+                out,
+                cursor::MoveTo(column, row),
+                SetBackgroundColor(background),
+                SetForegroundColor(foreground),
+                Print(format!(" {glyph} ")),
+                ResetColor,
+            )?;
+        }
+
+        for i in 0..8 {
+            let (file, rank) = match self.perspective() {
+                Color::White => (i, 7 - i),
+                Color::Black => (7 - i, i),
+            };
+
+            queue!(
+                out,
+                cursor::MoveTo(BOARD_LEFT - 2, BOARD_TOP + i as u16),
+                Print(Rank::from_index(rank).map_or(' ', Rank::to_char)),
+                cursor::MoveTo(BOARD_LEFT + i as u16 * SQUARE_WIDTH + 1, BOARD_TOP + 8),
+                Print(File::from_index(file).map_or(' ', File::to_char)),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_pane(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut lines = Vec::new();
+
+        for color in [Color::White, Color::Black] {
+            let player = match self.players[color.to_index()] {
+                Player::Human => "human",
+                Player::Engine(_) => "engine",
+            };
+            let used = self.clock.used(color).as_secs();
+            let marker = if self.game.get_board_state().side_to_move == color {
+                '*'
+            } else {
+                ' '
+            };
+
+            lines.push(format!(
+                "{marker} {:<6} {player:<7} {:02}:{:02}",
+                match color {
+                    Color::White => "White",
+                    Color::Black => "Black",
+                },
+                used / 60,
+                used % 60
+            ));
+        }
+
+        lines.push(String::new());
+        lines.push("Moves".to_string());
+
+        let move_list = self.move_list();
+        let first_row = move_list.len().saturating_sub(MOVE_LIST_ROWS);
+
+        lines.extend(move_list[first_row..].iter().cloned());
+        lines.resize(4 + MOVE_LIST_ROWS, String::new());
+
+        lines.push(String::new());
+        lines.push("Analysis".to_string());
+
+        match &self.analysis {
+            Some(analysis) => {
+                lines.push(format!(
+                    "depth {}  score {}  nodes {}",
+                    analysis.depth, analysis.score, analysis.nodes
+                ));
+                lines.push(analysis.pv.clone());
+            }
+            None => lines.push("press `a` to analyse".to_string()),
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            let line: String = line.chars().take(PANE_WIDTH).collect();
+
+            queue!(
+                out,
+                cursor::MoveTo(PANE_LEFT, BOARD_TOP + i as u16),
+                Print(line),
+                terminal::Clear(ClearType::UntilNewLine),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+
+    #[test]
+    fn test_square_at() {
+        let move_gen = MoveGenerator::new();
+        let mut tui = Tui::new(Game::new(&move_gen), Player::Human, Player::Human);
+
+        assert_eq!(tui.square_at(BOARD_LEFT, BOARD_TOP), Some(Square::A8));
+        assert_eq!(
+            tui.square_at(BOARD_LEFT + 8 * SQUARE_WIDTH - 1, BOARD_TOP + 7),
+            Some(Square::H1)
+        );
+        assert_eq!(tui.square_at(BOARD_LEFT - 1, BOARD_TOP), None);
+        assert_eq!(tui.square_at(BOARD_LEFT, BOARD_TOP + 8), None);
+
+        tui.flipped = true;
+        assert_eq!(tui.square_at(BOARD_LEFT, BOARD_TOP), Some(Square::H1));
+
+        for square in Square::ALL {
+            let (column, row) = tui.cell_of(square);
+            assert_eq!(tui.square_at(column + 1, row), Some(square));
+        }
+
+        // The cursor moves as seen on the screen.
+        tui.cursor = Square::E2;
+        tui.move_cursor(1, 1);
+        assert_eq!(tui.cursor, Square::D1);
+    }
+
+    #[test]
+    fn test_move_entry() {
+        let move_gen = MoveGenerator::new();
+        let game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", &move_gen).unwrap();
+        let mut tui = Tui::new(game, Player::Human, Player::Human);
+
+        // Black's pieces can't be selected on white's turn.
+        tui.activate(Square::E8);
+        assert_eq!(tui.selected, None);
+
+        tui.activate(Square::E1);
+        assert_eq!(tui.legal_targets().count(), 5);
+
+        tui.activate(Square::B7);
+        assert_eq!(tui.selected, Some(Square::B7));
+        tui.activate(Square::B8);
+        assert_eq!(tui.pending_promotion, Some((Square::B7, Square::B8)));
+
+        tui.promote(Piece::Knight);
+        assert_eq!(
+            tui.game().get_position().piece_at(Square::B8),
+            Some((Color::White, Piece::Knight))
+        );
+        assert_eq!(tui.move_list(), ["  1. b8=N"]);
+
+        // A lone knight can't mate, but humans claim the draw themselves.
+        assert_eq!(tui.game().outcome(), None);
+        assert_eq!(tui.message, "");
+
+        tui.claim_draw();
+        assert_eq!(tui.game().outcome(), Some(Outcome::Draw));
+        assert_eq!(tui.message, "Draw claimed!");
+    }
+
+    #[test]
+    fn test_engine_game_ends_in_drawn_position() {
+        let move_gen = MoveGenerator::new();
+        let fen = "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1";
+        let engine = Player::Engine(SearchLimits::depth(1));
+
+        // Engines would play on forever, so the game is adjudicated.
+        let mut tui = Tui::new(Game::from_fen(fen, &move_gen).unwrap(), engine, engine);
+        tui.play_move("b7b8n".parse().unwrap());
+        assert_eq!(tui.game().outcome(), Some(Outcome::Draw));
+        assert_eq!(tui.message, "Draw by insufficient material!");
+        assert_eq!(tui.engine_to_move(), None);
+
+        // Against a human, the game goes on.
+        let mut tui = Tui::new(
+            Game::from_fen(fen, &move_gen).unwrap(),
+            engine,
+            Player::Human,
+        );
+        tui.play_move("b7b8n".parse().unwrap());
+        assert_eq!(tui.game().outcome(), None);
+
+        // A knight and a king against a king can still mate, if not by force.
+        let fen = "4k3/1P6/8/8/8/8/8/1N2K3 w - - 0 1";
+        let mut tui = Tui::new(Game::from_fen(fen, &move_gen).unwrap(), engine, engine);
+        tui.play_move("b7b8n".parse().unwrap());
+        assert_eq!(tui.game().outcome(), None);
+    }
+}
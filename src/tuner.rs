@@ -0,0 +1,923 @@
+//! Texel-style tuning of the evaluation weights.
+//!
+//! The evaluation is linear in its weights once the game phase is known, so each position
+//! of a dataset is reduced to a sparse list of feature coefficients. The tuner then fits
+//! the weights to the game results by minimising the mean squared error between each
+//! result and the evaluation mapped to an expected score by a sigmoid,
+//! `1 / (1 + 10^(-K * eval / 400))`, with `K` fitted to the dataset first.
+
+use crate::boardstate::{BoardState, Endgame};
+use crate::eval::{self, ALL_PIECES, MAX_PHASE, PIECE_VALUES};
+use crate::game::Game;
+use crate::movegen::MoveGenerator;
+use crate::pawns::{self, PawnHashTable, PawnStructure};
+use crate::search;
+use crate::tournament;
+use crate::{Color, Piece};
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+
+/// The index of the first piece value: pawn, knight, bishop, rook and queen.
+const MATERIAL: usize = 0;
+/// The index of the first piece-square table entry. The tables follow the piece indices,
+/// with the king's endgame table last, 64 entries each.
+const TABLES: usize = MATERIAL + 5;
+/// The index of the first pawn structure term.
+const PAWN_TERMS: usize = TABLES + 7 * 64;
+const CANDIDATE: usize = PAWN_TERMS + 8;
+const CONNECTED: usize = CANDIDATE + 1;
+const ISOLATED: usize = CONNECTED + 1;
+const DOUBLED: usize = ISOLATED + 1;
+const BACKWARD: usize = DOUBLED + 1;
+const ISLANDS: usize = BACKWARD + 1;
+
+/// The number of tuned parameters.
+pub const PARAM_COUNT: usize = ISLANDS + 1;
+
+/// The range searched for the `K` constant.
+const K_RANGE: Range<f64> = 0.0..4.0;
+const K_ITERATIONS: u32 = 40;
+
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+/// A group of parameters, named after the constant of the evaluation it replaces.
+struct Segment {
+    name: &'static str,
+    len: usize,
+    defaults: &'static [i32],
+}
+
+/// The parameters in the order of the parameter vector.
+const SEGMENTS: [Segment; 15] = [
+    segment("PIECE_VALUES", 5, &PIECE_VALUES),
+    segment("PAWN_TABLE", 64, &eval::PAWN_TABLE),
+    segment("KNIGHT_TABLE", 64, &eval::KNIGHT_TABLE),
+    segment("BISHOP_TABLE", 64, &eval::BISHOP_TABLE),
+    segment("ROOK_TABLE", 64, &eval::ROOK_TABLE),
+    segment("QUEEN_TABLE", 64, &eval::QUEEN_TABLE),
+    segment("KING_MIDDLEGAME_TABLE", 64, &eval::KING_MIDDLEGAME_TABLE),
+    segment("KING_ENDGAME_TABLE", 64, &eval::KING_ENDGAME_TABLE),
+    segment("PASSED_PAWN_BONUS", 8, &pawns::PASSED_PAWN_BONUS),
+    segment("CANDIDATE_PAWN_BONUS", 1, &[pawns::CANDIDATE_PAWN_BONUS]),
+    segment("CONNECTED_PAWN_BONUS", 1, &[pawns::CONNECTED_PAWN_BONUS]),
+    segment("ISOLATED_PAWN_PENALTY", 1, &[pawns::ISOLATED_PAWN_PENALTY]),
+    segment("DOUBLED_PAWN_PENALTY", 1, &[pawns::DOUBLED_PAWN_PENALTY]),
+    segment("BACKWARD_PAWN_PENALTY", 1, &[pawns::BACKWARD_PAWN_PENALTY]),
+    segment("PAWN_ISLAND_PENALTY", 1, &[pawns::PAWN_ISLAND_PENALTY]),
+];
+
+const fn segment(name: &'static str, len: usize, defaults: &'static [i32]) -> Segment {
+    Segment {
+        name,
+        len,
+        defaults,
+    }
+}
+
+/// Returns each segment along with the range of its parameters.
+fn segments() -> impl Iterator<Item = (&'static Segment, Range<usize>)> {
+    SEGMENTS.iter().scan(0, |start, segment| {
+        let range = *start..*start + segment.len;
+        *start = range.end;
+
+        Some((segment, range))
+    })
+}
+
+/// The errors that can stop the tuner.
+#[derive(Debug)]
+pub enum TunerError {
+    /// A dataset or config file couldn't be read.
+    Io(io::Error),
+    /// A line of the dataset, with its line number, has no valid position or result.
+    BadLine(usize, String),
+    /// A line of a parameter config file is malformed.
+    BadConfig(String),
+    /// There are no positions to tune on.
+    EmptyDataset,
+}
+
+impl fmt::Display for TunerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::BadLine(line, err) => write!(f, "bad dataset line {line}: {err}"),
+            Self::BadConfig(line) => write!(f, "bad config line `{line}`"),
+            Self::EmptyDataset => write!(f, "the dataset has no positions"),
+        }
+    }
+}
+
+impl Error for TunerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TunerError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// A set of evaluation weights, in centipawns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalParams {
+    values: Vec<f64>,
+}
+
+impl EvalParams {
+    /// Returns every parameter, in the order of the parameter vector.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Returns the parameters replacing the evaluation constant with the given name.
+    pub fn get(&self, name: &str) -> Option<&[f64]> {
+        segments()
+            .find(|(segment, _)| segment.name == name)
+            .map(|(_, range)| &self.values[range])
+    }
+
+    /// Returns the parameters as the Rust constants of the evaluation, rounded to whole
+    /// centipawns, ready to replace the ones in `eval.rs` and `pawns.rs`.
+    pub fn to_rust(&self) -> String {
+        let mut rust = String::new();
+        let mut file = "";
+
+        for (segment, range) in segments() {
+            let source = if range.start < PAWN_TERMS {
+                "eval.rs"
+            } else {
+                "pawns.rs"
+            };
+            let values: Vec<i32> = self.values[range]
+                .iter()
+                .map(|value| value.round() as i32)
+                .collect();
+            let list = |values: &[i32]| {
+                values
+                    .iter()
+                    .map(i32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+
+            if source != file {
+                file = source;
+                let _ = writeln!(rust, "// {file}");
+            }
+
+            let _ = match segment.len {
+                // The king has no material value.
+                _ if segment.name == "PIECE_VALUES" => writeln!(
+                    rust,
+                    "pub const PIECE_VALUES: [i32; 6] = [{}, 0];",
+                    list(&values)
+                ),
+                64 => {
+                    let _ = writeln!(
+                        rust,
+                        "#[rustfmt::skip]\npub(crate) const {}: [i32; 64] = [",
+                        segment.name
+                    );
+
+                    for row in values.chunks(8) {
+                        let row: String = row.iter().map(|value| format!("{value:>3},")).collect();
+                        let _ = writeln!(rust, "    {row}");
+                    }
+
+                    writeln!(rust, "];")
+                }
+                1 => writeln!(
+                    rust,
+                    "pub(crate) const {}: i32 = {};",
+                    segment.name, values[0]
+                ),
+                len => writeln!(
+                    rust,
+                    "pub(crate) const {}: [i32; {len}] = [{}];",
+                    segment.name,
+                    list(&values)
+                ),
+            };
+        }
+
+        rust
+    }
+
+    /// Returns the parameters as a config file, one line per evaluation constant with its
+    /// name followed by its values. [`EvalParams::from_config`] reads it back, so that
+    /// tuning can resume from it.
+    pub fn to_config(&self) -> String {
+        let mut config = String::new();
+
+        for (segment, range) in segments() {
+            let values: Vec<String> = self.values[range]
+                .iter()
+                .map(|value| format!("{value:.2}"))
+                .collect();
+            let _ = writeln!(config, "{} {}", segment.name, values.join(" "));
+        }
+
+        config
+    }
+
+    /// Parses a config file written by [`EvalParams::to_config`]. Constants missing from
+    /// the file keep their current values, and lines starting with `#` are comments.
+    ///
+    /// # Errors
+    /// Returns [`TunerError::BadConfig`] if a line names an unknown constant or doesn't
+    /// have one number for each of its values.
+    pub fn from_config(config: &str) -> Result<Self, TunerError> {
+        let mut params = Self::default();
+
+        for line in config.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let bad_line = || TunerError::BadConfig(line.to_string());
+            let mut fields = line.split_ascii_whitespace();
+            let name = fields.next().ok_or_else(bad_line)?;
+            let (_, range) = segments()
+                .find(|(segment, _)| segment.name == name)
+                .ok_or_else(bad_line)?;
+            let values = fields
+                .map(str::parse)
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| bad_line())?;
+
+            if values.len() != range.len() {
+                return Err(bad_line());
+            }
+
+            params.values[range].copy_from_slice(&values);
+        }
+
+        Ok(params)
+    }
+}
+
+impl Default for EvalParams {
+    /// Returns the weights the evaluation currently uses.
+    fn default() -> Self {
+        let mut values = vec![0.0; PARAM_COUNT];
+
+        for (segment, range) in segments() {
+            for (value, &default) in values[range].iter_mut().zip(segment.defaults) {
+                *value = f64::from(default);
+            }
+        }
+
+        Self { values }
+    }
+}
+
+/// A position of the dataset, reduced to the coefficient of each parameter in its
+/// evaluation from white's point of view.
+#[derive(Debug, Clone)]
+struct Sample {
+    features: Vec<(u16, f32)>,
+    /// The factor the whole evaluation is scaled by, for drawn material and drawish
+    /// endgames.
+    scale: f32,
+    /// The result of the game from white's point of view: `1` for a win, `0.5` for a draw
+    /// and `0` for a loss.
+    result: f32,
+}
+
+impl Sample {
+    fn new(board_state: &BoardState, result: f32) -> Self {
+        let phase = eval::phase(board_state) as f32 / MAX_PHASE as f32;
+        let structure = PawnStructure::from_position(&board_state.position);
+        let mut features = Vec::new();
+
+        for (color, sign) in [(Color::White, 1.0), (Color::Black, -1.0)] {
+            for piece in ALL_PIECES {
+                for square in board_state.position.board(color, piece) {
+                    // The tables are written from white's point of view with the eighth
+                    // rank first.
+                    let index = match color {
+                        Color::White => square.flip_rank().index(),
+                        Color::Black => square.index(),
+                    };
+                    let table = TABLES + piece.to_index() * 64 + index;
+
+                    if piece == Piece::King {
+                        features.push((table, sign * phase));
+                        features.push((table + 64, sign * (1.0 - phase)));
+                    } else {
+                        features.push((MATERIAL + piece.to_index(), sign));
+                        features.push((table, sign));
+                    }
+                }
+            }
+
+            for square in structure.passed(color) {
+                let rank = match color {
+                    Color::White => square.rank(),
+                    Color::Black => square.rank().flip(),
+                };
+
+                features.push((PAWN_TERMS + rank.index(), sign));
+            }
+
+            let counts = [
+                (CANDIDATE, structure.candidate(color).count()),
+                (CONNECTED, structure.connected(color).count()),
+                (ISOLATED, structure.isolated(color).count()),
+                (DOUBLED, structure.doubled(color).count()),
+                (BACKWARD, structure.backward(color).count()),
+                (ISLANDS, structure.islands(color).saturating_sub(1)),
+            ];
+
+            for (index, count) in counts {
+                features.push((index, sign * count as f32));
+            }
+        }
+
+        // Merge the coefficients of each parameter, dropping the ones that cancel out.
+        features.sort_unstable_by_key(|&(index, _)| index);
+        let mut merged: Vec<(u16, f32)> = Vec::with_capacity(features.len());
+
+        for (index, coefficient) in features {
+            match merged.last_mut() {
+                Some(last) if usize::from(last.0) == index => last.1 += coefficient,
+                _ => merged.push((index as u16, coefficient)),
+            }
+        }
+
+        merged.retain(|&(_, coefficient)| coefficient != 0.0);
+
+        let scale = if board_state.is_drawn_material() {
+            0.0
+        } else if board_state.endgame() == Some(Endgame::OppositeColoredBishops) {
+            0.5
+        } else {
+            1.0
+        };
+
+        Self {
+            features: merged,
+            scale,
+            result,
+        }
+    }
+
+    /// Returns the evaluation from white's point of view with the given parameters.
+    fn evaluate(&self, params: &[f64]) -> f64 {
+        let score: f64 = self
+            .features
+            .iter()
+            .map(|&(index, coefficient)| params[usize::from(index)] * f64::from(coefficient))
+            .sum();
+
+        score * f64::from(self.scale)
+    }
+}
+
+/// A set of positions labelled with the result of the game they were played in.
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+    samples: Vec<Sample>,
+    skipped: usize,
+}
+
+impl Dataset {
+    /// Parses a dataset with one position per line, spread over the given number of
+    /// threads.
+    ///
+    /// A line is a FEN, or the first four fields of one, followed by the result from
+    /// white's point of view: either an EPD `c9` operation like `c9 "1-0";`, or a last
+    /// field like `1-0`, `1/2-1/2`, `[0.5]` or `0.0`. Blank lines and lines starting with
+    /// `#` are skipped.
+    ///
+    /// With `quiet_only`, positions in check or where the quiescence search finds a better
+    /// capture sequence than the static evaluation are left out, since their evaluation
+    /// says little about the result.
+    ///
+    /// # Errors
+    /// Returns [`TunerError::BadLine`] for the first line without a valid position or
+    /// result.
+    pub fn parse(
+        text: &str,
+        quiet_only: bool,
+        threads: usize,
+        move_gen: &MoveGenerator,
+    ) -> Result<Self, TunerError> {
+        let lines: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        let chunk_size = lines.len().div_ceil(threads.max(1)).max(1);
+
+        let chunks = thread::scope(|scope| {
+            let handles: Vec<_> = lines
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || parse_chunk(chunk, quiet_only, move_gen)))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("dataset parser panicked"))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        let mut dataset = Self::default();
+
+        for chunk in chunks {
+            dataset.append(chunk);
+        }
+
+        Ok(dataset)
+    }
+
+    /// Reads and parses a dataset file like [`Dataset::parse`].
+    ///
+    /// # Errors
+    /// Returns a [`TunerError`] if the file can't be read or parsed.
+    pub fn load(
+        path: &Path,
+        quiet_only: bool,
+        threads: usize,
+        move_gen: &MoveGenerator,
+    ) -> Result<Self, TunerError> {
+        Self::parse(&fs::read_to_string(path)?, quiet_only, threads, move_gen)
+    }
+
+    /// Moves the positions of another dataset into this one.
+    pub fn append(&mut self, other: Self) {
+        self.samples.extend(other.samples);
+        self.skipped += other.skipped;
+    }
+
+    /// Returns the number of positions.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns whether there are no positions.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns the number of positions left out for not being quiet.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+fn parse_chunk(
+    lines: &[(usize, &str)],
+    quiet_only: bool,
+    move_gen: &MoveGenerator,
+) -> Result<Dataset, TunerError> {
+    let mut dataset = Dataset::default();
+    let mut pawn_table = PawnHashTable::default();
+
+    for &(number, line) in lines {
+        let bad_line = |err: &str| TunerError::BadLine(number, err.to_string());
+        let fields: Vec<&str> = line.split_ascii_whitespace().collect();
+        let position_len = tournament::epd_position_len(&fields);
+        let board_state = BoardState::from_fen_lenient(&fields[..position_len].join(" "))
+            .map_err(|err| bad_line(&err.to_string()))?;
+        let result = parse_result(&fields[position_len..]).ok_or_else(|| bad_line("no result"))?;
+
+        if quiet_only {
+            let game = Game::from_board_state(board_state.clone(), move_gen);
+
+            if game.is_in_check()
+                || search::quiescence(&game, &mut pawn_table) != eval::evaluate(&board_state)
+            {
+                dataset.skipped += 1;
+                continue;
+            }
+        }
+
+        dataset.samples.push(Sample::new(&board_state, result));
+    }
+
+    Ok(dataset)
+}
+
+/// Parses the result following the position on a dataset line.
+fn parse_result(fields: &[&str]) -> Option<f32> {
+    let field = match fields.iter().position(|&field| field == "c9") {
+        Some(c9) => fields.get(c9 + 1)?,
+        None => fields.last()?,
+    };
+    let result = field.trim_matches(|c| matches!(c, '"' | ';' | '[' | ']'));
+
+    match result {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        _ => result
+            .parse()
+            .ok()
+            .filter(|result| (0.0..=1.0).contains(result)),
+    }
+}
+
+/// The algorithm updating the parameters after each pass over the dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Optimizer {
+    /// Plain gradient descent.
+    GradientDescent,
+    /// Adam, which adapts the step of each parameter to its own gradients. It copes much
+    /// better with parameters that only a few positions use.
+    #[default]
+    Adam,
+}
+
+impl Optimizer {
+    /// Returns a learning rate that works for most datasets.
+    pub fn default_learning_rate(self) -> f64 {
+        match self {
+            Self::GradientDescent => 1000.0,
+            Self::Adam => 1.0,
+        }
+    }
+}
+
+impl FromStr for Optimizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gd" | "gradient-descent" => Ok(Self::GradientDescent),
+            "adam" => Ok(Self::Adam),
+            _ => Err(format!("unknown optimizer `{s}`, expected `adam` or `gd`")),
+        }
+    }
+}
+
+/// The settings of a tuning run.
+#[derive(Debug, Clone)]
+pub struct TunerConfig {
+    pub optimizer: Optimizer,
+    /// The learning rate, or the optimizer's default.
+    pub learning_rate: Option<f64>,
+    /// The number of passes over the dataset.
+    pub epochs: u32,
+    /// The number of threads computing the gradient.
+    pub threads: usize,
+    /// The scaling constant of the sigmoid, or `None` to fit it to the dataset first.
+    pub k: Option<f64>,
+}
+
+impl Default for TunerConfig {
+    fn default() -> Self {
+        Self {
+            optimizer: Optimizer::default(),
+            learning_rate: None,
+            epochs: 1000,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            k: None,
+        }
+    }
+}
+
+/// The outcome of a tuning run.
+#[derive(Debug, Clone)]
+pub struct Tuning {
+    pub params: EvalParams,
+    /// The scaling constant of the sigmoid the parameters were fitted with.
+    pub k: f64,
+    /// The mean squared error of the initial parameters.
+    pub initial_error: f64,
+    /// The mean squared error of the tuned parameters.
+    pub error: f64,
+}
+
+/// Fits the parameters to the dataset, starting from `params`. The progress callback is
+/// called after every epoch with the epoch number and the error before its update.
+///
+/// # Errors
+/// Returns [`TunerError::EmptyDataset`] if there are no positions to tune on.
+pub fn tune(
+    dataset: &Dataset,
+    params: EvalParams,
+    config: &TunerConfig,
+    mut progress: impl FnMut(u32, f64),
+) -> Result<Tuning, TunerError> {
+    if dataset.is_empty() {
+        return Err(TunerError::EmptyDataset);
+    }
+
+    let samples = &dataset.samples;
+    let threads = config.threads.max(1);
+    let k = config
+        .k
+        .unwrap_or_else(|| fit_k(samples, &params.values, threads));
+    let initial_error = mean_error(samples, &params.values, k, threads);
+    let learning_rate = config
+        .learning_rate
+        .unwrap_or_else(|| config.optimizer.default_learning_rate());
+
+    let mut values = params.values;
+    let mut moments = vec![(0.0, 0.0); PARAM_COUNT];
+
+    for epoch in 1..=config.epochs {
+        let (gradient, error) = gradient(samples, &values, k, threads);
+
+        match config.optimizer {
+            Optimizer::GradientDescent => {
+                for (value, slope) in values.iter_mut().zip(&gradient) {
+                    *value -= learning_rate * slope;
+                }
+            }
+            Optimizer::Adam => {
+                let correction1 = 1.0 - ADAM_BETA1.powi(epoch as i32);
+                let correction2 = 1.0 - ADAM_BETA2.powi(epoch as i32);
+
+                for ((value, slope), (mean, variance)) in
+                    values.iter_mut().zip(&gradient).zip(&mut moments)
+                {
+                    *mean = ADAM_BETA1 * *mean + (1.0 - ADAM_BETA1) * slope;
+                    *variance = ADAM_BETA2 * *variance + (1.0 - ADAM_BETA2) * slope * slope;
+                    *value -= learning_rate * (*mean / correction1)
+                        / ((*variance / correction2).sqrt() + ADAM_EPSILON);
+                }
+            }
+        }
+
+        progress(epoch, error);
+    }
+
+    Ok(Tuning {
+        error: mean_error(samples, &values, k, threads),
+        params: EvalParams { values },
+        k,
+        initial_error,
+    })
+}
+
+/// Returns the expected score of an evaluation in centipawns.
+fn sigmoid(k: f64, score: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+/// Returns the `K` minimising the error of the given parameters, by golden-section search.
+fn fit_k(samples: &[Sample], params: &[f64], threads: usize) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let error = |k| mean_error(samples, params, k, threads);
+    let (mut low, mut high) = (K_RANGE.start, K_RANGE.end);
+
+    for _ in 0..K_ITERATIONS {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+
+        if error(left) < error(right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+/// Splits the samples between the threads and sums what `f` returns for each chunk.
+fn map_chunks<T: Send>(
+    samples: &[Sample],
+    threads: usize,
+    f: impl Fn(&[Sample]) -> T + Sync,
+) -> Vec<T> {
+    let chunk_size = samples.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = samples
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| f(chunk)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("tuner thread panicked"))
+            .collect()
+    })
+}
+
+/// Returns the mean squared error between the results and the expected scores.
+fn mean_error(samples: &[Sample], params: &[f64], k: f64, threads: usize) -> f64 {
+    let sums = map_chunks(samples, threads, |chunk| {
+        chunk
+            .iter()
+            .map(|sample| (f64::from(sample.result) - sigmoid(k, sample.evaluate(params))).powi(2))
+            .sum::<f64>()
+    });
+
+    sums.iter().sum::<f64>() / samples.len() as f64
+}
+
+/// Returns the gradient of the mean squared error with respect to each parameter, and the
+/// error itself.
+fn gradient(samples: &[Sample], params: &[f64], k: f64, threads: usize) -> (Vec<f64>, f64) {
+    let chunks = map_chunks(samples, threads, |chunk| {
+        let mut gradient = vec![0.0; PARAM_COUNT];
+        let mut error = 0.0;
+
+        for sample in chunk {
+            let expected = sigmoid(k, sample.evaluate(params));
+            let difference = f64::from(sample.result) - expected;
+            // The derivative of the error with respect to the evaluation.
+            let slope = -2.0 * difference * expected * (1.0 - expected) * 10f64.ln() * k / 400.0
+                * f64::from(sample.scale);
+
+            for &(index, coefficient) in &sample.features {
+                gradient[usize::from(index)] += slope * f64::from(coefficient);
+            }
+
+            error += difference * difference;
+        }
+
+        (gradient, error)
+    });
+
+    let count = samples.len() as f64;
+    let mut total = vec![0.0; PARAM_COUNT];
+    let mut error = 0.0;
+
+    for (gradient, chunk_error) in chunks {
+        for (sum, slope) in total.iter_mut().zip(gradient) {
+            *sum += slope / count;
+        }
+
+        error += chunk_error;
+    }
+
+    (total, error / count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dataset() {
+        let move_gen = MoveGenerator::new();
+        let text = "\
+# A comment, then a blank line.
+
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - c9 \"1/2-1/2\";
+4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 [1.0]
+4k3/4p3/8/8/8/8/8/4K3 b - - 0-1
+4k3/8/8/3q4/8/2N5/8/4K3 w - - 1-0
+";
+        let dataset = Dataset::parse(text, false, 2, &move_gen).unwrap();
+
+        assert_eq!(dataset.len(), 4);
+        assert_eq!(dataset.skipped(), 0);
+        assert_eq!(
+            dataset
+                .samples
+                .iter()
+                .map(|sample| sample.result)
+                .collect::<Vec<_>>(),
+            [0.5, 1.0, 0.0, 1.0]
+        );
+
+        // The knight takes the hanging queen, so that position isn't quiet.
+        let quiet = Dataset::parse(text, true, 3, &move_gen).unwrap();
+
+        assert_eq!(quiet.len(), 3);
+        assert_eq!(quiet.skipped(), 1);
+
+        assert!(matches!(
+            Dataset::parse("\n4k3/8/8/8/8/8/8/4K3 w - - draw\n", false, 1, &move_gen),
+            Err(TunerError::BadLine(2, _))
+        ));
+        assert!(matches!(
+            Dataset::parse("not a fen 1-0", false, 1, &move_gen),
+            Err(TunerError::BadLine(1, _))
+        ));
+    }
+
+    #[test]
+    fn test_features_match_evaluation() {
+        assert_eq!(
+            segments().map(|(segment, _)| segment.len).sum::<usize>(),
+            PARAM_COUNT
+        );
+
+        let params = EvalParams::default();
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R b KQ - 0 8",
+            "8/5k2/2p5/1pP5/1P3K2/8/6B1/3b4 w - - 0 50",
+            "4k3/1p6/8/P1P5/8/8/5PP1/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1",
+        ] {
+            let board_state = BoardState::from_fen(fen).unwrap();
+            let score = Sample::new(&board_state, 0.5).evaluate(params.values());
+            let expected = match board_state.side_to_move {
+                Color::White => eval::evaluate(&board_state),
+                Color::Black => -eval::evaluate(&board_state),
+            };
+
+            // The evaluation rounds the king's tapered score and halved scores down.
+            assert!((score - f64::from(expected)).abs() <= 2.0, "{fen}: {score}");
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let params = EvalParams::default();
+        let rust = params.to_rust();
+
+        assert!(rust.starts_with(
+            "// eval.rs\npub const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];\n"
+        ));
+        assert!(rust.contains(
+            "pub(crate) const KNIGHT_TABLE: [i32; 64] = [\n    -50,-40,-30,-30,-30,-30,-40,-50,\n"
+        ));
+        assert!(rust.contains("// pawns.rs\npub(crate) const PASSED_PAWN_BONUS: [i32; 8] = [0, 5, 10, 20, 35, 60, 100, 0];\n"));
+        assert!(rust.ends_with("pub(crate) const PAWN_ISLAND_PENALTY: i32 = -5;\n"));
+
+        let mut tuned = params.clone();
+        tuned.values[TABLES + 3] = 12.5;
+
+        assert_eq!(EvalParams::from_config(&tuned.to_config()).unwrap(), tuned);
+        assert_eq!(
+            EvalParams::from_config("# Only knights\nPIECE_VALUES 100 300 330 500 900\n")
+                .unwrap()
+                .get("PIECE_VALUES"),
+            Some(&[100.0, 300.0, 330.0, 500.0, 900.0][..])
+        );
+        assert_eq!(params.get("KING_TABLE"), None);
+        assert!(matches!(
+            EvalParams::from_config("PIECE_VALUES 100 300"),
+            Err(TunerError::BadConfig(_))
+        ));
+        assert!(matches!(
+            EvalParams::from_config("KING_VALUE 0"),
+            Err(TunerError::BadConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_tune() {
+        let move_gen = MoveGenerator::new();
+        // An extra knight only ever draws, and an extra rook always wins.
+        let text = "\
+4k3/pp6/8/8/8/8/PP6/1N2K3 w - - 1/2-1/2
+4k3/pp6/8/8/8/8/PP6/4K1N1 b - - 1/2-1/2
+4k3/1pp5/8/8/8/8/1PP5/2N1K3 w - - 1/2-1/2
+1n2k3/pp6/8/8/8/8/PP6/4K3 w - - 1/2-1/2
+r3k3/pp6/8/8/8/8/PP6/4K3 w - - 0-1
+4k2r/1pp5/8/8/8/8/1PP5/4K3 b - - 0-1
+4k3/pp6/8/8/8/8/PP6/R3K3 w - - 1-0
+4k3/1pp5/8/8/8/8/1PP5/4K2R b - - 1-0
+";
+        let dataset = Dataset::parse(text, false, 2, &move_gen).unwrap();
+        let config = TunerConfig {
+            epochs: 200,
+            threads: 2,
+            ..TunerConfig::default()
+        };
+        let mut epochs = 0;
+        let tuning = tune(&dataset, EvalParams::default(), &config, |epoch, _| {
+            epochs = epoch;
+        })
+        .unwrap();
+
+        assert_eq!(epochs, 200);
+        assert!(K_RANGE.contains(&tuning.k));
+        assert!(tuning.error < tuning.initial_error / 2.0);
+
+        let values = tuning.params.get("PIECE_VALUES").unwrap();
+        assert!(values[Piece::Knight.to_index()] < 320.0);
+        assert!(values[Piece::Rook.to_index()] > 500.0);
+
+        let gd = TunerConfig {
+            optimizer: Optimizer::GradientDescent,
+            k: Some(1.0),
+            epochs: 50,
+            ..config
+        };
+        let tuning = tune(&dataset, EvalParams::default(), &gd, |_, _| ()).unwrap();
+
+        assert_eq!(tuning.k, 1.0);
+        assert!(tuning.error < tuning.initial_error);
+        assert!(matches!(
+            tune(&Dataset::default(), EvalParams::default(), &gd, |_, _| ()),
+            Err(TunerError::EmptyDataset)
+        ));
+    }
+}
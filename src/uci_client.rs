@@ -0,0 +1,590 @@
+use crate::Color;
+use crate::game::Game;
+use crate::moves::Move;
+use crate::search::SearchLimits;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the engine gets to answer `uci` and `isready`.
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the engine gets to answer `go` beyond the time it was given to think.
+pub const DEFAULT_MOVE_OVERHEAD: Duration = Duration::from_secs(5);
+
+/// How long the engine gets to answer `go` when the search has no time limit.
+pub const DEFAULT_SEARCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the engine gets to answer `stop` after a `go` timed out.
+const STOP_GRACE: Duration = Duration::from_millis(500);
+
+/// How long the engine gets to exit after `quit` before it is killed.
+const QUIT_GRACE: Duration = Duration::from_millis(200);
+
+/// The errors that can occur while talking to an external engine.
+#[derive(Debug)]
+pub enum UciError {
+    /// The engine process couldn't be started or written to.
+    Io(io::Error),
+    /// The engine closed its output, usually because it exited.
+    Disconnected,
+    /// The engine didn't answer the given command in time.
+    Timeout(&'static str),
+    /// The engine sent a move that isn't legal in the position.
+    IllegalMove(String),
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "engine I/O error: {err}"),
+            Self::Disconnected => write!(f, "the engine closed its output"),
+            Self::Timeout(command) => write!(f, "the engine didn't answer `{command}` in time"),
+            Self::IllegalMove(mv) => write!(f, "the engine played the illegal move `{mv}`"),
+        }
+    }
+}
+
+impl Error for UciError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for UciError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// The score of an `info` line, from the point of view of the engine's side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// A score in centipawns.
+    Cp(i32),
+    /// Mate in the given number of moves, negative if the engine gets mated.
+    Mate(i32),
+}
+
+/// Whether a score is exact or only a bound, as reported in `info` lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bound {
+    #[default]
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// The search information of an `info` line. Fields the engine didn't send are [`None`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Info {
+    pub depth: Option<u8>,
+    pub seldepth: Option<u8>,
+    pub multipv: Option<u8>,
+    pub score: Option<Score>,
+    pub bound: Bound,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<Duration>,
+    /// The principal variation, up to its first move that isn't legal.
+    pub pv: Vec<Move>,
+    pub string: Option<String>,
+}
+
+/// Parses an `info` line sent while searching the current position of the game, or returns
+/// [`None`] if the line isn't an `info` line. Unknown fields are skipped.
+pub fn parse_info(game: &Game, line: &str) -> Option<Info> {
+    let mut tokens = line.split_ascii_whitespace();
+
+    if tokens.next()? != "info" {
+        return None;
+    }
+
+    let mut info = Info::default();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => info.depth = tokens.next().and_then(|depth| depth.parse().ok()),
+            "seldepth" => info.seldepth = tokens.next().and_then(|depth| depth.parse().ok()),
+            "multipv" => info.multipv = tokens.next().and_then(|index| index.parse().ok()),
+            "nodes" => info.nodes = tokens.next().and_then(|nodes| nodes.parse().ok()),
+            "nps" => info.nps = tokens.next().and_then(|nps| nps.parse().ok()),
+            "time" => {
+                info.time = tokens
+                    .next()
+                    .and_then(|time| time.parse().ok())
+                    .map(Duration::from_millis);
+            }
+            "score" => {
+                info.score = match (tokens.next(), tokens.next().map(str::parse)) {
+                    (Some("cp"), Some(Ok(score))) => Some(Score::Cp(score)),
+                    (Some("mate"), Some(Ok(moves))) => Some(Score::Mate(moves)),
+                    _ => None,
+                };
+            }
+            "lowerbound" => info.bound = Bound::Lower,
+            "upperbound" => info.bound = Bound::Upper,
+            "pv" => {
+                let moves: Vec<&str> = tokens.by_ref().collect();
+                info.pv = parse_moves(game, &moves);
+            }
+            "string" => info.string = Some(tokens.by_ref().collect::<Vec<_>>().join(" ")),
+            _ => (),
+        }
+    }
+
+    Some(info)
+}
+
+/// Returns the moves in UCI notation played one after the other from the current position
+/// of the game, up to the first one that isn't legal.
+pub fn parse_moves(game: &Game, moves: &[&str]) -> Vec<Move> {
+    let mut game = game.clone();
+    let mut parsed = Vec::with_capacity(moves.len());
+
+    for mv in moves {
+        let Some(mv) = mv.parse().ok().and_then(|mv| game.resolve_move(mv).ok()) else {
+            break;
+        };
+
+        game.unchecked_make_move(mv);
+        parsed.push(mv);
+    }
+
+    parsed
+}
+
+/// Returns the `position` command setting up the current position of the game, as its
+/// starting position followed by the moves played.
+pub fn position_command(game: &Game) -> String {
+    let mut command = format!("position fen {}", game.history().start().to_fen());
+
+    if game.ply() > 0 {
+        command.push_str(" moves");
+
+        for (_, mv, _) in game.history().iter() {
+            command.push_str(&format!(" {mv}"));
+        }
+    }
+
+    command
+}
+
+/// The limits of a `go` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GoParams {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    /// Search until `stop`. The search is stopped when the engine's
+    /// [timeout](UciEngine::timeout) runs out.
+    pub infinite: bool,
+}
+
+impl GoParams {
+    /// Returns the `go` command with these limits.
+    pub fn to_command(&self) -> String {
+        let mut command = String::from("go");
+        let millis = |duration: Duration| duration.as_millis();
+
+        let fields = [
+            ("depth", self.depth.map(u128::from)),
+            ("nodes", self.nodes.map(u128::from)),
+            ("movetime", self.movetime.map(millis)),
+            ("wtime", self.wtime.map(millis)),
+            ("btime", self.btime.map(millis)),
+            ("winc", self.winc.map(millis)),
+            ("binc", self.binc.map(millis)),
+            ("movestogo", self.movestogo.map(u128::from)),
+        ];
+
+        for (name, value) in fields {
+            if let Some(value) = value {
+                command.push_str(&format!(" {name} {value}"));
+            }
+        }
+
+        if self.infinite {
+            command.push_str(" infinite");
+        }
+
+        command
+    }
+
+    /// Returns the longest the engine may think, if the limits bound it.
+    fn think_time(&self, game: &Game) -> Option<Duration> {
+        if self.infinite {
+            return None;
+        }
+
+        let clock = match game.get_board_state().side_to_move {
+            Color::White => self.wtime,
+            Color::Black => self.btime,
+        };
+
+        self.movetime.or(clock)
+    }
+}
+
+impl From<SearchLimits> for GoParams {
+    fn from(limits: SearchLimits) -> Self {
+        Self {
+            depth: limits.depth,
+            nodes: limits.nodes,
+            movetime: limits.movetime,
+            ..Self::default()
+        }
+    }
+}
+
+/// The answer to a `go` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BestMove {
+    /// The move the engine chose, or [`None`] if it had no legal move (`bestmove 0000`).
+    pub best_move: Option<Move>,
+    /// The reply the engine expects.
+    pub ponder: Option<Move>,
+    /// The `info` lines sent during the search, in order.
+    pub info: Vec<Info>,
+    /// How long the engine took to answer.
+    pub elapsed: Duration,
+}
+
+impl BestMove {
+    /// Returns the last `info` line with a score, which usually belongs to the deepest
+    /// completed iteration.
+    pub fn last_scored(&self) -> Option<&Info> {
+        self.info.iter().rev().find(|info| info.score.is_some())
+    }
+}
+
+/// An external UCI engine running as a child process.
+#[derive(Debug)]
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    name: Option<String>,
+    author: Option<String>,
+    options: Vec<String>,
+    /// How long the engine gets to answer `go` beyond its thinking time.
+    pub move_overhead: Duration,
+    /// How long the engine gets to answer `go` when the search has no time limit, such as
+    /// `go infinite` or a depth or node limit alone.
+    pub timeout: Duration,
+}
+
+impl UciEngine {
+    /// Starts the engine at the given path and performs the `uci`/`isready` handshake.
+    ///
+    /// # Errors
+    /// Returns a [`UciError`] if the engine can't be started or doesn't complete the
+    /// handshake within [`DEFAULT_HANDSHAKE_TIMEOUT`].
+    pub fn spawn(program: impl AsRef<OsStr>, args: &[&str]) -> Result<Self, UciError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(UciError::Disconnected);
+        };
+
+        let (sender, lines) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            child,
+            stdin,
+            lines,
+            name: None,
+            author: None,
+            options: Vec::new(),
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            timeout: DEFAULT_SEARCH_TIMEOUT,
+        };
+
+        engine.send("uci")?;
+        let deadline = Instant::now() + DEFAULT_HANDSHAKE_TIMEOUT;
+
+        loop {
+            let line = engine.recv("uci", deadline)?;
+
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.trim().to_string());
+            } else if let Some(author) = line.strip_prefix("id author ") {
+                engine.author = Some(author.trim().to_string());
+            } else if let Some(option) = line.strip_prefix("option name ") {
+                let name = option.split(" type ").next().unwrap_or(option);
+                engine.options.push(name.trim().to_string());
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+
+        engine.is_ready()?;
+
+        Ok(engine)
+    }
+
+    /// Returns the name the engine gave in the handshake.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the author the engine gave in the handshake.
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    /// Returns the names of the options the engine supports.
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), UciError> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns the next line of the engine's output, waiting until the deadline for it.
+    fn recv(&self, command: &'static str, deadline: Instant) -> Result<String, UciError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(UciError::Timeout(command)),
+            Err(RecvTimeoutError::Disconnected) => Err(UciError::Disconnected),
+        }
+    }
+
+    /// Sends `isready` and waits for `readyok`, skipping any other output.
+    ///
+    /// # Errors
+    /// Returns a [`UciError`] if the engine doesn't answer in time.
+    pub fn is_ready(&mut self) -> Result<(), UciError> {
+        self.send("isready")?;
+        let deadline = Instant::now() + DEFAULT_HANDSHAKE_TIMEOUT;
+
+        while self.recv("isready", deadline)?.trim() != "readyok" {}
+
+        Ok(())
+    }
+
+    /// Sets an option of the engine, such as `Hash` or `Threads`.
+    ///
+    /// # Errors
+    /// Returns a [`UciError`] if the engine can't be written to.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), UciError> {
+        self.send(&format!("setoption name {name} value {value}"))
+    }
+
+    /// Tells the engine the next position belongs to a new game, and waits for it to be
+    /// ready.
+    ///
+    /// # Errors
+    /// Returns a [`UciError`] if the engine doesn't answer in time.
+    pub fn new_game(&mut self) -> Result<(), UciError> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    /// Searches the current position of the game, waiting for `bestmove`. If the engine
+    /// doesn't answer within its thinking time plus the [move
+    /// overhead](Self::move_overhead), it is sent `stop` and given a short grace period.
+    /// Searches without a time limit get the [timeout](Self::timeout) instead.
+    ///
+    /// # Errors
+    /// Returns a [`UciError`] if the engine doesn't answer in time or sends an illegal
+    /// best move.
+    pub fn go(&mut self, game: &Game, params: &GoParams) -> Result<BestMove, UciError> {
+        self.send(&position_command(game))?;
+        self.send(&params.to_command())?;
+
+        let start = Instant::now();
+        let mut deadline = match params.think_time(game) {
+            Some(think_time) => start + think_time + self.move_overhead,
+            None => start + self.timeout,
+        };
+        let mut stopped = false;
+        let mut info = Vec::new();
+
+        loop {
+            let line = match self.recv("go", deadline) {
+                Ok(line) => line,
+                Err(UciError::Timeout(_)) if !stopped => {
+                    self.send("stop")?;
+                    stopped = true;
+                    deadline = Instant::now() + STOP_GRACE;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            if let Some(parsed) = parse_info(game, &line) {
+                info.push(parsed);
+                continue;
+            }
+
+            let mut tokens = line.split_ascii_whitespace();
+
+            if tokens.next() != Some("bestmove") {
+                continue;
+            }
+
+            let best_move = match tokens.next() {
+                None | Some("0000" | "(none)") => None,
+                Some(mv) => match parse_moves(game, &[mv]).first() {
+                    Some(&mv) => Some(mv),
+                    None => return Err(UciError::IllegalMove(mv.to_string())),
+                },
+            };
+
+            let ponder = match (best_move, tokens.next(), tokens.next()) {
+                (Some(best_move), Some("ponder"), Some(ponder)) => {
+                    let mut after = game.clone();
+                    after.unchecked_make_move(best_move);
+                    parse_moves(&after, &[ponder]).first().copied()
+                }
+                _ => None,
+            };
+
+            return Ok(BestMove {
+                best_move,
+                ponder,
+                info,
+                elapsed: start.elapsed(),
+            });
+        }
+    }
+
+    /// Sends `quit` and waits briefly for the engine to exit, killing it otherwise.
+    pub fn quit(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + QUIT_GRACE;
+
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            self.shutdown();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+
+    #[test]
+    fn test_parse_info() {
+        let move_gen = MoveGenerator::new();
+        let game = Game::new(&move_gen);
+
+        let info = parse_info(
+            &game,
+            "info depth 12 seldepth 18 multipv 1 score cp -35 upperbound nodes 123456 nps 654321 \
+             time 189 hashfull 10 pv e2e4 e7e5 g1f3 b8c6 f1z9",
+        )
+        .unwrap();
+
+        assert_eq!(info.depth, Some(12));
+        assert_eq!(info.seldepth, Some(18));
+        assert_eq!(info.multipv, Some(1));
+        assert_eq!(info.score, Some(Score::Cp(-35)));
+        assert_eq!(info.bound, Bound::Upper);
+        assert_eq!(info.nodes, Some(123_456));
+        assert_eq!(info.nps, Some(654_321));
+        assert_eq!(info.time, Some(Duration::from_millis(189)));
+        assert_eq!(
+            info.pv.iter().map(|mv| mv.to_string()).collect::<Vec<_>>(),
+            ["e2e4", "e7e5", "g1f3", "b8c6"]
+        );
+        assert!(info.pv[0].is_double_pawn_push());
+
+        let info = parse_info(&game, "info score mate -3 string hello there").unwrap();
+
+        assert_eq!(info.score, Some(Score::Mate(-3)));
+        assert_eq!(info.string.as_deref(), Some("hello there"));
+        assert_eq!(parse_info(&game, "bestmove e2e4"), None);
+    }
+
+    #[test]
+    fn test_commands() {
+        let move_gen = MoveGenerator::new();
+        let mut game = Game::new(&move_gen);
+
+        assert_eq!(
+            position_command(&game),
+            "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+
+        game.try_make_move("e2e4".parse().unwrap()).unwrap();
+        game.try_make_move("c7c5".parse().unwrap()).unwrap();
+
+        assert!(position_command(&game).ends_with("KQkq - 0 1 moves e2e4 c7c5"));
+
+        let params = GoParams {
+            wtime: Some(Duration::from_secs(60)),
+            btime: Some(Duration::from_millis(59_500)),
+            winc: Some(Duration::from_secs(1)),
+            binc: Some(Duration::from_secs(1)),
+            ..GoParams::default()
+        };
+
+        assert_eq!(
+            params.to_command(),
+            "go wtime 60000 btime 59500 winc 1000 binc 1000"
+        );
+        assert_eq!(
+            GoParams::from(SearchLimits::depth(6)).to_command(),
+            "go depth 6"
+        );
+        assert_eq!(params.think_time(&game), Some(Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,686 @@
+use crate::Color;
+use crate::game::{DrawReason, Game, Outcome};
+use crate::moves::Move;
+use crate::nnue::Network;
+use crate::play;
+use crate::search::{self, SearchLimits, SearchResult};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The features announced in reply to `protover 2`.
+const FEATURES: &str = "feature myname=\"poob\" ping=1 setboard=1 usermove=1 analyze=1 \
+                        colors=0 sigint=0 sigterm=0 reuse=1 done=1";
+
+/// The number of moves the remaining time is divided between when the time control has no
+/// move count (`level 0 ...`).
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Commands that are handled without interrupting a running search.
+const NON_INTERRUPTING: [&str; 7] = [".", "time", "otim", "post", "nopost", "hard", "easy"];
+
+/// Commands that cancel the move of a running search when they interrupt it.
+const CANCELLING: [&str; 9] = [
+    "new", "force", "quit", "undo", "remove", "setboard", "result", "edit", "analyze",
+];
+
+/// The time control set by `level` or `st`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControl {
+    /// `moves` moves in `base` time, plus `increment` per move. A move count of `0` means
+    /// the whole game is played in `base` time.
+    Conventional {
+        moves: u32,
+        base: Duration,
+        increment: Duration,
+    },
+    /// A fixed time per move.
+    Fixed(Duration),
+}
+
+impl TimeControl {
+    /// Returns the time on the clock at the start of the game, or the time per move.
+    pub fn start_time(&self) -> Duration {
+        match *self {
+            Self::Conventional { base, .. } => base,
+            Self::Fixed(time) => time,
+        }
+    }
+
+    /// Returns how long to think about the next move with `remaining` time on the clock,
+    /// after playing `played` moves. Never uses more than half the remaining time.
+    pub fn allocate(&self, remaining: Duration, played: u32) -> Duration {
+        match *self {
+            Self::Fixed(time) => time,
+            Self::Conventional {
+                moves, increment, ..
+            } => {
+                let moves_to_go = match moves {
+                    0 => DEFAULT_MOVES_TO_GO,
+                    moves => moves - played % moves,
+                };
+
+                (remaining / moves_to_go + increment * 3 / 4).min(remaining / 2)
+            }
+        }
+    }
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self::Conventional {
+            moves: 40,
+            base: Duration::from_secs(300),
+            increment: Duration::ZERO,
+        }
+    }
+}
+
+/// A chess engine speaking the Chess Engine Communication Protocol (CECP) used by XBoard and
+/// WinBoard, reading commands line by line and writing its replies to `out`.
+#[derive(Debug)]
+pub struct XBoard<'a, W: Write> {
+    game: Game<'a>,
+    out: W,
+    /// In force mode, the engine only plays the moves it is given.
+    force: bool,
+    engine_color: Color,
+    post: bool,
+    analyzing: bool,
+    /// Set whenever the analysis should restart on the current position.
+    analysis_stale: bool,
+    time_control: TimeControl,
+    /// The time left on the engine's clock, as last reported by `time`.
+    engine_time: Option<Duration>,
+    max_depth: Option<u8>,
+    quit: bool,
+    /// Lines read while a search was running, still to be executed.
+    pending: VecDeque<String>,
+    input: Option<Receiver<String>>,
+    stop: Arc<AtomicBool>,
+    searching: Arc<AtomicBool>,
+    /// The NNUE network evaluating positions instead of the handcrafted evaluation.
+    network: Option<Network>,
+}
+
+impl<'a, W: Write> XBoard<'a, W> {
+    /// Creates an engine playing black from the starting position, as after `new`.
+    pub fn new(game: Game<'a>, out: W) -> Self {
+        Self {
+            game,
+            out,
+            force: false,
+            engine_color: Color::Black,
+            post: false,
+            analyzing: false,
+            analysis_stale: false,
+            time_control: TimeControl::default(),
+            engine_time: None,
+            max_depth: None,
+            quit: false,
+            pending: VecDeque::new(),
+            input: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            searching: Arc::new(AtomicBool::new(false)),
+            network: None,
+        }
+    }
+
+    /// Evaluates positions with the given NNUE network, or with the handcrafted evaluation
+    /// if there is none.
+    pub fn set_network(&mut self, network: Option<Network>) {
+        self.network = network;
+    }
+
+    /// Returns the game being played.
+    pub fn game(&self) -> &Game<'a> {
+        &self.game
+    }
+
+    /// Returns the writer the replies are written to.
+    pub fn out(&self) -> &W {
+        &self.out
+    }
+
+    /// Reads and executes commands until `quit` or the end of the input. The input is read
+    /// on a separate thread, so that commands such as `?` can interrupt a search.
+    ///
+    /// # Errors
+    /// Returns an error if writing a reply fails.
+    pub fn run(&mut self, input: impl BufRead + Send + 'static) -> io::Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::clone(&self.stop);
+        let searching = Arc::clone(&self.searching);
+
+        // The reader isn't joined: it may be blocked reading a terminal after `quit`.
+        thread::spawn(move || read_commands(input, sender, &stop, &searching));
+        self.input = Some(receiver);
+
+        while !self.quit {
+            if self.analyzing && self.analysis_stale && self.pending.is_empty() {
+                self.analysis_stale = false;
+                self.analyse()?;
+                continue;
+            }
+
+            let Some(line) = self.next_line() else {
+                break;
+            };
+
+            self.execute(&line)?;
+        }
+
+        self.input = None;
+
+        Ok(())
+    }
+
+    /// Returns the next line to execute, waiting for one if none has been read yet.
+    fn next_line(&mut self) -> Option<String> {
+        self.pending
+            .pop_front()
+            .or_else(|| self.input.as_ref()?.recv().ok())
+    }
+
+    /// Moves the lines read so far to the pending lines.
+    fn drain_input(&mut self) {
+        if let Some(input) = &self.input {
+            self.pending.extend(input.try_iter());
+        }
+    }
+
+    /// Executes a single command, writing its replies.
+    ///
+    /// # Errors
+    /// Returns an error if writing a reply fails.
+    pub fn execute(&mut self, line: &str) -> io::Result<()> {
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        let args = args.trim();
+
+        if command != "." {
+            self.analysis_stale = true;
+        }
+
+        match command {
+            "" | "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "name" | "rating" | "ics" | "draw" | "otim" | "." | "?" => (),
+            "protover" => writeln!(self.out, "{FEATURES}")?,
+            "new" => {
+                self.game = Game::new(self.game.move_gen());
+                self.force = false;
+                self.engine_color = Color::Black;
+                self.engine_time = None;
+                self.max_depth = None;
+            }
+            "force" | "result" => self.force = true,
+            "go" => {
+                self.force = false;
+                self.engine_color = self.game.get_board_state().side_to_move;
+                self.think()?;
+            }
+            "usermove" => self.user_move(args)?,
+            "undo" => {
+                self.game.undo();
+            }
+            "remove" => {
+                self.game.undo();
+                self.game.undo();
+            }
+            "level" => match parse_level(args) {
+                Some(time_control) => self.time_control = time_control,
+                None => self.error("bad level", line)?,
+            },
+            "st" => match parse_seconds(args) {
+                Some(time) => self.time_control = TimeControl::Fixed(time),
+                None => self.error("bad time", line)?,
+            },
+            "sd" => match args.parse() {
+                Ok(depth) => self.max_depth = Some(depth),
+                Err(_) => self.error("bad depth", line)?,
+            },
+            "time" => match args.parse::<u64>() {
+                Ok(centiseconds) => {
+                    self.engine_time = Some(Duration::from_millis(centiseconds * 10));
+                }
+                Err(_) => self.error("bad time", line)?,
+            },
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "setboard" => match Game::from_fen(args, self.game.move_gen()) {
+                Ok(game) => self.game = game,
+                Err(_) => writeln!(self.out, "tellusererror Illegal position")?,
+            },
+            "analyze" => {
+                self.analyzing = true;
+                self.force = true;
+            }
+            "exit" => self.analyzing = false,
+            "ping" => writeln!(self.out, "pong {args}")?,
+            "quit" => self.quit = true,
+            _ if play::parse_move(&self.game, line).is_ok() => self.user_move(line)?,
+            _ => self.error("unknown command", command)?,
+        }
+
+        self.out.flush()
+    }
+
+    fn error(&mut self, kind: &str, command: &str) -> io::Result<()> {
+        writeln!(self.out, "Error ({kind}): {command}")
+    }
+
+    /// Plays the opponent's move, then thinks if it is the engine's turn.
+    fn user_move(&mut self, input: &str) -> io::Result<()> {
+        let played =
+            play::parse_move(&self.game, input).is_ok_and(|mv| self.game.try_make_move(mv).is_ok());
+
+        if !played {
+            return writeln!(self.out, "Illegal move: {input}");
+        }
+
+        if self.report_result()? {
+            return Ok(());
+        }
+
+        if !self.force && !self.analyzing && self.side_to_move() == self.engine_color {
+            self.think()?;
+        }
+
+        Ok(())
+    }
+
+    fn side_to_move(&self) -> Color {
+        self.game.get_board_state().side_to_move
+    }
+
+    /// Returns the limits of the engine's next search, given the time control and the
+    /// time left on its clock.
+    fn search_limits(&self) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.max_depth,
+            ..SearchLimits::default()
+        };
+
+        if self.analyzing {
+            return limits;
+        }
+
+        let played = u32::from(self.game.get_board_state().turn_count - 1);
+        let remaining = self.engine_time.unwrap_or(self.time_control.start_time());
+
+        limits.movetime = Some(self.time_control.allocate(remaining, played));
+
+        limits
+    }
+
+    /// Searches the current position, printing the thinking output if `post` is on. Any
+    /// line read during the search stops it, apart from the [`NON_INTERRUPTING`] ones.
+    fn search(&mut self) -> io::Result<SearchResult> {
+        let limits = self.search_limits();
+        let start = Instant::now();
+
+        self.stop.store(false, Ordering::Relaxed);
+        self.searching.store(true, Ordering::Relaxed);
+
+        // Lines read before the search started don't stop it on their own.
+        self.drain_input();
+
+        if self.pending.iter().any(|line| interrupts(line)) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+
+        let Self {
+            game,
+            out,
+            post,
+            network,
+            ..
+        } = self;
+        let mut written = Ok(());
+        let result =
+            search::search_with_network(game, limits, &self.stop, network.as_ref(), |result| {
+                if *post && written.is_ok() {
+                    written = writeln!(out, "{}", thinking_line(game, result, start.elapsed()))
+                        .and_then(|()| out.flush());
+                }
+            });
+
+        self.searching.store(false, Ordering::Relaxed);
+        self.drain_input();
+
+        written.map(|()| result)
+    }
+
+    /// Searches for the engine's move and plays it, unless a command cancelled it while
+    /// searching.
+    fn think(&mut self) -> io::Result<()> {
+        if self.report_result()? {
+            return Ok(());
+        }
+
+        let result = self.search()?;
+
+        let cancelled = self.pending.iter().any(|line| {
+            let command = line.split_whitespace().next().unwrap_or("");
+
+            CANCELLING.contains(&command)
+        });
+
+        let Some(mv) = result.best_move.filter(|_| !cancelled) else {
+            return Ok(());
+        };
+
+        if self.game.try_make_move(mv).is_ok() {
+            writeln!(self.out, "move {mv}")?;
+            self.report_result()?;
+        }
+
+        Ok(())
+    }
+
+    /// Analyses the current position until the analysis is interrupted or reaches its
+    /// depth limit.
+    fn analyse(&mut self) -> io::Result<()> {
+        let post = self.post;
+
+        // Analysis output is always shown.
+        self.post = true;
+        let result = self.search();
+        self.post = post;
+
+        result.map(|_| ())
+    }
+
+    /// Writes the result of the game if it is over, returning `true` if it is.
+    fn report_result(&mut self) -> io::Result<bool> {
+        let Some(result) = game_result(&self.game) else {
+            return Ok(false);
+        };
+
+        if !self.analyzing {
+            writeln!(self.out, "{result}")?;
+        }
+
+        Ok(true)
+    }
+}
+
+/// Returns the result of the game in CECP format, such as `1-0 {White mates}`, or [`None`]
+/// if the game isn't over.
+pub fn game_result(game: &Game) -> Option<&'static str> {
+    let result = match (game.outcome(), game.draw_by_rule()) {
+        (Some(Outcome::Win(Color::White)), _) => "1-0 {White mates}",
+        (Some(Outcome::Win(Color::Black)), _) => "0-1 {Black mates}",
+        (Some(Outcome::Draw), _) if game.enumerate_moves().is_empty() => "1/2-1/2 {Stalemate}",
+        (_, Some(DrawReason::Repetition)) => "1/2-1/2 {Draw by repetition}",
+        (_, Some(DrawReason::FiftyMoves)) => "1/2-1/2 {Draw by fifty move rule}",
+        (_, Some(DrawReason::InsufficientMaterial)) => "1/2-1/2 {Insufficient material}",
+        // Agreed or adjudicated draws.
+        (Some(Outcome::Draw), None) => "1/2-1/2 {Draw}",
+        (None, None) => return None,
+    };
+
+    Some(result)
+}
+
+/// Formats a search iteration as a CECP thinking line: the depth, the score in centipawns
+/// (mates are `100000` plus the number of moves), the time in centiseconds, the nodes, and
+/// the principal variation in SAN.
+pub fn thinking_line(game: &Game, result: &SearchResult, elapsed: Duration) -> String {
+    let score = match search::mate_in(result.score) {
+        Some(moves) if moves > 0 => 100_000 + moves,
+        Some(moves) => -100_000 + moves,
+        None => result.score,
+    };
+
+    format!(
+        "{} {score} {} {} {}",
+        result.depth,
+        elapsed.as_millis() / 10,
+        result.nodes,
+        san_line(game, &result.pv)
+    )
+}
+
+/// Returns the moves in SAN, separated by spaces, stopping at the first illegal one.
+fn san_line(game: &Game, moves: &[Move]) -> String {
+    let mut game = game.clone();
+    let mut sans = Vec::with_capacity(moves.len());
+
+    for &mv in moves {
+        let Ok(san) = game.to_san(mv) else {
+            break;
+        };
+
+        game.unchecked_make_move(mv);
+        sans.push(san);
+    }
+
+    sans.join(" ")
+}
+
+/// Parses the arguments of `level`: the number of moves, the base time in minutes or
+/// `minutes:seconds`, and the increment in seconds.
+fn parse_level(args: &str) -> Option<TimeControl> {
+    let mut args = args.split_ascii_whitespace();
+    let (moves, base, increment) = (args.next()?, args.next()?, args.next()?);
+
+    let base = match base.split_once(':') {
+        Some((minutes, seconds)) => {
+            minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?
+        }
+        None => base.parse::<u64>().ok()? * 60,
+    };
+
+    Some(TimeControl::Conventional {
+        moves: moves.parse().ok()?,
+        base: Duration::from_secs(base),
+        increment: parse_seconds(increment)?,
+    })
+}
+
+fn parse_seconds(seconds: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds.parse().ok()?).ok()
+}
+
+/// Returns `true` if the line stops a running search, and `false` if it can wait for the
+/// search to end.
+fn interrupts(line: &str) -> bool {
+    let command = line.split_whitespace().next().unwrap_or("");
+
+    !NON_INTERRUPTING.contains(&command)
+}
+
+/// Sends the lines of the input to the engine, setting `stop` if a line arrives while it
+/// is searching.
+fn read_commands(
+    input: impl BufRead,
+    sender: Sender<String>,
+    stop: &AtomicBool,
+    searching: &AtomicBool,
+) {
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let quit = line.trim() == "quit";
+
+        if searching.load(Ordering::Relaxed) && interrupts(&line) {
+            stop.store(true, Ordering::Relaxed);
+        }
+
+        if sender.send(line).is_err() || quit {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+    use std::io::Cursor;
+
+    fn output(xboard: &mut XBoard<Vec<u8>>) -> String {
+        String::from_utf8(std::mem::take(&mut xboard.out)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_time_controls() {
+        assert_eq!(
+            parse_level("40 5 0"),
+            Some(TimeControl::Conventional {
+                moves: 40,
+                base: Duration::from_secs(300),
+                increment: Duration::ZERO,
+            })
+        );
+        assert_eq!(
+            parse_level("0 2:30 1.5"),
+            Some(TimeControl::Conventional {
+                moves: 0,
+                base: Duration::from_secs(150),
+                increment: Duration::from_millis(1500),
+            })
+        );
+        assert_eq!(parse_level("40 5"), None);
+        assert_eq!(parse_seconds("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_seconds("-1"), None);
+    }
+
+    #[test]
+    fn test_game_result() {
+        let move_gen = MoveGenerator::new();
+        let result = |fen| game_result(&Game::from_fen(fen, &move_gen).unwrap());
+
+        assert_eq!(
+            result("4k3/8/8/8/8/8/8/R3K3 w - - 100 80"),
+            Some("1/2-1/2 {Draw by fifty move rule}")
+        );
+        assert_eq!(
+            result("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"),
+            Some("1/2-1/2 {Insufficient material}")
+        );
+        assert_eq!(result("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1"), None);
+
+        let mut game = Game::from_fen("7k/8/4Q1K1/8/8/8/8/8 w - - 0 1", &move_gen).unwrap();
+        game.try_make_move("e6f7".parse().unwrap()).unwrap();
+        assert_eq!(game_result(&game), Some("1/2-1/2 {Stalemate}"));
+
+        let mut game = Game::new(&move_gen);
+        for mv in "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8".split(' ') {
+            game.try_make_move(mv.parse().unwrap()).unwrap();
+        }
+        assert_eq!(game_result(&game), Some("1/2-1/2 {Draw by repetition}"));
+
+        let mut game = Game::new(&move_gen);
+        game.agree_draw();
+        assert_eq!(game_result(&game), Some("1/2-1/2 {Draw}"));
+    }
+
+    #[test]
+    fn test_execute() {
+        let move_gen = MoveGenerator::new();
+        let mut xboard = XBoard::new(Game::new(&move_gen), Vec::new());
+
+        xboard.execute("xboard").unwrap();
+        xboard.execute("protover 2").unwrap();
+        assert_eq!(output(&mut xboard), format!("{FEATURES}\n"));
+
+        xboard.execute("ping 3").unwrap();
+        assert_eq!(output(&mut xboard), "pong 3\n");
+
+        // In force mode, moves are only played.
+        xboard.execute("force").unwrap();
+        xboard.execute("usermove e2e4").unwrap();
+        xboard.execute("e7e5").unwrap();
+        xboard.execute("usermove e1e3").unwrap();
+        assert_eq!(output(&mut xboard), "Illegal move: e1e3\n");
+        assert_eq!(xboard.game().ply(), 2);
+
+        xboard.execute("remove").unwrap();
+        assert_eq!(xboard.game().ply(), 0);
+
+        xboard.execute("foo").unwrap();
+        xboard.execute("sd x").unwrap();
+        assert_eq!(
+            output(&mut xboard),
+            "Error (unknown command): foo\nError (bad depth): sd x\n"
+        );
+
+        // The engine plays the side to move on `go`, and answers the opponent's moves.
+        xboard.execute("sd 2").unwrap();
+        xboard.execute("post").unwrap();
+        xboard
+            .execute("setboard 6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1")
+            .unwrap();
+        xboard.execute("go").unwrap();
+
+        let out = output(&mut xboard);
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("1 "));
+        assert!(lines[1].starts_with("2 100001 "));
+        assert!(lines[1].ends_with(" Ra8#"));
+        assert_eq!(lines[2], "move a1a8");
+        assert_eq!(lines[3], "1-0 {White mates}");
+
+        xboard.execute("setboard not a fen").unwrap();
+        assert_eq!(output(&mut xboard), "tellusererror Illegal position\n");
+
+        xboard.execute("new").unwrap();
+        xboard.execute("nopost").unwrap();
+        xboard.execute("st 1").unwrap();
+        xboard.execute("sd 1").unwrap();
+        xboard.execute("usermove e2e4").unwrap();
+
+        let out = output(&mut xboard);
+
+        assert!(out.starts_with("move "));
+        assert_eq!(xboard.game().ply(), 2);
+    }
+
+    #[test]
+    fn test_search_limits() {
+        let move_gen = MoveGenerator::new();
+        let mut xboard = XBoard::new(Game::new(&move_gen), Vec::new());
+
+        xboard.execute("level 40 5 0").unwrap();
+        xboard.execute("time 6000").unwrap();
+        assert_eq!(
+            xboard.search_limits().movetime,
+            Some(Duration::from_millis(1500))
+        );
+
+        xboard.execute("st 3").unwrap();
+        xboard.execute("sd 4").unwrap();
+        assert_eq!(
+            xboard.search_limits(),
+            SearchLimits {
+                depth: Some(4),
+                nodes: None,
+                movetime: Some(Duration::from_secs(3)),
+            }
+        );
+
+        xboard.execute("analyze").unwrap();
+        assert_eq!(xboard.search_limits(), SearchLimits::depth(4));
+    }
+
+    #[test]
+    fn test_run() {
+        let move_gen = MoveGenerator::new();
+        let mut xboard = XBoard::new(Game::new(&move_gen), Vec::new());
+        let input = Cursor::new("xboard\nprotover 2\nping 1\nquit\nping 2\n");
+
+        xboard.run(input).unwrap();
+
+        assert_eq!(output(&mut xboard), format!("{FEATURES}\npong 1\n"));
+    }
+}
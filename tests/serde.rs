@@ -0,0 +1,129 @@
+#![cfg(feature = "serde")]
+
+use poob::boardstate::BoardState;
+use poob::encoding;
+use poob::game::{Game, Outcome};
+use poob::history::BoardHistory;
+use poob::movegen::MoveGenerator;
+use poob::moves::Move;
+use poob::{Color, Piece};
+use serde_test::{Configure, Token};
+
+const FEN: &str = "r3k2r/6P1/8/3pP3/8/8/2p5/R3K2R w KQkq d6 0 1";
+
+#[test]
+fn test_json() {
+    let board_state = BoardState::from_fen(FEN).unwrap();
+    let json = serde_json::to_string(&board_state).unwrap();
+
+    assert_eq!(json, format!("\"{FEN}\""));
+    assert_eq!(
+        serde_json::from_str::<BoardState>(&json).unwrap(),
+        board_state
+    );
+
+    // Illegal positions are rejected like by `from_fen_strict()`: no kings, or the side
+    // not to move in check.
+    assert!(serde_json::from_str::<BoardState>("\"8/8/8/8/8/8/8/8 w - - 0 1\"").is_err());
+    assert!(serde_json::from_str::<BoardState>("\"4k3/8/8/8/8/8/8/4KR2 b - - 0 1\"").is_ok());
+    assert!(serde_json::from_str::<BoardState>("\"4k3/8/8/8/8/8/8/4R1K1 w - - 0 1\"").is_err());
+
+    let mv: Move = "e7e8q".parse().unwrap();
+    assert_eq!(serde_json::to_string(&mv).unwrap(), "\"e7e8q\"");
+    assert_eq!(serde_json::from_str::<Move>("\"e7e8q\"").unwrap(), mv);
+    assert!(serde_json::from_str::<Move>("\"e7e9\"").is_err());
+
+    assert_eq!(serde_json::to_string(&Color::White).unwrap(), "\"White\"");
+    assert_eq!(serde_json::to_string(&Piece::Knight).unwrap(), "\"Knight\"");
+    assert_eq!(
+        serde_json::to_string(&Outcome::Win(Color::Black)).unwrap(),
+        "{\"Win\":\"Black\"}"
+    );
+    assert_eq!(
+        serde_json::from_str::<Outcome>("\"Draw\"").unwrap(),
+        Outcome::Draw
+    );
+}
+
+#[test]
+fn test_compact() {
+    let board_state = BoardState::from_fen(FEN).unwrap();
+    // Tokens only borrow static data.
+    let packed: &'static [u8] = Vec::leak(encoding::pack_board(&board_state).unwrap().to_vec());
+
+    serde_test::assert_tokens(&board_state.clone().compact(), &[Token::Bytes(packed)]);
+
+    let mut tokens = vec![Token::Seq {
+        len: Some(packed.len()),
+    }];
+    tokens.extend(packed.iter().map(|&byte| Token::U8(byte)));
+    tokens.push(Token::SeqEnd);
+    serde_test::assert_de_tokens(&board_state.compact(), &tokens);
+
+    serde_test::assert_de_tokens_error::<serde_test::Compact<BoardState>>(
+        &[Token::Bytes(&packed[1..])],
+        "invalid length 31, expected a position packed in 32 bytes",
+    );
+
+    // The raw form keeps the flags that UCI notation leaves out.
+    let move_gen = MoveGenerator::new();
+    let mv = Game::from_fen(FEN, &move_gen)
+        .unwrap()
+        .resolve_move("e5d6".parse().unwrap())
+        .unwrap();
+
+    serde_test::assert_tokens(&mv.compact(), &[Token::U16(mv.to_u16())]);
+    serde_test::assert_ser_tokens(&mv.readable(), &[Token::Str("e5d6")]);
+    serde_test::assert_de_tokens(
+        &Move::from_squares_str("e5", "d6").unwrap().readable(),
+        &[Token::Str("e5d6")],
+    );
+    assert!(mv.is_capture());
+}
+
+#[test]
+fn test_history() {
+    let move_gen = MoveGenerator::new();
+    let mut game = Game::from_fen(FEN, &move_gen).unwrap();
+
+    for mv in ["e5d6", "e8c8", "e1g1", "c2c1q", "g7h8n"] {
+        game.try_make_move(mv.parse().unwrap()).unwrap();
+    }
+    game.undo();
+
+    // Moves come back fully flagged from their UCI notation.
+    let json = serde_json::to_string(game.history()).unwrap();
+    assert!(json.contains("\"mv\":\"e1g1\""));
+    assert_eq!(
+        &serde_json::from_str::<BoardHistory>(&json).unwrap(),
+        game.history()
+    );
+
+    let bad_ply = json.replace("\"ply\":4", "\"ply\":6");
+    assert!(
+        serde_json::from_str::<BoardHistory>(&bad_ply)
+            .unwrap_err()
+            .to_string()
+            .contains("ply 6 is past the 5 recorded moves")
+    );
+
+    let bad_move = json.replace("\"mv\":\"e8c8\"", "\"mv\":\"e8g8\"");
+    assert!(serde_json::from_str::<BoardHistory>(&bad_move).is_err());
+}
+
+#[test]
+fn test_history_past_fifty_moves() {
+    let move_gen = MoveGenerator::new();
+    let mut game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 98 80", &move_gen).unwrap();
+
+    for mv in ["h1h2", "e8d8", "h2h1"] {
+        game.try_make_move(mv.parse().unwrap()).unwrap();
+    }
+    assert_eq!(game.get_board_state().fifty_move_rule, 101);
+
+    let json = serde_json::to_string(game.history()).unwrap();
+    assert_eq!(
+        &serde_json::from_str::<BoardHistory>(&json).unwrap(),
+        game.history()
+    );
+}
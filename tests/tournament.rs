@@ -0,0 +1,54 @@
+use poob::Color;
+use poob::game::Outcome;
+use poob::movegen::MoveGenerator;
+use poob::tournament::{self, EngineConfig, MatchConfig, TournamentError};
+use poob::xboard::TimeControl;
+use std::time::Duration;
+
+const FAKE_ENGINE: &str = env!("CARGO_BIN_EXE_fake_uci");
+
+#[test]
+fn test_match_against_uci_engine() {
+    let move_gen = MoveGenerator::new();
+    let config = MatchConfig {
+        engines: vec![
+            format!("uci:{FAKE_ENGINE},Hash=32").parse().unwrap(),
+            EngineConfig::Poob(Some(2)),
+        ],
+        time_control: TimeControl::Fixed(Duration::from_millis(200)),
+        openings: tournament::parse_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - -").unwrap(),
+        ..MatchConfig::default()
+    };
+    let report = tournament::run_match(&config, &move_gen, |_| ()).unwrap();
+
+    assert_eq!(report.engines, ["fake_uci", "poob:2"]);
+    assert_eq!(report.games.len(), 2);
+
+    // The fake engine misses the mate and the game goes on until it ends by the rules.
+    let first = &report.games[0];
+    assert_eq!(first.pairing.white, 0);
+    assert!(first.pgn.contains("[White \"fake_uci\"]"));
+    assert!(!first.reason.contains("disconnects"), "{}", first.reason);
+
+    let second = &report.games[1];
+    assert_eq!(second.outcome, Outcome::Win(Color::White));
+    assert_eq!(second.reason, "White mates");
+    assert!(second.pgn.ends_with("1. Ra8# 1-0\n"));
+}
+
+#[test]
+fn test_missing_engine() {
+    let move_gen = MoveGenerator::new();
+    let config = MatchConfig {
+        engines: vec![
+            EngineConfig::Poob(Some(1)),
+            "uci:/nonexistent/engine".parse().unwrap(),
+        ],
+        ..MatchConfig::default()
+    };
+
+    assert!(matches!(
+        tournament::run_match(&config, &move_gen, |_| ()),
+        Err(TournamentError::Uci(_))
+    ));
+}
@@ -0,0 +1,100 @@
+use poob::game::Game;
+use poob::movegen::MoveGenerator;
+use poob::search::SearchLimits;
+use poob::uci_client::{GoParams, Score, UciEngine, UciError};
+use std::time::Duration;
+
+const FAKE_ENGINE: &str = env!("CARGO_BIN_EXE_fake_uci");
+
+#[test]
+fn test_handshake_and_go() {
+    let move_gen = MoveGenerator::new();
+    let mut game = Game::new(&move_gen);
+    let mut engine = UciEngine::spawn(FAKE_ENGINE, &[]).unwrap();
+
+    assert_eq!(engine.name(), Some("poob fake engine"));
+    assert_eq!(engine.author(), Some("the poob developers"));
+    assert_eq!(engine.options(), ["Hash", "Mute"]);
+
+    engine.set_option("Hash", "32").unwrap();
+    engine.new_game().unwrap();
+
+    for _ in 0..4 {
+        let reply = engine
+            .go(&game, &GoParams::from(SearchLimits::depth(1)))
+            .unwrap();
+        let best_move = reply.best_move.unwrap();
+        let info = reply.last_scored().unwrap();
+
+        assert_eq!(info.depth, Some(1));
+        assert_eq!(info.score, Some(Score::Cp(0)));
+        assert_eq!(info.pv.first(), Some(&best_move));
+        assert_eq!(info.pv.get(1), reply.ponder.as_ref());
+
+        game.try_make_move(best_move).unwrap();
+    }
+
+    assert_eq!(game.ply(), 4);
+    engine.quit();
+}
+
+#[test]
+fn test_checkmated_and_stop() {
+    let move_gen = MoveGenerator::new();
+    let mut engine = UciEngine::spawn(FAKE_ENGINE, &[]).unwrap();
+
+    // Fool's mate: white has no legal move.
+    let mated = Game::from_fen(
+        "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        &move_gen,
+    )
+    .unwrap();
+    let reply = engine.go(&mated, &GoParams::default()).unwrap();
+
+    assert_eq!(reply.best_move, None);
+    assert_eq!(reply.ponder, None);
+
+    // An infinite search is stopped once the timeout runs out.
+    engine.timeout = Duration::from_millis(100);
+    let params = GoParams {
+        infinite: true,
+        ..GoParams::default()
+    };
+    let reply = engine.go(&Game::new(&move_gen), &params).unwrap();
+
+    assert!(reply.best_move.is_some());
+    assert!(reply.elapsed >= Duration::from_millis(100));
+}
+
+#[test]
+fn test_timeout() {
+    let move_gen = MoveGenerator::new();
+    let mut engine = UciEngine::spawn(FAKE_ENGINE, &[]).unwrap();
+
+    engine.set_option("Mute", "true").unwrap();
+    engine.is_ready().unwrap();
+    engine.move_overhead = Duration::from_millis(100);
+
+    // Timed searches get their thinking time plus the move overhead.
+    let params = GoParams {
+        movetime: Some(Duration::from_millis(50)),
+        ..GoParams::default()
+    };
+    let result = engine.go(&Game::new(&move_gen), &params);
+
+    assert!(matches!(result, Err(UciError::Timeout("go"))));
+
+    // Searches without a time limit get the timeout, not the move overhead.
+    engine.timeout = Duration::from_millis(100);
+
+    let result = engine.go(&Game::new(&move_gen), &GoParams::default());
+
+    assert!(matches!(result, Err(UciError::Timeout("go"))));
+}
+
+#[test]
+fn test_spawn_failure() {
+    let result = UciEngine::spawn("/nonexistent/engine", &[]);
+
+    assert!(matches!(result, Err(UciError::Io(_))));
+}